@@ -53,5 +53,17 @@ app_{0}_end:"#,
             idx, app, TARGET_PATH
         )?;
     }
+
+    // 以'\0'结尾的应用名，顺序与上面的应用数据一致，作为任务的默认名字
+    writeln!(
+        f,
+        r#"
+    .section .data
+    .global _app_names
+_app_names:"#
+    )?;
+    for app in apps.iter() {
+        writeln!(f, r#"    .string "{}""#, app)?;
+    }
     Ok(())
 }
@@ -1,18 +1,90 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
+/// MAP_STACK映射默认最多能向下增长到的大小（字节）
+pub const MAP_STACK_MAX_SIZE: usize = 4096 * 16;
+/// 新建的用户地址空间默认拒绝同时可写且可执行（W^X）的mmap/mprotect，除非带上MAP_JIT
+pub const MMAP_WX_STRICT: bool = true;
+/// 加载ELF时把可执行段（.text）标记为不可变：之后mprotect不能再给它们加上W权限
+pub const ELF_TEXT_IMMUTABLE: bool = true;
+/// 只有这个编号的任务（第一个应用，充当驱动任务）可以调用sys_map_phys
+pub const MAP_PHYS_PID: usize = 0;
+/// 这个编号的任务（监督任务）可以用sys_dump_maps查看任何任务的映射，其他任务只能查看自己的子任务
+pub const SUPERVISOR_PID: usize = 0;
+/// 每个内核栈的大小（字节），必须是页大小的整数倍且不小于KERNEL_STACK_MIN_SIZE
 pub const KERNEL_STACK_SIZE: usize = 4096 * 20;
+/// 内核栈大小的下限：trap处理、from_elf和fork等路径在更小的栈上会溢出到保护页
+pub const KERNEL_STACK_MIN_SIZE: usize = 4096 * 4;
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
+/// 设备树中探测不到物理内存大小时使用的物理内存结束地址
 pub const MEMORY_END: usize = 0x88000000;
+/// 探测到的物理内存结束地址的上限，超出的部分不会被映射和分配
+pub const MEMORY_END_MAX: usize = 0x1_0000_0000;
 pub const PAGE_SIZE: usize = 0x1000;
+/// 页帧的颜色数（物理页号的低位），用于缓存着色实验：颜色相同的页帧映射到相同的一组cache set
+pub const FRAME_COLORS: usize = 8;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 pub const MAX_SYSCALL_NUM: usize = 500;
+/// 任务名字的最大长度（字节），超出部分被截断
+pub const TASK_NAME_LEN: usize = 16;
+/// 内核日志环形缓冲区的大小（字节），sys_dmesg最多能读到这么多最近的日志
+pub const LOG_BUFFER_SIZE: usize = 4096;
+
+/// SV39 用户地址空间为低半部分 [0, 1 << 38)，高半部分（跳板、TrapContext）只能由内核访问
+pub const USER_SPACE_END: usize = 1 << 38;
+
+/// 每个任务私有的一页TLS区域，放在用户地址空间的最顶端（启用user-tls特性时由from_elf映射）
+#[cfg(feature = "user-tls")]
+pub const TLS_BASE: usize = USER_SPACE_END - PAGE_SIZE;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+/// 映射到每个用户地址空间的只读时间页（R|U），内核每次返回用户态前写入当前的mtime和CLOCK_FREQ，
+/// 用户程序无需系统调用即可读取时间；与跳板、TrapContext共用同一个叶子页表节点
+pub const TIME_PAGE: usize = TRAP_CONTEXT - PAGE_SIZE;
+
+/// 跳板必须位于SV39地址空间的最后一页，TrapContext紧挨在它下面，二者都按页对齐；
+/// from_elf和new_kernel都假设了这样的布局
+pub const fn trampoline_layout_ok(trampoline: usize, trap_context: usize) -> bool {
+    trampoline % PAGE_SIZE == 0
+        && trap_context % PAGE_SIZE == 0
+        && trampoline == usize::MAX - PAGE_SIZE + 1
+        && trap_context.wrapping_add(PAGE_SIZE) == trampoline
+}
+
+const _: () = assert!(
+    trampoline_layout_ok(TRAMPOLINE, TRAP_CONTEXT),
+    "inconsistent TRAMPOLINE/TRAP_CONTEXT layout"
+);
+
+const _: () = assert!(
+    KERNEL_STACK_SIZE % PAGE_SIZE == 0 && KERNEL_STACK_SIZE >= KERNEL_STACK_MIN_SIZE,
+    "KERNEL_STACK_SIZE must be a page multiple of at least KERNEL_STACK_MIN_SIZE"
+);
+
+/// 相邻两个内核栈槽位之间的距离：内核栈本身加上它下方一个不映射的保护页，溢出时触发缺页而不是踩坏相邻的栈
+pub const KERNEL_STACK_STRIDE: usize = KERNEL_STACK_SIZE + PAGE_SIZE;
+
+/// 内核栈槽位从跳板向下依次排列（中间隔一个保护页），最多同时存在这么多个内核栈
+pub const MAX_KERNEL_STACKS: usize = 1024;
+
+const _: () = assert!(
+    TRAMPOLINE - MAX_KERNEL_STACKS * KERNEL_STACK_STRIDE >= MEMORY_END_MAX,
+    "kernel stacks would overlap the identity-mapped physical memory"
+);
+
 /// Return (bottom, top) of a kernel stack in kernel space.
-pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+/// slot超过MAX_KERNEL_STACKS时返回None，避免与其他内核区域重叠
+pub fn kernel_stack_position(slot: usize) -> Option<(usize, usize)> {
+    if slot >= MAX_KERNEL_STACKS {
+        return None;
+    }
+    let top = TRAMPOLINE - slot * KERNEL_STACK_STRIDE;
     let bottom = top - KERNEL_STACK_SIZE;
-    (bottom, top)
+    Some((bottom, top))
 }
 
 pub const CLOCK_FREQ: usize = 12500000;
+
+/// 需要恒等映射的MMIO区域(start, len)；与[ekernel, memory_end())重叠的部分不会被分配为普通物理页帧
+pub const MMIO: &[(usize, usize)] = &[
+    (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC in virt machine
+];
@@ -1,3 +1,6 @@
+use alloc::vec::Vec;
+use lazy_static::*;
+
 pub fn get_num_app() -> usize {
     extern "C" {
         fn _num_app();
@@ -20,3 +23,31 @@ pub fn get_app_data(app_id: usize) -> &'static [u8] {
         )
     }
 }
+
+lazy_static! {
+    /// 各应用的名字（ELF文件名去掉扩展名），由build.rs写入link_app.S
+    static ref APP_NAMES: Vec<&'static str> = {
+        extern "C" {
+            fn _app_names();
+        }
+        let mut start = _app_names as usize as *const u8;
+        let mut names = Vec::new();
+        unsafe {
+            for _ in 0..get_num_app() {
+                let mut end = start;
+                while end.read_volatile() != b'\0' {
+                    end = end.add(1);
+                }
+                let slice = core::slice::from_raw_parts(start, end as usize - start as usize);
+                names.push(core::str::from_utf8(slice).unwrap());
+                start = end.add(1);
+            }
+        }
+        names
+    };
+}
+
+/// 第app_id个应用的名字
+pub fn get_app_name(app_id: usize) -> &'static str {
+    APP_NAMES[app_id]
+}
@@ -1,7 +1,72 @@
+use crate::config::LOG_BUFFER_SIZE;
+use crate::sync::UPSafeCell;
+use core::fmt::{self, Write};
+use lazy_static::*;
 use log::{self, Level, LevelFilter, Log, Metadata, Record};
 
+/// 固定大小的日志环形缓冲区，写满后新的字节覆盖最旧的字节
+pub struct LogRing {
+    buf: [u8; LOG_BUFFER_SIZE],
+    /// 累计写入的字节数，buf[written % LOG_BUFFER_SIZE]是下一个要写的位置
+    written: usize,
+}
+
+impl LogRing {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; LOG_BUFFER_SIZE],
+            written: 0,
+        }
+    }
+    pub fn push(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.buf[self.written % LOG_BUFFER_SIZE] = byte;
+            self.written += 1;
+        }
+    }
+    /// 把最近的min(out.len(), 缓冲区中的字节数)个字节按写入顺序拷贝到out，返回拷贝的字节数
+    pub fn read_recent(&self, out: &mut [u8]) -> usize {
+        let n = out.len().min(self.written).min(LOG_BUFFER_SIZE);
+        let first = self.written - n;
+        for (i, byte) in out[..n].iter_mut().enumerate() {
+            *byte = self.buf[(first + i) % LOG_BUFFER_SIZE];
+        }
+        n
+    }
+}
+
+impl Write for LogRing {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+lazy_static! {
+    /// 内核日志的环形缓冲区，供sys_dmesg读取
+    pub static ref LOG_RING: UPSafeCell<LogRing> = unsafe { UPSafeCell::new(LogRing::new()) };
+}
+
+/// 把最近的内核日志拷贝到out，返回拷贝的字节数
+pub fn read_recent_logs(out: &mut [u8]) -> usize {
+    LOG_RING.exclusive_access().read_recent(out)
+}
+
 struct SimpleLogger;
 
+/// 输出到控制台的最高日志级别，由编译时的LOG环境变量决定；
+/// 环形缓冲区则总是记录Info及以上的日志
+fn console_level() -> LevelFilter {
+    match option_env!("LOG") {
+        Some("ERROR") => LevelFilter::Error,
+        Some("WARN") => LevelFilter::Warn,
+        Some("INFO") => LevelFilter::Info,
+        Some("DEBUG") => LevelFilter::Debug,
+        Some("TRACE") => LevelFilter::Trace,
+        _ => LevelFilter::Off,
+    }
+}
+
 impl Log for SimpleLogger {
     fn enabled(&self, _metadata: &Metadata) -> bool {
         true
@@ -10,6 +75,18 @@ impl Log for SimpleLogger {
         if !self.enabled(record.metadata()) {
             return;
         }
+        if record.level() <= Level::Info {
+            writeln!(
+                LOG_RING.exclusive_access(),
+                "[{:>5}] {}",
+                record.level(),
+                record.args()
+            )
+            .unwrap();
+        }
+        if record.level() > console_level() {
+            return;
+        }
         let color = match record.level() {
             Level::Error => 31, // Red
             Level::Warn => 93,  // BrightYellow
@@ -30,12 +107,31 @@ impl Log for SimpleLogger {
 pub fn init() {
     static LOGGER: SimpleLogger = SimpleLogger;
     log::set_logger(&LOGGER).unwrap();
-    log::set_max_level(match option_env!("LOG") {
-        Some("ERROR") => LevelFilter::Error,
-        Some("WARN") => LevelFilter::Warn,
-        Some("INFO") => LevelFilter::Info,
-        Some("DEBUG") => LevelFilter::Debug,
-        Some("TRACE") => LevelFilter::Trace,
-        _ => LevelFilter::Off,
-    });
+    log::set_max_level(console_level().max(LevelFilter::Info));
+}
+
+#[allow(unused)]
+/// 检查日志会写入环形缓冲区，且缓冲区写满后按写入顺序保留最近的字节
+pub fn log_ring_test() {
+    info!("log_ring_test marker");
+    let mut out = [0u8; 64];
+    let n = read_recent_logs(&mut out);
+    assert!(out[..n].ends_with(b"[ INFO] log_ring_test marker\n"));
+    let mut ring = LogRing::new();
+    assert_eq!(ring.read_recent(&mut out), 0);
+    for i in 0..LOG_BUFFER_SIZE + 10 {
+        ring.push(&[(i % 251) as u8]);
+    }
+    let mut all = [0u8; LOG_BUFFER_SIZE + 10];
+    assert_eq!(ring.read_recent(&mut all), LOG_BUFFER_SIZE);
+    for (i, byte) in all[..LOG_BUFFER_SIZE].iter().enumerate() {
+        assert_eq!(*byte, ((i + 10) % 251) as u8);
+    }
+    assert_eq!(ring.read_recent(&mut out[..3]), 3);
+    assert_eq!(&out[..3], &[
+        ((LOG_BUFFER_SIZE + 7) % 251) as u8,
+        ((LOG_BUFFER_SIZE + 8) % 251) as u8,
+        ((LOG_BUFFER_SIZE + 9) % 251) as u8,
+    ]);
+    info!("log_ring_test passed!");
 }
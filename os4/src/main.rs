@@ -38,14 +38,17 @@ fn clear_bss() {
     }
 }
 
+/// a0为hart id，a1为SBI传入的设备树物理地址
 #[no_mangle]
-pub fn rust_main() -> ! {
+pub fn rust_main(_hartid: usize, dtb: usize) -> ! {
     clear_bss();
     logging::init();
     println!("[kernel] Hello, world!");
-    mm::init();
+    mm::init(dtb);
     println!("[kernel] back to world!");
     mm::remap_test();
+    #[cfg(feature = "selftest")]
+    selftest();
     trap::init();
     //trap::enable_interrupt();
     trap::enable_timer_interrupt();
@@ -53,3 +56,105 @@ pub fn rust_main() -> ! {
     task::run_first_task();
     panic!("Unreachable in rust_main!");
 }
+
+/// 启动时依次运行各模块的自测；其中一些会耗尽物理页帧或创建许多任务，默认不运行
+#[cfg(feature = "selftest")]
+fn selftest() {
+    mm::audit_kernel_test();
+    mm::paging_scheme_test();
+    mm::pte_rsw_test();
+    mm::vpn_indexes_test();
+    mm::pte_flags_display_test();
+    mm::page_table_drop_test();
+    mm::page_table_nodes_test();
+    mm::translated_iovec_test();
+    mm::frame_reserve_test();
+    mm::dtb_probe_test();
+    mm::frame_tracker_owned_test();
+    mm::frame_color_test();
+    #[cfg(feature = "deterministic")]
+    mm::frame_deterministic_test();
+    mm::vm_error_test();
+    mm::mmap_batch_test();
+    mm::mmap_commit_test();
+    mm::madvise_test();
+    mm::madvise_willneed_test();
+    mm::prepare_user_access_test();
+    mm::zero_page_test();
+    mm::fault_permission_test();
+    mm::mprotect_partial_test();
+    #[cfg(feature = "access-trace")]
+    mm::access_trace_test();
+    mm::page_counts_test();
+    mm::clear_user_areas_test();
+    mm::frame_quota_test();
+    mm::virtual_quota_test();
+    mm::time_page_test();
+    mm::wx_strict_test();
+    mm::immutable_text_test();
+    mm::mapping_state_test();
+    mm::pin_test();
+    mm::mlock_test();
+    mm::lazy_zero_test();
+    mm::alias_pages_test();
+    mm::huge_page_test();
+    mm::fork_share_test();
+    mm::diff_test();
+    mm::map_phys_test();
+    mm::verify_invariants_test();
+    mm::heap_test();
+    mm::trampoline_layout_test();
+    mm::kernel_stack_test();
+    mm::areas_view_test();
+    mm::dump_maps_test();
+    mm::grows_down_test();
+    mm::mmap_stack_test();
+    mm::mmap_anywhere_test();
+    mm::null_page_test();
+    mm::remap_frames_test();
+    mm::snapshot_test();
+    mm::zero_len_test();
+    mm::munmap_split_test();
+    mm::mmap_round_up_test();
+    logging::log_ring_test();
+    trap::trap_stats_test();
+    trap::nested_trap_test();
+    mm::user_stack_overlap_test();
+    mm::shared_elf_page_test();
+    mm::munmap_owned_test();
+    mm::user_stack_size_test();
+    mm::elf_bounds_test();
+    mm::coalesce_areas_test();
+    mm::map_area_relation_test();
+    mm::copy_data_overflow_test();
+    mm::cache_coloring_test();
+    mm::copy_data_fast_path_test();
+    mm::from_elf_oom_test();
+    mm::fork_oom_test();
+    mm::fault_oom_test();
+    mm::map_anon_test();
+    #[cfg(feature = "user-tls")]
+    mm::tls_test();
+    task::yield_to_test();
+    task::sched_yield_test();
+    task::sched_stats_test();
+    task::kernel_time_test();
+    task::pause_test();
+    task::futex_test();
+    task::idle_test();
+    task::wait_status_test();
+    task::task_name_test();
+    task::ready_count_test();
+    task::exit_group_test();
+    task::drain_tasks_test();
+    task::syscall_filter_test();
+    task::signal_test();
+    task::kernel_stack_recycle_test();
+    task::relocate_kernel_stack_test();
+    task::kernel_stack_depth_test();
+    task::entry_point_test();
+    task::task_stack_size_test();
+    task::inspect_permission_test();
+    task::timed_wait_test();
+    info!("selftest passed!");
+}
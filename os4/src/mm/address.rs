@@ -1,6 +1,6 @@
 //! Implementation of physical and virtual address and page number.
 
-use super::PageTableEntry;
+use super::{PageTableEntry, Paging, PagingScheme};
 use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS};
 use core::fmt::{self, Debug, Formatter};
 
@@ -101,6 +101,10 @@ impl VirtAddr {
     pub fn aligned(&self) -> bool {
         self.page_offset() == 0
     }
+    /// self + rhs，溢出时返回None
+    pub fn checked_add(self, rhs: usize) -> Option<VirtAddr> {
+        self.0.checked_add(rhs).map(Self)
+    }
 }
 impl From<VirtAddr> for VirtPageNum {
     fn from(v: VirtAddr) -> Self {
@@ -126,6 +130,10 @@ impl PhysAddr {
     pub fn aligned(&self) -> bool {
         self.page_offset() == 0
     }
+    /// self + rhs，溢出时返回None
+    pub fn checked_add(self, rhs: usize) -> Option<PhysAddr> {
+        self.0.checked_add(rhs).map(Self)
+    }
 }
 impl From<PhysAddr> for PhysPageNum {
     fn from(v: PhysAddr) -> Self {
@@ -140,12 +148,12 @@ impl From<PhysPageNum> for PhysAddr {
 }
 
 impl VirtPageNum {
-    pub fn indexes(&self) -> [usize; 3] {
-        let mut vpn = self.0;
-        let mut idx = [0usize; 3];
-        for i in (0..3).rev() {
-            idx[i] = vpn & 511;
-            vpn >>= 9;
+    /// 将vpn拆分为各级页表的索引（SV39中为三级、每级9位），顺序与页表的下降顺序一致：
+    /// idx[0]用于根（一级）页表，最后一个用于叶子页表
+    pub fn indexes(&self) -> [usize; Paging::LEVELS] {
+        let mut idx = [0usize; Paging::LEVELS];
+        for (level, idx) in idx.iter_mut().enumerate() {
+            *idx = Paging::index(self.0, level);
         }
         idx
     }
@@ -155,13 +163,13 @@ impl PhysPageNum {
     // 获得self对应的物理frame中所有”字“的可变引用
     pub fn get_pte_array(&self) -> &'static mut [PageTableEntry] {
         let pa: PhysAddr = (*self).into();
-        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut PageTableEntry, 512) }
+        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut PageTableEntry, Paging::ENTRIES_PER_NODE) }
     }
 
     // 获得self对应的物理frame中所有”字节“的可变引用
     pub fn get_bytes_array(&self) -> &'static mut [u8] {
         let pa: PhysAddr = (*self).into();
-        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, 4096) }
+        unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, PAGE_SIZE) }
     }
 
     // 获得self对应的物理frame中恰好放在开头的且类型为”T“的物理空间的可变引用
@@ -0,0 +1,225 @@
+//! 从启动时传入的设备树（FDT）中探测物理内存的结束地址。
+//!
+//! 只解析找到memory节点所需的最小子集：根节点的#address-cells/#size-cells
+//! 以及根节点下memory节点的reg属性
+
+use crate::config::{MEMORY_END, MEMORY_END_MAX, PAGE_SIZE};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+const FDT_END: u32 = 9;
+/// 设备树头部的大小（字节）
+const FDT_HEADER_SIZE: usize = 40;
+/// 信任的设备树最大长度，防止错误的指针让内核读到任意大的区域
+const FDT_MAX_SIZE: usize = 0x10_0000;
+
+fn be32(fdt: &[u8], offset: usize) -> Option<u32> {
+    let bytes = fdt.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// 读取由cells个32位大端数拼成的数，超过64位时返回None
+fn read_cells(value: &[u8], cells: usize) -> Option<u64> {
+    if cells == 0 || cells > 2 || value.len() < cells * 4 {
+        return None;
+    }
+    let mut result = 0u64;
+    for i in 0..cells {
+        result = (result << 32) | be32(value, i * 4)? as u64;
+    }
+    Some(result)
+}
+
+/// 从strings块中读出以0结尾的属性名
+fn prop_name(fdt: &[u8], strings: usize, nameoff: usize) -> Option<&[u8]> {
+    let start = strings.checked_add(nameoff)?;
+    let rest = fdt.get(start..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    Some(&rest[..len])
+}
+
+/// 解析设备树，返回包含addr的那段物理内存区域的结束地址；
+/// 设备树格式不对或者没有内存区域包含addr时返回None
+pub fn memory_end_from_dtb(fdt: &[u8], addr: usize) -> Option<usize> {
+    if be32(fdt, 0)? != FDT_MAGIC {
+        return None;
+    }
+    let total = (be32(fdt, 4)? as usize).min(fdt.len());
+    let fdt = &fdt[..total];
+    let mut offset = be32(fdt, 8)? as usize;
+    let strings = be32(fdt, 12)? as usize;
+    // 根节点未给出时按规范取默认值
+    let mut address_cells = 2;
+    let mut size_cells = 1;
+    let mut depth = 0;
+    let mut in_memory = false;
+    let mut regions: Vec<(u64, u64)> = Vec::new();
+    loop {
+        let token = be32(fdt, offset)?;
+        offset += 4;
+        match token {
+            FDT_BEGIN_NODE => {
+                let rest = fdt.get(offset..)?;
+                let len = rest.iter().position(|&b| b == 0)?;
+                let name = &rest[..len];
+                depth += 1;
+                in_memory = depth == 2 && (name == b"memory" || name.starts_with(b"memory@"));
+                offset = (offset + len + 1 + 3) & !3;
+            }
+            FDT_END_NODE => {
+                if depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+                in_memory = false;
+            }
+            FDT_PROP => {
+                let len = be32(fdt, offset)? as usize;
+                let name = prop_name(fdt, strings, be32(fdt, offset + 4)? as usize)?;
+                let value = fdt.get(offset + 8..offset + 8 + len)?;
+                if depth == 1 && name == b"#address-cells" {
+                    address_cells = be32(value, 0)? as usize;
+                } else if depth == 1 && name == b"#size-cells" {
+                    size_cells = be32(value, 0)? as usize;
+                } else if in_memory && name == b"reg" {
+                    let entry = (address_cells + size_cells) * 4;
+                    for chunk in value.chunks_exact(entry) {
+                        let base = read_cells(chunk, address_cells)?;
+                        let size = read_cells(&chunk[address_cells * 4..], size_cells)?;
+                        regions.push((base, size));
+                    }
+                }
+                offset = (offset + 8 + len + 3) & !3;
+            }
+            FDT_NOP => {}
+            FDT_END => break,
+            _ => return None,
+        }
+    }
+    let addr = addr as u64;
+    regions
+        .iter()
+        .find(|&&(base, size)| base <= addr && addr - base < size)
+        .and_then(|&(base, size)| usize::try_from(base.checked_add(size)?).ok())
+}
+
+/// 由探测结果确定可用物理内存的结束地址：探测失败或者结果不合理（不在ekernel之上）时退回MEMORY_END，
+/// 超过MEMORY_END_MAX的部分不使用，结果按页向下对齐
+pub fn memory_bound(probed: Option<usize>, ekernel: usize) -> usize {
+    match probed {
+        Some(end) if end > ekernel => end.min(MEMORY_END_MAX) & !(PAGE_SIZE - 1),
+        _ => MEMORY_END,
+    }
+}
+
+/// 读取启动时传入的设备树（物理地址dtb，须在开启分页之前调用），返回可用物理内存的结束地址
+pub fn probe_memory_end(dtb: usize, ekernel: usize) -> usize {
+    if dtb == 0 || dtb % 4 != 0 {
+        return memory_bound(None, ekernel);
+    }
+    // 先只看头部，确认是设备树之后再按totalsize读取整棵树
+    let header = unsafe { core::slice::from_raw_parts(dtb as *const u8, FDT_HEADER_SIZE) };
+    if be32(header, 0) != Some(FDT_MAGIC) {
+        return memory_bound(None, ekernel);
+    }
+    let total = (be32(header, 4).unwrap() as usize).min(FDT_MAX_SIZE);
+    let fdt = unsafe { core::slice::from_raw_parts(dtb as *const u8, total) };
+    memory_bound(memory_end_from_dtb(fdt, ekernel), ekernel)
+}
+
+#[allow(unused)]
+/// 用构造出来的设备树检查memory节点的解析，以及按探测结果初始化的页帧分配器的上界
+pub fn dtb_probe_test() {
+    use super::frame_allocator::{FrameAllocator, StackFrameAllocator};
+    use super::PhysAddr;
+    let fdt = mock_dtb(0x9000_0000 - 0x8000_0000);
+    let ekernel = 0x8040_0000;
+    assert_eq!(memory_end_from_dtb(&fdt, ekernel), Some(0x9000_0000));
+    // 不在任何内存区域中的地址
+    assert_eq!(memory_end_from_dtb(&fdt, 0x9000_0000), None);
+    let mut bad = fdt.clone();
+    bad[0] = 0;
+    assert_eq!(memory_end_from_dtb(&bad, ekernel), None);
+    assert_eq!(memory_end_from_dtb(&fdt[..fdt.len() - 8], ekernel), None);
+    assert_eq!(memory_bound(None, ekernel), MEMORY_END);
+    assert_eq!(memory_bound(Some(0x8000_0000), ekernel), MEMORY_END);
+    assert_eq!(memory_bound(Some(usize::MAX), ekernel), MEMORY_END_MAX);
+    let end = memory_bound(memory_end_from_dtb(&fdt, ekernel), ekernel);
+    assert_eq!(end, 0x9000_0000);
+    let mut allocator = StackFrameAllocator::new();
+    allocator.init(PhysAddr::from(ekernel).ceil(), PhysAddr::from(end).floor());
+    assert_eq!(allocator.get_num_empty_frame(), (end - ekernel) / PAGE_SIZE);
+    info!("dtb_probe_test passed!");
+}
+
+/// 构造一棵只有根节点、cpus节点和memory@80000000节点的设备树，内存从0x80000000开始共size字节
+fn mock_dtb(size: u64) -> Vec<u8> {
+    fn push32(buf: &mut Vec<u8>, v: u32) {
+        buf.extend_from_slice(&v.to_be_bytes());
+    }
+    fn begin_node(buf: &mut Vec<u8>, name: &str) {
+        push32(buf, FDT_BEGIN_NODE);
+        buf.extend_from_slice(name.as_bytes());
+        buf.push(0);
+        while buf.len() % 4 != 0 {
+            buf.push(0);
+        }
+    }
+    fn prop(buf: &mut Vec<u8>, nameoff: u32, value: &[u8]) {
+        push32(buf, FDT_PROP);
+        push32(buf, value.len() as u32);
+        push32(buf, nameoff);
+        buf.extend_from_slice(value);
+        while buf.len() % 4 != 0 {
+            buf.push(0);
+        }
+    }
+    let strings = b"#address-cells\0#size-cells\0reg\0device_type\0";
+    let mut reg = Vec::new();
+    for v in [0u32, 0x8000_0000, (size >> 32) as u32, size as u32] {
+        push32(&mut reg, v);
+    }
+    let mut dt_struct = Vec::new();
+    begin_node(&mut dt_struct, "");
+    prop(&mut dt_struct, 0, &2u32.to_be_bytes());
+    prop(&mut dt_struct, 15, &2u32.to_be_bytes());
+    begin_node(&mut dt_struct, "cpus");
+    // cpus下的reg不是内存区域
+    prop(&mut dt_struct, 27, &[0; 16]);
+    push32(&mut dt_struct, FDT_END_NODE);
+    push32(&mut dt_struct, FDT_NOP);
+    begin_node(&mut dt_struct, "memory@80000000");
+    prop(&mut dt_struct, 31, b"memory\0");
+    prop(&mut dt_struct, 27, &reg);
+    push32(&mut dt_struct, FDT_END_NODE);
+    push32(&mut dt_struct, FDT_END_NODE);
+    push32(&mut dt_struct, FDT_END);
+    let off_struct = FDT_HEADER_SIZE + 16;
+    let off_strings = off_struct + dt_struct.len();
+    let total = off_strings + strings.len();
+    let mut fdt = Vec::new();
+    for v in [
+        FDT_MAGIC,
+        total as u32,
+        off_struct as u32,
+        off_strings as u32,
+        FDT_HEADER_SIZE as u32,
+        17,
+        16,
+        0,
+        strings.len() as u32,
+        dt_struct.len() as u32,
+    ] {
+        push32(&mut fdt, v);
+    }
+    // 空的内存保留表
+    fdt.extend_from_slice(&[0; 16]);
+    fdt.extend_from_slice(&dt_struct);
+    fdt.extend_from_slice(strings);
+    fdt
+}
@@ -0,0 +1,28 @@
+//! Error type of the virtual-memory subsystem.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+/// 虚拟内存相关操作（mmap/munmap/mprotect/地址转换）的失败原因
+pub enum VmError {
+    /// 起始地址或长度没有按页对齐
+    Unaligned,
+    /// 权限参数非法（含有多余的位，或者没有任何权限）
+    BadPermission,
+    /// 空闲物理页帧不足
+    OutOfFrames,
+    /// 用户地址空间中找不到足够大的空隙来放置映射，或者内核栈槽位已经用完
+    NoSpace,
+    /// 目标区间与已有映射重叠
+    Overlap,
+    /// 目标区间中存在尚未映射的页
+    NotMapped,
+    /// 地址计算溢出或超出用户可映射的地址空间（第0页不可映射）
+    Overflow,
+    /// 访问了无效的用户地址
+    Fault,
+    /// 超出了地址空间的物理页帧配额或虚拟地址配额
+    QuotaExceeded,
+    /// 目标区间中有不归调用者所有的映射（ELF段、用户栈等内核建立的区域，或map_phys借用的物理页帧）
+    NotOwned,
+    /// ELF文件格式非法（头部无法解析、魔数不对或程序头损坏）
+    BadElf,
+}
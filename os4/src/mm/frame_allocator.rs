@@ -2,26 +2,50 @@
 //! controls all the frames in the operating system.
 
 use super::{PhysAddr, PhysPageNum};
-use crate::config::MEMORY_END;
+use crate::config::{FRAME_COLORS, MMIO};
 use crate::sync::UPSafeCell;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
 use lazy_static::*;
 
 /// manage a frame which has the same lifecycle as the tracker
-/// 仅包含一个PhysPageNum
+/// 仅包含一个PhysPageNum；owned为false时只是借用该页帧，drop时不会归还给分配器
 pub struct FrameTracker {
-    pub ppn: PhysPageNum,
+    ppn: PhysPageNum,
+    owned: bool,
 }
 
 impl FrameTracker {
+    /// 接管一个刚分配出来的页帧并清零，drop时归还
     pub fn new(ppn: PhysPageNum) -> Self {
         // page cleaning
         let bytes_array = ppn.get_bytes_array();// 将这个物理frame中所有的字节都初始化为0
         for i in bytes_array {
             *i = 0;
         }
-        Self { ppn }
+        Self { ppn, owned: true }
+    }
+    /// 接管一个刚分配出来的页帧但不清零，内容是上一个使用者留下的，drop时归还
+    fn new_uninit(ppn: PhysPageNum) -> Self {
+        Self { ppn, owned: true }
+    }
+    /// 归还一个调用者确认全为零的页帧：分配器把它记为干净的，下次分配时不必再清零
+    pub fn release_clean(self) {
+        if self.owned {
+            FRAME_ALLOCATOR.exclusive_access().dealloc_clean(self.ppn);
+        }
+        core::mem::forget(self);
+    }
+    /// 按PPN重建一个不拥有页帧的tracker：不清零页面内容，drop时也不释放
+    pub fn from_ppn_unowned(ppn: PhysPageNum) -> Self {
+        Self { ppn, owned: false }
+    }
+    pub fn ppn(&self) -> PhysPageNum {
+        self.ppn
+    }
+    pub fn is_owned(&self) -> bool {
+        self.owned
     }
 }
 
@@ -33,11 +57,13 @@ impl Debug for FrameTracker {
 
 impl Drop for FrameTracker {
     fn drop(&mut self) {
-        frame_dealloc(self.ppn);
+        if self.owned {
+            frame_dealloc(self.ppn);
+        }
     }
 }
 
-trait FrameAllocator {
+pub(super) trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
     fn dealloc(&mut self, ppn: PhysPageNum);
@@ -46,47 +72,192 @@ trait FrameAllocator {
 
 /// an implementation for frame allocator
 pub struct StackFrameAllocator {
+    /// 可分配区间的起点，init之后不再变化
+    base: usize,
     current: usize,
     end: usize,
     recycled: Vec<usize>,
+    /// 被保留（不参与分配）的物理页号区间[l, r)，按l升序排列且互不相交
+    reserved: Vec<(usize, usize)>,
+    /// 已承诺给MAP_COMMIT映射、但尚未真正分配出去的页帧数
+    committed: usize,
+    /// 故障注入：为Some(n)时再成功分配n次，第n+1次frame_alloc返回None，仅供测试使用
+    fail_after: Option<usize>,
+    /// 脏位图：第i位对应页帧base + i，为1表示内容可能不为零，分配给需要清零的使用者时要先清零。
+    /// 初始时内容未知，全部为脏；归还时置为脏，只有确认全为零的页帧以干净的状态归还
+    dirty: Vec<u64>,
 }
 
 impl StackFrameAllocator {
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.base = l.0;
         self.current = l.0;
         self.end = r.0;
+        self.dirty = vec![u64::MAX; (r.0.saturating_sub(l.0) + 63) / 64];
+    }
+    /// ppn的内容是否可能不为零
+    pub fn is_dirty(&self, ppn: PhysPageNum) -> bool {
+        let idx = ppn.0 - self.base;
+        self.dirty[idx / 64] & (1 << (idx % 64)) != 0
+    }
+    fn set_dirty(&mut self, ppn: PhysPageNum, dirty: bool) {
+        let idx = ppn.0 - self.base;
+        if dirty {
+            self.dirty[idx / 64] |= 1 << (idx % 64);
+        } else {
+            self.dirty[idx / 64] &= !(1 << (idx % 64));
+        }
+    }
+    /// 与dealloc相同，但调用者保证页帧的内容全为零
+    pub fn dealloc_clean(&mut self, ppn: PhysPageNum) {
+        self.dealloc(ppn);
+        self.set_dirty(ppn, false);
+    }
+    /// 将[start_ppn, end_ppn)从空闲页帧中排除（例如恒等映射的MMIO区域），
+    /// 只有尚未分配过的部分会被保留，因此应在初始化时调用
+    pub fn reserve_region(&mut self, start_ppn: PhysPageNum, end_ppn: PhysPageNum) {
+        let mut l = start_ppn.0.max(self.current);
+        let mut r = end_ppn.0.min(self.end);
+        if l >= r {
+            return;
+        }
+        // 与已有的保留区间合并
+        self.reserved.retain(|&(rl, rr)| {
+            let overlapped = rl <= r && l <= rr;
+            if overlapped {
+                l = l.min(rl);
+                r = r.max(rr);
+            }
+            !overlapped
+        });
+        let pos = self
+            .reserved
+            .iter()
+            .position(|&(rl, _)| rl > l)
+            .unwrap_or(self.reserved.len());
+        self.reserved.insert(pos, (l, r));
+    }
+    fn is_reserved(&self, ppn: usize) -> bool {
+        self.reserved.iter().any(|&(l, r)| l <= ppn && ppn < r)
+    }
+    /// [start_ppn, end_ppn)中是否有页帧可能被分配出去（或已经分配出去），即落在可分配区间内且未被保留
+    pub fn overlaps_allocatable(&self, start_ppn: PhysPageNum, end_ppn: PhysPageNum) -> bool {
+        let mut l = start_ppn.0.max(self.base);
+        let r = end_ppn.0.min(self.end);
+        // 保留区间按l升序且互不相交，依次跳过覆盖l的区间
+        for &(rl, rr) in self.reserved.iter() {
+            if rl <= l && l < rr {
+                l = rr;
+            }
+        }
+        l < r
+    }
+    /// 从尚未分配过的区间中取出n个物理页号连续、起始页号按n对齐的页帧，返回起始页号；
+    /// 为对齐而跳过的页帧放入recycled，仍可单独分配。剩余区间不够或与保留区间重叠时返回None
+    pub fn alloc_contiguous(&mut self, n: usize) -> Option<PhysPageNum> {
+        let start = (self.current + n - 1) / n * n;
+        let end = start.checked_add(n)?;
+        if end > self.end || self.reserved.iter().any(|&(l, r)| l < end && start < r) {
+            return None;
+        }
+        for ppn in self.current..start {
+            if !self.is_reserved(ppn) {
+                self.recycled.push(ppn);
+            }
+        }
+        self.current = end;
+        Some(PhysPageNum(start))
+    }
+    /// 分配一个颜色（物理页号模FRAME_COLORS）为color的页帧：先找回收的页帧，再从尚未分配过的区间中取，
+    /// 为此跳过的页帧放入recycled，仍可分配给其他颜色；两处都没有这种颜色时退化为普通的alloc
+    pub fn alloc_colored(&mut self, color: usize) -> Option<PhysPageNum> {
+        if let Some(idx) = self.recycled.iter().rposition(|ppn| ppn % FRAME_COLORS == color) {
+            return Some(self.recycled.remove(idx).into());
+        }
+        let mut ppn = self.current;
+        while ppn < self.end && (ppn % FRAME_COLORS != color || self.is_reserved(ppn)) {
+            ppn += 1;
+        }
+        if ppn == self.end {
+            return self.alloc();
+        }
+        for skipped in self.current..ppn {
+            if !self.is_reserved(skipped) {
+                self.recycled.push(skipped);
+            }
+        }
+        self.current = ppn + 1;
+        Some(PhysPageNum(ppn))
+    }
+    /// 承诺n个页帧，之后未经承诺的分配不能再使用它们；空闲页帧不足时返回false
+    pub fn commit(&mut self, n: usize) -> bool {
+        if self.get_num_empty_frame() - self.committed < n {
+            return false;
+        }
+        self.committed += n;
+        true
+    }
+    pub fn uncommit(&mut self, n: usize) {
+        assert!(n <= self.committed, "uncommit {} frames but only {} committed", n, self.committed);
+        self.committed -= n;
     }
 }
 impl FrameAllocator for StackFrameAllocator {
     fn new() -> Self {
         Self {
+            base: 0,
             current: 0,
             end: 0,
             recycled: Vec::new(),
+            reserved: Vec::new(),
+            committed: 0,
+            fail_after: None,
+            dirty: Vec::new(),
         }
     }
     fn alloc(&mut self) -> Option<PhysPageNum> {
+        // 跳过保留区间
+        for &(l, r) in self.reserved.iter() {
+            if l <= self.current && self.current < r {
+                self.current = r;
+            }
+        }
+        #[cfg(not(feature = "deterministic"))]
         if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else if self.current == self.end {
-            None
-        } else {
+            return Some(ppn.into());
+        }
+        if self.current != self.end {
             self.current += 1;
-            Some((self.current - 1).into())
+            return Some((self.current - 1).into());
         }
+        // deterministic：新页帧用完之后才从回收的页帧中取编号最小的一个
+        #[cfg(feature = "deterministic")]
+        if let Some((idx, _)) = self.recycled.iter().enumerate().min_by_key(|(_, ppn)| **ppn) {
+            return Some(self.recycled.swap_remove(idx).into());
+        }
+        None
     }
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
         // validity check
-        if ppn >= self.current || self.recycled.iter().any(|v| *v == ppn) {
+        if ppn >= self.current
+            || self.is_reserved(ppn)
+            || self.recycled.iter().any(|v| *v == ppn)
+        {
             panic!("Frame ppn={:#x} has not been allocated!", ppn);
         }
         // recycle
         self.recycled.push(ppn);
+        self.set_dirty(PhysPageNum(ppn), true);
     }
 
     fn get_num_empty_frame(&self) -> usize {
-        self.recycled.len() + (self.end - self.current)
+        let reserved: usize = self
+            .reserved
+            .iter()
+            .map(|&(l, r)| r.min(self.end).saturating_sub(l.max(self.current)))
+            .sum();
+        self.recycled.len() + (self.end - self.current) - reserved
     }
 }
 
@@ -96,25 +267,144 @@ lazy_static! {
     /// frame allocator instance through lazy_static!
     pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
         unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
+    /// 全局共享的只读零页：尚未写过的匿名页面都映射到这里，第一次写入时再复制出私有页帧
+    pub static ref ZERO_FRAME: FrameTracker = frame_alloc().unwrap();
+    /// 全局共享的时间页，以只读方式映射在每个用户地址空间的TIME_PAGE处
+    pub static ref TIME_FRAME: FrameTracker = frame_alloc().unwrap();
 }
 
-/// initiate the frame allocator using `ekernel` and `MEMORY_END`
-pub fn init_frame_allocator() {
+/// initiate the frame allocator using `ekernel` and the probed `memory_end`
+pub fn init_frame_allocator(memory_end: usize) {
     extern "C" {
         fn ekernel();
     }
     FRAME_ALLOCATOR.exclusive_access().init(
         PhysAddr::from(ekernel as usize).ceil(),
-        PhysAddr::from(MEMORY_END).floor(),
+        PhysAddr::from(memory_end).floor(),
     );
+    for &(start, len) in MMIO {
+        reserve_region(PhysAddr::from(start).floor(), PhysAddr::from(start + len).ceil());
+    }
 }
 
-/// allocate a frame
-pub fn frame_alloc() -> Option<FrameTracker> {
+/// 物理内存的结束地址，即可分配区间的上界；内核地址空间也只恒等映射到这里
+pub fn memory_end() -> usize {
+    PhysAddr::from(PhysPageNum(FRAME_ALLOCATOR.exclusive_access().end)).0
+}
+
+/// 将[start_ppn, end_ppn)从空闲页帧中排除
+pub fn reserve_region(start_ppn: PhysPageNum, end_ppn: PhysPageNum) {
     FRAME_ALLOCATOR
         .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+        .reserve_region(start_ppn, end_ppn);
+}
+
+/// [start_ppn, end_ppn)是否与可分配的物理页帧重叠；MMIO等被保留或不在可分配区间内的页帧不算
+pub fn frames_overlap_allocatable(start_ppn: PhysPageNum, end_ppn: PhysPageNum) -> bool {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .overlaps_allocatable(start_ppn, end_ppn)
+}
+
+/// 分配一个页帧，不清零；color不为None时尽量分配这种颜色的页帧。已承诺出去的页帧不会被分配
+fn alloc_uncommitted(allocator: &mut StackFrameAllocator, color: Option<usize>) -> Option<PhysPageNum> {
+    if let Some(n) = allocator.fail_after {
+        if n == 0 {
+            allocator.fail_after = None;
+            return None;
+        }
+        allocator.fail_after = Some(n - 1);
+    }
+    if allocator.get_num_empty_frame() <= allocator.committed {
+        return None;
+    }
+    match color {
+        Some(color) => allocator.alloc_colored(color),
+        None => allocator.alloc(),
+    }
+}
+
+/// 把刚分配的ppn交给需要全零页帧的使用者：只有脏的页帧才清零
+fn zeroed_tracker(allocator: &StackFrameAllocator, ppn: PhysPageNum) -> FrameTracker {
+    if allocator.is_dirty(ppn) {
+        FrameTracker::new(ppn)
+    } else {
+        FrameTracker::new_uninit(ppn)
+    }
+}
+
+/// allocate a frame
+/// 返回的页帧内容全为零（用户页面、页表节点等都依赖这一点）；已承诺出去的页帧不会被分配
+pub fn frame_alloc() -> Option<FrameTracker> {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    let ppn = alloc_uncommitted(&mut allocator, None)?;
+    Some(zeroed_tracker(&allocator, ppn))
+}
+
+/// 分配一个颜色为color % FRAME_COLORS的页帧，内容全为零；这种颜色的页帧用完时返回任意颜色的页帧
+pub fn frame_alloc_colored(color: usize) -> Option<FrameTracker> {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    let ppn = alloc_uncommitted(&mut allocator, Some(color % FRAME_COLORS))?;
+    Some(zeroed_tracker(&allocator, ppn))
+}
+
+/// 分配一个内容未定义的页帧，不做清零；只能用于内核自己使用、或者马上会被整页覆盖的页帧，
+/// 绝不能不经覆盖就映射给用户
+pub fn frame_alloc_uninit() -> Option<FrameTracker> {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    alloc_uncommitted(&mut allocator, None).map(FrameTracker::new_uninit)
+}
+
+/// 分配n个物理页号连续且起始页号按n对齐的页帧（用于大页），内容全为零；
+/// 没有这样的一段空闲页帧或者会用到已承诺出去的页帧时返回None
+pub fn frame_alloc_contiguous(n: usize) -> Option<Vec<FrameTracker>> {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    if allocator.get_num_empty_frame() < allocator.committed + n {
+        return None;
+    }
+    let start = allocator.alloc_contiguous(n)?;
+    Some(
+        (start.0..start.0 + n)
+            .map(|ppn| zeroed_tracker(&allocator, PhysPageNum(ppn)))
+            .collect(),
+    )
+}
+
+/// 消耗一个之前承诺的页帧进行分配，一定成功，返回的页帧内容全为零
+pub fn frame_alloc_committed() -> FrameTracker {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    allocator.uncommit(1);
+    let ppn = allocator.alloc().unwrap();
+    zeroed_tracker(&allocator, ppn)
+}
+
+#[allow(unused)]
+/// 仅供测试：ppn的内容是否可能不为零
+pub fn frame_is_dirty(ppn: PhysPageNum) -> bool {
+    FRAME_ALLOCATOR.exclusive_access().is_dirty(ppn)
+}
+
+/// 为MAP_COMMIT映射承诺n个页帧，空闲页帧不足时返回false
+pub fn frame_commit(n: usize) -> bool {
+    FRAME_ALLOCATOR.exclusive_access().commit(n)
+}
+
+/// 撤销n个尚未使用的承诺
+pub fn frame_uncommit(n: usize) {
+    FRAME_ALLOCATOR.exclusive_access().uncommit(n);
+}
+
+#[allow(unused)]
+/// 仅供测试：之后的第n+1次frame_alloc无论是否还有空闲页帧都返回None，触发后自动解除；
+/// frame_alloc_committed不受影响
+pub fn fail_after(n: usize) {
+    FRAME_ALLOCATOR.exclusive_access().fail_after = Some(n);
+}
+
+#[allow(unused)]
+/// 解除尚未触发的故障注入，每个使用fail_after的测试结束时都应调用
+pub fn clear_fail_after() {
+    FRAME_ALLOCATOR.exclusive_access().fail_after = None;
 }
 
 /// deallocate a frame
@@ -122,11 +412,10 @@ fn frame_dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
 }
 
-/// 计算先有为分配的内存容量
+/// 计算先有为分配的内存容量，已承诺出去的页帧不计在内
 pub fn get_num_empty_frame() -> usize {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .get_num_empty_frame()
+    let allocator = FRAME_ALLOCATOR.exclusive_access();
+    allocator.get_num_empty_frame() - allocator.committed
 }
 
 #[allow(unused)]
@@ -146,4 +435,134 @@ pub fn frame_allocator_test() {
     }
     drop(v);
     info!("frame_allocator_test passed!");
-}
\ No newline at end of file
+}
+#[allow(unused)]
+/// 检查保留区间内的物理页帧不会被分配出去
+pub fn frame_reserve_test() {
+    let mut allocator = StackFrameAllocator::new();
+    allocator.init(PhysPageNum(0x1000), PhysPageNum(0x1100));
+    allocator.reserve_region(PhysPageNum(0x1010), PhysPageNum(0x1020));
+    allocator.reserve_region(PhysPageNum(0x1018), PhysPageNum(0x1030));
+    allocator.reserve_region(PhysPageNum(0x10f0), PhysPageNum(0x2000));
+    assert_eq!(allocator.get_num_empty_frame(), 0x100 - 0x20 - 0x10);
+    let mut count = 0;
+    while let Some(ppn) = allocator.alloc() {
+        assert!(!(0x1010..0x1030).contains(&ppn.0) && ppn.0 < 0x10f0);
+        count += 1;
+    }
+    assert_eq!(count, 0x100 - 0x20 - 0x10);
+    assert_eq!(allocator.get_num_empty_frame(), 0);
+    assert!(!allocator.overlaps_allocatable(PhysPageNum(0x1010), PhysPageNum(0x1030)));
+    assert!(!allocator.overlaps_allocatable(PhysPageNum(0x10f0), PhysPageNum(0x3000)));
+    assert!(!allocator.overlaps_allocatable(PhysPageNum(0x800), PhysPageNum(0x1000)));
+    assert!(allocator.overlaps_allocatable(PhysPageNum(0x1010), PhysPageNum(0x1031)));
+    assert!(allocator.overlaps_allocatable(PhysPageNum(0xfff), PhysPageNum(0x1001)));
+    info!("frame_reserve_test passed!");
+}
+#[allow(unused)]
+#[cfg(feature = "deterministic")]
+/// deterministic特性下页帧按物理页号严格递增地分配，回收的页帧只在新页帧用完后才被复用
+pub fn frame_deterministic_test() {
+    let mut allocator = StackFrameAllocator::new();
+    allocator.init(PhysPageNum(0x1000), PhysPageNum(0x1008));
+    let a = allocator.alloc().unwrap();
+    let b = allocator.alloc().unwrap();
+    assert_eq!(b.0, a.0 + 1);
+    allocator.dealloc(a);
+    for i in 0..6 {
+        assert_eq!(allocator.alloc().unwrap().0, b.0 + 1 + i);
+    }
+    allocator.dealloc(PhysPageNum(0x1005));
+    allocator.dealloc(PhysPageNum(0x1003));
+    assert_eq!(allocator.alloc().unwrap().0, 0x1000);
+    assert_eq!(allocator.alloc().unwrap().0, 0x1003);
+    assert_eq!(allocator.alloc().unwrap().0, 0x1005);
+    assert!(allocator.alloc().is_none());
+    info!("frame_deterministic_test passed!");
+}
+#[cfg(feature = "frame-walk")]
+/// 逐个分配所有空闲页帧直到耗尽：检查每个页帧都已清零、都在[ekernel, memory_end)内且未被保留，
+/// 写入由PPN生成的数据后再全部读回比较，最后全部归还并把分配器恢复到刚初始化时的状态。
+/// 必须在分配任何其他页帧之前调用；每个页帧要在内核堆上占用一个FrameTracker，
+/// 并且dealloc的重复释放检查让归还的总开销是页帧数的平方，所以只在打开frame-walk时运行
+pub fn frame_walk_test() {
+    extern "C" {
+        fn ekernel();
+    }
+    let total = get_num_empty_frame();
+    // 记录页帧的Vec和归还时增长的回收栈都在内核堆上，物理内存太大时放不下
+    let heap_needed = total * (core::mem::size_of::<FrameTracker>() + 2 * core::mem::size_of::<usize>());
+    if heap_needed > crate::config::KERNEL_HEAP_SIZE / 2 {
+        info!("frame_walk_test skipped: {} frames need {:#x} bytes of kernel heap", total, heap_needed);
+        return;
+    }
+    let first = PhysAddr::from(ekernel as usize).ceil();
+    let end = PhysAddr::from(memory_end()).floor();
+    let pattern = |ppn: PhysPageNum, i: usize| ppn.0.wrapping_mul(0x9e37_79b9).wrapping_add(i);
+    let mut frames: Vec<FrameTracker> = Vec::with_capacity(total);
+    while let Some(frame) = frame_alloc() {
+        let ppn = frame.ppn();
+        assert!(first <= ppn && ppn < end, "frame {:?} is outside [{:?}, {:?})", ppn, first, end);
+        assert!(!FRAME_ALLOCATOR.exclusive_access().is_reserved(ppn.0));
+        let words = ppn.get_mut::<[usize; 512]>();
+        assert!(words.iter().all(|&word| word == 0), "frame {:?} is not zeroed", ppn);
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = pattern(ppn, i);
+        }
+        frames.push(frame);
+    }
+    assert_eq!(frames.len(), total);
+    assert_eq!(get_num_empty_frame(), 0);
+    // 全部写完之后再读回，两个页帧重叠时先写的内容会被覆盖
+    for frame in frames.iter() {
+        let ppn = frame.ppn();
+        let words = ppn.get_mut::<[usize; 512]>();
+        assert!(words.iter().enumerate().all(|(i, &word)| word == pattern(ppn, i)));
+    }
+    // 从高到低归还
+    while frames.pop().is_some() {}
+    assert_eq!(get_num_empty_frame(), total);
+    // 所有页帧都已回收，丢弃回收栈让之后的分配仍从base开始按顺序进行
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    allocator.recycled = Vec::new();
+    allocator.current = allocator.base;
+    drop(allocator);
+    assert_eq!(get_num_empty_frame(), total);
+    info!("frame_walk_test passed! {} frames", total);
+}
+#[allow(unused)]
+/// 检查frame_alloc_colored分配的页帧颜色与请求的一致，归还的页帧可以按颜色再次分配
+pub fn frame_color_test() {
+    let before = get_num_empty_frame();
+    let colors = [3, 0, 7, 3, 5, FRAME_COLORS + 1];
+    let frames: Vec<FrameTracker> = colors.iter().map(|&color| frame_alloc_colored(color).unwrap()).collect();
+    for (frame, &color) in frames.iter().zip(colors.iter()) {
+        assert_eq!(frame.ppn().0 % FRAME_COLORS, color % FRAME_COLORS);
+        assert!(frame.ppn().get_bytes_array().iter().all(|byte| *byte == 0));
+    }
+    // 为着色跳过的页帧仍然空闲
+    assert_eq!(get_num_empty_frame(), before - colors.len());
+    let ppn = frames[4].ppn();
+    drop(frames);
+    assert_eq!(frame_alloc_colored(5).unwrap().ppn(), ppn);
+    assert_eq!(get_num_empty_frame(), before);
+    info!("frame_color_test passed!");
+}
+#[allow(unused)]
+/// 拥有页帧的tracker在drop时归还页帧，不拥有的则不会
+pub fn frame_tracker_owned_test() {
+    let before = get_num_empty_frame();
+    let frame = frame_alloc().unwrap();
+    let ppn = frame.ppn();
+    assert!(frame.is_owned());
+    assert_eq!(get_num_empty_frame(), before - 1);
+    ppn.get_bytes_array()[0] = 0x5a;
+    let alias = FrameTracker::from_ppn_unowned(ppn);
+    assert!(!alias.is_owned() && alias.ppn() == ppn);
+    drop(alias);
+    assert_eq!(get_num_empty_frame(), before - 1);
+    assert_eq!(ppn.get_bytes_array()[0], 0x5a);
+    drop(frame);
+    assert_eq!(get_num_empty_frame(), before);
+    info!("frame_tracker_owned_test passed!");
+}
@@ -1,15 +1,28 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
 use super::{
-    frame_alloc, get_num_empty_frame, vpn_range_is_unused, vpn_range_is_used, FrameTracker,
+    clear_fail_after, copy_to_user, fail_after, frame_is_dirty, reserve_region, frame_alloc, frame_alloc_committed, frame_alloc_uninit, frames_overlap_allocatable, frame_commit, frame_uncommit, get_num_empty_frame,
+    memory_end, translated_byte_buffer, vpn_range_is_unused, vpn_range_is_used, FrameTracker, TIME_FRAME,
+    ZERO_FRAME,
 };
-use super::{PTEFlags, PageTable, PageTableEntry};
-use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
-use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
-use alloc::collections::BTreeMap;
+use super::{frame_alloc_colored, frame_alloc_contiguous, PTEFlags, PTESoftFlags, PageTable, PageTableEntry, HUGE_PAGES};
+use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum, VmError};
+use super::{Paging, PagingScheme, StepByOne, VPNRange};
+use crate::config::{
+    kernel_stack_position, trampoline_layout_ok, MAP_STACK_MAX_SIZE, MAX_KERNEL_STACKS, MEMORY_END_MAX,
+    ELF_TEXT_IMMUTABLE, FRAME_COLORS, MMAP_WX_STRICT, PAGE_SIZE,
+    TIME_PAGE, TRAMPOLINE, TRAP_CONTEXT, USER_SPACE_END, USER_STACK_SIZE,
+};
+#[cfg(feature = "user-tls")]
+use crate::config::TLS_BASE;
+use crate::timer::get_time;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt::Write;
 use lazy_static::*;
 use riscv::register::satp;
 use spin::Mutex;
@@ -37,16 +50,48 @@ lazy_static! {
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// 各逻辑段最多能持有的物理页帧总数，默认不限制
+    frame_quota: usize,
+    /// 各逻辑段虚拟地址范围总大小的上限（字节），默认不限制
+    virtual_quota: usize,
+    /// 为true时拒绝不带MAP_JIT的W|X映射
+    wx_strict: bool,
+    /// 被钉住、不能被reclaim_one_page回收的页面
+    pinned: BTreeSet<VirtPageNum>,
+    /// 被mlock锁定的页面及其锁定次数，锁定的页面保持驻留，不会被reclaim_one_page或MADV_DONTNEED回收
+    locked: BTreeMap<VirtPageNum, usize>,
+    /// 时钟算法的指针，指向下一个待检查的驻留页面
+    clock_hand: usize,
+    /// 访问跟踪中被去掉U位、尚未被访问的页面
+    #[cfg(feature = "access-trace")]
+    traced: BTreeSet<VirtPageNum>,
+    /// 访问跟踪记录到的页面，按第一次访问的顺序排列
+    #[cfg(feature = "access-trace")]
+    access_trace: Vec<VirtPageNum>,
 }
 
 impl MemorySet {
     /// 返回一个初始化后的MemorySet,其中包含一个仅有根节点的PageTable
     /// 创建MemorySet的过程中没有分配用于存储普通数据的物理页，只分配了存储PageTable的物理页
     pub fn new_bare() -> Self {
-        Self {
-            page_table: PageTable::new(), //此处为PageTable分配了一个物理frame，用于存储根页表
+        Self::try_new_bare().expect("no frame left for a new address space")
+    }
+    /// new_bare的可失败版本，没有空闲页帧存放根页表时返回Err(VmError::OutOfFrames)
+    pub fn try_new_bare() -> Result<Self, VmError> {
+        Ok(Self {
+            page_table: PageTable::try_new()?, //此处为PageTable分配了一个物理frame，用于存储根页表
             areas: Vec::new(),
-        }
+            frame_quota: usize::MAX,
+            virtual_quota: usize::MAX,
+            wx_strict: MMAP_WX_STRICT,
+            pinned: BTreeSet::new(),
+            locked: BTreeMap::new(),
+            clock_hand: 0,
+            #[cfg(feature = "access-trace")]
+            traced: BTreeSet::new(),
+            #[cfg(feature = "access-trace")]
+            access_trace: Vec::new(),
+        })
     }
     /// 获得self中的页表对应的satp字段（对应一个CSR寄存器）的值
     pub fn token(&self) -> usize {
@@ -54,36 +99,149 @@ impl MemorySet {
     }
     /// Assume that no conflicts.
     /// 将self.vpn_range中的所有vpn都分配一个对应的物理内存中的frame，并为他们在页表中创建页表项；
-    /// 无需存入实际
+    /// 无需存入实际。第0页不能通过这里映射，区间包含第0页时返回Err(VmError::Overflow)
     pub fn insert_framed_area(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
         permission: MapPermission,
-    ) {
-        self.push(
+    ) -> Result<(), VmError> {
+        if start_va.floor().0 == 0 && end_va.ceil().0 != 0 {
+            return Err(VmError::Overflow);
+        }
+        self.try_push(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
-        );
+        )
+    }
+    /// 移除起始页号为start_vpn的逻辑段并回收其物理页帧，没有这样的逻辑段时什么也不做
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some(idx) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
+        {
+            let mut area = self.areas.remove(idx);
+            area.unmap(&mut self.page_table);
+            self.flush_tlb();
+        }
+    }
+    /// 插入一个匿名逻辑段，但不分配物理页帧：可读的页面先映射到共享的只读零页，
+    /// 第一次写入时由缺页异常复制出私有页帧（写时复制）
+    pub fn insert_zero_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Framed, permission);
+        map_area.map_zero(&mut self.page_table);
+        self.areas.push(map_area);
+    }
+    /// 与insert_zero_area相同，但[start_va, end_va)中的每个大页先尝试立即映射到一段清零的连续页帧；
+    /// 分配不到连续页帧的部分退回到映射零页的普通页面。start_va和end_va都必须按大页对齐
+    pub fn insert_huge_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Framed, permission);
+        let (start, end) = (map_area.vpn_range.get_start().0, map_area.vpn_range.get_end().0);
+        for huge_start in (start..end).step_by(HUGE_PAGES) {
+            if map_area.try_map_huge(&mut self.page_table, VirtPageNum(huge_start)) {
+                continue;
+            }
+            if permission.contains(MapPermission::R) {
+                for vpn in huge_start..huge_start + HUGE_PAGES {
+                    map_area.map_zero_one(&mut self.page_table, VirtPageNum(vpn));
+                }
+            }
+        }
+        self.areas.push(map_area);
     }
     /// 将self.vpn_range中的所有vpn都分配一个对应的物理内存中的frame，并为他们在页表中创建页表项；
-    /// 并将data中的数据都推入分配的物理内存中
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&mut self.page_table);
+    /// 并将data中的数据都推入分配的物理内存中；data必须放得下
+    fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) {
+        self.try_push(map_area, data)
+            .expect("push: data is longer than the area");
+    }
+    /// push的可失败版本：data比逻辑段长时解除映射并返回Err(VmError::Overflow)
+    fn try_push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> Result<(), VmError> {
+        map_area.try_map(&mut self.page_table)?;
         if let Some(data) = data {
-            map_area.copy_data(&mut self.page_table, data);
+            if let Err(err) = map_area.copy_data(data) {
+                map_area.unmap(&mut self.page_table);
+                return Err(err);
+            }
         }
         self.areas.push(map_area);
+        Ok(())
+    }
+    /// ELF中两个段共用vpn这一页（areas[idx]的最后一页）时，让这一页的权限变为原权限与perm的并集：
+    /// 权限已经包含perm时不做任何事；否则把这一页切成单独的逻辑段并更新页表项。返回这一页所在逻辑段的下标
+    fn widen_page_permission(&mut self, idx: usize, vpn: VirtPageNum, perm: MapPermission) -> usize {
+        let union = self.areas[idx].map_perm | perm;
+        if union == self.areas[idx].map_perm {
+            return idx;
+        }
+        let mut idx = idx;
+        if vpn > self.areas[idx].vpn_range.get_start() {
+            let page = self.areas[idx].split_off(vpn);
+            self.areas.insert(idx + 1, page);
+            idx += 1;
+        }
+        self.areas[idx].map_perm = union;
+        self.page_table
+            .set_flags(vpn, PTEFlags::from_bits(union.bits).unwrap())
+            .unwrap();
+        idx
+    }
+    /// 把首尾相接、用途与权限都相同的Framed逻辑段合并为一个，页帧随之转移；
+    /// 向下增长或带页帧承诺的逻辑段保持独立
+    pub fn coalesce_areas(&mut self) {
+        let mergeable = |a: &MapArea, b: &MapArea| {
+            a.map_type == MapType::Framed
+                && b.map_type == MapType::Framed
+                && a.kind == b.kind
+                && a.same_perm(b)
+                && a.grows_down.is_none()
+                && b.grows_down.is_none()
+                && !a.committed
+                && !b.committed
+                && a.immutable == b.immutable
+                && a.is_adjacent_to(b)
+        };
+        while let Some((i, j)) = (0..self.areas.len())
+            .flat_map(|i| (i + 1..self.areas.len()).map(move |j| (i, j)))
+            .find(|&(i, j)| mergeable(&self.areas[i], &self.areas[j]))
+        {
+            let mut other = self.areas.remove(j);
+            let area = &mut self.areas[i];
+            area.vpn_range = VPNRange::new(
+                area.vpn_range.get_start().min(other.vpn_range.get_start()),
+                area.vpn_range.get_end().max(other.vpn_range.get_end()),
+            );
+            area.data_frames.append(&mut other.data_frames);
+        }
     }
     /// Mention that trampoline is not collected by areas.
     /// 将跳板放入PageTable(self)中，建立与PhysAddr:strampoline的页表项，
     /// strampoline是在将OS载入内存时“.text.trampoline”这部分数据的起始
-    fn map_trampoline(&mut self) {
-        self.page_table.map(
+    fn map_trampoline(&mut self) -> Result<(), VmError> {
+        self.page_table.try_map(
             VirtAddr::from(TRAMPOLINE).into(),
             PhysAddr::from(strampoline as usize).into(),
             PTEFlags::R | PTEFlags::X,
-        );
+        )
+    }
+    /// 把共享的时间页以只读方式映射到TIME_PAGE，只有用户地址空间需要
+    fn map_time_page(&mut self) -> Result<(), VmError> {
+        self.page_table.try_map(
+            VirtAddr::from(TIME_PAGE).into(),
+            TIME_FRAME.ppn(),
+            PTEFlags::R | PTEFlags::U,
+        )
     }
     /// Without kernel stacks.
     /// 将OS 对应的“.text, .rodata, .data, .bss”纳入内存管理，
@@ -91,7 +249,7 @@ impl MemorySet {
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.map_trampoline().unwrap();
         // map kernel sections
         info!(".text [{:#x}, {:#x})", stext as usize, etext as usize);
         info!(".rodata [{:#x}, {:#x})", srodata as usize, erodata as usize);
@@ -144,7 +302,7 @@ impl MemorySet {
         memory_set.push(
             MapArea::new(
                 (ekernel as usize).into(),
-                MEMORY_END.into(),
+                memory_end().into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
             ),
@@ -155,26 +313,101 @@ impl MemorySet {
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
     /// 为单个app创建页表，同时将app的各个逻辑段（.text, .rodata, .data, .bss）放入新的物理内存中，并为这个app创建user stack (4.6)
+    #[allow(unused)]
     pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
-        // map trampoline
-        memory_set.map_trampoline();
-        // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap(); // 用crate xmas_elf 来解析传入的应用 ELF 数据并可以轻松取出各个部分 （4.6）
+        Self::try_from_elf(elf_data, USER_STACK_SIZE)
+            .unwrap_or_else(|err| panic!("from_elf: failed to build user space: {:?}", err))
+    }
+    /// from_elf的可失败版本，用户栈大小为user_stack_size字节（必须按页对齐且非零）。
+    /// 用户栈与已放入的逻辑段重叠时返回Err(VmError::Overlap)，而不是重复映射；
+    /// 空闲物理页帧不足以放下用户栈时返回Err(VmError::OutOfFrames)；
+    /// 某个LOAD段的文件内容超出elf_data时返回Err(VmError::Overflow)；
+    /// elf_data无法解析、魔数不对或程序头损坏时返回Err(VmError::BadElf)；
+    /// 中途任何一次页帧分配失败都返回Err(VmError::OutOfFrames)，已分配的页帧全部归还。
+    /// 相邻的两个LOAD段可以在前一段末尾不满一页处共用一页：该页只分配一个页帧，两段的数据都写在各自的位置上，
+    /// 权限取两段权限的并集（这一页因此可能比某一段要求的更宽松）。两段的字节范围重叠、共用的不止一页，
+    /// 或者段与更早的段相交时返回Err(VmError::Overlap)
+    pub fn try_from_elf(
+        elf_data: &[u8],
+        user_stack_size: usize,
+    ) -> Result<(Self, usize, usize), VmError> {
+        if user_stack_size == 0 || user_stack_size % PAGE_SIZE != 0 {
+            return Err(VmError::Unaligned);
+        }
+        if get_num_empty_frame() < user_stack_size / PAGE_SIZE {
+            return Err(VmError::OutOfFrames);
+        }
+        // 用crate xmas_elf 来解析传入的应用 ELF 数据并可以轻松取出各个部分 （4.6）
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(|err| {
+            error!("invalid elf: {}", err);
+            VmError::BadElf
+        })?;
         let elf_header = elf.header;
 
         // 取出魔数来判断这个ELF文件是否合法
         let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            error!("invalid elf magic: {:x?}", magic);
+            return Err(VmError::BadElf);
+        }
+        // xmas_elf读取程序头时不检查边界，程序头表必须完整地落在elf_data内
+        let pt2 = &elf_header.pt2;
+        let ph_table_end = (pt2.ph_count() as usize)
+            .checked_mul(pt2.ph_entry_size() as usize)
+            .and_then(|size| (pt2.ph_offset() as usize).checked_add(size));
+        if pt2.ph_entry_size() < 56 || ph_table_end.map_or(true, |end| end > elf_data.len()) {
+            error!("program header table is outside the {}-byte ELF", elf_data.len());
+            return Err(VmError::BadElf);
+        }
+        let mut memory_set = Self::try_new_bare()?;
+        // map trampoline
+        memory_set.map_trampoline()?;
+        memory_set.map_time_page()?;
+        // map program headers of elf, with U flag
 
         let ph_count = elf_header.pt2.ph_count(); // pt2中存储了elf文件的第19行到29行的内容；ph_count==ProgramHeaderCount
         let mut max_end_vpn = VirtPageNum(0);
+        // 上一个LOAD段最后一页所在逻辑段的下标及该段的结束地址，用于发现与之共用一页的下一个段
+        let mut last_elf_area: Option<usize> = None;
+        let mut last_end_va = VirtAddr(0);
         // 在for循环中将所有类型为“LOAD”的programhead放入物理内存的应用部分，并这部分物理空间构建的页表项
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+            let ph = elf.program_header(i).map_err(|_| VmError::BadElf)?;
+            if ph.get_type().map_err(|_| VmError::BadElf)? == xmas_elf::program::Type::Load {
+                let offset = ph.offset() as usize;
+                let file_end = offset
+                    .checked_add(ph.file_size() as usize)
+                    .filter(|&end| end <= elf_data.len());
+                let file_end = match file_end {
+                    Some(end) => end,
+                    None => {
+                        error!(
+                            "program header {}: [{:#x}, +{:#x}) is outside the {}-byte ELF",
+                            i,
+                            ph.offset(),
+                            ph.file_size(),
+                            elf_data.len()
+                        );
+                        return Err(VmError::Overflow);
+                    }
+                };
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+                // 段必须完整地落在用户地址空间内，畸形的mem_size不能让结束地址回绕
+                let end_va = match start_va
+                    .checked_add(ph.mem_size() as usize)
+                    .filter(|end| end.0 <= USER_SPACE_END)
+                {
+                    Some(end) => end,
+                    None => {
+                        error!(
+                            "program header {}: [{:#x}, +{:#x}) is outside the user address space",
+                            i,
+                            ph.virtual_addr(),
+                            ph.mem_size()
+                        );
+                        return Err(VmError::Overflow);
+                    }
+                };
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
                 if ph_flags.is_read() {
@@ -186,46 +419,324 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                let mut data = &elf.input[offset..file_end];
+                let mut data_offset = start_va.page_offset();
+                let mut start_vpn = start_va.floor();
+                let end_vpn = end_va.ceil();
+                max_end_vpn = end_vpn;
+                // 与上一个段共用首页时不再分配新的页帧：这一段在该页上的数据直接写入已有页帧
+                if let Some(idx) = last_elf_area {
+                    let prev = &memory_set.areas[idx];
+                    if prev.contains(start_vpn) {
+                        if start_vpn.0 + 1 != prev.vpn_range.get_end().0 || start_va < last_end_va {
+                            error!(
+                                "program header {}: [{:#x}, +{:#x}) overlaps the previous segment ending at {:#x}",
+                                i,
+                                ph.virtual_addr(),
+                                ph.mem_size(),
+                                last_end_va.0
+                            );
+                            return Err(VmError::Overlap);
+                        }
+                        let idx = memory_set.widen_page_permission(idx, start_vpn, map_perm);
+                        let area = &memory_set.areas[idx];
+                        let head = data.len().min(PAGE_SIZE - data_offset);
+                        let area_offset = (start_vpn.0 - area.vpn_range.get_start().0) * PAGE_SIZE;
+                        area.copy_data_at(area_offset + data_offset, &data[..head])?;
+                        last_elf_area = Some(idx);
+                        data = &data[head..];
+                        data_offset = 0;
+                        start_vpn.step();
+                    }
+                }
+                last_end_va = end_va;
+                if start_vpn >= end_vpn {
+                    // 整个段都落在共用的页上；file_size大于mem_size时剩余的数据放不下
+                    if !data.is_empty() {
+                        return Err(VmError::Overflow);
+                    }
+                    continue;
+                }
+                if memory_set.areas.iter().any(|area| area.overlaps_range(start_vpn.0, end_vpn.0)) {
+                    error!(
+                        "program header {}: [{:#x}, +{:#x}) overlaps an earlier segment",
+                        i,
+                        ph.virtual_addr(),
+                        ph.mem_size()
+                    );
+                    return Err(VmError::Overlap);
+                }
+                let map_area = MapArea::new(start_vpn.into(), end_va, MapType::Framed, map_perm)
+                    .with_kind(AreaKind::Elf);
+                memory_set.try_push(map_area, None)?;
+                // 段的数据从首页的页内偏移处开始；file_size大于mem_size的畸形段放不进自己的逻辑段
+                memory_set.areas.last().unwrap().copy_data_at(data_offset, data)?;
+                last_elf_area = Some(memory_set.areas.len() - 1);
             }
         }
+        memory_set.coalesce_areas();
+        memory_set.set_text_immutable(ELF_TEXT_IMMUTABLE);
         // map user stack with U flags
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
         // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set.push(
+        let stack_range = max_end_va.checked_add(PAGE_SIZE).and_then(|bottom| {
+            let top = bottom.checked_add(user_stack_size)?;
+            Some((bottom.0, top.0))
+        });
+        let (user_stack_bottom, user_stack_top) = match stack_range {
+            Some((bottom, top)) if top <= USER_SPACE_END => (bottom, top),
+            _ => {
+                error!("user stack of {:#x} bytes does not fit above {:#x}", user_stack_size, max_end_va.0);
+                return Err(VmError::Overflow);
+            }
+        };
+        let stack_start_vpn = VirtAddr::from(user_stack_bottom).floor().0;
+        let stack_end_vpn = VirtAddr::from(user_stack_top).ceil().0;
+        if memory_set
+            .areas
+            .iter()
+            .any(|area| area.overlaps_range(stack_start_vpn, stack_end_vpn))
+        {
+            error!(
+                "user stack [{:#x}, {:#x}) overlaps an ELF segment",
+                user_stack_bottom, user_stack_top
+            );
+            return Err(VmError::Overlap);
+        }
+        memory_set.try_push(
             MapArea::new(
                 user_stack_bottom.into(),
                 user_stack_top.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
+            )
+            .with_kind(AreaKind::Stack),
             None,
-        );
+        )?;
+        // 堆紧接在用户栈之上，初始大小为0，由sbrk/brk调整
+        memory_set.try_push(
+            MapArea::new(
+                user_stack_top.into(),
+                user_stack_top.into(),
+                MapType::Framed,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            )
+            .with_kind(AreaKind::Heap),
+            None,
+        )?;
+        // 每个任务一页私有的TLS，地址固定为TLS_BASE
+        #[cfg(feature = "user-tls")]
+        {
+            let tls_vpn = TLS_BASE / PAGE_SIZE;
+            if memory_set.areas.iter().any(|area| area.overlaps_range(tls_vpn, tls_vpn + 1)) {
+                error!("TLS page {:#x} overlaps an ELF segment", TLS_BASE);
+                return Err(VmError::Overlap);
+            }
+            memory_set.try_push(
+                MapArea::new(
+                    TLS_BASE.into(),
+                    (TLS_BASE + PAGE_SIZE).into(),
+                    MapType::Framed,
+                    MapPermission::R | MapPermission::W | MapPermission::U,
+                )
+                .with_kind(AreaKind::Tls),
+                None,
+            )?;
+        }
 
         // map TrapContext
         // 此处未作任何初始化
-        memory_set.push(
+        memory_set.try_push(
             MapArea::new(
                 TRAP_CONTEXT.into(),
                 TRAMPOLINE.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W,
-            ),
+            )
+            .with_kind(AreaKind::TrapContext),
             None,
-        );
-        (
+        )?;
+        // Trap上下文在每次进出内核时都会被访问，永远不能被回收
+        let trap_cx_vpn = VirtAddr::from(TRAP_CONTEXT).floor();
+        memory_set.pinned.insert(trap_cx_vpn);
+        Ok((
             memory_set,
             user_stack_top,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
+    }
+
+    /// 为fork复制用户地址空间：只读页面与user_space共享同一个物理页帧（引用计数），
+    /// 可写页面（含Trap上下文）复制到新的物理页帧，映射到零页的页面仍映射到零页。
+    /// 空闲页帧不够复制所有可写页面时返回Err(VmError::OutOfFrames)，
+    /// 子地址空间继承的配额容纳不下这些页帧时返回Err(VmError::QuotaExceeded)，都不会分配任何页帧
+    pub fn from_existed_user(user_space: &MemorySet) -> Result<MemorySet, VmError> {
+        if user_space.framed_pages() > user_space.frame_quota {
+            return Err(VmError::QuotaExceeded);
+        }
+        let copies = user_space
+            .areas
+            .iter()
+            .flat_map(|area| area.data_frames.keys())
+            .filter(|&&vpn| user_space.page_table.translate(vpn).map_or(false, |pte| pte.writable()))
+            .count();
+        if get_num_empty_frame() < copies {
+            return Err(VmError::OutOfFrames);
+        }
+        let mut memory_set = Self::try_new_bare()?;
+        memory_set.frame_quota = user_space.frame_quota;
+        memory_set.virtual_quota = user_space.virtual_quota;
+        memory_set.wx_strict = user_space.wx_strict;
+        memory_set.pinned = user_space.pinned.clone();
+        memory_set.map_trampoline()?;
+        memory_set.map_time_page()?;
+        for area in user_space.areas.iter() {
+            // 子任务的页面都已复制或共享，不继承父任务的页帧承诺
+            let mut new_area = MapArea::from_another(area);
+            if let MapType::Linear(_) = area.map_type {
+                // 设备等物理区域不属于任何任务，父子任务映射到同一组物理页帧
+                new_area.try_map(&mut memory_set.page_table)?;
+                memory_set.areas.push(new_area);
+                continue;
+            }
+            for vpn in area.vpn_range {
+                if let Some(frame) = area.data_frames.get(&vpn) {
+                    let pte = user_space.page_table.translate(vpn).unwrap();
+                    let flags = pte.flags() - (PTEFlags::A | PTEFlags::D);
+                    if pte.writable() {
+                        // 整页都会被覆盖，不必先清零
+                        let new_frame = frame_alloc_uninit().ok_or(VmError::OutOfFrames)?;
+                        new_frame
+                            .ppn()
+                            .get_bytes_array()
+                            .copy_from_slice(frame.ppn().get_bytes_array());
+                        memory_set.page_table.try_map(vpn, new_frame.ppn(), flags)?;
+                        new_area.data_frames.insert(vpn, Arc::new(new_frame));
+                    } else {
+                        memory_set.page_table.try_map(vpn, frame.ppn(), flags)?;
+                        new_area.data_frames.insert(vpn, Arc::clone(frame));
+                    }
+                } else if user_space.is_zero_mapped(vpn) {
+                    new_area.try_map_zero_one(&mut memory_set.page_table, vpn)?;
+                }
+            }
+            memory_set.areas.push(new_area);
+        }
+        Ok(memory_set)
+    }
+
+    /// 检查内核地址空间的页表：各内核段与物理内存区域的每一页都恒等映射（vpn == ppn）且用户不可访问，
+    /// .text为R|X，.rodata为R，.data/.bss与可分配的物理内存为R|W，只有.text可执行；
+    /// 其余恒等映射的逻辑段（如MMIO）也必须恒等映射。返回发现的第一个问题
+    pub fn audit_kernel(&self) -> Result<(), String> {
+        let sections = [
+            (".text", stext as usize, etext as usize, false, true),
+            (".rodata", srodata as usize, erodata as usize, false, false),
+            (".data", sdata as usize, edata as usize, true, false),
+            (".bss", sbss_with_stack as usize, ebss as usize, true, false),
+            ("physical memory", ekernel as usize, memory_end(), true, false),
+        ];
+        let check_identity = |vpn: VirtPageNum| -> Result<PageTableEntry, String> {
+            let pte = self
+                .page_table
+                .translate(vpn)
+                .filter(|pte| pte.is_valid())
+                .ok_or_else(|| format!("{:?} is not mapped", vpn))?;
+            if pte.ppn().0 != vpn.0 {
+                return Err(format!("{:?} maps to {:?}", vpn, pte.ppn()));
+            }
+            if pte.flags().contains(PTEFlags::U) {
+                return Err(format!("{:?} is user accessible", vpn));
+            }
+            Ok(pte)
+        };
+        for &(name, start, end, writable, executable) in sections.iter() {
+            let range = VPNRange::new(VirtAddr::from(start).floor(), VirtAddr::from(end).ceil());
+            for vpn in range {
+                let pte = check_identity(vpn).map_err(|err| format!("{}: {}", name, err))?;
+                if !pte.readable() || pte.writable() != writable || pte.executable() != executable {
+                    return Err(format!("{}: {:?} has flags {}", name, vpn, pte.flags()));
+                }
+            }
+        }
+        for area in self.areas.iter().filter(|area| area.map_type == MapType::Identical) {
+            for vpn in area.vpn_range {
+                check_identity(vpn)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查地址空间的内部一致性，供测试在每次操作后调用：
+    /// 逻辑段互不重叠；data_frames只出现在Framed逻辑段中，且每个驻留页面都有指向对应页帧的合法页表项；
+    /// 未驻留的页面要么没有页表项，要么只读地映射到零页；页表项的U位与逻辑段一致；
+    /// 跳板和（若存在）Trap上下文的映射都存在
+    pub fn verify_invariants(&self) -> Result<(), String> {
+        for (i, a) in self.areas.iter().enumerate() {
+            for b in self.areas[i + 1..].iter() {
+                if a.overlaps(b) {
+                    return Err(format!(
+                        "areas {:?} and {:?} overlap",
+                        a.vpn_range.get_start(),
+                        b.vpn_range.get_start()
+                    ));
+                }
+            }
+        }
+        for area in self.areas.iter() {
+            if area.map_type != MapType::Framed && !area.data_frames.is_empty() {
+                return Err(format!("{:?} area {:?} owns frames", area.map_type, area.vpn_range.get_start()));
+            }
+            if let Some(vpn) = area.data_frames.keys().find(|vpn| !area.contains(**vpn)) {
+                return Err(format!("frame of {:?} lies outside its area", vpn));
+            }
+            let user = area.map_perm.contains(MapPermission::U);
+            for vpn in area.vpn_range {
+                let pte = self.page_table.translate(vpn).filter(|pte| pte.is_valid());
+                let expected_ppn = match area.map_type {
+                    MapType::Identical => Some(PhysPageNum(vpn.0)),
+                    MapType::Linear(offset) => Some(PhysPageNum(vpn.0.wrapping_add(offset))),
+                    MapType::Framed => area.data_frames.get(&vpn).map(|frame| frame.ppn()),
+                };
+                match (pte, expected_ppn) {
+                    (Some(pte), Some(ppn)) if pte.ppn() != ppn => {
+                        return Err(format!("{:?} maps to a frame it does not own", vpn));
+                    }
+                    (None, Some(_)) => {
+                        return Err(format!("resident page {:?} has no valid pte", vpn));
+                    }
+                    (Some(pte), None)
+                        if pte.ppn() != ZERO_FRAME.ppn()
+                            || pte.writable()
+                            || !pte.rsw().contains(PTESoftFlags::LAZY) =>
+                    {
+                        return Err(format!("non-resident page {:?} is mapped", vpn));
+                    }
+                    (Some(pte), Some(_)) if !pte.rsw().is_empty() => {
+                        return Err(format!("resident page {:?} is marked {:?}", vpn, pte.rsw()));
+                    }
+                    _ => {}
+                }
+                if let Some(pte) = pte {
+                    if pte.user_accessible() != user {
+                        return Err(format!("U flag of {:?} differs from its area", vpn));
+                    }
+                }
+            }
+        }
+        let trampoline = self.page_table.translate(VirtAddr::from(TRAMPOLINE).floor());
+        if !trampoline.map_or(false, |pte| {
+            pte.is_valid() && pte.ppn() == PhysAddr::from(strampoline as usize).floor()
+        }) {
+            return Err(String::from("trampoline is not mapped"));
+        }
+        let trap_cx_vpn = VirtAddr::from(TRAP_CONTEXT).floor();
+        if let Some(area) = self.areas.iter().find(|area| area.kind == AreaKind::TrapContext) {
+            if !area.contains(trap_cx_vpn) || !area.data_frames.contains_key(&trap_cx_vpn) {
+                return Err(String::from("trap context is not mapped"));
+            }
+        }
+        Ok(())
     }
 
     /// 将OS的自己的页表放入satp这个寄存器中，同时将这个寄存器中的mode字段置为8以启动SV39分页机制。
@@ -234,202 +745,1730 @@ impl MemorySet {
         let satp = self.page_table.token();
         unsafe {
             satp::write(satp);
+        }
+        self.flush_tlb();
+    }
+    /// 使用“sfence.vma”清空TLB中缓存的地址转换。
+    /// 由于没有使用ASID，这会清空所有地址空间的缓存；从trap返回用户态时跳板代码也会执行一次
+    pub fn flush_tlb(&self) {
+        unsafe {
             core::arch::asm!("sfence.vma");
         }
     }
+    /// 清空TLB并用“fence.i”同步指令缓存，使之前写入可执行页面的指令对取指可见
+    pub fn membarrier(&self) {
+        self.flush_tlb();
+        unsafe {
+            core::arch::asm!("fence.i");
+        }
+    }
     /// 寻早self中对应于vpn的页表项，如果能够找到，就将页表项拷贝一份并返回
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
 
-    pub fn mmap(&mut self, start: usize, len: usize, port: usize) -> isize {
-        let len_n = (len - 1 + PAGE_SIZE) / PAGE_SIZE;
-        let start_n = start / PAGE_SIZE;
-        let pt = &mut self.page_table;
+    /// 页表节点占用的物理页帧数
+    pub fn page_table_frames(&self) -> usize {
+        self.page_table.node_count()
+    }
 
-        if VirtAddr(start).page_offset() != 0
-            || (port & !0x7) != 0
-            || port & 0x7 == 0
-            || get_num_empty_frame() < len_n
-            || !vpn_range_is_unused(pt, start_n, len_n)
-        {
-            -1
-        } else {
-            if len == 0 {
-                0
-            } else {
-                let mut map_perm = MapPermission::U;
-                if port & 0x1 != 0 {
-                    map_perm |= MapPermission::R;
-                }
-                if port & 0x2 != 0 {
-                    map_perm |= MapPermission::W;
+    /// 返回在self与other中内容不同的虚拟页号（升序）：两边都驻留且字节不同，或只在一边驻留的Framed页面；
+    /// 映射到零页或尚未驻留的页面不参与比较，用于检查fork与写时复制的行为
+    pub fn diff(&self, other: &MemorySet) -> Vec<VirtPageNum> {
+        let resident = |memory_set: &MemorySet| {
+            let mut frames = BTreeMap::new();
+            for area in memory_set.areas.iter() {
+                for (vpn, frame) in area.data_frames.iter() {
+                    frames.insert(*vpn, frame.ppn());
                 }
-                if port & 0x4 != 0 {
-                    map_perm |= MapPermission::X;
+            }
+            frames
+        };
+        let ours = resident(self);
+        let theirs = resident(other);
+        let mut vpns: Vec<VirtPageNum> = ours
+            .iter()
+            .filter(|(vpn, ppn)| match theirs.get(vpn) {
+                Some(other_ppn) => {
+                    other_ppn != *ppn && other_ppn.get_bytes_array() != ppn.get_bytes_array()
                 }
+                None => true,
+            })
+            .map(|(vpn, _)| *vpn)
+            .collect();
+        vpns.extend(theirs.keys().filter(|vpn| !ours.contains_key(vpn)));
+        vpns.sort();
+        vpns
+    }
 
-                self.insert_framed_area(
-                    VirtAddr::from(VirtPageNum::from(start_n)),
-                    VirtAddr::from(VirtPageNum::from(len_n + start_n)),
-                    map_perm,
-                );
-                0
-            }
+    /// 返回各Framed逻辑段当前持有的物理页帧总数（不含页表节点和共享的零页）
+    pub fn framed_pages(&self) -> usize {
+        self.areas.iter().map(|area| area.data_frames.len()).sum()
+    }
+
+    /// 设置物理页帧配额（单位为页，usize::MAX表示不限制），不能低于当前已持有的页帧数
+    pub fn set_frame_quota(&mut self, pages: usize) -> Result<(), VmError> {
+        if pages < self.framed_pages() {
+            return Err(VmError::QuotaExceeded);
         }
+        self.frame_quota = pages;
+        Ok(())
     }
 
-    pub fn munmap(&mut self, start: usize, len: usize) -> isize {
-        let pt = &mut self.page_table;
-        if start % PAGE_SIZE != 0 || !vpn_range_is_used(pt, start, len) || len % PAGE_SIZE != 0 {
-            -1
-        } else {
-            let start_va = start / PAGE_SIZE;
-            let end_va = (len + start) / PAGE_SIZE;
+    /// 检查再分配pages个物理页帧后是否仍在配额之内
+    fn check_quota(&self, pages: usize) -> Result<(), VmError> {
+        if self.framed_pages().saturating_add(pages) > self.frame_quota {
+            return Err(VmError::QuotaExceeded);
+        }
+        Ok(())
+    }
 
-            // 循环体
-            for map_area in &mut self.areas {
-                if map_area.vpn_range.get_start().0 == start_va
-                    && map_area.vpn_range.get_end().0 <= end_va
-                {
-                    map_area.unmap(pt);
-                    break;
-                }
-            }
-            0
+    /// 所有逻辑段的虚拟地址范围总大小（字节），无论页面是否驻留
+    pub fn virtual_size(&self) -> usize {
+        self.areas
+            .iter()
+            .map(|area| area.vpn_range.get_end().0 - area.vpn_range.get_start().0)
+            .sum::<usize>()
+            * PAGE_SIZE
+    }
+
+    /// 设置虚拟地址配额（字节，usize::MAX表示不限制），不能低于当前的virtual_size
+    pub fn set_virtual_quota(&mut self, bytes: usize) -> Result<(), VmError> {
+        if bytes < self.virtual_size() {
+            return Err(VmError::QuotaExceeded);
         }
+        self.virtual_quota = bytes;
+        Ok(())
     }
-}
 
-/// map area structure, controls a contiguous piece of virtual memory
-/// start_va: 虚拟内存的起始地址（4.6）
-/// end_va: 虚拟内存的结束地址（4.6）
-/// map_tpye: 描述该逻辑段内的所有虚拟页面映射到物理页帧的同一种方式 （identitial/frame两种）（4.6）
-/// map_perm: 控制该逻辑段的访问方式，它是页表项标志位 PTEFlags 的一个子集（4.6）
-pub struct MapArea {
-    vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
-    map_type: MapType,
-    map_perm: MapPermission,
-}
+    /// 检查再映射pages页后虚拟地址范围总大小是否仍在配额之内
+    fn check_virtual_quota(&self, pages: usize) -> Result<(), VmError> {
+        let size = pages
+            .checked_mul(PAGE_SIZE)
+            .and_then(|bytes| bytes.checked_add(self.virtual_size()));
+        match size {
+            Some(size) if size <= self.virtual_quota => Ok(()),
+            _ => Err(VmError::QuotaExceeded),
+        }
+    }
 
-impl MapArea {
-    /// 返回一个MapArea（逻辑段）, 它描述一段连续地址的虚拟内存 （4.6）
-    pub fn new(
-        start_va: VirtAddr,
-        end_va: VirtAddr,
-        map_type: MapType,
-        map_perm: MapPermission,
-    ) -> Self {
-        let start_vpn: VirtPageNum = start_va.floor();
-        let end_vpn: VirtPageNum = end_va.ceil();
-        Self {
-            vpn_range: VPNRange::new(start_vpn, end_vpn),
-            data_frames: BTreeMap::new(),
-            map_type,
-            map_perm,
+    /// 按插入顺序遍历所有逻辑段的只读视图，不暴露MapArea和FrameTracker
+    pub fn areas(&self) -> impl Iterator<Item = AreaView> + '_ {
+        self.areas.iter().map(|area| AreaView {
+            start: area.vpn_range.get_start().into(),
+            end: area.vpn_range.get_end().into(),
+            perm: area.map_perm,
+            kind: area.kind,
+            resident_frames: area.data_frames.len(),
+        })
+    }
+
+    /// 以/proc/self/maps的格式列出所有逻辑段，每行为“起始-结束 权限 用途”，
+    /// 权限依次为r/w/x/u，例如“10000000-10001000 rw-u mmap”
+    pub fn maps(&self) -> String {
+        let mut text = String::new();
+        for area in self.areas() {
+            writeln!(
+                text,
+                "{:08x}-{:08x} {} {}",
+                area.start.0,
+                area.end.0,
+                perm_string(area.perm),
+                area.kind.name(),
+            )
+            .unwrap();
         }
+        text
     }
 
-    /// 将单个vpn与物理内空间中的一个frame建立关联，并将相应的页表项放入页表中。
-    /// 关于如何为vnp挑选合适的frame： 如果MapType为identital,则vpn和ppn值一样，如果为framed则由frame分配器生成。
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        let ppn: PhysPageNum;
-        match self.map_type {
-            MapType::Identical => {
-                ppn = PhysPageNum(vpn.0);
+    /// 在maps的每一行之后列出该逻辑段内的叶子页表项，每行为“  vpn -> ppn VRWXUAD标志位”，
+    /// 映射到共享零页的页面标注zero，大页只列出一行并标注huge；不属于任何逻辑段的叶子（如跳板）列在最后的unowned之下
+    pub fn dump_maps(&self) -> String {
+        let leaves = self.page_table.leaves();
+        let mut text = String::new();
+        let write_leaf = |text: &mut String, vpn: VirtPageNum, pte: &PageTableEntry| {
+            let zero = if self.is_zero_mapped(vpn) { " zero" } else { "" };
+            let huge = if self.page_table.is_huge(vpn) { " huge" } else { "" };
+            writeln!(text, "  {:#x} -> {:#x} {}{}{}", vpn.0, pte.ppn().0, pte.flags(), zero, huge).unwrap();
+        };
+        for (area, view) in self.areas.iter().zip(self.areas()) {
+            writeln!(
+                text,
+                "{:08x}-{:08x} {} {}",
+                view.start.0,
+                view.end.0,
+                perm_string(view.perm),
+                view.kind.name(),
+            )
+            .unwrap();
+            for (vpn, pte) in leaves.iter().filter(|(vpn, _)| area.contains(*vpn)) {
+                write_leaf(&mut text, *vpn, pte);
             }
-            MapType::Framed => {
-                let frame = frame_alloc().unwrap();
-                ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+        }
+        let mut unowned = leaves
+            .iter()
+            .filter(|(vpn, _)| self.find_area(*vpn).is_none())
+            .peekable();
+        if unowned.peek().is_some() {
+            writeln!(text, "unowned").unwrap();
+            for (vpn, pte) in unowned {
+                write_leaf(&mut text, *vpn, pte);
             }
         }
-        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
+        text
     }
-    /// 将vpn在page_table对应的页表项删除，并将对应的物理页回收
-    #[allow(unused)]
-    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        #[allow(clippy::single_match)]
-        match self.map_type {
-            MapType::Framed => {
-                self.data_frames.remove(&vpn);
+
+    /// 检查mmap的参数，返回待映射区间的起始vpn、页数和对应的权限；
+    /// 区间不能超出用户地址空间，也不能与已有映射重叠
+    fn check_mmap_args(
+        &self,
+        start: usize,
+        len: usize,
+        port: usize,
+    ) -> Result<(usize, usize, MapPermission), VmError> {
+        if !VirtAddr(start).aligned() {
+            return Err(VmError::Unaligned);
+        }
+        let map_perm = self.port_to_checked_permission(port)?;
+        let (start_n, len_n) = self.check_unused_range(start, len)?;
+        Ok((start_n, len_n, map_perm))
+    }
+
+    /// 设置或清除所有可执行ELF逻辑段（.text）的不可变标记；
+    /// 不可变的逻辑段不能被mprotect加上W权限，即使先去掉X权限也一样
+    pub fn set_text_immutable(&mut self, immutable: bool) {
+        for area in self.areas.iter_mut() {
+            if area.kind == AreaKind::Elf && (area.immutable || area.map_perm.contains(MapPermission::X)) {
+                area.immutable = immutable;
             }
-            _ => {}
         }
-        page_table.unmap(vpn);
+    }
+    /// 开启或关闭W^X检查
+    pub fn set_wx_strict(&mut self, strict: bool) {
+        self.wx_strict = strict;
     }
 
-    /// 将self.vpn_range中的所有vpn都分配一个对应的frame，并为他们在页表中创建页表项
-    pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+    /// 与port_to_permission相同，但在W^X检查开启且port不带MAP_JIT时拒绝同时可写且可执行的权限
+    fn port_to_checked_permission(&self, port: usize) -> Result<MapPermission, VmError> {
+        let jit = port & MAP_JIT != 0;
+        let map_perm = port_to_permission(port & !MAP_JIT)?;
+        if self.wx_strict && !jit && map_perm.contains(MapPermission::W | MapPermission::X) {
+            return Err(VmError::BadPermission);
         }
+        Ok(map_perm)
     }
-    /// 将self.vpn_range中的所有vpn对应的页表项都删除，并将相应的物理页回收
-    #[allow(unused)]
-    pub fn unmap(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.unmap_one(page_table, vpn);
+
+    /// 检查[start, start + len)按页对齐、位于用户地址空间内且与已有映射不重叠，返回起始vpn和页数；
+    /// 第0页永远不允许映射，保证空指针解引用一定触发缺页
+    fn check_unused_range(&self, start: usize, len: usize) -> Result<(usize, usize), VmError> {
+        if !VirtAddr(start).aligned() {
+            return Err(VmError::Unaligned);
+        }
+        let end = start.checked_add(len).ok_or(VmError::Overflow)?;
+        if end > USER_SPACE_END || (start < PAGE_SIZE && len > 0) {
+            return Err(VmError::Overflow);
         }
+        let len_n = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let start_n = start / PAGE_SIZE;
+        if !vpn_range_is_unused(&self.page_table, start_n, len_n)
+            || self
+                .areas
+                .iter()
+                .any(|area| area.overlaps_range(start_n, start_n + len_n))
+        {
+            return Err(VmError::Overlap);
+        }
+        Ok((start_n, len_n))
     }
-    /// data: start-aligned but maybe with shorter length
-    /// assume that all frames were cleared before
-    /// 将切片 data 中的数据拷贝到当前逻辑段实际被内核放置在的各物理页帧上 （4.6）
-    pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
-        assert_eq!(self.map_type, MapType::Framed);
-        let mut start: usize = 0;
-        let mut current_vpn = self.vpn_range.get_start();
-        let len = data.len();
-        loop {
-            let src = &data[start..len.min(start + PAGE_SIZE)];
-            let dst = &mut page_table
-                .translate(current_vpn)
-                .unwrap()
-                .ppn()
-                .get_bytes_array()[..src.len()];
-            dst.copy_from_slice(src);
-            start += PAGE_SIZE;
-            if start >= len {
-                break;
-            }
-            current_vpn.step();
+
+    /// 把[va, va + len)直接映射到从pa开始的物理页帧，不经过frame_alloc，页帧也不归这段映射所有；
+    /// 物理区间必须完全落在不可分配的页帧上（可分配区间之外或被reserve_region保留，如MMIO），
+    /// 否则返回Err(VmError::Overlap)。len按页向上取整，va与pa都必须按页对齐
+    pub fn map_phys(&mut self, va: usize, pa: usize, len: usize, port: usize) -> Result<(), VmError> {
+        if len == 0 || !PhysAddr(pa).aligned() {
+            return Err(VmError::Unaligned);
+        }
+        let map_perm = self.port_to_checked_permission(port)?;
+        let (start_n, len_n) = self.check_unused_range(va, len)?;
+        let start_ppn = PhysAddr(pa).floor();
+        let end_ppn = PhysAddr(pa)
+            .checked_add(len_n * PAGE_SIZE)
+            .map(|end| end.floor())
+            .filter(|end| end.0 <= 1 << Paging::PPN_BITS)
+            .ok_or(VmError::Overflow)?;
+        if frames_overlap_allocatable(start_ppn, end_ppn) {
+            return Err(VmError::Overlap);
         }
+        let map_area = MapArea::new(
+            VirtPageNum(start_n).into(),
+            VirtPageNum(start_n + len_n).into(),
+            MapType::Linear(start_ppn.0.wrapping_sub(start_n)),
+            map_perm,
+        );
+        self.try_push(map_area, None)
     }
-}
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-/// map type for memory set: identical or framed
-pub enum MapType {
-    Identical,
-    Framed,
-}
+    /// 在[start, start + len)上建立一段立即分配物理页帧的匿名映射（带校验的insert_framed_area），
+    /// 主要供测试在没有ELF的情况下搭建地址空间；perm须至少包含R/W/X之一
+    pub fn map_anon(&mut self, start: usize, len: usize, perm: MapPermission) -> Result<(), VmError> {
+        if len == 0 {
+            return Err(VmError::Unaligned);
+        }
+        if !perm.intersects(MapPermission::R | MapPermission::W | MapPermission::X) {
+            return Err(VmError::BadPermission);
+        }
+        let (start_n, len_n) = self.check_unused_range(start, len)?;
+        if get_num_empty_frame() < len_n {
+            return Err(VmError::OutOfFrames);
+        }
+        self.check_quota(len_n)?;
+        self.insert_framed_area(
+            VirtPageNum(start_n).into(),
+            VirtPageNum(start_n + len_n).into(),
+            perm,
+        )
+    }
 
-bitflags! {
-    /// map permission corresponding to that in pte: `R W X U`.
-    /// R(Read)/W(Write)/X(eXecute)：分别控制索引到这个页表项的对应虚拟页面是否允许读/写/执行,
-    /// U(User)：控制索引到这个页表项的对应虚拟页面是否在 CPU 处于 U 特权级的情况下是否被允许访问
-    pub struct MapPermission: u8 {
-        const R = 1 << 1;
-        const W = 1 << 2;
-        const X = 1 << 3;
-        const U = 1 << 4;
+    /// 经由页表把data写到va处，尚未驻留或映射到零页的页面会先像用户写入一样分配私有页帧
+    pub fn write_bytes(&mut self, va: usize, data: &[u8]) -> Result<(), VmError> {
+        self.prepare_user_write(va, data.len())?;
+        copy_to_user(self.token(), va as *mut u8, data)
     }
-}
 
-#[allow(unused)]
-pub fn remap_test() {
-    let mut kernel_space = KERNEL_SPACE.lock();
-    let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
-    let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
-    let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
-    assert!(!kernel_space
-        .page_table
-        .translate(mid_text.floor())
-        .unwrap()
-        .writable());
-    assert!(!kernel_space
+    /// 经由页表读出[va, va + len)的内容，区间内的页面都必须已经驻留
+    pub fn read_bytes(&self, va: usize, len: usize) -> Result<Vec<u8>, VmError> {
+        let mut data = Vec::with_capacity(len);
+        for buffer in translated_byte_buffer(self.token(), va as *const u8, len)? {
+            data.extend_from_slice(buffer);
+        }
+        Ok(data)
+    }
+
+    /// 在[start, start + len)上建立一段新的用户映射，port的低三位依次对应R/W/X。
+    /// len被向上取整到页的整数倍，实际映射的长度计入virtual_size；munmap等对len做同样的取整，
+    /// 因此用同一个len就能完整地解除映射。len为0时不做任何检查，直接成功返回（与munmap一致）
+    pub fn mmap(&mut self, start: usize, len: usize, port: usize) -> Result<(), VmError> {
+        if len == 0 {
+            return Ok(());
+        }
+        let commit = port & MAP_COMMIT != 0;
+        let huge = port & MAP_HUGE != 0;
+        let (start_n, len_n, map_perm) = self.check_mmap_args(start, len, port & !(MAP_COMMIT | MAP_HUGE))?;
+        self.check_virtual_quota(len_n)?;
+        // 页面虽然在写入时才分配，但整段映射都写满后也不能超出配额；
+        // 当前空闲页帧是否足够只对MAP_COMMIT有意义，惰性映射留到缺页时再分配
+        self.check_quota(len_n)?;
+        if commit && !frame_commit(len_n) {
+            return Err(VmError::OutOfFrames);
+        }
+        let (start_va, end_va) = (
+            VirtAddr::from(VirtPageNum::from(start_n)),
+            VirtAddr::from(VirtPageNum::from(len_n + start_n)),
+        );
+        if huge && !commit && start_n % HUGE_PAGES == 0 && len_n % HUGE_PAGES == 0 {
+            self.insert_huge_area(start_va, end_va, map_perm);
+        } else {
+            self.insert_zero_area(start_va, end_va, map_perm);
+        }
+        if commit {
+            let area = self.areas.last_mut().unwrap();
+            area.committed = true;
+            area.reserved = len_n;
+        }
+        Ok(())
+    }
+
+    /// 与mmap相同，但映射出的逻辑段向下增长：访问其当前起始地址之下的页面时会自动扩展，
+    /// 直到总长度达到max_len字节
+    pub fn mmap_grows_down(
+        &mut self,
+        start: usize,
+        len: usize,
+        port: usize,
+        max_len: usize,
+    ) -> Result<(), VmError> {
+        if max_len % PAGE_SIZE != 0 || max_len < len {
+            return Err(VmError::Unaligned);
+        }
+        self.mmap(start, len, port)?;
+        if len != 0 {
+            let area = self.areas.last_mut().unwrap();
+            area.grows_down = Some(max_len / PAGE_SIZE);
+        }
+        Ok(())
+    }
+
+    /// 在用户地址空间中尽量高的位置自动放置一段向下增长的栈映射（类似MAP_STACK），返回其起始地址。
+    /// 放置时为整个增长上限（至少MAP_STACK_MAX_SIZE）预留空间，其他向下增长逻辑段的增长空间同样不会被占用；
+    /// 找不到这样的空隙时返回Err(VmError::NoSpace)
+    pub fn mmap_stack(&mut self, len: usize, port: usize) -> Result<usize, VmError> {
+        if len == 0 {
+            return Err(VmError::Unaligned);
+        }
+        let len_n = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let max_n = len_n.max(MAP_STACK_MAX_SIZE / PAGE_SIZE);
+        let top = self.find_gap_top_down(max_n).ok_or(VmError::NoSpace)?;
+        let start = (top - len_n) * PAGE_SIZE;
+        self.mmap_grows_down(start, len, port & !MAP_STACK, max_n * PAGE_SIZE)?;
+        Ok(start)
+    }
+
+    /// 与mmap相同，但由内核在用户地址空间中从高到低选择第一个放得下的位置，返回映射的起始地址；
+    /// 用户地址空间中放不下时返回Err(VmError::NoSpace)
+    pub fn mmap_anywhere(&mut self, len: usize, port: usize) -> Result<usize, VmError> {
+        if len == 0 {
+            return Err(VmError::Unaligned);
+        }
+        let len_n = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        // MAP_HUGE且长度是大页的整数倍时，多找出对齐所需的空间，再把起始地址向下对齐到大页
+        let align = if port & MAP_HUGE != 0 && len_n % HUGE_PAGES == 0 { HUGE_PAGES } else { 1 };
+        let top = self.find_gap_top_down(len_n + align - 1).ok_or(VmError::NoSpace)?;
+        let start = (top - len_n) / align * align * PAGE_SIZE;
+        self.mmap(start, len, port)?;
+        Ok(start)
+    }
+
+    /// 按各逻辑段（含向下增长的预留）的起始位置从高到低寻找第一个至少有pages页的空隙，
+    /// 返回空隙顶端的vpn；用户地址空间中放不下时返回None
+    fn find_gap_top_down(&self, pages: usize) -> Option<usize> {
+        let mut ranges: Vec<(usize, usize)> = self
+            .areas
+            .iter()
+            .map(|area| {
+                let end = area.vpn_range.get_end().0;
+                let start = match area.grows_down {
+                    Some(max_pages) => end.saturating_sub(max_pages),
+                    None => area.vpn_range.get_start().0,
+                };
+                (start.min(area.vpn_range.get_start().0), end)
+            })
+            .collect();
+        ranges.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let mut top = USER_SPACE_END / PAGE_SIZE;
+        for (start, end) in ranges {
+            if end <= top && top - end >= pages {
+                break;
+            }
+            top = top.min(start);
+        }
+        // 空隙的底部不能落在第0页
+        if top <= pages {
+            return None;
+        }
+        Some(top)
+    }
+
+    /// 一次性映射多段区间，每个请求为(start, len, port)。
+    /// 先检查全部请求（对齐、彼此之间及与已有映射的重叠、总的物理页帧预算），
+    /// 全部合法后才开始映射；任意一个请求失败时不会映射任何区间
+    pub fn mmap_batch(&mut self, requests: &[(usize, usize, usize)]) -> Result<(), VmError> {
+        let mut checked: Vec<(usize, usize, MapPermission)> = Vec::new();
+        let mut total_frames: usize = 0;
+        // 与mmap一致，len为0的请求不做任何检查
+        for &(start, len, port) in requests.iter().filter(|&&(_, len, _)| len != 0) {
+            let (start_n, len_n, map_perm) = self.check_mmap_args(start, len, port)?;
+            if checked
+                .iter()
+                .any(|&(l, n, _)| start_n < l + n && l < start_n + len_n)
+            {
+                return Err(VmError::Overlap);
+            }
+            total_frames += len_n;
+            checked.push((start_n, len_n, map_perm));
+        }
+        self.check_virtual_quota(total_frames)?;
+        if get_num_empty_frame() < total_frames {
+            return Err(VmError::OutOfFrames);
+        }
+        self.check_quota(total_frames)?;
+        for (start_n, len_n, map_perm) in checked {
+            self.insert_zero_area(
+                VirtAddr::from(VirtPageNum::from(start_n)),
+                VirtAddr::from(VirtPageNum::from(len_n + start_n)),
+                map_perm,
+            );
+        }
+        Ok(())
+    }
+
+    /// 检查start按页对齐、[start, start + len)位于用户地址空间内，并且每一页都属于某个逻辑段
+    /// （页面本身可以尚未驻留），返回区间的起止vpn；与mmap一样，len被向上取整到页的整数倍
+    fn check_mapped_range(&self, start: usize, len: usize) -> Result<(usize, usize), VmError> {
+        if start % PAGE_SIZE != 0 {
+            return Err(VmError::Unaligned);
+        }
+        let end = start.checked_add(len).ok_or(VmError::Overflow)?;
+        if end > USER_SPACE_END {
+            return Err(VmError::Overflow);
+        }
+        let start_vpn = start / PAGE_SIZE;
+        let end_vpn = (end + PAGE_SIZE - 1) / PAGE_SIZE;
+        if !(start_vpn..end_vpn).all(|vpn| self.find_area(VirtPageNum(vpn)).is_some()) {
+            return Err(VmError::NotMapped);
+        }
+        Ok((start_vpn, end_vpn))
+    }
+
+    /// 返回包含vpn的逻辑段在areas中的下标
+    fn find_area(&self, vpn: VirtPageNum) -> Option<usize> {
+        self.areas.iter().position(|area| area.contains(vpn))
+    }
+
+    /// 解除[start, start + len)上的映射，区间内的每一页都必须已被映射，但可以跨越多个逻辑段。
+    /// 完整落在区间内的逻辑段被删除，只有一部分落在区间内的逻辑段被切开，保留区间外的部分。
+    /// 区间只能落在调用者用mmap申请的逻辑段上，碰到其他逻辑段时什么也不做，返回Err(VmError::NotOwned)。
+    /// len为0时不做任何检查，直接成功返回（与mmap一致）
+    pub fn munmap(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        if len == 0 {
+            return Ok(());
+        }
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        if self
+            .areas
+            .iter()
+            .any(|area| area.overlaps_range(start_vpn, end_vpn) && area.kind != AreaKind::Mmap)
+        {
+            return Err(VmError::NotOwned);
+        }
+        let (start_vpn, end_vpn) = (VirtPageNum(start_vpn), VirtPageNum(end_vpn));
+        let mut areas = Vec::with_capacity(self.areas.len() + 1);
+        for mut map_area in self.areas.drain(..) {
+            if !map_area.overlaps_range(start_vpn.0, end_vpn.0) {
+                areas.push(map_area);
+                continue;
+            }
+            // 先切下区间右侧的部分，再切下区间左侧的部分，中间的部分被回收
+            let right = if end_vpn < map_area.vpn_range.get_end() {
+                Some(map_area.split_off(end_vpn))
+            } else {
+                None
+            };
+            if start_vpn > map_area.vpn_range.get_start() {
+                let mut middle = map_area.split_off(start_vpn);
+                middle.unmap(&mut self.page_table);
+                areas.push(map_area);
+            } else {
+                map_area.unmap(&mut self.page_table);
+            }
+            areas.extend(right);
+        }
+        self.areas = areas;
+        self.drop_stale_pins();
+        Ok(())
+    }
+
+    /// 回收所有带U权限的逻辑段（ELF段、用户栈、mmap区域）及其物理页帧，
+    /// 保留跳板和Trap上下文的映射，并回收因此变空的页表节点；用于exec或重置任务
+    pub fn clear_user_areas(&mut self) {
+        let page_table = &mut self.page_table;
+        self.areas.retain_mut(|map_area| {
+            let is_user = map_area.map_perm.contains(MapPermission::U);
+            if is_user {
+                map_area.unmap(page_table);
+            }
+            !is_user
+        });
+        self.drop_stale_pins();
+        self.page_table.reclaim_empty_nodes();
+    }
+
+    /// 将起始于start的逻辑段的结尾收缩到new_end，回收被移出的页面
+    pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> Result<(), VmError> {
+        let start_vpn = start.floor();
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() == start_vpn)
+            .ok_or(VmError::NotMapped)?;
+        let new_end_vpn = new_end.ceil();
+        if new_end_vpn < start_vpn || new_end_vpn > area.vpn_range.get_end() {
+            return Err(VmError::Overflow);
+        }
+        area.shrink_to(&mut self.page_table, new_end_vpn);
+        self.drop_stale_pins();
+        Ok(())
+    }
+
+    /// 将起始于start的逻辑段的结尾扩展到new_end，新增的页面在写入时才分配物理页帧
+    pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> Result<(), VmError> {
+        let start_vpn = start.floor();
+        let idx = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
+            .ok_or(VmError::NotMapped)?;
+        let old_end_vpn = self.areas[idx].vpn_range.get_end();
+        let new_end_vpn = new_end.ceil();
+        if new_end_vpn < old_end_vpn || new_end.0 > USER_SPACE_END {
+            return Err(VmError::Overflow);
+        }
+        if self
+            .areas
+            .iter()
+            .any(|area| area.overlaps_range(old_end_vpn.0, new_end_vpn.0))
+        {
+            return Err(VmError::Overlap);
+        }
+        self.check_quota(new_end_vpn.0 - old_end_vpn.0)?;
+        self.areas[idx].append_to(&mut self.page_table, new_end_vpn);
+        Ok(())
+    }
+
+    /// 钉住[start, start + len)中的页面，使其不会被reclaim_one_page回收；区间必须已被映射
+    pub fn pin_range(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        self.pinned.extend((start_vpn..end_vpn).map(VirtPageNum));
+        Ok(())
+    }
+
+    /// 取消[start, start + len)中页面的钉住状态
+    pub fn unpin_range(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        for vpn in start_vpn..end_vpn {
+            self.pinned.remove(&VirtPageNum(vpn));
+        }
+        Ok(())
+    }
+
+    /// 锁定[start, start + len)中的页面：先让Framed逻辑段中尚未驻留的可写页面分配私有页帧，
+    /// 再把每页的锁定次数加一。区间必须已被映射；分配页帧失败时返回错误，已分配的页帧保留，但不锁定任何页面
+    pub fn mlock(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        for vpn in (start_vpn..end_vpn).map(VirtPageNum) {
+            let area = &self.areas[self.find_area(vpn).unwrap()];
+            if area.map_type != MapType::Framed || area.data_frames.contains_key(&vpn) {
+                continue;
+            }
+            // 只读页面没有可回收的页帧，映射到零页即可
+            if area.map_perm.contains(MapPermission::W) {
+                self.handle_page_fault(vpn.into(), FaultKind::Write)?;
+            } else if area.map_perm.contains(MapPermission::R) && !self.is_zero_mapped(vpn) {
+                self.handle_page_fault(vpn.into(), FaultKind::Read)?;
+            }
+        }
+        for vpn in start_vpn..end_vpn {
+            *self.locked.entry(VirtPageNum(vpn)).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// 把[start, start + len)中每页的锁定次数减一，减到零的页面重新可以被回收；
+    /// 区间必须已被映射，其中没有被锁定的页面保持不变
+    pub fn munlock(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        for vpn in start_vpn..end_vpn {
+            let vpn = VirtPageNum(vpn);
+            if let Some(count) = self.locked.get_mut(&vpn) {
+                *count -= 1;
+                if *count == 0 {
+                    self.locked.remove(&vpn);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// vpn被mlock锁定的次数
+    pub fn lock_count(&self, vpn: VirtPageNum) -> usize {
+        self.locked.get(&vpn).copied().unwrap_or(0)
+    }
+
+    /// 删除已不属于任何逻辑段的页面的钉住和锁定记录
+    fn drop_stale_pins(&mut self) {
+        let areas = &self.areas;
+        self.pinned
+            .retain(|vpn| areas.iter().any(|area| area.contains(*vpn)));
+        self.locked
+            .retain(|vpn, _| areas.iter().any(|area| area.contains(*vpn)));
+    }
+
+    /// 用时钟算法回收一个用户页面，返回被回收页面的vpn：
+    /// 跳过被钉住或被mlock锁定的页面，最近被访问过（A位为1）的页面清除A位后获得第二次机会。
+    /// 由于没有交换区，只有内容全为零的页面可以被回收，回收后重新映射到共享的零页
+    pub fn reclaim_one_page(&mut self) -> Option<VirtPageNum> {
+        let resident: Vec<(usize, VirtPageNum)> = self
+            .areas
+            .iter()
+            .enumerate()
+            .filter(|(_, area)| area.map_perm.contains(MapPermission::U))
+            .flat_map(|(idx, area)| area.data_frames.keys().map(move |vpn| (idx, *vpn)))
+            .collect();
+        let count = resident.len();
+        // 最多转两圈：第一圈清除A位，第二圈必然能遇到所有未被钉住的页面
+        for step in 0..2 * count {
+            let (idx, vpn) = resident[(self.clock_hand + step) % count];
+            if self.pinned.contains(&vpn) || self.locked.contains_key(&vpn) {
+                continue;
+            }
+            let pte = self.page_table.translate(vpn).unwrap();
+            if pte.flags().contains(PTEFlags::A) {
+                self.page_table.set_flags(vpn, pte.flags() - PTEFlags::A).unwrap();
+                continue;
+            }
+            if pte.ppn().get_bytes_array().iter().any(|byte| *byte != 0) {
+                continue;
+            }
+            self.clock_hand = (self.clock_hand + step + 1) % count;
+            let map_area = &mut self.areas[idx];
+            // 刚确认过内容全为零，页帧再分配时不必清零
+            map_area.unmap_one_with(&mut self.page_table, vpn, true);
+            if map_area.map_perm.contains(MapPermission::R) {
+                map_area.map_zero_one(&mut self.page_table, vpn);
+            }
+            return Some(vpn);
+        }
+        None
+    }
+
+    /// 修改[start, start + len)上已有映射的权限，port的含义与mmap相同；
+    /// 区间与不可变的逻辑段相交时不能加上W权限，返回Err(VmError::BadPermission)且不做任何修改；
+    /// 空闲页帧不够复制区间内与其他地址空间共享的页面时返回Err(VmError::OutOfFrames)，同样不做任何修改
+    pub fn mprotect(&mut self, start: usize, len: usize, port: usize) -> Result<(), VmError> {
+        let map_perm = self.port_to_checked_permission(port)?;
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        if map_perm.contains(MapPermission::W)
+            && self
+                .areas
+                .iter()
+                .any(|area| area.immutable && area.overlaps_range(start_vpn, end_vpn))
+        {
+            return Err(VmError::BadPermission);
+        }
+        // fork后共享的页面变为可写之前要复制，先确认页帧足够，以免改到一半失败
+        if map_perm.contains(MapPermission::W) {
+            let shared = self
+                .areas
+                .iter()
+                .flat_map(|area| area.data_frames.range(VirtPageNum(start_vpn)..VirtPageNum(end_vpn)))
+                .filter(|(_, frame)| Arc::strong_count(frame) > 1)
+                .count();
+            if get_num_empty_frame() < shared {
+                return Err(VmError::OutOfFrames);
+            }
+        }
+        let pte_flags = PTEFlags::from_bits(map_perm.bits).unwrap();
+        for vpn in start_vpn..end_vpn {
+            let vpn = VirtPageNum(vpn);
+            // 尚未驻留的页面在缺页时按逻辑段的权限映射，逻辑段的权限在下面更新
+            if !vpn_range_is_used(&self.page_table, vpn.0, 1) {
+                continue;
+            }
+            if self.is_zero_mapped(vpn) {
+                // 零页永远只读；不再可读的页面直接解除映射，之后按需重新分配
+                if map_perm.contains(MapPermission::R) {
+                    self.page_table.set_flags(vpn, pte_flags - PTEFlags::W)?;
+                } else {
+                    self.page_table.unmap(vpn);
+                }
+            } else {
+                // fork后共享的只读页面在变为可写之前必须先复制一份
+                if map_perm.contains(MapPermission::W) {
+                    let idx = self.find_area(vpn).unwrap();
+                    self.areas[idx].make_private(&mut self.page_table, vpn)?;
+                }
+                self.page_table.set_flags(vpn, pte_flags)?;
+            }
+        }
+        // 与munmap一样在区间两端切分逻辑段，区间内的部分换成新的权限，
+        // 这样之后的缺页不会按旧权限映射尚未驻留的页面
+        let (start_vpn, end_vpn) = (VirtPageNum(start_vpn), VirtPageNum(end_vpn));
+        let mut areas = Vec::with_capacity(self.areas.len() + 2);
+        for mut map_area in self.areas.drain(..) {
+            if !map_area.overlaps_range(start_vpn.0, end_vpn.0) {
+                areas.push(map_area);
+                continue;
+            }
+            let right = if end_vpn < map_area.vpn_range.get_end() {
+                Some(map_area.split_off(end_vpn))
+            } else {
+                None
+            };
+            if start_vpn > map_area.vpn_range.get_start() {
+                let mut middle = map_area.split_off(start_vpn);
+                middle.map_perm = map_perm;
+                areas.push(map_area);
+                areas.push(middle);
+            } else {
+                map_area.map_perm = map_perm;
+                areas.push(map_area);
+            }
+            areas.extend(right);
+        }
+        self.areas = areas;
+        Ok(())
+    }
+
+    /// 在起始于base的mmap逻辑段内，让第target_offset页起的count页映射到第offset页起的count页所用的物理页帧，
+    /// 两处共享同一组页帧（例如构造首尾相接的环形缓冲区）。源页面尚未驻留时先分配清零的页帧，
+    /// 目标页面原有的页帧被释放。两个区间都必须落在逻辑段内且互不重叠。
+    /// 别名只在本地址空间内有效：fork、remap_frames或MADV_DONTNEED之后相应的页面各自独立
+    pub fn alias_pages(
+        &mut self,
+        base: usize,
+        offset: usize,
+        count: usize,
+        target_offset: usize,
+    ) -> Result<(), VmError> {
+        if base % PAGE_SIZE != 0 {
+            return Err(VmError::Unaligned);
+        }
+        let base_vpn = VirtPageNum(base / PAGE_SIZE);
+        let idx = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == base_vpn)
+            .ok_or(VmError::NotMapped)?;
+        if self.areas[idx].kind != AreaKind::Mmap || self.areas[idx].map_type != MapType::Framed {
+            return Err(VmError::NotOwned);
+        }
+        let pages = self.areas[idx].vpn_range.get_end().0 - base_vpn.0;
+        let in_area = |first: usize| first.checked_add(count).map_or(false, |end| end <= pages);
+        if !in_area(offset) || !in_area(target_offset) {
+            return Err(VmError::Overflow);
+        }
+        if offset < target_offset + count && target_offset < offset + count {
+            return Err(VmError::Overlap);
+        }
+        let sources: Vec<VirtPageNum> = (0..count).map(|i| VirtPageNum(base_vpn.0 + offset + i)).collect();
+        let missing = sources
+            .iter()
+            .filter(|vpn| !self.areas[idx].data_frames.contains_key(vpn))
+            .count();
+        if get_num_empty_frame() < missing {
+            return Err(VmError::OutOfFrames);
+        }
+        self.check_quota(missing)?;
+        for &vpn in sources.iter() {
+            if self.areas[idx].data_frames.contains_key(&vpn) {
+                continue;
+            }
+            if self.is_zero_mapped(vpn) {
+                self.page_table.unmap(vpn);
+            }
+            self.areas[idx].try_map_one(&mut self.page_table, vpn)?;
+        }
+        // 旧页帧要等TLB刷新之后才能释放
+        let mut old_frames = Vec::new();
+        for (i, &src) in sources.iter().enumerate() {
+            let dst = VirtPageNum(base_vpn.0 + target_offset + i);
+            let map_area = &mut self.areas[idx];
+            let frame = Arc::clone(&map_area.data_frames[&src]);
+            let flags = self.page_table.translate(src).unwrap().flags() - (PTEFlags::A | PTEFlags::D);
+            if let Some(old) = map_area.data_frames.insert(dst, Arc::clone(&frame)) {
+                old_frames.push(old);
+            }
+            if self.page_table.translate(dst).map_or(false, |pte| pte.is_valid()) {
+                self.page_table.unmap(dst);
+            }
+            if let Err(err) = self.page_table.try_map(dst, frame.ppn(), flags) {
+                self.areas[idx].data_frames.remove(&dst);
+                self.flush_tlb();
+                return Err(err);
+            }
+        }
+        self.flush_tlb();
+        drop(old_frames);
+        Ok(())
+    }
+
+    /// 为[start, start + len)中每个已驻留的页面分配新的物理页帧并复制内容，
+    /// 保持权限不变地把页表项换成新页帧，然后刷新TLB并释放旧页帧（用于模拟页面迁移）
+    pub fn remap_frames(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        let resident: Vec<(usize, VirtPageNum)> = (start_vpn..end_vpn)
+            .map(VirtPageNum)
+            .filter_map(|vpn| {
+                let idx = self.find_area(vpn).unwrap();
+                self.areas[idx].data_frames.get(&vpn).map(|_| (idx, vpn))
+            })
+            .collect();
+        if get_num_empty_frame() < resident.len() {
+            return Err(VmError::OutOfFrames);
+        }
+        // 旧页帧要等TLB刷新之后才能释放，否则仍缓存着旧映射的TLB项可能写到已被重新分配的页帧
+        let mut old_frames = Vec::new();
+        for (idx, vpn) in resident {
+            let frame = frame_alloc_uninit().unwrap();
+            let map_area = &mut self.areas[idx];
+            let old_frame = map_area.data_frames.insert(vpn, Arc::new(frame)).unwrap();
+            let new_ppn = map_area.data_frames[&vpn].ppn();
+            new_ppn.get_bytes_array().copy_from_slice(old_frame.ppn().get_bytes_array());
+            let flags = self.page_table.translate(vpn).unwrap().flags();
+            self.page_table.unmap(vpn);
+            self.page_table.map(vpn, new_ppn, flags);
+            old_frames.push(old_frame);
+        }
+        self.flush_tlb();
+        drop(old_frames);
+        Ok(())
+    }
+
+    /// MADV_DONTNEED：释放[start, start + len)中已驻留页面的物理页帧并使页表项失效，
+    /// 逻辑段本身保留，之后再访问这些页面时由缺页异常重新映射全零的页面；被mlock锁定的页面保持不变
+    pub fn madvise_dontneed(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        for vpn in start_vpn..end_vpn {
+            let vpn = VirtPageNum(vpn);
+            if self.locked.contains_key(&vpn) {
+                continue;
+            }
+            let idx = self.find_area(vpn).unwrap();
+            let map_area = &mut self.areas[idx];
+            if map_area.map_type == MapType::Framed && map_area.data_frames.contains_key(&vpn) {
+                map_area.unmap_one(&mut self.page_table, vpn);
+            }
+        }
+        Ok(())
+    }
+
+    /// MADV_WILLNEED：立即调入[start, start + len)中尚未驻留的页面，之后访问它们不再缺页。
+    /// 可写的页面分配私有页帧，只读的页面映射到零页。调入前先检查空闲页帧（含逻辑段的页帧承诺）
+    /// 和配额是否足够调入全部页面，不够时什么都不做，返回Err(VmError::OutOfFrames)或Err(VmError::QuotaExceeded)
+    pub fn madvise_willneed(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let (start_vpn, end_vpn) = self.check_mapped_range(start, len)?;
+        let mut needed: BTreeMap<usize, usize> = BTreeMap::new();
+        for vpn in (start_vpn..end_vpn).map(VirtPageNum) {
+            let idx = self.find_area(vpn).unwrap();
+            let area = &self.areas[idx];
+            if area.map_type == MapType::Framed
+                && area.map_perm.contains(MapPermission::W)
+                && !area.data_frames.contains_key(&vpn)
+            {
+                *needed.entry(idx).or_insert(0) += 1;
+            }
+        }
+        let frames: usize = needed.values().sum();
+        let uncommitted: usize = needed
+            .iter()
+            .map(|(&idx, &count)| count.saturating_sub(self.areas[idx].reserved))
+            .sum();
+        self.check_quota(frames)?;
+        if uncommitted > get_num_empty_frame() {
+            return Err(VmError::OutOfFrames);
+        }
+        for vpn in (start_vpn..end_vpn).map(VirtPageNum) {
+            let area = &self.areas[self.find_area(vpn).unwrap()];
+            if area.map_type != MapType::Framed || area.data_frames.contains_key(&vpn) {
+                continue;
+            }
+            if area.map_perm.contains(MapPermission::W) {
+                self.handle_page_fault(vpn.into(), FaultKind::Write)?;
+            } else if area.map_perm.contains(MapPermission::R) && !self.is_zero_mapped(vpn) {
+                self.handle_page_fault(vpn.into(), FaultKind::Read)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// vpn当前是否映射到共享的零页
+    fn is_zero_mapped(&self, vpn: VirtPageNum) -> bool {
+        match self.page_table.translate(vpn) {
+            Some(pte) => pte.is_valid() && pte.rsw().contains(PTESoftFlags::LAZY),
+            None => false,
+        }
+    }
+
+    /// 统计所有逻辑段中的页面，返回(resident, reserved)：resident为已有物理页帧的页面数，
+    /// reserved为属于某个逻辑段、但尚未驻留（没有页表项或映射到零页）的页面数
+    pub fn page_counts(&self) -> (usize, usize) {
+        let mut resident = 0;
+        let mut reserved = 0;
+        for area in self.areas.iter() {
+            for vpn in area.vpn_range {
+                match self.page_table.translate(vpn) {
+                    Some(pte) if pte.is_valid() && !self.is_zero_mapped(vpn) => resident += 1,
+                    _ => reserved += 1,
+                }
+            }
+        }
+        (resident, reserved)
+    }
+
+    /// vpn的映射状态：页表项有效且不是零页时为Resident，否则落在某个逻辑段内时为Reserved
+    pub fn mapping_state(&self, vpn: VirtPageNum) -> MappingState {
+        let resident = match self.page_table.translate(vpn) {
+            Some(pte) => pte.is_valid() && !self.is_zero_mapped(vpn),
+            None => false,
+        };
+        if resident {
+            MappingState::Resident
+        } else if self.find_area(vpn).is_some() {
+            MappingState::Reserved
+        } else {
+            MappingState::Unmapped
+        }
+    }
+
+    /// 处理用户态的缺页异常，kind是触发缺页的访问类型，va须落在某个Framed逻辑段内：
+    /// 读或取指一个尚未驻留的页面时映射共享的零页（只执行的逻辑段中零页也只可执行）；
+    /// 写一个尚未驻留或映射到零页的页面时分配一个新的全零物理页帧（写时复制，零页无需真正拷贝）。
+    /// 新页表项的权限与逻辑段的map_perm完全一致（零页再去掉W）；逻辑段不允许这种访问时返回
+    /// Err(VmError::Fault)，由trap处理函数杀死任务，而不会把页面提升为可写；
+    /// 没有空闲页帧存放数据或新的页表节点时返回Err(VmError::OutOfFrames)，同样杀死任务而不是让内核panic
+    pub fn handle_page_fault(&mut self, va: VirtAddr, kind: FaultKind) -> Result<(), VmError> {
+        let vpn = va.floor();
+        #[cfg(feature = "access-trace")]
+        if self.untrace(vpn) {
+            match self.page_table.translate(vpn) {
+                // 恢复U位后访问已经可以完成；写零页等情况继续按普通缺页处理
+                Some(pte) if kind != FaultKind::Write || pte.writable() => return Ok(()),
+                _ => {}
+            }
+        }
+        let idx = match self.find_area(vpn) {
+            Some(idx) => idx,
+            None => self.grow_down_to(vpn)?,
+        };
+        let zero_mapped = self.is_zero_mapped(vpn);
+        let map_area = &mut self.areas[idx];
+        if map_area.map_type != MapType::Framed || map_area.data_frames.contains_key(&vpn) {
+            return Err(VmError::Fault);
+        }
+        let required = match kind {
+            FaultKind::Read => MapPermission::R,
+            FaultKind::Write => MapPermission::W,
+            FaultKind::Execute => MapPermission::X,
+        };
+        if !map_area.map_perm.contains(required) {
+            return Err(VmError::Fault);
+        }
+        if kind != FaultKind::Write {
+            // 已经映射到零页的页面仍然缺页，说明访问不被允许
+            if zero_mapped {
+                return Err(VmError::Fault);
+            }
+            return map_area.try_map_zero_one(&mut self.page_table, vpn);
+        }
+        if self.areas[idx].reserved == 0 && get_num_empty_frame() == 0 {
+            return Err(VmError::OutOfFrames);
+        }
+        self.check_quota(1)?;
+        let map_area = &mut self.areas[idx];
+        if zero_mapped {
+            self.page_table.unmap(vpn);
+        }
+        // 新建页表节点时也可能没有空闲页帧，此时返回Err(VmError::OutOfFrames)由trap处理函数杀死任务；
+        // 原来映射到零页的页面重新映射回零页，所需的节点都还在，不会失败
+        if let Err(err) = map_area.try_map_one(&mut self.page_table, vpn) {
+            if zero_mapped {
+                map_area.try_map_zero_one(&mut self.page_table, vpn)?;
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// 开启（enable为true）或关闭访问跟踪。开启时去掉所有带U权限的逻辑段中合法叶子页表项的U位，
+    /// 用户态第一次访问这些页面时触发缺页，handle_page_fault记录页号、恢复U位后返回用户态重新执行，
+    /// 之后的访问不再缺页，于是take_access_trace得到按首次访问排序的页号序列。
+    /// 只去掉U位而不清除V位，页表项对内核仍然合法，unmap、reclaim_empty_nodes等无需区分被跟踪的页面；
+    /// 关闭时恢复所有尚未被访问的页面的U位
+    #[cfg(feature = "access-trace")]
+    pub fn trace_accesses(&mut self, enable: bool) {
+        if enable {
+            for area in self.areas.iter().filter(|area| area.map_perm.contains(MapPermission::U)) {
+                for vpn in area.vpn_range {
+                    match self.page_table.translate(vpn) {
+                        Some(pte) if pte.is_valid() && pte.user_accessible() => {
+                            self.page_table.set_flags(vpn, pte.flags() - PTEFlags::U).unwrap();
+                            self.traced.insert(vpn);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        } else {
+            let traced: Vec<VirtPageNum> = self.traced.iter().copied().collect();
+            for vpn in traced {
+                self.untrace(vpn);
+            }
+        }
+        self.flush_tlb();
+    }
+    /// 取出并清空已记录的访问跟踪
+    #[cfg(feature = "access-trace")]
+    pub fn take_access_trace(&mut self) -> Vec<VirtPageNum> {
+        core::mem::take(&mut self.access_trace)
+    }
+    /// vpn正被跟踪时恢复它的U位并记录这次访问，返回vpn是否正被跟踪；
+    /// 跟踪期间被munmap、mprotect等重新映射过的页面只从集合中删除
+    #[cfg(feature = "access-trace")]
+    fn untrace(&mut self, vpn: VirtPageNum) -> bool {
+        if !self.traced.remove(&vpn) {
+            return false;
+        }
+        match self.page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && !pte.user_accessible() => {
+                self.page_table.set_flags(vpn, pte.flags() | PTEFlags::U).unwrap();
+                self.access_trace.push(vpn);
+                debug!("[kernel] access trace: vpn {:#x}", vpn.0);
+                self.flush_tlb();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// vpn不属于任何逻辑段时，若它上方最近的逻辑段向下增长且扩展到vpn后不超过上限，
+    /// 则扩展该逻辑段并返回它在areas中的下标
+    fn grow_down_to(&mut self, vpn: VirtPageNum) -> Result<usize, VmError> {
+        let idx = self
+            .areas
+            .iter()
+            .enumerate()
+            .filter(|(_, area)| area.vpn_range.get_start() > vpn)
+            .min_by_key(|(_, area)| area.vpn_range.get_start())
+            .map(|(idx, _)| idx)
+            .ok_or(VmError::Fault)?;
+        let area = &mut self.areas[idx];
+        let max_pages = area.grows_down.ok_or(VmError::Fault)?;
+        if area.vpn_range.get_end().0 - vpn.0 > max_pages {
+            return Err(VmError::Fault);
+        }
+        area.expand_down(vpn);
+        Ok(idx)
+    }
+
+    /// 确保内核可以写入用户区间[start, start + len)：映射到零页或尚未驻留的页面
+    /// 会像用户写入一样先复制出私有页帧，不可写的页面返回Err(VmError::Fault)
+    pub fn prepare_user_write(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let end = start.checked_add(len).ok_or(VmError::Overflow)?;
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            match self.page_table.translate(vpn) {
+                Some(pte) if pte.is_valid() && pte.writable() => {}
+                _ => self.handle_page_fault(vpn.into(), FaultKind::Write)?,
+            }
+        }
+        Ok(())
+    }
+    /// 把所有带U权限的Framed逻辑段的内容复制到内核堆上；尚未驻留的页面只记录为全零
+    pub fn snapshot(&self) -> Snapshot {
+        let mut pages = Vec::new();
+        for area in self.areas.iter() {
+            if area.map_type != MapType::Framed || !area.map_perm.contains(MapPermission::U) {
+                continue;
+            }
+            for vpn in area.vpn_range {
+                let data = area
+                    .data_frames
+                    .get(&vpn)
+                    .map(|frame| frame.ppn().get_bytes_array().to_vec());
+                pages.push((vpn, data));
+            }
+        }
+        Snapshot { pages }
+    }
+    /// 把snapshot中的内容写回：有内容的页面写入私有页帧（必要时重新分配），
+    /// 快照时尚未驻留的页面重新变为未驻留。快照中的页面已不属于任何逻辑段时返回Err(VmError::NotMapped)
+    /// 且不做任何修改；页帧不足或超出配额时返回错误，此时可能已经写回了一部分页面
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), VmError> {
+        if snapshot.pages.iter().any(|(vpn, _)| self.find_area(*vpn).is_none()) {
+            return Err(VmError::NotMapped);
+        }
+        for (vpn, data) in snapshot.pages.iter() {
+            let vpn = *vpn;
+            let idx = self.find_area(vpn).unwrap();
+            let resident = self.areas[idx].data_frames.contains_key(&vpn);
+            match data {
+                Some(data) => {
+                    if resident {
+                        self.areas[idx].make_private(&mut self.page_table, vpn)?;
+                    } else {
+                        if get_num_empty_frame() == 0 && self.areas[idx].reserved == 0 {
+                            return Err(VmError::OutOfFrames);
+                        }
+                        self.check_quota(1)?;
+                        if self.is_zero_mapped(vpn) {
+                            self.page_table.unmap(vpn);
+                        }
+                        self.areas[idx].map_one(&mut self.page_table, vpn);
+                    }
+                    let ppn = self.areas[idx].data_frames[&vpn].ppn();
+                    ppn.get_bytes_array().copy_from_slice(data);
+                }
+                None if resident => {
+                    let map_area = &mut self.areas[idx];
+                    map_area.unmap_one(&mut self.page_table, vpn);
+                    if map_area.map_perm.contains(MapPermission::R) {
+                        map_area.map_zero_one(&mut self.page_table, vpn);
+                    }
+                }
+                None => {}
+            }
+        }
+        self.flush_tlb();
+        Ok(())
+    }
+    /// 确保内核可以读取用户区间[start, start + len)：尚未驻留的页面会像用户读取一样
+    /// 先映射到零页，不可读的页面返回Err(VmError::Fault)
+    pub fn prepare_user_read(&mut self, start: usize, len: usize) -> Result<(), VmError> {
+        let end = start.checked_add(len).ok_or(VmError::Overflow)?;
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            match self.page_table.translate(vpn) {
+                Some(pte) if pte.is_valid() && pte.readable() => {}
+                _ => self.handle_page_fault(vpn.into(), FaultKind::Read)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 在启动时打印内核段、可分配的物理页帧区间、跳板地址以及内核地址空间的各逻辑段，
+/// 并检查物理页帧区间中的页帧要么空闲、要么被内核页表占用，返回区间内的页帧数
+pub fn print_memory_layout() -> usize {
+    info!("[kernel] memory layout:");
+    info!("  .text   [{:#x}, {:#x})", stext as usize, etext as usize);
+    info!("  .rodata [{:#x}, {:#x})", srodata as usize, erodata as usize);
+    info!("  .data   [{:#x}, {:#x})", sdata as usize, edata as usize);
+    info!("  .bss    [{:#x}, {:#x})", sbss_with_stack as usize, ebss as usize);
+    let frame_start = PhysAddr::from(ekernel as usize).ceil();
+    let frame_end = PhysAddr::from(memory_end()).floor();
+    let frames = frame_end.0 - frame_start.0;
+    info!(
+        "  frames  [{:#x}, {:#x}): {} frames",
+        ekernel as usize, memory_end(), frames
+    );
+    info!("  trampoline {:#x} -> {:#x}", TRAMPOLINE, strampoline as usize);
+    let kernel_space = KERNEL_SPACE.lock();
+    for area in kernel_space.areas() {
+        info!(
+            "  {:<12} [{:#x}, {:#x}): {} pages",
+            area.kind.name(),
+            area.start.0,
+            area.end.0,
+            (area.end.0 - area.start.0) / PAGE_SIZE
+        );
+    }
+    let page_table_frames = kernel_space.page_table_frames();
+    info!("  kernel page table: {} frames", page_table_frames);
+    assert_eq!(frames, get_num_empty_frame() + page_table_frames);
+    frames
+}
+
+/// sys_mmap的port中的标志位：映射时就为整段承诺物理页帧，之后的缺页一定能分配到页帧
+pub const MAP_COMMIT: usize = 1 << 8;
+
+/// sys_mmap的port中的标志位：忽略start，由内核在用户地址空间高端选择位置并映射为向下增长的栈
+pub const MAP_STACK: usize = 1 << 10;
+
+/// sys_mmap/sys_mprotect的port中的标志位：允许该映射同时可写且可执行（供JIT一类的程序使用）
+pub const MAP_JIT: usize = 1 << 11;
+
+/// sys_mmap的port中的标志位：len是2MiB的整数倍时尽量用大页映射，start为0时选择的地址按2MiB对齐；
+/// 映射时立即分配并清零连续的页帧，分配不到时退回到普通页面。对大页的部分解除映射或修改权限时，
+/// 大页会先被拆成普通页面
+pub const MAP_HUGE: usize = 1 << 12;
+
+/// 按r/w/x/u的顺序列出权限，没有的位用'-'表示，例如“rw-u”
+fn perm_string(perm: MapPermission) -> String {
+    [
+        (MapPermission::R, 'r'),
+        (MapPermission::W, 'w'),
+        (MapPermission::X, 'x'),
+        (MapPermission::U, 'u'),
+    ]
+    .iter()
+    .map(|&(bit, c)| if perm.contains(bit) { c } else { '-' })
+    .collect()
+}
+
+/// 将mmap/mprotect的port参数转换为带U标志的MapPermission；
+/// SV39中W=1、R=0的页表项是保留的组合，因此只写的port按可读可写映射
+fn port_to_permission(port: usize) -> Result<MapPermission, VmError> {
+    if (port & !0x7) != 0 || port & 0x7 == 0 {
+        return Err(VmError::BadPermission);
+    }
+    let mut map_perm = MapPermission::U;
+    if port & 0x1 != 0 {
+        map_perm |= MapPermission::R;
+    }
+    if port & 0x2 != 0 {
+        map_perm |= MapPermission::R | MapPermission::W;
+    }
+    if port & 0x4 != 0 {
+        map_perm |= MapPermission::X;
+    }
+    Ok(map_perm)
+}
+
+/// map area structure, controls a contiguous piece of virtual memory
+/// start_va: 虚拟内存的起始地址（4.6）
+/// end_va: 虚拟内存的结束地址（4.6）
+/// map_tpye: 描述该逻辑段内的所有虚拟页面映射到物理页帧的同一种方式 （identitial/frame两种）（4.6）
+/// map_perm: 控制该逻辑段的访问方式，它是页表项标志位 PTEFlags 的一个子集（4.6）
+/// kind: 逻辑段的用途，仅用于调试输出（如sys_maps）
+pub struct MapArea {
+    vpn_range: VPNRange,
+    /// fork出的子进程与父进程共享只读页面的物理页帧，最后一个引用释放时页帧才被回收
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
+    map_type: MapType,
+    map_perm: MapPermission,
+    kind: AreaKind,
+    /// 向下增长（类似MAP_GROWSDOWN）的逻辑段允许的最大页数；None表示大小固定
+    grows_down: Option<usize>,
+    /// 由MAP_COMMIT映射：释放页帧后重新承诺一个页帧，保证之后的缺页一定成功
+    committed: bool,
+    /// 本逻辑段持有的、尚未用掉的页帧承诺数
+    reserved: usize,
+    /// 不可变（如ELF的.text）：mprotect不能给它加上W权限
+    immutable: bool,
+    /// 缓存着色：为Some时map_one按颜色轮流分配页帧，值为下一个页面的颜色；None时不区分颜色
+    next_color: Option<usize>,
+}
+
+impl MapArea {
+    /// 返回一个MapArea（逻辑段）, 它描述一段连续地址的虚拟内存 （4.6）
+    pub fn new(
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        map_type: MapType,
+        map_perm: MapPermission,
+    ) -> Self {
+        let start_vpn: VirtPageNum = start_va.floor();
+        let end_vpn: VirtPageNum = end_va.ceil();
+        // 默认用途：恒等映射属于内核；Framed且用户可访问的是mmap区域，否则是内核栈
+        let kind = match map_type {
+            MapType::Identical => AreaKind::Kernel,
+            MapType::Linear(_) => AreaKind::Phys,
+            MapType::Framed if map_perm.contains(MapPermission::U) => AreaKind::Mmap,
+            MapType::Framed => AreaKind::KernelStack,
+        };
+        Self {
+            vpn_range: VPNRange::new(start_vpn, end_vpn),
+            data_frames: BTreeMap::new(),
+            map_type,
+            map_perm,
+            kind,
+            grows_down: None,
+            committed: false,
+            reserved: 0,
+            immutable: false,
+            next_color: None,
+        }
+    }
+    /// 复制another的地址区间、映射方式、权限和用途，但不复制任何页面和页帧承诺
+    pub fn from_another(another: &MapArea) -> Self {
+        Self {
+            vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
+            data_frames: BTreeMap::new(),
+            map_type: another.map_type,
+            map_perm: another.map_perm,
+            kind: another.kind,
+            grows_down: another.grows_down,
+            committed: false,
+            reserved: 0,
+            immutable: another.immutable,
+            next_color: another.next_color.map(|_| 0),
+        }
+    }
+    /// 覆盖逻辑段的默认用途
+    pub fn with_kind(mut self, kind: AreaKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    /// 开启缓存着色：之后映射的页面依次请求颜色0, 1, ..., FRAME_COLORS - 1, 0, ...的页帧，
+    /// 把逻辑段的页面分散到不同的cache set上
+    pub fn with_cache_coloring(mut self) -> Self {
+        self.next_color = Some(0);
+        self
+    }
+
+    /// vpn是否落在本逻辑段内
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+    /// 本逻辑段是否与[start_vpn, end_vpn)相交，两者之一为空时不相交
+    pub fn overlaps_range(&self, start_vpn: usize, end_vpn: usize) -> bool {
+        let (start, end) = (self.vpn_range.get_start().0, self.vpn_range.get_end().0);
+        start < end && start_vpn < end_vpn && start < end_vpn && start_vpn < end
+    }
+    /// 两个逻辑段是否至少共有一个页面；空的逻辑段与任何逻辑段都不相交
+    pub fn overlaps(&self, other: &MapArea) -> bool {
+        self.overlaps_range(other.vpn_range.get_start().0, other.vpn_range.get_end().0)
+    }
+    /// 两个逻辑段是否首尾相接（一个的end等于另一个的start）且互不相交，不区分先后
+    pub fn is_adjacent_to(&self, other: &MapArea) -> bool {
+        !self.overlaps(other)
+            && (self.vpn_range.get_end() == other.vpn_range.get_start()
+                || other.vpn_range.get_end() == self.vpn_range.get_start())
+    }
+    /// 两个逻辑段的访问权限是否完全相同
+    pub fn same_perm(&self, other: &MapArea) -> bool {
+        self.map_perm == other.map_perm
+    }
+
+    /// 将单个vpn与物理内空间中的一个frame建立关联，并将相应的页表项放入页表中。
+    /// 关于如何为vnp挑选合适的frame： 如果MapType为identital,则vpn和ppn值一样，如果为framed则由frame分配器生成。
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        self.try_map_one(page_table, vpn)
+            .expect("no frame left to map a page");
+    }
+    /// map_one的可失败版本：数据页帧或页表节点分配失败时返回Err(VmError::OutOfFrames)，
+    /// 此时vpn保持未映射，已分配的数据页帧被归还
+    pub fn try_map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Result<(), VmError> {
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        match self.map_type {
+            MapType::Identical => page_table.try_map(vpn, PhysPageNum(vpn.0), pte_flags),
+            MapType::Linear(offset) => {
+                page_table.try_map(vpn, PhysPageNum(vpn.0.wrapping_add(offset)), pte_flags)
+            }
+            MapType::Framed => {
+                // 用户可见的页帧必须清零，以免泄露之前使用者的数据；内核栈等内核自己的页面不必清零
+                let committed = self.reserved > 0;
+                let frame = if committed {
+                    self.reserved -= 1;
+                    frame_alloc_committed()
+                } else if let Some(color) = self.next_color {
+                    let frame = frame_alloc_colored(color).ok_or(VmError::OutOfFrames)?;
+                    self.next_color = Some((color + 1) % FRAME_COLORS);
+                    frame
+                } else if self.map_perm.contains(MapPermission::U) {
+                    frame_alloc().ok_or(VmError::OutOfFrames)?
+                } else {
+                    frame_alloc_uninit().ok_or(VmError::OutOfFrames)?
+                };
+                if let Err(err) = page_table.try_map(vpn, frame.ppn(), pte_flags) {
+                    // 归还的页帧重新承诺给这个逻辑段，之后的缺页仍能分配到页帧
+                    if committed {
+                        drop(frame);
+                        assert!(frame_commit(1));
+                        self.reserved += 1;
+                    }
+                    return Err(err);
+                }
+                self.data_frames.insert(vpn, Arc::new(frame));
+                Ok(())
+            }
+        }
+    }
+    /// 为从vpn开始的HUGE_PAGES个页面分配一段连续的清零页帧，并用一个大页页表项映射；
+    /// 页帧仍逐页记录在data_frames中。分配不到连续页帧或无法放下大页时返回false，什么都不改变
+    pub fn try_map_huge(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        assert_eq!(self.map_type, MapType::Framed);
+        let frames = match frame_alloc_contiguous(HUGE_PAGES) {
+            Some(frames) => frames,
+            None => return false,
+        };
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        if page_table.try_map_huge(vpn, frames[0].ppn(), pte_flags).is_err() {
+            return false;
+        }
+        for (i, frame) in frames.into_iter().enumerate() {
+            self.data_frames.insert(VirtPageNum(vpn.0 + i), Arc::new(frame));
+        }
+        true
+    }
+    /// 将vpn以去掉W的权限映射到共享的零页，不分配新的物理页帧
+    pub fn map_zero_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        self.try_map_zero_one(page_table, vpn)
+            .expect("no frame left for page table nodes");
+    }
+    /// map_zero_one的可失败版本，没有空闲页帧新建页表中间节点时返回Err(VmError::OutOfFrames)
+    pub fn try_map_zero_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Result<(), VmError> {
+        assert_eq!(self.map_type, MapType::Framed);
+        let pte_flags = PTEFlags::from_bits((self.map_perm - MapPermission::W).bits).unwrap();
+        page_table.try_map(vpn, ZERO_FRAME.ppn(), pte_flags)?;
+        // 零页是只读共享的：写入时复制出私有页帧
+        page_table
+            .set_rsw(vpn, PTESoftFlags::COW | PTESoftFlags::LAZY)
+            .unwrap();
+        Ok(())
+    }
+    /// 若vpn的物理页帧与其他地址空间共享（fork后的只读页面），复制出一个私有页帧替换它，
+    /// 页表项的权限保持不变；没有空闲页帧时返回Err(VmError::OutOfFrames)，什么都不改变
+    pub fn make_private(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Result<(), VmError> {
+        let old_ppn = match self.data_frames.get(&vpn) {
+            Some(frame) if Arc::strong_count(frame) > 1 => frame.ppn(),
+            _ => return Ok(()),
+        };
+        let frame = frame_alloc_uninit().ok_or(VmError::OutOfFrames)?;
+        frame.ppn().get_bytes_array().copy_from_slice(old_ppn.get_bytes_array());
+        let flags = page_table.translate(vpn).unwrap().flags();
+        page_table.unmap(vpn);
+        page_table.map(vpn, frame.ppn(), flags);
+        self.data_frames.insert(vpn, Arc::new(frame));
+        Ok(())
+    }
+    /// 将vpn在page_table对应的页表项删除，并将对应的物理页回收
+    /// Framed逻辑段中尚未驻留（如被MADV_DONTNEED释放）的页面没有页表项，直接跳过；
+    /// 映射到零页的页面只需删除页表项
+    #[allow(unused)]
+    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+        self.unmap_one_with(page_table, vpn, false);
+    }
+    /// 与unmap_one相同；clean为true时调用者保证页帧全为零，未被共享的页帧以干净的状态归还
+    fn unmap_one_with(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, clean: bool) {
+        #[allow(clippy::single_match)]
+        match self.map_type {
+            MapType::Framed => match self.data_frames.remove(&vpn) {
+                Some(frame) => {
+                    match Arc::try_unwrap(frame) {
+                        Ok(frame) if clean => frame.release_clean(),
+                        frame => drop(frame),
+                    }
+                    // 页帧（未被共享时）已经归还，把它重新承诺给这个页面
+                    if self.committed && frame_commit(1) {
+                        self.reserved += 1;
+                    }
+                }
+                None if !page_table.translate(vpn).map_or(false, |pte| pte.is_valid()) => return,
+                None => {}
+            },
+            _ => {}
+        }
+        page_table.unmap(vpn);
+    }
+
+    /// 将self.vpn_range中的所有vpn都分配一个对应的frame，并为他们在页表中创建页表项
+    pub fn map(&mut self, page_table: &mut PageTable) {
+        for vpn in self.vpn_range {
+            self.map_one(page_table, vpn);
+        }
+    }
+    /// map的可失败版本：中途分配失败时撤销本次已建立的映射并归还页帧，再返回错误
+    pub fn try_map(&mut self, page_table: &mut PageTable) -> Result<(), VmError> {
+        for vpn in self.vpn_range {
+            if let Err(err) = self.try_map_one(page_table, vpn) {
+                for mapped in VPNRange::new(self.vpn_range.get_start(), vpn) {
+                    self.unmap_one(page_table, mapped);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    /// 将self.vpn_range中所有可读的vpn都映射到共享的零页；
+    /// 不可读的页面（如只执行）无法只读地映射，留待缺页时再分配
+    pub fn map_zero(&mut self, page_table: &mut PageTable) {
+        if !self.map_perm.contains(MapPermission::R) {
+            return;
+        }
+        for vpn in self.vpn_range {
+            self.map_zero_one(page_table, vpn);
+        }
+    }
+    /// 在at处把逻辑段切成两段：self保留[start, at)，返回[at, end)及其页帧。
+    /// 切下的右半段不再向下增长
+    pub fn split_off(&mut self, at: VirtPageNum) -> MapArea {
+        assert!(self.contains(at) && at > self.vpn_range.get_start());
+        let mut right = MapArea::from_another(self);
+        right.vpn_range = VPNRange::new(at, self.vpn_range.get_end());
+        right.data_frames = self.data_frames.split_off(&at);
+        right.grows_down = None;
+        // 页帧承诺跟随尚未驻留的页面
+        right.committed = self.committed;
+        let right_pages = self.vpn_range.get_end().0 - at.0;
+        right.reserved = self.reserved.min(right_pages - right.data_frames.len());
+        self.reserved -= right.reserved;
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        right
+    }
+    /// 把向下增长的逻辑段的起始页改为new_start，新增的页面留待缺页时映射
+    pub fn expand_down(&mut self, new_start: VirtPageNum) {
+        assert!(new_start <= self.vpn_range.get_start());
+        self.vpn_range = VPNRange::new(new_start, self.vpn_range.get_end());
+    }
+    /// 解除[new_end, 原结尾)上的映射并把逻辑段的结尾改为new_end
+    pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
+            self.unmap_one(page_table, vpn);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
+    /// 把逻辑段的结尾扩展到new_end，新增的可读页面映射到零页
+    pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        let old_end = self.vpn_range.get_end();
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        if self.map_perm.contains(MapPermission::R) {
+            for vpn in VPNRange::new(old_end, new_end) {
+                self.map_zero_one(page_table, vpn);
+            }
+        }
+    }
+    /// 将self.vpn_range中的所有vpn对应的页表项都删除，并将相应的物理页回收
+    #[allow(unused)]
+    pub fn unmap(&mut self, page_table: &mut PageTable) {
+        for vpn in self.vpn_range {
+            self.unmap_one(page_table, vpn);
+        }
+    }
+    /// data: start-aligned but maybe with shorter length
+    /// assume that all frames were cleared before
+    /// 将切片 data 中的数据拷贝到当前逻辑段实际被内核放置在的各物理页帧上 （4.6）；
+    /// 逻辑段必须已经整段映射，按vpn顺序直接遍历data_frames整页拷贝，不再逐页查页表。
+    /// data比逻辑段长时不拷贝任何数据，返回Err(VmError::Overflow)
+    pub fn copy_data(&self, data: &[u8]) -> Result<(), VmError> {
+        self.copy_data_at(0, data)
+    }
+    /// 与copy_data相同，但把data拷贝到距逻辑段起点offset字节处（例如起始地址不按页对齐的ELF段）
+    pub fn copy_data_at(&self, offset: usize, data: &[u8]) -> Result<(), VmError> {
+        assert_eq!(self.map_type, MapType::Framed);
+        let pages = self.vpn_range.get_end().0 - self.vpn_range.get_start().0;
+        match offset.checked_add(data.len()) {
+            Some(end) if end <= pages * PAGE_SIZE => {}
+            _ => return Err(VmError::Overflow),
+        }
+        assert_eq!(self.data_frames.len(), pages, "copy_data: area is not fully mapped");
+        let mut src = data;
+        let mut page_offset = offset % PAGE_SIZE;
+        for frame in self.data_frames.values().skip(offset / PAGE_SIZE) {
+            if src.is_empty() {
+                break;
+            }
+            let n = src.len().min(PAGE_SIZE - page_offset);
+            frame.ppn().get_bytes_array()[page_offset..page_offset + n].copy_from_slice(&src[..n]);
+            src = &src[n..];
+            page_offset = 0;
+        }
+        Ok(())
+    }
+    #[allow(unused)]
+    /// copy_data原来的实现：每一页都通过页表查找物理页帧，仅用于对照测试和性能比较
+    pub fn copy_data_translated(&mut self, page_table: &mut PageTable, data: &[u8]) -> Result<(), VmError> {
+        assert_eq!(self.map_type, MapType::Framed);
+        let pages = self.vpn_range.get_end().0 - self.vpn_range.get_start().0;
+        if data.len() > pages * PAGE_SIZE {
+            return Err(VmError::Overflow);
+        }
+        let mut start: usize = 0;
+        let mut current_vpn = self.vpn_range.get_start();
+        let len = data.len();
+        loop {
+            let src = &data[start..len.min(start + PAGE_SIZE)];
+            let dst = &mut page_table
+                .translate(current_vpn)
+                .unwrap()
+                .ppn()
+                .get_bytes_array()[..src.len()];
+            dst.copy_from_slice(src);
+            start += PAGE_SIZE;
+            if start >= len {
+                break;
+            }
+            current_vpn.step();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MapArea {
+    /// 归还尚未用掉的页帧承诺
+    fn drop(&mut self) {
+        if self.reserved > 0 {
+            frame_uncommit(self.reserved);
+        }
+    }
+}
+
+/// MemorySet::snapshot保存的用户页面内容，None表示快照时页面尚未驻留（全零）
+pub struct Snapshot {
+    pages: Vec<(VirtPageNum, Option<Vec<u8>>)>,
+}
+
+#[derive(Copy, Clone, Debug)]
+/// 逻辑段的只读视图，由MemorySet::areas产生
+pub struct AreaView {
+    /// 起始虚拟地址（按页对齐）
+    pub start: VirtAddr,
+    /// 结束虚拟地址（不含，按页对齐）
+    pub end: VirtAddr,
+    pub perm: MapPermission,
+    pub kind: AreaKind,
+    /// 当前驻留的物理页帧数，不含映射到零页的页面
+    pub resident_frames: usize,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+/// 触发缺页异常的访问类型，由trap处理函数根据scause给出
+pub enum FaultKind {
+    /// 读（LoadPageFault）
+    Read,
+    /// 写（StorePageFault）
+    Write,
+    /// 取指（InstructionPageFault）
+    Execute,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+/// 一个虚拟页面的映射状态，由MemorySet::mapping_state给出
+pub enum MappingState {
+    /// 不属于任何逻辑段，也没有有效的页表项
+    Unmapped = 0,
+    /// 属于某个逻辑段，但还没有私有的物理页帧（尚未访问，或只映射到零页）
+    Reserved = 1,
+    /// 页表项有效且指向页面自己的物理页帧
+    Resident = 2,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+/// what a map area is used for
+pub enum AreaKind {
+    Kernel,
+    KernelStack,
+    Elf,
+    Stack,
+    TrapContext,
+    Mmap,
+    Heap,
+    #[cfg_attr(not(feature = "user-tls"), allow(dead_code))]
+    Tls,
+    /// 由map_phys映射到指定物理页帧（如MMIO）的区域
+    Phys,
+}
+
+impl AreaKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AreaKind::Kernel => "kernel",
+            AreaKind::KernelStack => "kstack",
+            AreaKind::Elf => "elf",
+            AreaKind::Stack => "stack",
+            AreaKind::TrapContext => "trap_cx",
+            AreaKind::Mmap => "mmap",
+            AreaKind::Heap => "heap",
+            AreaKind::Tls => "tls",
+            AreaKind::Phys => "phys",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+/// map type for memory set: identical or framed
+/// Linear(offset)把vpn映射到物理页号vpn + offset（按usize回绕），不拥有这些页帧
+pub enum MapType {
+    Identical,
+    Framed,
+    Linear(usize),
+}
+
+bitflags! {
+    /// map permission corresponding to that in pte: `R W X U`.
+    /// R(Read)/W(Write)/X(eXecute)：分别控制索引到这个页表项的对应虚拟页面是否允许读/写/执行,
+    /// U(User)：控制索引到这个页表项的对应虚拟页面是否在 CPU 处于 U 特权级的情况下是否被允许访问
+    pub struct MapPermission: u8 {
+        const R = 1 << 1;
+        const W = 1 << 2;
+        const X = 1 << 3;
+        const U = 1 << 4;
+    }
+}
+
+#[allow(unused)]
+pub fn remap_test() {
+    let mut kernel_space = KERNEL_SPACE.lock();
+    let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
+    let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
+    let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
+    assert!(!kernel_space
+        .page_table
+        .translate(mid_text.floor())
+        .unwrap()
+        .writable());
+    assert!(!kernel_space
         .page_table
         .translate(mid_rodata.floor())
         .unwrap()
@@ -440,4 +2479,1812 @@ pub fn remap_test() {
         .unwrap()
         .executable());
     info!("remap_test passed!");
-}
\ No newline at end of file
+}
+#[allow(unused)]
+/// 内核地址空间通过审计；改变某一页的权限或把它映射到别的页帧后审计能发现
+pub fn audit_kernel_test() {
+    assert_eq!(KERNEL_SPACE.lock().audit_kernel(), Ok(()));
+    let mut kernel_space = MemorySet::new_kernel();
+    assert_eq!(kernel_space.audit_kernel(), Ok(()));
+    let rodata = VirtAddr::from(srodata as usize).floor();
+    let flags = kernel_space.translate(rodata).unwrap().flags();
+    kernel_space.page_table.set_flags(rodata, flags | PTEFlags::W).unwrap();
+    assert!(kernel_space.audit_kernel().unwrap_err().starts_with(".rodata"));
+    kernel_space.page_table.set_flags(rodata, flags).unwrap();
+    assert_eq!(kernel_space.audit_kernel(), Ok(()));
+    let data = VirtAddr::from(sdata as usize).floor();
+    let flags = kernel_space.translate(data).unwrap().flags();
+    kernel_space.page_table.set_flags(data, flags | PTEFlags::X).unwrap();
+    assert!(kernel_space.audit_kernel().unwrap_err().starts_with(".data"));
+    kernel_space.page_table.set_flags(data, flags).unwrap();
+    kernel_space.page_table.unmap(data);
+    kernel_space.page_table.map(data, PhysPageNum(data.0 + 1), flags);
+    assert!(kernel_space.audit_kernel().unwrap_err().starts_with(".data"));
+    info!("audit_kernel_test passed!");
+}
+#[allow(unused)]
+/// 检查mmap/munmap/mprotect及地址转换在各类非法参数下返回正确的VmError
+pub fn vm_error_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start + 1, PAGE_SIZE, 3), Err(VmError::Unaligned));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 0), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3 | 8), Err(VmError::BadPermission));
+    // 写+执行（隐含可读）被W^X检查拒绝
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 6), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mmap(start, usize::MAX, 3), Err(VmError::Overflow));
+    assert_eq!(memory_set.mmap(USER_SPACE_END, PAGE_SIZE, 3), Err(VmError::Overflow));
+    // 比空闲物理内存更大的惰性映射可以建立，只有MAP_COMMIT要求页帧立即可用
+    let too_big = (get_num_empty_frame() + 1) * PAGE_SIZE;
+    assert_eq!(memory_set.mmap(start, too_big, 3 | MAP_COMMIT), Err(VmError::OutOfFrames));
+    assert_eq!(memory_set.mmap(start, too_big, 3), Ok(()));
+    assert_eq!(memory_set.munmap(start, too_big), Ok(()));
+    assert_eq!(memory_set.set_frame_quota(memory_set.framed_pages()), Ok(()));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.set_frame_quota(usize::MAX), Ok(()));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Err(VmError::Overlap));
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 1), Ok(()));
+    assert!(!memory_set.translate(VirtAddr(start).floor()).unwrap().writable());
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 8), Err(VmError::BadPermission));
+    // 只写按可读可写处理，因为SV39中W=1、R=0的页表项是保留的
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 2), Ok(()));
+    let pte = memory_set.translate(VirtAddr(start).floor()).unwrap();
+    assert!(pte.readable() && pte.writable());
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 1), Ok(()));
+    assert_eq!(memory_set.mprotect(start + PAGE_SIZE, PAGE_SIZE, 1), Err(VmError::NotMapped));
+    assert_eq!(memory_set.munmap(start, PAGE_SIZE + 1), Err(VmError::NotMapped));
+    assert_eq!(memory_set.munmap(start, PAGE_SIZE * 2), Err(VmError::NotMapped));
+    assert_eq!(memory_set.munmap(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.munmap(start, PAGE_SIZE), Err(VmError::NotMapped));
+    assert_eq!(
+        translated_byte_buffer(memory_set.token(), start as *const u8, 1).err(),
+        Some(VmError::NotMapped)
+    );
+    memory_set.insert_framed_area(start.into(), (start + PAGE_SIZE).into(), MapPermission::R).unwrap();
+    assert_eq!(
+        translated_byte_buffer(memory_set.token(), start as *const u8, 1).err(),
+        Some(VmError::Fault)
+    );
+    info!("vm_error_test passed!");
+}
+
+#[allow(unused)]
+/// 检查mmap_batch在存在重叠请求时不会映射任何区间
+pub fn mmap_batch_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    let requests = [
+        (start, PAGE_SIZE, 3),
+        (start + 2 * PAGE_SIZE, PAGE_SIZE, 1),
+        (start + PAGE_SIZE / 2 * 5, PAGE_SIZE, 3),
+    ];
+    assert_eq!(memory_set.mmap_batch(&requests), Err(VmError::Unaligned));
+    let requests = [
+        (start, PAGE_SIZE, 3),
+        (start + 2 * PAGE_SIZE, PAGE_SIZE, 1),
+        (start, 2 * PAGE_SIZE, 3),
+    ];
+    assert_eq!(memory_set.mmap_batch(&requests), Err(VmError::Overlap));
+    assert!(vpn_range_is_unused(&memory_set.page_table, start / PAGE_SIZE, 3));
+    assert!(memory_set.areas.is_empty());
+    assert_eq!(memory_set.mmap_batch(&requests[..2]), Ok(()));
+    assert!(memory_set.translate(VirtAddr(start).floor()).unwrap().readable());
+    assert!(vpn_range_is_unused(&memory_set.page_table, start / PAGE_SIZE + 1, 1));
+    assert!(memory_set.translate(VirtAddr(start + 2 * PAGE_SIZE).floor()).unwrap().readable());
+    info!("mmap_batch_test passed!");
+}
+
+#[allow(unused)]
+/// 检查MADV_DONTNEED释放页面后，再次访问会经缺页异常映射一个全零的页面
+pub fn madvise_test() {
+    let start: usize = 0x10000000;
+    let vpn = VirtAddr(start).floor();
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 2 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    memory_set.translate(vpn).unwrap().ppn().get_bytes_array().fill(0xff);
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.madvise_dontneed(start + 1, PAGE_SIZE), Err(VmError::Unaligned));
+    assert_eq!(memory_set.madvise_dontneed(start, 3 * PAGE_SIZE), Err(VmError::NotMapped));
+    assert_eq!(memory_set.madvise_dontneed(start, PAGE_SIZE), Ok(()));
+    assert_eq!(get_num_empty_frame(), empty_frames + 1);
+    assert!(vpn_range_is_unused(&memory_set.page_table, vpn.0, 1));
+    // 逻辑段仍然保留，不能在其上重新mmap
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Err(VmError::Overlap));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start + 8), FaultKind::Read), Ok(()));
+    assert!(memory_set
+        .translate(vpn)
+        .unwrap()
+        .ppn()
+        .get_bytes_array()
+        .iter()
+        .all(|byte| *byte == 0));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Read), Err(VmError::Fault));
+    assert_eq!(memory_set.madvise_dontneed(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.munmap(start, 2 * PAGE_SIZE), Ok(()));
+    info!("madvise_test passed!");
+}
+
+#[allow(unused)]
+/// 检查MADV_WILLNEED一次调入整个区间：之后每页都已驻留且可写，访问不再缺页；
+/// 页帧配额不够调入全部页面时什么都不做
+pub fn madvise_willneed_test() {
+    let start: usize = 0x10000000;
+    let pages = 8;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.page_counts(), (1, pages - 1));
+    assert_eq!(memory_set.set_frame_quota(pages - 1), Ok(()));
+    assert_eq!(memory_set.madvise_willneed(start, pages * PAGE_SIZE), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.page_counts(), (1, pages - 1));
+    assert_eq!(memory_set.set_frame_quota(usize::MAX), Ok(()));
+    assert_eq!(memory_set.madvise_willneed(start, (pages + 1) * PAGE_SIZE), Err(VmError::NotMapped));
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.madvise_willneed(start, pages * PAGE_SIZE), Ok(()));
+    assert_eq!(empty_frames - get_num_empty_frame(), pages - 1);
+    assert_eq!(memory_set.page_counts(), (pages, 0));
+    for i in 0..pages {
+        let pte = memory_set.translate(VirtAddr(start + i * PAGE_SIZE).floor()).unwrap();
+        assert!(pte.is_valid() && pte.writable() && pte.ppn() != ZERO_FRAME.ppn());
+    }
+    // 已经驻留的页面不受影响
+    assert_eq!(memory_set.madvise_willneed(start, pages * PAGE_SIZE), Ok(()));
+    assert_eq!(empty_frames - get_num_empty_frame(), pages - 1);
+    info!("madvise_willneed_test passed!");
+}
+
+#[allow(unused)]
+/// 检查内核访问用户区间前会先处理尚未驻留的页面和零页
+pub fn prepare_user_access_test() {
+    let start: usize = 0x10000000;
+    let vpn = VirtAddr(start).floor();
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 2 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.prepare_user_write(start, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.madvise_dontneed(start, 2 * PAGE_SIZE), Ok(()));
+    assert!(vpn_range_is_unused(&memory_set.page_table, vpn.0, 2));
+    // 读取：页面被映射到零页，不分配页帧
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.prepare_user_read(start + 8, PAGE_SIZE), Ok(()));
+    assert!(memory_set.is_zero_mapped(vpn) && memory_set.is_zero_mapped(VirtPageNum(vpn.0 + 1)));
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    // 已经可读的页面不会再次触发缺页处理
+    assert_eq!(memory_set.prepare_user_read(start, 2 * PAGE_SIZE), Ok(()));
+    // 写入：零页被替换为私有页帧
+    assert_eq!(memory_set.prepare_user_write(start, 8), Ok(()));
+    let pte = memory_set.translate(vpn).unwrap();
+    assert!(pte.writable() && pte.ppn() != ZERO_FRAME.ppn());
+    assert_eq!(get_num_empty_frame(), empty_frames - 1);
+    assert_eq!(memory_set.prepare_user_read(start + 2 * PAGE_SIZE, 8), Err(VmError::Fault));
+    assert_eq!(memory_set.prepare_user_read(usize::MAX, 8), Err(VmError::Overflow));
+    assert_eq!(memory_set.munmap(start, 2 * PAGE_SIZE), Ok(()));
+    info!("prepare_user_access_test passed!");
+}
+
+#[allow(unused)]
+/// 检查MAP_COMMIT在映射时就承诺页帧：超额承诺立即失败，已承诺的页面缺页时一定能分配到页帧
+pub fn mmap_commit_test() {
+    let a: usize = 0x10000000;
+    let b: usize = 0x80000000;
+    let free = get_num_empty_frame();
+    {
+        let mut memory_set = MemorySet::new_bare();
+        let pages = free / 2 + 1;
+        // 不承诺时两段映射各自都能通过检查，合起来超出了空闲页帧
+        assert_eq!(memory_set.mmap(a, pages * PAGE_SIZE, 3), Ok(()));
+        assert_eq!(memory_set.mmap(b, pages * PAGE_SIZE, 3), Ok(()));
+        assert_eq!(memory_set.munmap(a, pages * PAGE_SIZE), Ok(()));
+        assert_eq!(memory_set.munmap(b, pages * PAGE_SIZE), Ok(()));
+        assert_eq!(memory_set.mmap(a, pages * PAGE_SIZE, 3 | MAP_COMMIT), Ok(()));
+        assert_eq!(
+            memory_set.mmap(b, pages * PAGE_SIZE, 3 | MAP_COMMIT),
+            Err(VmError::OutOfFrames)
+        );
+        assert!(get_num_empty_frame() < free - pages);
+        // 其余页帧被全部占用后，已承诺的页面仍然可以写入
+        let mut hog = Vec::new();
+        while let Some(frame) = frame_alloc() {
+            hog.push(frame);
+        }
+        for i in 0..pages {
+            assert_eq!(memory_set.handle_page_fault(VirtAddr(a + i * PAGE_SIZE), FaultKind::Write), Ok(()));
+        }
+        drop(hog);
+        // 释放后重新承诺，再次写入也不会失败
+        assert_eq!(memory_set.madvise_dontneed(a, PAGE_SIZE), Ok(()));
+        assert_eq!(memory_set.munmap(a + PAGE_SIZE, PAGE_SIZE), Ok(()));
+        assert_eq!(memory_set.handle_page_fault(VirtAddr(a), FaultKind::Write), Ok(()));
+        assert_eq!(memory_set.mprotect(a, PAGE_SIZE, 1 | MAP_COMMIT), Err(VmError::BadPermission));
+    }
+    // 逻辑段释放时归还剩余的承诺
+    assert_eq!(get_num_empty_frame(), free);
+    info!("mmap_commit_test passed!");
+}
+
+#[allow(unused)]
+/// 检查restore把页面内容、驻留状态恢复到snapshot时的样子
+pub fn snapshot_test() {
+    let start: usize = 0x10000000;
+    let page = |i: usize| start + i * PAGE_SIZE;
+    let bytes = |memory_set: &MemorySet, i: usize| {
+        memory_set
+            .translate(VirtAddr(page(i)).floor())
+            .unwrap()
+            .ppn()
+            .get_bytes_array()
+    };
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 3 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.prepare_user_write(start, 2 * PAGE_SIZE), Ok(()));
+    bytes(&memory_set, 0).fill(0x11);
+    bytes(&memory_set, 1).fill(0x22);
+    let snapshot = memory_set.snapshot();
+    // 修改已驻留的页面、释放一个页面、写入原本未驻留的页面
+    bytes(&memory_set, 0).fill(0x33);
+    assert_eq!(memory_set.madvise_dontneed(page(1), PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.prepare_user_write(page(2), PAGE_SIZE), Ok(()));
+    bytes(&memory_set, 2).fill(0x44);
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.restore(&snapshot), Ok(()));
+    assert!(bytes(&memory_set, 0).iter().all(|byte| *byte == 0x11));
+    assert!(bytes(&memory_set, 1).iter().all(|byte| *byte == 0x22));
+    assert!(memory_set.is_zero_mapped(VirtAddr(page(2)).floor()));
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 快照中的页面被munmap之后无法恢复
+    assert_eq!(memory_set.munmap(page(1), PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.restore(&snapshot), Err(VmError::NotMapped));
+    info!("snapshot_test passed!");
+}
+
+/// contrived_elf构造的ELF中ELF头和每个程序头的大小，程序头表紧跟在ELF头之后
+const CONTRIVED_EHDR_SIZE: usize = 64;
+const CONTRIVED_PHDR_SIZE: usize = 56;
+
+/// 把val的低size字节按小端序写到buf[off..]
+fn put_le(buf: &mut [u8], off: usize, val: u64, size: usize) {
+    buf[off..off + size].copy_from_slice(&val.to_le_bytes()[..size]);
+}
+
+/// 构造一个只包含若干个LOAD段（无文件内容）的最小RISC-V ELF，段由(vaddr, mem_size)给出，权限为R|W
+#[allow(unused)]
+fn contrived_elf(segments: &[(usize, usize)]) -> Vec<u8> {
+    let mut elf = vec![0u8; CONTRIVED_EHDR_SIZE + CONTRIVED_PHDR_SIZE * segments.len()];
+    elf[..8].copy_from_slice(&[0x7f, 0x45, 0x4c, 0x46, 2, 1, 1, 0]);
+    put_le(&mut elf, 16, 2, 2); // e_type: EXEC
+    put_le(&mut elf, 18, 0xf3, 2); // e_machine: RISC-V
+    put_le(&mut elf, 20, 1, 4); // e_version
+    put_le(&mut elf, 24, segments[0].0 as u64, 8); // e_entry
+    put_le(&mut elf, 32, CONTRIVED_EHDR_SIZE as u64, 8); // e_phoff
+    put_le(&mut elf, 52, CONTRIVED_EHDR_SIZE as u64, 2); // e_ehsize
+    put_le(&mut elf, 54, CONTRIVED_PHDR_SIZE as u64, 2); // e_phentsize
+    put_le(&mut elf, 56, segments.len() as u64, 2); // e_phnum
+    put_le(&mut elf, 58, 64, 2); // e_shentsize
+    for (i, &(vaddr, mem_size)) in segments.iter().enumerate() {
+        let ph = CONTRIVED_EHDR_SIZE + CONTRIVED_PHDR_SIZE * i;
+        put_le(&mut elf, ph, 1, 4); // p_type: LOAD
+        put_le(&mut elf, ph + 4, 0x6, 4); // p_flags: R | W
+        put_le(&mut elf, ph + 24, vaddr as u64, 8); // p_paddr
+        put_le(&mut elf, ph + 48, PAGE_SIZE as u64, 8); // p_align
+        set_elf_mem_range(&mut elf, i, vaddr as u64, mem_size as u64);
+    }
+    elf
+}
+
+/// 把contrived_elf中第i个段的p_flags改为flags（R=4、W=2、X=1）
+#[allow(unused)]
+fn set_elf_flags(elf: &mut [u8], i: usize, flags: u32) {
+    put_le(elf, CONTRIVED_EHDR_SIZE + CONTRIVED_PHDR_SIZE * i + 4, flags as u64, 4);
+}
+
+/// 直接设置contrived_elf中第i个段的p_offset和p_filesz，不检查是否落在文件内，可用来构造畸形的ELF
+#[allow(unused)]
+fn set_elf_file_range(elf: &mut [u8], i: usize, offset: u64, file_size: u64) {
+    let ph = CONTRIVED_EHDR_SIZE + CONTRIVED_PHDR_SIZE * i;
+    put_le(elf, ph + 8, offset, 8); // p_offset
+    put_le(elf, ph + 32, file_size, 8); // p_filesz
+}
+
+/// 直接设置contrived_elf中第i个段的p_vaddr和p_memsz
+#[allow(unused)]
+fn set_elf_mem_range(elf: &mut [u8], i: usize, vaddr: u64, mem_size: u64) {
+    let ph = CONTRIVED_EHDR_SIZE + CONTRIVED_PHDR_SIZE * i;
+    put_le(elf, ph + 16, vaddr, 8); // p_vaddr
+    put_le(elf, ph + 40, mem_size, 8); // p_memsz
+}
+
+/// 把data追加到contrived_elf的末尾，作为第i个段的文件内容
+#[allow(unused)]
+fn set_elf_data(elf: &mut Vec<u8>, i: usize, data: &[u8]) {
+    let offset = elf.len();
+    elf.extend_from_slice(data);
+    set_elf_file_range(elf, i, offset as u64, data.len() as u64);
+}
+
+#[allow(unused)]
+/// 检查两个LOAD段共用一页时只分配一个页帧：两段的数据都在该页的正确位置上，
+/// 该页的权限是两段权限的并集，其余页面保持各自段的权限
+pub fn shared_elf_page_test() {
+    // 段0：[0x10000, 0x11800) R|X；段1：[0x11800, 0x12800) R|W，两段共用0x11这一页
+    let mut elf = contrived_elf(&[(0x10000, 0x1800), (0x11800, 0x1000)]);
+    set_elf_flags(&mut elf, 0, 0x5);
+    set_elf_data(&mut elf, 0, &vec![0xaa; 0x1800]);
+    set_elf_data(&mut elf, 1, &vec![0xbb; 0x900]);
+    let empty_frames = get_num_empty_frame();
+    let (memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let page = |vpn: usize| memory_set.translate(VirtPageNum(vpn)).unwrap();
+    let bytes = |vpn: usize| page(vpn).ppn().get_bytes_array();
+    assert!(bytes(0x10).iter().all(|&b| b == 0xaa));
+    assert!(bytes(0x11)[..0x800].iter().all(|&b| b == 0xaa));
+    assert!(bytes(0x11)[0x800..].iter().all(|&b| b == 0xbb));
+    assert!(bytes(0x12)[..0x100].iter().all(|&b| b == 0xbb));
+    assert!(bytes(0x12)[0x100..].iter().all(|&b| b == 0));
+    let rxu = PTEFlags::V | PTEFlags::R | PTEFlags::X | PTEFlags::U;
+    assert_eq!(page(0x10).flags(), rxu);
+    assert_eq!(page(0x11).flags(), rxu | PTEFlags::W);
+    assert_eq!(page(0x12).flags(), PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::U);
+    // 共用的页只属于一个逻辑段
+    assert_eq!(memory_set.areas.iter().filter(|area| area.contains(VirtPageNum(0x11))).count(), 1);
+    assert!(memory_set.verify_invariants().is_ok());
+    drop(memory_set);
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    // 第二个段完全落在第一个段的最后一页里
+    let mut elf = contrived_elf(&[(0x10000, 0x800), (0x10800, 0x100)]);
+    set_elf_data(&mut elf, 1, &[0xcc; 0x100]);
+    let (memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let bytes = memory_set.translate(VirtPageNum(0x10)).unwrap().ppn().get_bytes_array();
+    assert!(bytes[..0x800].iter().all(|&b| b == 0));
+    assert!(bytes[0x800..0x900].iter().all(|&b| b == 0xcc));
+    // 段0的文件内容在页中间结束、其后是.bss，段1从同一页的.bss之后开始且权限不同
+    let mut elf = contrived_elf(&[(0x10000, 0x1800), (0x11800, 0x800)]);
+    set_elf_flags(&mut elf, 0, 0x5);
+    set_elf_data(&mut elf, 0, &vec![0xaa; 0x1200]);
+    set_elf_data(&mut elf, 1, &[0xbb; 0x800]);
+    let (memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let pte = memory_set.translate(VirtPageNum(0x11)).unwrap();
+    let bytes = pte.ppn().get_bytes_array();
+    assert!(bytes[..0x200].iter().all(|&b| b == 0xaa));
+    assert!(bytes[0x200..0x800].iter().all(|&b| b == 0));
+    assert!(bytes[0x800..].iter().all(|&b| b == 0xbb));
+    assert!(pte.readable() && pte.writable() && pte.executable());
+    assert!(!memory_set.translate(VirtPageNum(0x10)).unwrap().writable());
+    // 重叠超过一页的段仍被拒绝
+    let elf = contrived_elf(&[(0x10000, 0x2000), (0x10800, 0x100)]);
+    assert_eq!(MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(), Some(VmError::Overlap));
+    // 共用一页但字节范围重叠的段被拒绝
+    let elf = contrived_elf(&[(0x10000, 0x900), (0x10800, 0x100)]);
+    assert_eq!(MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(), Some(VmError::Overlap));
+    // 与更早的（不是上一个）段相交的段被拒绝，而不是重复映射
+    let elf = contrived_elf(&[(0x10000, 0x1000), (0x20000, 0x1000), (0x10000, 0x100)]);
+    assert_eq!(MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(), Some(VmError::Overlap));
+    info!("shared_elf_page_test passed!");
+}
+
+#[allow(unused)]
+/// 检查munmap只能解除mmap申请的映射：ELF段、用户栈、Trap上下文都被拒绝且保持原样
+pub fn munmap_owned_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, user_sp, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.munmap(0x10000, PAGE_SIZE), Err(VmError::NotOwned));
+    assert_eq!(memory_set.munmap(user_sp - PAGE_SIZE, PAGE_SIZE), Err(VmError::NotOwned));
+    // Trap上下文在用户地址空间之外
+    assert_eq!(memory_set.munmap(TRAP_CONTEXT, PAGE_SIZE), Err(VmError::Overflow));
+    // 紧挨着ELF段的mmap区域可以单独解除，但不能连同ELF段一起解除
+    assert_eq!(memory_set.mmap(0x11000, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.munmap(0x10000, 2 * PAGE_SIZE), Err(VmError::NotOwned));
+    assert!(memory_set.translate(VirtPageNum(0x11)).unwrap().is_valid());
+    assert_eq!(memory_set.munmap(0x11000, PAGE_SIZE), Ok(()));
+    assert!(memory_set.translate(VirtPageNum(0x10)).unwrap().is_valid());
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("munmap_owned_test passed!");
+}
+
+#[allow(unused)]
+/// 检查from_elf在用户栈会与某个高地址段重叠时拒绝构建地址空间
+pub fn user_stack_overlap_test() {
+    // 用户栈放在最后一个段之后（隔一个guard page），即[0x12000, 0x12000 + USER_STACK_SIZE)
+    let elf = contrived_elf(&[(0x13000, PAGE_SIZE), (0x10000, PAGE_SIZE)]);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overlap)
+    );
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE), (0x20000, PAGE_SIZE)]);
+    let (_, user_sp, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(user_sp, 0x22000 + USER_STACK_SIZE);
+    info!("user_stack_overlap_test passed!");
+}
+
+#[allow(unused)]
+/// 检查map_anon/write_bytes/read_bytes可以不借助ELF搭建并校验用户内存
+pub fn map_anon_test() {
+    let start: usize = 0x10000000;
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.map_anon(start, 2 * PAGE_SIZE, perm), Ok(()));
+    assert_eq!(
+        memory_set.map_anon(start + PAGE_SIZE, PAGE_SIZE, perm),
+        Err(VmError::Overlap)
+    );
+    assert_eq!(memory_set.map_anon(start + 1, PAGE_SIZE, perm), Err(VmError::Unaligned));
+    assert_eq!(
+        memory_set.map_anon(start + 4 * PAGE_SIZE, PAGE_SIZE, MapPermission::U),
+        Err(VmError::BadPermission)
+    );
+    // 跨越页边界的写入和读取
+    let data: Vec<u8> = (0..64u8).collect();
+    let va = start + PAGE_SIZE - 32;
+    assert_eq!(memory_set.write_bytes(va, &data), Ok(()));
+    assert_eq!(memory_set.read_bytes(va, data.len()), Ok(data));
+    assert_eq!(memory_set.read_bytes(start, 4), Ok(vec![0; 4]));
+    assert_eq!(memory_set.munmap(start, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.read_bytes(va, 1), Err(VmError::NotMapped));
+    assert_eq!(memory_set.write_bytes(va, &[1]), Err(VmError::Fault));
+    info!("map_anon_test passed!");
+}
+
+#[allow(unused)]
+/// 检查from_elf拒绝文件内容超出ELF末尾的LOAD段，而不是在切片时panic
+pub fn elf_bounds_test() {
+    let mut elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let len = elf.len() as u64;
+    set_elf_file_range(&mut elf, 0, len - 8, 16);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    set_elf_file_range(&mut elf, 0, len + PAGE_SIZE as u64, 0);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    set_elf_file_range(&mut elf, 0, u64::MAX, 1);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    // 恰好到文件末尾的段是合法的
+    set_elf_file_range(&mut elf, 0, 0, len);
+    assert!(MemorySet::try_from_elf(&elf, USER_STACK_SIZE).is_ok());
+    // virtual_addr + mem_size回绕或超出用户地址空间
+    assert_eq!(VirtAddr(usize::MAX - 1).checked_add(2), None);
+    assert_eq!(PhysAddr(usize::MAX).checked_add(1), None);
+    assert_eq!(VirtAddr(0x10000).checked_add(PAGE_SIZE).map(|va| va.0), Some(0x11000));
+    set_elf_mem_range(&mut elf, 0, 0x10000, u64::MAX - 0x8000);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    set_elf_mem_range(&mut elf, 0, 0x10000, USER_SPACE_END as u64);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    // 段本身合法，但其上方放不下保护页和用户栈
+    let top = (USER_SPACE_END - 2 * PAGE_SIZE) as u64;
+    set_elf_file_range(&mut elf, 0, 0, 0);
+    set_elf_mem_range(&mut elf, 0, top, PAGE_SIZE as u64);
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    // 无法解析的头部、错误的魔数和越界的程序头表都返回BadElf而不是panic
+    let before = get_num_empty_frame();
+    assert_eq!(
+        MemorySet::try_from_elf(&elf[..16], USER_STACK_SIZE).err(),
+        Some(VmError::BadElf)
+    );
+    let mut bad_magic = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    bad_magic[0] = 0;
+    assert_eq!(
+        MemorySet::try_from_elf(&bad_magic, USER_STACK_SIZE).err(),
+        Some(VmError::BadElf)
+    );
+    let mut bad_phoff = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    bad_phoff[32..40].copy_from_slice(&u64::MAX.to_le_bytes()); // e_phoff
+    assert_eq!(
+        MemorySet::try_from_elf(&bad_phoff, USER_STACK_SIZE).err(),
+        Some(VmError::BadElf)
+    );
+    assert_eq!(get_num_empty_frame(), before);
+    info!("elf_bounds_test passed!");
+}
+
+#[allow(unused)]
+/// 检查开启缓存着色的逻辑段按颜色轮流为页面分配页帧
+pub fn cache_coloring_test() {
+    let mut page_table = PageTable::new();
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    let pages = FRAME_COLORS + 3;
+    let start = VirtAddr(0x10000000);
+    let mut area = MapArea::new(start, VirtAddr(start.0 + pages * PAGE_SIZE), MapType::Framed, perm)
+        .with_cache_coloring();
+    area.map(&mut page_table);
+    for (i, vpn) in area.vpn_range.into_iter().enumerate() {
+        assert_eq!(area.data_frames[&vpn].ppn().0 % FRAME_COLORS, i % FRAME_COLORS);
+    }
+    area.unmap(&mut page_table);
+    info!("cache_coloring_test passed!");
+}
+
+#[allow(unused)]
+/// 检查copy_data拒绝比逻辑段长的数据，from_elf遇到file_size大于mem_size的段时干净地失败
+pub fn copy_data_overflow_test() {
+    let mut page_table = PageTable::new();
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    let start = VirtAddr(0x10000000);
+    let mut area = MapArea::new(start, VirtAddr(0x10000000 + 2 * PAGE_SIZE), MapType::Framed, perm);
+    area.map(&mut page_table);
+    let data = vec![0x5au8; 2 * PAGE_SIZE + 1];
+    assert_eq!(area.copy_data(&data), Err(VmError::Overflow));
+    assert_eq!(area.copy_data(&data[..2 * PAGE_SIZE]), Ok(()));
+    area.unmap(&mut page_table);
+    // 一页的段带着两页多的文件内容
+    let mut elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    set_elf_data(&mut elf, 0, &vec![0x5a; 2 * PAGE_SIZE + 8]);
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).err(),
+        Some(VmError::Overflow)
+    );
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    info!("copy_data_overflow_test passed!");
+}
+
+#[allow(unused)]
+/// 检查直接遍历data_frames的copy_data与逐页查页表的原实现拷贝结果逐字节相同，并比较两者的耗时
+pub fn copy_data_fast_path_test() {
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    let start = VirtAddr(0x10000000);
+    let new_area = |pages: usize| {
+        MapArea::new(start, VirtAddr(start.0 + pages * PAGE_SIZE), MapType::Framed, perm)
+    };
+    // 最后一页只拷贝一部分
+    let pages = 4;
+    let data: Vec<u8> = (0..3 * PAGE_SIZE + 123).map(|i| (i * 7 + i / PAGE_SIZE) as u8).collect();
+    let mut page_table_a = PageTable::new();
+    let mut page_table_b = PageTable::new();
+    let mut area_a = new_area(pages);
+    let mut area_b = new_area(pages);
+    area_a.map(&mut page_table_a);
+    area_b.map(&mut page_table_b);
+    assert_eq!(area_a.copy_data(&data), Ok(()));
+    assert_eq!(area_b.copy_data_translated(&mut page_table_b, &data), Ok(()));
+    for (frame_a, frame_b) in area_a.data_frames.values().zip(area_b.data_frames.values()) {
+        assert!(frame_a.ppn().get_bytes_array() == frame_b.ppn().get_bytes_array());
+    }
+    area_a.unmap(&mut page_table_a);
+    area_b.unmap(&mut page_table_b);
+    // 用一个较大的段比较两种实现的耗时
+    let pages = 64;
+    let data = vec![0x5au8; pages * PAGE_SIZE];
+    let mut page_table = PageTable::new();
+    let mut area = new_area(pages);
+    area.map(&mut page_table);
+    let t0 = get_time();
+    assert_eq!(area.copy_data_translated(&mut page_table, &data), Ok(()));
+    let t1 = get_time();
+    assert_eq!(area.copy_data(&data), Ok(()));
+    let t2 = get_time();
+    info!(
+        "copy_data of {} pages: {} ticks via page table, {} ticks via data_frames",
+        pages,
+        t1 - t0,
+        t2 - t1
+    );
+    area.unmap(&mut page_table);
+    info!("copy_data_fast_path_test passed!");
+}
+
+#[allow(unused)]
+/// 检查可以为任务指定更大的用户栈，且多出来的页面都已映射
+pub fn user_stack_size_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let stack_size = USER_STACK_SIZE * 4;
+    let (memory_set, user_sp, _) = MemorySet::try_from_elf(&elf, stack_size).unwrap();
+    let stack_bottom = user_sp - stack_size;
+    assert!(vpn_range_is_used(
+        &memory_set.page_table,
+        stack_bottom / PAGE_SIZE,
+        stack_size / PAGE_SIZE
+    ));
+    // guard page
+    assert!(vpn_range_is_unused(&memory_set.page_table, stack_bottom / PAGE_SIZE - 1, 1));
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, PAGE_SIZE + 1).err(),
+        Some(VmError::Unaligned)
+    );
+    assert_eq!(
+        MemorySet::try_from_elf(&elf, (get_num_empty_frame() + 1) * PAGE_SIZE).err(),
+        Some(VmError::OutOfFrames)
+    );
+    info!("user_stack_size_test passed!");
+}
+
+#[allow(unused)]
+#[cfg(feature = "user-tls")]
+/// 检查每个地址空间都有一页私有的TLS，且mmap不能覆盖它
+pub fn tls_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set_a, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let (memory_set_b, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let tls_vpn = VirtAddr::from(TLS_BASE).floor();
+    let pte_a = memory_set_a.translate(tls_vpn).unwrap();
+    let pte_b = memory_set_b.translate(tls_vpn).unwrap();
+    assert!(pte_a.is_valid() && pte_a.writable() && pte_a.user_accessible());
+    assert!(pte_a.ppn() != pte_b.ppn());
+    pte_a.ppn().get_bytes_array()[0] = 0x5a;
+    assert_eq!(pte_b.ppn().get_bytes_array()[0], 0);
+    assert_eq!(memory_set_a.mmap(TLS_BASE, PAGE_SIZE, 3), Err(VmError::Overlap));
+    assert_eq!(
+        memory_set_a.mmap(TLS_BASE - PAGE_SIZE, 2 * PAGE_SIZE, 3),
+        Err(VmError::Overlap)
+    );
+    assert!(memory_set_a
+        .areas()
+        .any(|area| area.kind == AreaKind::Tls && area.start.0 == TLS_BASE));
+    info!("tls_test passed!");
+}
+
+#[allow(unused)]
+/// 检查两个地址空间中的大片匿名映射共享同一个零页，直到写入时才分配私有页帧
+pub fn zero_page_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 256;
+    let zero_ppn = ZERO_FRAME.ppn();
+    let empty_frames = get_num_empty_frame();
+    let mut memory_set_a = MemorySet::new_bare();
+    let mut memory_set_b = MemorySet::new_bare();
+    assert_eq!(memory_set_a.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set_b.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    // 只有页表节点占用了物理页帧
+    let used = empty_frames - get_num_empty_frame();
+    assert!(used <= 8, "{} frames used by zero mappings", used);
+    for i in 0..pages {
+        let vpn = VirtPageNum(start / PAGE_SIZE + i);
+        assert!(memory_set_a.translate(vpn).unwrap().ppn() == zero_ppn);
+        assert!(memory_set_b.translate(vpn).unwrap().ppn() == zero_ppn);
+        assert!(!memory_set_a.translate(vpn).unwrap().writable());
+    }
+    let vpn = VirtAddr(start).floor();
+    assert_eq!(memory_set_a.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    assert_eq!(empty_frames - get_num_empty_frame(), used + 1);
+    let pte = memory_set_a.translate(vpn).unwrap();
+    assert!(pte.writable() && pte.ppn() != zero_ppn);
+    assert!(memory_set_b.translate(vpn).unwrap().ppn() == zero_ppn);
+    assert!(zero_ppn.get_bytes_array().iter().all(|byte| *byte == 0));
+    info!("zero_page_test passed!");
+}
+
+#[allow(unused)]
+#[cfg(feature = "access-trace")]
+/// 检查访问跟踪：开启后每个用户页面只在第一次访问时缺页，记录的页号按首次访问排序；
+/// 写被跟踪的零页时仍会复制出私有页帧，关闭后所有页面恢复U位
+pub fn access_trace_test() {
+    let start: usize = 0x10000000;
+    let base = VirtAddr(start).floor().0;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 4 * PAGE_SIZE, 3), Ok(()));
+    for i in 0..2 {
+        assert_eq!(memory_set.handle_page_fault(VirtAddr(start + i * PAGE_SIZE), FaultKind::Write), Ok(()));
+    }
+    memory_set.trace_accesses(true);
+    for i in 0..4 {
+        assert!(!memory_set.translate(VirtPageNum(base + i)).unwrap().user_accessible());
+    }
+    // 模拟用户态的循环：页表项没有U位的页面才会缺页
+    let mut faults = 0;
+    for &(page, write) in [(2, false), (0, false), (2, false), (0, true), (1, false), (2, true), (0, false)].iter() {
+        let vpn = VirtPageNum(base + page);
+        let pte = memory_set.translate(vpn).unwrap();
+        if !pte.user_accessible() || (write && !pte.writable()) {
+            faults += 1;
+            let kind = if write { FaultKind::Write } else { FaultKind::Read };
+            assert_eq!(memory_set.handle_page_fault(vpn.into(), kind), Ok(()));
+        }
+        let pte = memory_set.translate(vpn).unwrap();
+        assert!(pte.user_accessible() && (!write || pte.writable()));
+    }
+    // 页面2先以零页被跟踪，写入时又因写时复制缺页一次
+    assert_eq!(faults, 4);
+    let trace: Vec<usize> = memory_set.take_access_trace().iter().map(|vpn| vpn.0 - base).collect();
+    assert_eq!(trace, vec![2, 0, 1]);
+    assert!(memory_set.translate(VirtPageNum(base + 2)).unwrap().ppn() != ZERO_FRAME.ppn());
+    memory_set.trace_accesses(false);
+    assert!(memory_set.translate(VirtPageNum(base + 3)).unwrap().user_accessible());
+    assert!(memory_set.take_access_trace().is_empty());
+    info!("access_trace_test passed!");
+}
+
+#[allow(unused)]
+/// 检查缺页时安装的页表项权限与逻辑段一致：只读逻辑段的读缺页得到只读页面，写缺页失败且不会变为可写；
+/// 只执行的逻辑段只能由取指缺页映射
+pub fn fault_permission_test() {
+    let start: usize = 0x10000000;
+    let vpn = VirtAddr(start).floor();
+    let mut memory_set = MemorySet::new_bare();
+    // 先写入使页面驻留，改为只读后再释放，得到一个未驻留的只读页面
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 1), Ok(()));
+    assert_eq!(memory_set.madvise_dontneed(start, PAGE_SIZE), Ok(()));
+    assert!(!memory_set.translate(vpn).map_or(false, |pte| pte.is_valid()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Read), Ok(()));
+    let pte = memory_set.translate(vpn).unwrap();
+    assert_eq!(pte.flags(), PTEFlags::V | PTEFlags::R | PTEFlags::U);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Err(VmError::Fault));
+    assert_eq!(memory_set.translate(vpn).unwrap().flags(), PTEFlags::V | PTEFlags::R | PTEFlags::U);
+    // 可写逻辑段的写缺页得到的页面恰好是R|W|U，不会多出X
+    let rw = start + 2 * PAGE_SIZE;
+    assert_eq!(memory_set.mmap(rw, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(rw), FaultKind::Write), Ok(()));
+    assert_eq!(
+        memory_set.translate(VirtAddr(rw).floor()).unwrap().flags(),
+        PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::U
+    );
+    // 只执行的映射在取指缺页时映射为只可执行的零页，读写缺页都失败；不可执行的映射不能取指
+    let xo = start + 4 * PAGE_SIZE;
+    assert_eq!(memory_set.mmap(xo, PAGE_SIZE, 4), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(xo), FaultKind::Read), Err(VmError::Fault));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(xo), FaultKind::Execute), Ok(()));
+    let pte = memory_set.translate(VirtAddr(xo).floor()).unwrap();
+    assert_eq!(pte.flags(), PTEFlags::V | PTEFlags::X | PTEFlags::U);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(xo), FaultKind::Write), Err(VmError::Fault));
+    let lazy_rw = start + 5 * PAGE_SIZE;
+    assert_eq!(memory_set.mmap(lazy_rw, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(lazy_rw), FaultKind::Execute), Err(VmError::Fault));
+    assert!(!memory_set.translate(VirtAddr(lazy_rw).floor()).map_or(false, |pte| pte.is_valid()));
+    info!("fault_permission_test passed!");
+}
+
+#[allow(unused)]
+/// 对逻辑段的一部分mprotect后，区间内尚未驻留或映射到零页的页面在之后的缺页中按新权限映射，
+/// 区间外的页面仍按原权限映射
+pub fn mprotect_partial_test() {
+    let start: usize = 0x10000000;
+    let page = |i: usize| start + i * PAGE_SIZE;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 4 * PAGE_SIZE, 3), Ok(()));
+    // 第2页先映射到零页，第1页保持未驻留
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(2)), FaultKind::Read), Ok(()));
+    assert_eq!(memory_set.mprotect(page(1), 2 * PAGE_SIZE, 1), Ok(()));
+    assert_eq!(memory_set.areas().count(), 3);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(1)), FaultKind::Write), Err(VmError::Fault));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(2)), FaultKind::Write), Err(VmError::Fault));
+    assert!(!memory_set.translate(VirtAddr(page(2)).floor()).unwrap().writable());
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(1)), FaultKind::Read), Ok(()));
+    assert!(!memory_set.translate(VirtAddr(page(1)).floor()).unwrap().writable());
+    // 区间两侧仍然可写
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(0)), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(3)), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 恢复可写之后写缺页再次成功
+    assert_eq!(memory_set.mprotect(page(1), PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(page(1)), FaultKind::Write), Ok(()));
+    assert!(memory_set.translate(VirtAddr(page(1)).floor()).unwrap().writable());
+    assert_eq!(memory_set.munmap(start, 4 * PAGE_SIZE), Ok(()));
+    info!("mprotect_partial_test passed!");
+}
+
+#[allow(unused)]
+/// 检查page_counts把写过的懒分配页面计为resident，其余页面（包括只读过的零页）计为reserved
+pub fn page_counts_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.page_counts(), (0, 0));
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.page_counts(), (0, pages));
+    for i in 0..pages / 2 {
+        let va = VirtAddr(start + i * 2 * PAGE_SIZE);
+        assert_eq!(memory_set.handle_page_fault(va, FaultKind::Write), Ok(()));
+    }
+    assert_eq!(memory_set.page_counts(), (pages / 2, pages / 2));
+    // 释放的页面重新变为reserved
+    assert_eq!(memory_set.madvise_dontneed(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.page_counts(), (pages / 2 - 1, pages / 2 + 1));
+    info!("page_counts_test passed!");
+}
+
+#[allow(unused)]
+/// 检查clear_user_areas回收了用户段和对应的页表节点，但保留跳板和Trap上下文
+pub fn clear_user_areas_test() {
+    let empty_frames = get_num_empty_frame();
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, user_sp, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.mmap(0x10000000, PAGE_SIZE, 3), Ok(()));
+    memory_set.clear_user_areas();
+    let trampoline = memory_set.translate(VirtAddr::from(TRAMPOLINE).floor()).unwrap();
+    assert!(trampoline.is_valid());
+    let trap_cx = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).floor()).unwrap();
+    assert!(trap_cx.is_valid());
+    let stack_vpn = VirtAddr::from(user_sp - PAGE_SIZE).floor();
+    assert!(memory_set.translate(stack_vpn).map_or(true, |pte| !pte.is_valid()));
+    assert!(memory_set.translate(VirtAddr::from(0x10000).floor()).is_none());
+    // 剩下：根节点、跳板与Trap上下文共用的两级节点、Trap上下文所在的页帧
+    assert_eq!(empty_frames - get_num_empty_frame(), 4);
+    info!("clear_user_areas_test passed!");
+}
+
+#[allow(unused)]
+/// 检查用户地址空间（含fork出的副本）都把共享的时间页映射为用户只读
+pub fn time_page_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let child = MemorySet::from_existed_user(&memory_set).unwrap();
+    for memory_set in [&memory_set, &child] {
+        let pte = memory_set.translate(VirtAddr::from(TIME_PAGE).floor()).unwrap();
+        assert!(pte.is_valid() && pte.readable() && pte.user_accessible());
+        assert!(!pte.writable() && !pte.executable());
+        assert!(pte.ppn() == TIME_FRAME.ppn());
+        assert_eq!(memory_set.verify_invariants(), Ok(()));
+    }
+    // 内核地址空间中没有时间页
+    let kernel_pte = KERNEL_SPACE.lock().translate(VirtAddr::from(TIME_PAGE).floor());
+    assert!(kernel_pte.map_or(true, |pte| !pte.is_valid()));
+    info!("time_page_test passed!");
+}
+
+#[allow(unused)]
+/// 检查W^X：默认拒绝同时可写且可执行的mmap/mprotect，带MAP_JIT或关闭检查后允许
+pub fn wx_strict_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    // 写+执行隐含可读，同样被拒绝
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 6), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 7), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 5), Ok(()));
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 7), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mprotect(start, PAGE_SIZE, 7 | MAP_JIT), Ok(()));
+    assert_eq!(memory_set.mmap(start + PAGE_SIZE, PAGE_SIZE, 7 | MAP_JIT), Ok(()));
+    let requests = [(start + 2 * PAGE_SIZE, PAGE_SIZE, 7)];
+    assert_eq!(memory_set.mmap_batch(&requests), Err(VmError::BadPermission));
+    // fork出的地址空间继承设置
+    memory_set.set_wx_strict(false);
+    let mut child = MemorySet::from_existed_user(&memory_set).unwrap();
+    assert_eq!(child.mmap(start + 2 * PAGE_SIZE, PAGE_SIZE, 7), Ok(()));
+    assert_eq!(child.mmap(start + 3 * PAGE_SIZE, PAGE_SIZE, 6), Err(VmError::BadPermission));
+    info!("wx_strict_test passed!");
+}
+
+#[allow(unused)]
+/// 检查ELF的.text被标记为不可变：mprotect不能给它加上W权限（MAP_JIT也不行），去掉X之后同样不行，
+/// fork出的地址空间继承这一限制；其他段不受影响，清除标记后恢复正常
+pub fn immutable_text_test() {
+    let text = 0x10000;
+    let data = 0x11000;
+    let mut elf = contrived_elf(&[(text, PAGE_SIZE), (data, PAGE_SIZE)]);
+    set_elf_flags(&mut elf, 0, 0x5); // R | X
+    let (mut memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.mprotect(text, PAGE_SIZE, 3), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mprotect(text, PAGE_SIZE, 7 | MAP_JIT), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mprotect(text, 2 * PAGE_SIZE, 3), Err(VmError::BadPermission));
+    assert!(!memory_set.translate(VirtAddr(text).floor()).unwrap().writable());
+    assert!(memory_set.translate(VirtAddr(data).floor()).unwrap().writable());
+    assert_eq!(memory_set.mprotect(data, PAGE_SIZE, 1), Ok(()));
+    assert_eq!(memory_set.mprotect(data, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.mprotect(text, PAGE_SIZE, 1), Ok(()));
+    assert_eq!(memory_set.mprotect(text, PAGE_SIZE, 3), Err(VmError::BadPermission));
+    let mut child = MemorySet::from_existed_user(&memory_set).unwrap();
+    assert_eq!(child.mprotect(text, PAGE_SIZE, 3), Err(VmError::BadPermission));
+    memory_set.set_text_immutable(false);
+    assert_eq!(memory_set.mprotect(text, PAGE_SIZE, 3), Ok(()));
+    assert!(memory_set.translate(VirtAddr(text).floor()).unwrap().writable());
+    info!("immutable_text_test passed!");
+}
+
+#[allow(unused)]
+/// 检查mapping_state区分空洞、惰性映射尚未驻留的页面和已驻留的页面
+pub fn mapping_state_test() {
+    let start: usize = 0x10000000;
+    let vpn = |i: usize| VirtAddr(start + i * PAGE_SIZE).floor();
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 3 * PAGE_SIZE, 3), Ok(()));
+    for i in 0..3 {
+        assert_eq!(memory_set.mapping_state(vpn(i)), MappingState::Reserved);
+    }
+    assert_eq!(memory_set.mapping_state(vpn(3)), MappingState::Unmapped);
+    // 读只映射零页，仍然是Reserved；写才分配私有页帧
+    assert_eq!(memory_set.handle_page_fault(vpn(0).into(), FaultKind::Read), Ok(()));
+    assert!(memory_set.translate(vpn(0)).unwrap().is_valid());
+    assert_eq!(memory_set.mapping_state(vpn(0)), MappingState::Reserved);
+    assert_eq!(memory_set.handle_page_fault(vpn(1).into(), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.mapping_state(vpn(1)), MappingState::Resident);
+    assert_eq!(memory_set.munmap(start, 3 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.mapping_state(vpn(1)), MappingState::Unmapped);
+    info!("mapping_state_test passed!");
+}
+
+#[allow(unused)]
+/// 检查物理页帧配额：超出配额的mmap和写缺页都会被拒绝
+pub fn frame_quota_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.set_frame_quota(2), Ok(()));
+    assert_eq!(memory_set.mmap(start, 3 * PAGE_SIZE, 3), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.mmap(start, 2 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.mmap(start + 2 * PAGE_SIZE, PAGE_SIZE, 3), Ok(()));
+    for i in 0..2 {
+        let va = VirtAddr(start + i * PAGE_SIZE);
+        assert_eq!(memory_set.handle_page_fault(va, FaultKind::Write), Ok(()));
+    }
+    assert_eq!(
+        memory_set.handle_page_fault(VirtAddr(start + 2 * PAGE_SIZE), FaultKind::Write),
+        Err(VmError::QuotaExceeded)
+    );
+    assert_eq!(memory_set.framed_pages(), 2);
+    assert_eq!(memory_set.set_frame_quota(1), Err(VmError::QuotaExceeded));
+    info!("frame_quota_test passed!");
+}
+
+#[allow(unused)]
+/// 检查虚拟地址配额：总大小超出配额的mmap被拒绝，即使其中的页面从未被访问
+pub fn virtual_quota_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.virtual_size(), 0);
+    assert_eq!(memory_set.set_virtual_quota(8 * PAGE_SIZE), Ok(()));
+    // 远大于物理内存的惰性映射被配额拒绝，而不是因为页帧不足
+    assert_eq!(memory_set.mmap(start, 1 << 30, 3), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.mmap(start, 9 * PAGE_SIZE, 3), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.mmap(start, 6 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.virtual_size(), 6 * PAGE_SIZE);
+    assert_eq!(memory_set.framed_pages(), 0);
+    let requests = [(start + 8 * PAGE_SIZE, PAGE_SIZE, 3), (start + 10 * PAGE_SIZE, 2 * PAGE_SIZE, 3)];
+    assert_eq!(memory_set.mmap_batch(&requests), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.mmap(start + 8 * PAGE_SIZE, 2 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.set_virtual_quota(4 * PAGE_SIZE), Err(VmError::QuotaExceeded));
+    assert_eq!(memory_set.munmap(start, 6 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.virtual_size(), 2 * PAGE_SIZE);
+    info!("virtual_quota_test passed!");
+}
+
+#[allow(unused)]
+/// 检查reclaim_one_page不会回收被钉住的页面和Trap上下文
+pub fn pin_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    for i in 0..pages {
+        let va = VirtAddr(start + i * PAGE_SIZE);
+        assert_eq!(memory_set.handle_page_fault(va, FaultKind::Write), Ok(()));
+    }
+    assert_eq!(memory_set.pin_range(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.pin_range(start + pages * PAGE_SIZE, PAGE_SIZE), Err(VmError::NotMapped));
+    let pinned_ppn = memory_set.translate(VirtAddr(start).floor()).unwrap().ppn();
+    // 一直回收直到没有可回收的页面（ELF段、用户栈和mmap区域中的零页面）
+    while let Some(vpn) = memory_set.reclaim_one_page() {
+        assert!(vpn != VirtAddr(start).floor());
+    }
+    assert!(memory_set.translate(VirtAddr(start).floor()).unwrap().ppn() == pinned_ppn);
+    for i in 1..pages {
+        let vpn = VirtAddr(start + i * PAGE_SIZE).floor();
+        assert!(memory_set.translate(vpn).unwrap().ppn() == ZERO_FRAME.ppn());
+    }
+    let trap_cx = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).floor()).unwrap();
+    assert!(trap_cx.ppn() != ZERO_FRAME.ppn());
+    assert_eq!(memory_set.unpin_range(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.reclaim_one_page(), Some(VirtAddr(start).floor()));
+    info!("pin_test passed!");
+}
+
+#[allow(unused)]
+/// 检查mlock让页面立即驻留，在回收压力下锁定的页面保持驻留而未锁定的被回收；
+/// 嵌套锁定需要同样次数的munlock才能解除，MADV_DONTNEED也跳过锁定的页面
+pub fn mlock_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let vpn = |i: usize| VirtAddr(start + i * PAGE_SIZE).floor();
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.mlock(start + 1, PAGE_SIZE), Err(VmError::Unaligned));
+    assert_eq!(memory_set.mlock(start, (pages + 1) * PAGE_SIZE), Err(VmError::NotMapped));
+    assert_eq!(memory_set.lock_count(vpn(0)), 0);
+    // 锁定前两页两次、第三页一次，锁定的页面不经缺页就已驻留
+    assert_eq!(memory_set.mlock(start, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.mlock(start, 3 * PAGE_SIZE), Ok(()));
+    for i in 0..3 {
+        assert_eq!(memory_set.mapping_state(vpn(i)), MappingState::Resident);
+    }
+    assert_eq!(memory_set.lock_count(vpn(1)), 2);
+    assert_eq!(memory_set.lock_count(vpn(2)), 1);
+    for i in 3..pages {
+        assert_eq!(memory_set.handle_page_fault(VirtAddr(start + i * PAGE_SIZE), FaultKind::Write), Ok(()));
+    }
+    // 内存压力：一直回收直到没有可回收的页面
+    while let Some(reclaimed) = memory_set.reclaim_one_page() {
+        assert!(reclaimed < vpn(0) || reclaimed >= vpn(3));
+    }
+    for i in 0..pages {
+        let expected = if i < 3 { MappingState::Resident } else { MappingState::Reserved };
+        assert_eq!(memory_set.mapping_state(vpn(i)), expected);
+    }
+    assert_eq!(memory_set.madvise_dontneed(start, pages * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.mapping_state(vpn(0)), MappingState::Resident);
+    // 第一次munlock只解除第三页
+    assert_eq!(memory_set.munlock(start, 3 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.lock_count(vpn(0)), 1);
+    assert_eq!(memory_set.reclaim_one_page(), Some(vpn(2)));
+    assert_eq!(memory_set.reclaim_one_page(), None);
+    assert_eq!(memory_set.munlock(start, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.lock_count(vpn(0)), 0);
+    assert!(memory_set.reclaim_one_page().is_some());
+    // 解除映射时锁定记录随之删除
+    assert_eq!(memory_set.mlock(start, PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.munmap(start, pages * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.lock_count(vpn(0)), 0);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("mlock_test passed!");
+}
+
+#[allow(unused)]
+/// 检查脏位：内核写脏后归还的页帧再映射给用户时读到的是零，
+/// 回收的全零页面以干净的状态归还，再次分配时不必清零；并比较跳过清零能省下的时间
+pub fn lazy_zero_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE, 3), Ok(()));
+    // 用户缺页拿到的页帧是刚被内核写脏的那一个，仍然读到零
+    let frame = frame_alloc_uninit().unwrap();
+    let dirty_ppn = frame.ppn();
+    dirty_ppn.get_bytes_array().fill(0xa5);
+    drop(frame);
+    assert!(frame_is_dirty(dirty_ppn));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    let ppn = memory_set.translate(VirtAddr(start).floor()).unwrap().ppn();
+    #[cfg(not(feature = "deterministic"))]
+    assert!(ppn == dirty_ppn);
+    assert!(ppn.get_bytes_array().iter().all(|byte| *byte == 0));
+    // 回收这个全零的页面，页帧以干净的状态归还
+    assert_eq!(memory_set.reclaim_one_page(), Some(VirtAddr(start).floor()));
+    assert!(!frame_is_dirty(ppn));
+    let frame = frame_alloc().unwrap();
+    assert!(frame.ppn().get_bytes_array().iter().all(|byte| *byte == 0));
+    drop(frame);
+    // 分配同样多的脏页帧，比较清零与不清零的耗时
+    const N: usize = 64;
+    let dirty: Vec<FrameTracker> = (0..N).map(|_| frame_alloc_uninit().unwrap()).collect();
+    for frame in dirty.iter() {
+        frame.ppn().get_bytes_array().fill(0xff);
+    }
+    drop(dirty);
+    let begin = get_time();
+    let zeroed: Vec<FrameTracker> = (0..N).map(|_| frame_alloc().unwrap()).collect();
+    let zeroing = get_time() - begin;
+    assert!(zeroed.iter().all(|frame| frame.ppn().get_bytes_array().iter().all(|byte| *byte == 0)));
+    drop(zeroed);
+    let begin = get_time();
+    let uninit: Vec<FrameTracker> = (0..N).map(|_| frame_alloc_uninit().unwrap()).collect();
+    let skipped = get_time() - begin;
+    drop(uninit);
+    info!("lazy_zero_test passed! {} dirty frames: {} ticks zeroed, {} ticks uninit", N, zeroing, skipped);
+}
+
+#[allow(unused)]
+/// 检查fork出的地址空间共享只读页面的物理页帧，而可写页面各自拥有一份拷贝
+pub fn fork_share_test() {
+    let text: usize = 0x10000;
+    let data: usize = 0x20000;
+    let mut parent = MemorySet::new_bare();
+    parent.insert_framed_area(
+        text.into(),
+        (text + PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::X | MapPermission::U,
+    ).unwrap();
+    parent.insert_framed_area(
+        data.into(),
+        (data + PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    ).unwrap();
+    let text_vpn = VirtAddr(text).floor();
+    let data_vpn = VirtAddr(data).floor();
+    let parent_data = parent.translate(data_vpn).unwrap().ppn();
+    parent_data.get_bytes_array()[0] = 0x5a;
+    let empty_frames = get_num_empty_frame();
+    let mut child = MemorySet::from_existed_user(&parent).unwrap();
+    let text_ppn = parent.translate(text_vpn).unwrap().ppn();
+    assert!(child.translate(text_vpn).unwrap().ppn() == text_ppn);
+    let child_data = child.translate(data_vpn).unwrap().ppn();
+    assert!(child_data != parent_data);
+    assert_eq!(child_data.get_bytes_array()[0], 0x5a);
+    child_data.get_bytes_array()[0] = 0;
+    assert_eq!(parent_data.get_bytes_array()[0], 0x5a);
+    // 子进程只为可写页面和自己的页表（根节点及用户区、跳板各两级节点）分配了物理页帧
+    assert_eq!(empty_frames - get_num_empty_frame(), 1 + 5);
+    // 共享的只读页面变为可写前会先被复制
+    assert_eq!(child.mprotect(text, PAGE_SIZE, 3), Ok(()));
+    assert!(child.translate(text_vpn).unwrap().ppn() != text_ppn);
+    drop(child);
+    assert!(parent.translate(text_vpn).unwrap().ppn() == text_ppn);
+    info!("fork_share_test passed!");
+}
+
+#[allow(unused)]
+/// map_phys只能映射不可分配的物理页帧；映射后通过虚拟地址读写的就是那个物理页帧，
+/// fork出的子地址空间映射到同一页帧，解除映射也不会把页帧还给分配器
+pub fn map_phys_test() {
+    let va: usize = 0x10000000;
+    let top = PhysAddr::from(memory_end() - PAGE_SIZE);
+    let mut memory_set = MemorySet::new_bare();
+    // 可分配的页帧不能被映射
+    assert_eq!(memory_set.map_phys(va, top.0, PAGE_SIZE, 3), Err(VmError::Overlap));
+    let frame = frame_alloc().unwrap();
+    let pa = PhysAddr::from(frame.ppn()).0;
+    assert_eq!(memory_set.map_phys(va, pa, PAGE_SIZE, 3), Err(VmError::Overlap));
+    assert_eq!(memory_set.map_phys(va, pa + 8, PAGE_SIZE, 3), Err(VmError::Unaligned));
+    drop(frame);
+    // 保留物理内存的最后一页，把它当作设备区域
+    reserve_region(top.floor(), PhysAddr::from(memory_end()).floor());
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.map_phys(va, top.0, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.map_phys(va, top.0, PAGE_SIZE, 3), Err(VmError::Overlap));
+    assert_eq!(memory_set.translate(VirtAddr(va).floor()).unwrap().ppn(), top.floor());
+    top.floor().get_bytes_array()[..4].copy_from_slice(b"mmio");
+    assert_eq!(memory_set.read_bytes(va, 4).unwrap(), b"mmio");
+    copy_to_user(memory_set.token(), (va + 4) as *mut u8, b"!").unwrap();
+    assert_eq!(top.floor().get_bytes_array()[4], b'!');
+    let child = MemorySet::from_existed_user(&memory_set).unwrap();
+    assert_eq!(child.translate(VirtAddr(va).floor()).unwrap().ppn(), top.floor());
+    assert_eq!(child.verify_invariants(), Ok(()));
+    drop(child);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 借用的物理页帧不归地址空间所有，不能munmap，包括与mmap区域一起解除映射
+    assert_eq!(memory_set.munmap(va, PAGE_SIZE), Err(VmError::NotOwned));
+    assert_eq!(memory_set.mmap(va + PAGE_SIZE, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.munmap(va, 2 * PAGE_SIZE), Err(VmError::NotOwned));
+    assert_eq!(memory_set.translate(VirtAddr(va).floor()).unwrap().ppn(), top.floor());
+    assert_eq!(memory_set.munmap(va + PAGE_SIZE, PAGE_SIZE), Ok(()));
+    drop(memory_set);
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    info!("map_phys_test passed!");
+}
+
+#[allow(unused)]
+/// fork后未写入时两个地址空间没有差异，子进程写入一页后只有这一页出现在diff中
+pub fn diff_test() {
+    let data: usize = 0x20000;
+    let mut parent = MemorySet::new_bare();
+    parent.insert_framed_area(
+        0x10000.into(),
+        0x11000.into(),
+        MapPermission::R | MapPermission::X | MapPermission::U,
+    ).unwrap();
+    parent.insert_framed_area(
+        data.into(),
+        (data + 3 * PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    ).unwrap();
+    parent.write_bytes(data + PAGE_SIZE, b"parent").unwrap();
+    let child = MemorySet::from_existed_user(&parent).unwrap();
+    assert!(parent.diff(&child).is_empty());
+    let written = VirtAddr(data + 2 * PAGE_SIZE).floor();
+    child.translate(written).unwrap().ppn().get_bytes_array()[7] = 1;
+    assert_eq!(parent.diff(&child), vec![written]);
+    assert_eq!(child.diff(&parent), vec![written]);
+    // 写回相同的内容后差异消失
+    child.translate(written).unwrap().ppn().get_bytes_array()[7] = 0;
+    assert!(child.diff(&parent).is_empty());
+    info!("diff_test passed!");
+}
+
+#[allow(unused)]
+/// from_elf把相邻且权限相同的两个只读段合并为一个逻辑段，权限不同的相邻段不合并
+pub fn coalesce_areas_test() {
+    let mut elf = contrived_elf(&[
+        (0x11000, 2 * PAGE_SIZE),
+        (0x10000, PAGE_SIZE),
+        (0x13000, PAGE_SIZE),
+    ]);
+    // 前两个段只读，第三个段保持R|W
+    set_elf_flags(&mut elf, 0, 0x4);
+    set_elf_flags(&mut elf, 1, 0x4);
+    let empty_frames = get_num_empty_frame();
+    let (memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let elf_areas: Vec<AreaView> = memory_set
+        .areas()
+        .filter(|view| view.kind == AreaKind::Elf)
+        .collect();
+    assert_eq!(elf_areas.len(), 2);
+    let ro = elf_areas.iter().find(|view| view.start.0 == 0x10000).unwrap();
+    assert_eq!(ro.end.0, 0x13000);
+    assert_eq!(ro.perm, MapPermission::R | MapPermission::U);
+    assert_eq!(ro.resident_frames, 3);
+    let rw = elf_areas.iter().find(|view| view.start.0 == 0x13000).unwrap();
+    assert_eq!(rw.end.0, 0x14000);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    drop(memory_set);
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    info!("coalesce_areas_test passed!");
+}
+
+#[allow(unused)]
+/// 检查MapArea的overlaps/is_adjacent_to/same_perm：按左闭右开的vpn_range判断相交与相接
+pub fn map_area_relation_test() {
+    let area = |start: usize, end: usize, perm: MapPermission| {
+        MapArea::new(VirtAddr(start * PAGE_SIZE), VirtAddr(end * PAGE_SIZE), MapType::Framed, perm)
+    };
+    let rw = MapPermission::R | MapPermission::W | MapPermission::U;
+    let ro = MapPermission::R | MapPermission::U;
+    let a = area(0x10, 0x14, rw);
+    // 相交
+    let b = area(0x13, 0x18, rw);
+    assert!(a.overlaps(&b) && b.overlaps(&a));
+    assert!(!a.is_adjacent_to(&b) && !b.is_adjacent_to(&a));
+    assert!(a.overlaps(&area(0x11, 0x12, ro)));
+    // 相接且权限相同：a的end恰好是c的start，不算相交
+    let c = area(0x14, 0x16, rw);
+    assert!(!a.overlaps(&c) && !c.overlaps(&a));
+    assert!(a.is_adjacent_to(&c) && c.is_adjacent_to(&a));
+    assert!(a.same_perm(&c));
+    // 相接但权限不同
+    let d = area(0xc, 0x10, ro);
+    assert!(!a.overlaps(&d) && a.is_adjacent_to(&d) && d.is_adjacent_to(&a));
+    assert!(!a.same_perm(&d) && !d.same_perm(&a));
+    // 中间隔了一页
+    assert!(!a.overlaps(&area(0x15, 0x16, rw)) && !a.is_adjacent_to(&area(0x15, 0x16, rw)));
+    // 空的逻辑段不与任何逻辑段相交
+    assert!(!a.overlaps(&area(0x12, 0x12, rw)));
+    info!("map_area_relation_test passed!");
+}
+
+#[allow(unused)]
+/// 检查verify_invariants在正常操作后通过，并能发现被人为破坏的不变量
+pub fn verify_invariants_test() {
+    let start: usize = 0x10000000;
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    assert_eq!(memory_set.mmap(start, 4 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    assert_eq!(memory_set.munmap(start, 4 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    let mut forked = MemorySet::from_existed_user(&memory_set).unwrap();
+    assert_eq!(forked.verify_invariants(), Ok(()));
+    // 人为地让两个逻辑段重叠
+    forked.areas.push(MapArea::new(
+        0x10000.into(),
+        (0x10000 + PAGE_SIZE).into(),
+        MapType::Framed,
+        MapPermission::R | MapPermission::U,
+    ));
+    assert!(forked.verify_invariants().is_err());
+    // 人为地删除一个驻留页面的页表项
+    memory_set.page_table.unmap(VirtAddr(0x10000).floor());
+    assert!(memory_set.verify_invariants().is_err());
+    info!("verify_invariants_test passed!");
+}
+
+#[allow(unused)]
+/// 检查堆逻辑段可以向上扩展、收缩，且不会扩展进已有的映射
+pub fn heap_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, heap_bottom, _) =
+        MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let bottom = VirtAddr(heap_bottom);
+    assert_eq!(memory_set.append_to(bottom, VirtAddr(heap_bottom + 2 * PAGE_SIZE)), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(heap_bottom + PAGE_SIZE), FaultKind::Write), Ok(()));
+    assert!(memory_set.translate(VirtAddr(heap_bottom + PAGE_SIZE).floor()).unwrap().writable());
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    assert_eq!(memory_set.shrink_to(bottom, VirtAddr(heap_bottom + 1)), Ok(()));
+    assert!(vpn_range_is_unused(&memory_set.page_table, heap_bottom / PAGE_SIZE + 1, 1));
+    assert_eq!(memory_set.shrink_to(bottom, VirtAddr(heap_bottom)), Ok(()));
+    assert!(vpn_range_is_unused(&memory_set.page_table, heap_bottom / PAGE_SIZE, 1));
+    assert_eq!(memory_set.mmap(heap_bottom + 4 * PAGE_SIZE, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(
+        memory_set.append_to(bottom, VirtAddr(heap_bottom + 8 * PAGE_SIZE)),
+        Err(VmError::Overlap)
+    );
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("heap_test passed!");
+}
+
+#[allow(unused)]
+/// 检查跳板/TrapContext布局的校验能发现不一致的配置
+pub fn trampoline_layout_test() {
+    assert!(trampoline_layout_ok(TRAMPOLINE, TRAP_CONTEXT));
+    // TrapContext与跳板重叠
+    assert!(!trampoline_layout_ok(TRAMPOLINE, TRAMPOLINE));
+    // TrapContext没有按页对齐
+    assert!(!trampoline_layout_ok(TRAMPOLINE, TRAP_CONTEXT + 8));
+    // 跳板不在地址空间的最后一页
+    assert!(!trampoline_layout_ok(TRAMPOLINE - PAGE_SIZE, TRAP_CONTEXT - PAGE_SIZE));
+    info!("trampoline_layout_test passed!");
+}
+
+#[allow(unused)]
+/// 检查所有内核栈槽位互不重叠、彼此之间留有保护页，且都在跳板之下、物理内存之上
+pub fn kernel_stack_test() {
+    let mut prev_bottom = TRAMPOLINE;
+    for app_id in 0..MAX_KERNEL_STACKS {
+        let (bottom, top) = kernel_stack_position(app_id).unwrap();
+        assert!(bottom < top && top <= prev_bottom && bottom >= MEMORY_END_MAX);
+        if app_id > 0 {
+            assert!(prev_bottom - top >= PAGE_SIZE);
+        }
+        prev_bottom = bottom;
+    }
+    assert!(kernel_stack_position(MAX_KERNEL_STACKS).is_none());
+    assert!(kernel_stack_position(usize::MAX).is_none());
+    // 实际映射一批内核栈
+    let mut memory_set = MemorySet::new_bare();
+    for app_id in 0..16 {
+        let (bottom, top) = kernel_stack_position(app_id).unwrap();
+        memory_set.insert_framed_area(bottom.into(), top.into(), MapPermission::R | MapPermission::W).unwrap();
+    }
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("kernel_stack_test passed!");
+}
+
+#[allow(unused)]
+/// 检查areas()产生的视图与放入的逻辑段一致
+pub fn areas_view_test() {
+    let elf = contrived_elf(&[(0x10000, 2 * PAGE_SIZE)]);
+    let (mut memory_set, user_sp, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let start: usize = 0x10000000;
+    assert_eq!(memory_set.mmap(start, 3 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    let views: Vec<AreaView> = memory_set.areas().collect();
+    let mut expected = vec![
+        (0x10000, 0x10000 + 2 * PAGE_SIZE, AreaKind::Elf, 2),
+        (user_sp - USER_STACK_SIZE, user_sp, AreaKind::Stack, USER_STACK_SIZE / PAGE_SIZE),
+        (user_sp, user_sp, AreaKind::Heap, 0),
+    ];
+    #[cfg(feature = "user-tls")]
+    expected.push((TLS_BASE, TLS_BASE + PAGE_SIZE, AreaKind::Tls, 1));
+    expected.push((TRAP_CONTEXT, TRAMPOLINE, AreaKind::TrapContext, 1));
+    expected.push((start, start + 3 * PAGE_SIZE, AreaKind::Mmap, 1));
+    assert_eq!(views.len(), expected.len());
+    for (view, &(start, end, kind, frames)) in views.iter().zip(expected.iter()) {
+        assert_eq!((view.start.0, view.end.0), (start, end));
+        assert_eq!(view.kind, kind);
+        assert_eq!(view.resident_frames, frames);
+    }
+    assert!(views[views.len() - 1].perm == MapPermission::R | MapPermission::W | MapPermission::U);
+    info!("areas_view_test passed!");
+}
+
+#[allow(unused)]
+/// 检查页表遍历得到的叶子与translate一致（含高半部分的跳板），
+/// 以及dump_maps在每个逻辑段之下列出其叶子、标注零页并把跳板列为unowned
+pub fn dump_maps_test() {
+    let elf = contrived_elf(&[(0x10000, PAGE_SIZE)]);
+    let (mut memory_set, user_sp, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    let start: usize = 0x10000000;
+    assert_eq!(memory_set.mmap(start, 3 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start + PAGE_SIZE), FaultKind::Read), Ok(()));
+    let leaves = memory_set.page_table.leaves();
+    assert!(leaves.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (vpn, pte) in leaves.iter() {
+        assert_eq!(memory_set.translate(*vpn).unwrap().bits, pte.bits);
+    }
+    let trampoline = VirtAddr::from(TRAMPOLINE).floor();
+    assert_eq!(leaves.last().unwrap().0, trampoline);
+    let text = memory_set.dump_maps();
+    let stack_line = format!("{:08x}-{:08x} rw-u stack\n", user_sp - USER_STACK_SIZE, user_sp);
+    assert!(text.contains(&stack_line));
+    let mmap_pos = text.find("10000000-10003000 rw-u mmap\n").unwrap();
+    let mmap_leaves: Vec<&str> = text[mmap_pos..].lines().skip(1).take_while(|line| line.starts_with("  ")).collect();
+    assert_eq!(mmap_leaves.len(), 2);
+    fn flags(line: &str) -> &str {
+        line.split(' ').nth(5).unwrap()
+    }
+    assert!(mmap_leaves[0].starts_with("  0x10000 -> ") && flags(mmap_leaves[0]).starts_with("VRW-U"));
+    assert!(mmap_leaves[1].starts_with("  0x10001 -> ") && mmap_leaves[1].ends_with(" zero"));
+    assert!(flags(mmap_leaves[1]).starts_with("VR--U"));
+    assert!(text.contains(&format!("unowned\n  {:#x} -> ", trampoline.0)));
+    info!("dump_maps_test passed!");
+}
+
+#[allow(unused)]
+/// 检查向下增长的逻辑段在缺页时逐页扩展，达到上限后不再扩展
+pub fn grows_down_test() {
+    let top: usize = 0x20000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(
+        memory_set.mmap_grows_down(top - PAGE_SIZE, PAGE_SIZE, 3, 4 * PAGE_SIZE),
+        Ok(())
+    );
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(top - 2 * PAGE_SIZE + 8), FaultKind::Write), Ok(()));
+    assert!(memory_set.translate(VirtAddr(top - 2 * PAGE_SIZE).floor()).unwrap().writable());
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(top - 4 * PAGE_SIZE), FaultKind::Read), Ok(()));
+    let area = memory_set.areas().next().unwrap();
+    assert_eq!((area.start.0, area.end.0), (top - 4 * PAGE_SIZE, top));
+    // 已经达到4页的上限
+    assert_eq!(
+        memory_set.handle_page_fault(VirtAddr(top - 5 * PAGE_SIZE), FaultKind::Write),
+        Err(VmError::Fault)
+    );
+    // 普通的mmap区域不会向下增长
+    assert_eq!(memory_set.mmap(0x10000000, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(
+        memory_set.handle_page_fault(VirtAddr(0x10000000 - PAGE_SIZE), FaultKind::Write),
+        Err(VmError::Fault)
+    );
+    info!("grows_down_test passed!");
+}
+
+#[allow(unused)]
+/// 检查MAP_STACK映射被放在用户地址空间高端，缺页时向下扩展，且后续栈不会占用它的增长空间
+pub fn mmap_stack_test() {
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(0x10000000, PAGE_SIZE, 3), Ok(()));
+    let top = USER_SPACE_END;
+    let a = memory_set.mmap_stack(2 * PAGE_SIZE, 3 | MAP_STACK).unwrap();
+    assert_eq!(a, top - 2 * PAGE_SIZE);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(a - PAGE_SIZE + 8), FaultKind::Write), Ok(()));
+    assert!(memory_set.translate(VirtAddr(a - PAGE_SIZE).floor()).unwrap().writable());
+    let area = memory_set
+        .areas()
+        .find(|area| area.end.0 == top)
+        .unwrap();
+    assert_eq!(area.start.0, a - PAGE_SIZE);
+    // 第二个栈放在第一个栈的整个增长上限之下
+    let b = memory_set.mmap_stack(PAGE_SIZE, 3 | MAP_STACK).unwrap();
+    assert_eq!(b, top - MAP_STACK_MAX_SIZE - PAGE_SIZE);
+    // 第一个栈仍能增长到上限
+    let limit = top - MAP_STACK_MAX_SIZE;
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(limit), FaultKind::Read), Ok(()));
+    assert!(memory_set.areas().any(|area| area.start.0 == limit && area.end.0 == top));
+    assert_eq!(memory_set.mmap_stack(0, 3), Err(VmError::Unaligned));
+    assert_eq!(memory_set.mmap_stack(PAGE_SIZE, 0), Err(VmError::BadPermission));
+    assert_eq!(memory_set.mmap_stack(USER_SPACE_END, 3), Err(VmError::NoSpace));
+    info!("mmap_stack_test passed!");
+}
+
+#[allow(unused)]
+/// 检查mmap_anywhere从用户地址空间高端向下放置映射，并避开已有的逻辑段
+pub fn mmap_anywhere_test() {
+    let mut memory_set = MemorySet::new_bare();
+    let top = USER_SPACE_END;
+    let a = memory_set.mmap_anywhere(PAGE_SIZE + 1, 3).unwrap();
+    assert_eq!(a, top - 2 * PAGE_SIZE);
+    let b = memory_set.mmap_anywhere(PAGE_SIZE, 3).unwrap();
+    assert_eq!(b, a - PAGE_SIZE);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(b), FaultKind::Write), Ok(()));
+    assert!(memory_set.translate(VirtAddr(b).floor()).unwrap().writable());
+    // 解除映射后空出的空隙会被重新使用
+    assert_eq!(memory_set.munmap(a, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.mmap_anywhere(PAGE_SIZE, 1).unwrap(), top - PAGE_SIZE);
+    assert_eq!(memory_set.mmap_anywhere(0, 3), Err(VmError::Unaligned));
+    assert_eq!(memory_set.mmap_anywhere(PAGE_SIZE, 0), Err(VmError::BadPermission));
+    // 没有足够大的空隙是地址空间不够，而不是物理页帧不够
+    assert_eq!(memory_set.mmap_anywhere(USER_SPACE_END, 3), Err(VmError::NoSpace));
+    info!("mmap_anywhere_test passed!");
+}
+
+#[allow(unused)]
+/// 检查第0页不能被固定地址的mmap、map_anon、map_phys映射，与start为0时的自动放置相区别
+pub fn null_page_test() {
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(0, PAGE_SIZE, 3), Err(VmError::Overflow));
+    assert_eq!(memory_set.mmap(0, 2 * PAGE_SIZE, 3), Err(VmError::Overflow));
+    // len为0仍是空操作
+    assert_eq!(memory_set.mmap(0, 0, 3), Ok(()));
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+    assert_eq!(memory_set.map_anon(0, PAGE_SIZE, perm), Err(VmError::Overflow));
+    assert_eq!(memory_set.map_phys(0, 0, PAGE_SIZE, 3), Err(VmError::Overflow));
+    assert_eq!(
+        memory_set.insert_framed_area(0.into(), (2 * PAGE_SIZE).into(), perm),
+        Err(VmError::Overflow)
+    );
+    assert!(memory_set.translate(VirtPageNum(0)).is_none());
+    // 第1页可以映射
+    assert_eq!(memory_set.mmap(PAGE_SIZE, PAGE_SIZE, 3), Ok(()));
+    // 自动放置的映射也不会落在第0页
+    let a = memory_set.mmap_anywhere(PAGE_SIZE, 3).unwrap();
+    assert!(a >= PAGE_SIZE);
+    info!("null_page_test passed!");
+}
+
+#[allow(unused)]
+/// 检查remap_frames更换了物理页帧，但页面内容和权限保持不变
+pub fn remap_frames_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 3;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    // 只写入前两页，第三页仍映射到零页
+    let mut old_ppns = Vec::new();
+    for i in 0..2 {
+        let va = VirtAddr(start + i * PAGE_SIZE);
+        assert_eq!(memory_set.handle_page_fault(va, FaultKind::Write), Ok(()));
+        let ppn = memory_set.translate(va.floor()).unwrap().ppn();
+        for (j, byte) in ppn.get_bytes_array().iter_mut().enumerate() {
+            *byte = (i + j) as u8;
+        }
+        old_ppns.push(ppn);
+    }
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(memory_set.remap_frames(start, pages * PAGE_SIZE), Ok(()));
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    for i in 0..2 {
+        let pte = memory_set.translate(VirtAddr(start + i * PAGE_SIZE).floor()).unwrap();
+        assert!(pte.ppn() != old_ppns[i]);
+        assert!(pte.readable() && pte.writable() && pte.user_accessible());
+        for (j, byte) in pte.ppn().get_bytes_array().iter().enumerate() {
+            assert_eq!(*byte, (i + j) as u8);
+        }
+    }
+    let zero_vpn = VirtAddr(start + 2 * PAGE_SIZE).floor();
+    assert!(memory_set.translate(zero_vpn).unwrap().ppn() == ZERO_FRAME.ppn());
+    assert_eq!(memory_set.remap_frames(start + 8, PAGE_SIZE), Err(VmError::Unaligned));
+    info!("remap_frames_test passed!");
+}
+
+#[allow(unused)]
+/// 用alias_pages构造两倍别名的环形缓冲区：写低处的别名能从高处读到，越界和重叠的区间被拒绝
+pub fn alias_pages_test() {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let vpn = |i: usize| VirtAddr(start + i * PAGE_SIZE).floor();
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, pages * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.alias_pages(start + 8, 0, 4, 4), Err(VmError::Unaligned));
+    assert_eq!(memory_set.alias_pages(start + PAGE_SIZE, 0, 1, 2), Err(VmError::NotMapped));
+    assert_eq!(memory_set.alias_pages(start, 0, 4, 5), Err(VmError::Overflow));
+    assert_eq!(memory_set.alias_pages(start, usize::MAX, 2, 4), Err(VmError::Overflow));
+    assert_eq!(memory_set.alias_pages(start, 0, 4, 3), Err(VmError::Overlap));
+    // 高处已驻留的页帧被换掉，低处尚未驻留的页面先分配
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(start + 5 * PAGE_SIZE), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.alias_pages(start, 0, 4, 4), Ok(()));
+    for i in 0..4 {
+        let low = memory_set.translate(vpn(i)).unwrap();
+        let high = memory_set.translate(vpn(i + 4)).unwrap();
+        assert!(low.ppn() == high.ppn() && low.ppn() != ZERO_FRAME.ppn());
+        assert!(high.readable() && high.writable() && high.user_accessible());
+    }
+    let low = memory_set.translate(vpn(1)).unwrap().ppn();
+    low.get_bytes_array()[PAGE_SIZE - 1] = 0x5a;
+    let high = memory_set.translate(vpn(5)).unwrap().ppn();
+    assert_eq!(high.get_bytes_array()[PAGE_SIZE - 1], 0x5a);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 解除一处映射后另一处仍然可用
+    assert_eq!(memory_set.munmap(start + 4 * PAGE_SIZE, 4 * PAGE_SIZE), Ok(()));
+    assert_eq!(memory_set.translate(vpn(1)).unwrap().ppn().get_bytes_array()[PAGE_SIZE - 1], 0x5a);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("alias_pages_test passed!");
+}
+
+#[allow(unused)]
+/// MAP_HUGE的映射用大页页表项指向对齐的连续页帧，dump_maps标注huge；
+/// 修改其中一页的权限时只拆分所在的大页，内容不变；不对齐时退回到普通页面
+pub fn huge_page_test() {
+    let huge_size = HUGE_PAGES * PAGE_SIZE;
+    let start: usize = 0x10000000;
+    let vpn = |i: usize| VirtAddr(start + i * PAGE_SIZE).floor();
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 2 * huge_size, 3 | MAP_HUGE), Ok(()));
+    for i in [0, HUGE_PAGES] {
+        assert!(memory_set.page_table.is_huge(vpn(i)));
+        let base = memory_set.translate(vpn(i)).unwrap().ppn();
+        assert_eq!(base.0 % HUGE_PAGES, 0);
+        let last = memory_set.translate(vpn(i + HUGE_PAGES - 1)).unwrap();
+        assert_eq!(last.ppn().0, base.0 + HUGE_PAGES - 1);
+        assert!(last.writable() && last.user_accessible());
+    }
+    let text = memory_set.dump_maps();
+    assert_eq!(text.lines().filter(|line| line.ends_with(" huge")).count(), 2);
+    assert!(text.contains(&format!("  {:#x} -> ", vpn(HUGE_PAGES).0)));
+    let ppn = memory_set.translate(vpn(7)).unwrap().ppn();
+    ppn.get_bytes_array()[0] = 0x5a;
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 只拆分第一个大页
+    assert_eq!(memory_set.mprotect(start + PAGE_SIZE, PAGE_SIZE, 1), Ok(()));
+    assert!(!memory_set.page_table.is_huge(vpn(0)) && memory_set.page_table.is_huge(vpn(HUGE_PAGES)));
+    assert!(!memory_set.translate(vpn(1)).unwrap().writable());
+    assert!(memory_set.translate(vpn(7)).unwrap().ppn() == ppn);
+    assert_eq!(ppn.get_bytes_array()[0], 0x5a);
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    // 拆分后留下的空节点在再次映射大页时被回收
+    assert_eq!(memory_set.munmap(start, 2 * huge_size), Ok(()));
+    assert_eq!(memory_set.framed_pages(), 0);
+    assert_eq!(memory_set.mmap(start, huge_size, 3 | MAP_HUGE), Ok(()));
+    assert!(memory_set.page_table.is_huge(vpn(0)));
+    // 由内核选择位置时按大页对齐
+    let anywhere = memory_set.mmap_anywhere(huge_size, 3 | MAP_HUGE).unwrap();
+    assert_eq!(anywhere % huge_size, 0);
+    assert!(memory_set.page_table.is_huge(VirtAddr(anywhere).floor()));
+    // 起始地址不对齐时退回到映射零页的普通页面
+    let unaligned = start + 2 * huge_size + PAGE_SIZE;
+    assert_eq!(memory_set.mmap(unaligned, huge_size, 3 | MAP_HUGE), Ok(()));
+    assert!(!memory_set.page_table.is_huge(VirtAddr(unaligned).floor()));
+    assert!(memory_set.is_zero_mapped(VirtAddr(unaligned).floor()));
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("huge_page_test passed!");
+}
+
+#[allow(unused)]
+/// 检查len为0的mmap/munmap在任何参数检查之前就成功返回，且不改变地址空间
+pub fn zero_len_test() {
+    let mut memory_set = MemorySet::new_bare();
+    // 未对齐的地址、非法的port、超出用户地址空间的地址都不会被检查
+    assert_eq!(memory_set.mmap(0x10000001, 0, 0), Ok(()));
+    assert_eq!(memory_set.mmap(USER_SPACE_END, 0, 3), Ok(()));
+    assert_eq!(memory_set.munmap(0x10000001, 0), Ok(()));
+    assert_eq!(memory_set.munmap(0x10000000, 0), Ok(()));
+    assert_eq!(memory_set.mmap_batch(&[(0x10000001, 0, 8)]), Ok(()));
+    assert_eq!(memory_set.areas().count(), 0);
+    // len不为0时照常检查
+    assert_eq!(memory_set.mmap(0x10000001, 1, 3), Err(VmError::Unaligned));
+    assert_eq!(memory_set.munmap(0x10000000, PAGE_SIZE), Err(VmError::NotMapped));
+    info!("zero_len_test passed!");
+}
+
+#[allow(unused)]
+/// mmap与munmap、mprotect对len的取整一致：映射1字节得到一整页，用同样的len可以完整地解除映射
+pub fn mmap_round_up_test() {
+    let start: usize = 0x10000000;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(start, 1, 3), Ok(()));
+    assert_eq!(memory_set.virtual_size(), PAGE_SIZE);
+    let views: Vec<AreaView> = memory_set.areas().collect();
+    assert_eq!((views[0].start.0, views[0].end.0), (start, start + PAGE_SIZE));
+    // 取整后的整页都可以访问
+    assert_eq!(memory_set.write_bytes(start + PAGE_SIZE - 1, b"x"), Ok(()));
+    assert_eq!(memory_set.mprotect(start, 1, 1), Ok(()));
+    assert!(!memory_set.translate(VirtAddr(start).floor()).unwrap().writable());
+    assert_eq!(memory_set.munmap(start, 1), Ok(()));
+    assert_eq!(memory_set.virtual_size(), 0);
+    assert_eq!(memory_set.areas().count(), 0);
+    assert_eq!(memory_set.mapping_state(VirtAddr(start).floor()), MappingState::Unmapped);
+    // 超出映射的部分仍然报错
+    assert_eq!(memory_set.mmap(start, PAGE_SIZE + 1, 3), Ok(()));
+    assert_eq!(memory_set.munmap(start, 2 * PAGE_SIZE + 1), Err(VmError::NotMapped));
+    assert_eq!(memory_set.munmap(start, PAGE_SIZE + 1), Ok(()));
+    assert_eq!(memory_set.virtual_size(), 0);
+    info!("mmap_round_up_test passed!");
+}
+
+#[allow(unused)]
+/// 检查munmap对恰好一个逻辑段、前缀、后缀、中间部分以及跨越两个逻辑段的区间的处理
+pub fn munmap_split_test() {
+    let base: usize = 0x10000000;
+    let page = |i: usize| base + i * PAGE_SIZE;
+    // 每个用例：解除映射的页[l, r)，以及之后剩下的逻辑段（以页为单位）
+    let cases: [((usize, usize), &[(usize, usize)]); 5] = [
+        ((0, 4), &[(4, 8)]),
+        ((0, 1), &[(1, 4), (4, 8)]),
+        ((3, 4), &[(0, 3), (4, 8)]),
+        ((1, 3), &[(0, 1), (3, 4), (4, 8)]),
+        ((2, 6), &[(0, 2), (6, 8)]),
+    ];
+    for &((l, r), expected) in cases.iter() {
+        let mut memory_set = MemorySet::new_bare();
+        assert_eq!(memory_set.mmap(page(0), 4 * PAGE_SIZE, 3), Ok(()));
+        assert_eq!(memory_set.mmap(page(4), 4 * PAGE_SIZE, 3), Ok(()));
+        for i in 0..8 {
+            assert_eq!(memory_set.handle_page_fault(VirtAddr(page(i)), FaultKind::Write), Ok(()));
+        }
+        let empty_frames = get_num_empty_frame();
+        assert_eq!(memory_set.munmap(page(l), (r - l) * PAGE_SIZE), Ok(()));
+        assert_eq!(get_num_empty_frame(), empty_frames + (r - l));
+        let mut remaining: Vec<(usize, usize)> = memory_set
+            .areas()
+            .map(|area| ((area.start.0 - base) / PAGE_SIZE, (area.end.0 - base) / PAGE_SIZE))
+            .collect();
+        remaining.sort_unstable();
+        assert_eq!(remaining.as_slice(), expected);
+        for i in 0..8 {
+            let unmapped = l <= i && i < r;
+            assert_eq!(vpn_range_is_unused(&memory_set.page_table, page(i) / PAGE_SIZE, 1), unmapped);
+        }
+        for area in memory_set.areas() {
+            let pages = (area.end.0 - area.start.0) / PAGE_SIZE;
+            assert_eq!(area.resident_frames, pages);
+        }
+    }
+    // 区间中有未映射的页面时整体失败
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(page(0), PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.munmap(page(0), 2 * PAGE_SIZE), Err(VmError::NotMapped));
+    info!("munmap_split_test passed!");
+}
+
+#[allow(unused)]
+/// 用fail_after让from_elf在每一次页帧分配处依次失败，检查它总是干净地返回错误且不泄漏页帧
+pub fn from_elf_oom_test() {
+    let elf = contrived_elf(&[(0x10000, 2 * PAGE_SIZE), (0x20000, PAGE_SIZE)]);
+    let before = get_num_empty_frame();
+    let mut n = 0;
+    loop {
+        fail_after(n);
+        let result = MemorySet::try_from_elf(&elf, USER_STACK_SIZE);
+        clear_fail_after();
+        match result {
+            Ok(built) => {
+                drop(built);
+                break;
+            }
+            Err(err) => assert_eq!(err, VmError::OutOfFrames),
+        }
+        assert_eq!(get_num_empty_frame(), before, "frames leaked when allocation {} failed", n + 1);
+        n += 1;
+    }
+    // 成功时至少分配了根页表、3个ELF数据页、用户栈和Trap上下文，每一次都经过了一次失败
+    assert!(n >= 1 + 3 + USER_STACK_SIZE / PAGE_SIZE + 1);
+    assert_eq!(get_num_empty_frame(), before);
+    // 没有触发的注入可以被解除
+    fail_after(0);
+    clear_fail_after();
+    assert!(frame_alloc().is_some());
+    info!("from_elf_oom_test passed!");
+}
+
+#[allow(unused)]
+/// fork复制地址空间时，空闲页帧不够复制可写页面则直接失败且不分配任何页帧；
+/// 复制途中任何一次分配失败都返回OutOfFrames，已分配的页帧全部归还
+pub fn fork_oom_test() {
+    let start: usize = 0x10000000;
+    let elf = contrived_elf(&[(0x10000, 2 * PAGE_SIZE)]);
+    let (mut parent, _, _) = MemorySet::try_from_elf(&elf, USER_STACK_SIZE).unwrap();
+    assert_eq!(parent.mmap(start, 2 * PAGE_SIZE, 3), Ok(()));
+    assert_eq!(parent.handle_page_fault(VirtAddr(start), FaultKind::Write), Ok(()));
+    let before = get_num_empty_frame();
+    let hog: Vec<FrameTracker> = core::iter::from_fn(frame_alloc).collect();
+    assert_eq!(MemorySet::from_existed_user(&parent).err(), Some(VmError::OutOfFrames));
+    assert_eq!(get_num_empty_frame(), 0);
+    drop(hog);
+    let mut n = 0;
+    loop {
+        fail_after(n);
+        let result = MemorySet::from_existed_user(&parent);
+        clear_fail_after();
+        match result {
+            Ok(child) => {
+                assert_eq!(child.verify_invariants(), Ok(()));
+                break;
+            }
+            Err(err) => assert_eq!(err, VmError::OutOfFrames),
+        }
+        assert_eq!(get_num_empty_frame(), before, "frames leaked when allocation {} failed", n + 1);
+        n += 1;
+    }
+    assert_eq!(get_num_empty_frame(), before);
+    // 子地址空间把与父地址空间共享的只读页面改为可写时需要复制，没有空闲页帧时失败且不做任何修改
+    let ro: usize = 0x20000000;
+    let perm = MapPermission::R | MapPermission::U;
+    parent.insert_framed_area(ro.into(), (ro + PAGE_SIZE).into(), perm).unwrap();
+    let mut child = MemorySet::from_existed_user(&parent).unwrap();
+    let shared = parent.translate(VirtAddr(ro).floor()).unwrap().ppn();
+    let hog: Vec<FrameTracker> = core::iter::from_fn(frame_alloc).collect();
+    assert_eq!(child.mprotect(ro, PAGE_SIZE, 3), Err(VmError::OutOfFrames));
+    let pte = child.translate(VirtAddr(ro).floor()).unwrap();
+    assert!(pte.ppn() == shared && !pte.writable());
+    drop(hog);
+    assert_eq!(child.mprotect(ro, PAGE_SIZE, 3), Ok(()));
+    let pte = child.translate(VirtAddr(ro).floor()).unwrap();
+    assert!(pte.ppn() != shared && pte.writable());
+    info!("fork_oom_test passed!");
+}
+
+#[allow(unused)]
+/// 缺页时新建页表节点也需要页帧：没有空闲页帧时读、写缺页都返回OutOfFrames而不是panic，
+/// 承诺过页帧的逻辑段在节点分配失败后仍保有承诺
+pub fn fault_oom_test() {
+    // 两段映射各自落在一个新的一级索引下，缺页时需要新建中间节点
+    let lazy: usize = 1 << 30;
+    let committed: usize = 2 << 30;
+    let mut memory_set = MemorySet::new_bare();
+    assert_eq!(memory_set.mmap(lazy, PAGE_SIZE, 3), Ok(()));
+    assert_eq!(memory_set.mmap(committed, PAGE_SIZE, 3 | MAP_COMMIT), Ok(()));
+    let before = get_num_empty_frame();
+    let hog: Vec<FrameTracker> = core::iter::from_fn(frame_alloc).collect();
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(lazy), FaultKind::Read), Err(VmError::OutOfFrames));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(lazy), FaultKind::Write), Err(VmError::OutOfFrames));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(committed), FaultKind::Write), Err(VmError::OutOfFrames));
+    assert!(memory_set.translate(VirtAddr(lazy).floor()).is_none());
+    assert!(memory_set.translate(VirtAddr(committed).floor()).is_none());
+    drop(hog);
+    assert_eq!(get_num_empty_frame(), before);
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(committed), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(lazy), FaultKind::Read), Ok(()));
+    assert_eq!(memory_set.handle_page_fault(VirtAddr(lazy), FaultKind::Write), Ok(()));
+    assert_eq!(memory_set.verify_invariants(), Ok(()));
+    info!("fault_oom_test passed!");
+}
@@ -8,22 +8,69 @@
 
 
 mod address;
+mod dtb;
+mod error;
 mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
 mod page_table;
+mod paging;
 
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use address::{StepByOne, VPNRange};
-pub use frame_allocator::{frame_alloc, FrameTracker, get_num_empty_frame};
-pub use memory_set::remap_test;
-pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
-pub use page_table::{translated_byte_buffer, vpn_range_is_unused, vpn_range_is_used, PageTableEntry};
-pub use page_table::{PTEFlags, PageTable};
+pub use error::VmError;
+pub use frame_allocator::{clear_fail_after, fail_after, frame_alloc, frames_overlap_allocatable, reserve_region, frame_alloc_committed, frame_alloc_colored, frame_alloc_contiguous, frame_alloc_uninit, frame_commit, frame_is_dirty, frame_uncommit, FrameTracker, get_num_empty_frame, memory_end, TIME_FRAME, ZERO_FRAME};
+pub use memory_set::{print_memory_layout, remap_test};
+pub use memory_set::{FaultKind, MapPermission, MappingState, MemorySet, KERNEL_SPACE, MAP_STACK};
+pub use page_table::{copy_to_user, is_user_addr_range, translated_byte_buffer, vpn_range_is_unused, vpn_range_is_used, PageTableEntry};
+pub use paging::{Paging, PagingScheme};
+pub use page_table::{PTEFlags, PTESoftFlags, PageTable, HUGE_PAGES};
+
+#[cfg(feature = "selftest")]
+pub use dtb::dtb_probe_test;
+#[cfg(all(feature = "selftest", feature = "deterministic"))]
+pub use frame_allocator::frame_deterministic_test;
+#[cfg(feature = "selftest")]
+pub use frame_allocator::{frame_color_test, frame_reserve_test, frame_tracker_owned_test};
+#[cfg(feature = "selftest")]
+pub use memory_set::{
+    areas_view_test, audit_kernel_test, cache_coloring_test, clear_user_areas_test, coalesce_areas_test, copy_data_fast_path_test, copy_data_overflow_test, diff_test, dump_maps_test, map_phys_test, mlock_test, lazy_zero_test, mmap_round_up_test, elf_bounds_test, fault_permission_test, mprotect_partial_test, shared_elf_page_test, from_elf_oom_test, fork_oom_test, fault_oom_test, fork_share_test, frame_quota_test, grows_down_test, heap_test, immutable_text_test, mapping_state_test, wx_strict_test, time_page_test, kernel_stack_test, madvise_test, madvise_willneed_test, map_anon_test, map_area_relation_test, mmap_batch_test, page_counts_test, mmap_anywhere_test, mmap_commit_test, mmap_stack_test, munmap_owned_test, munmap_split_test, null_page_test, pin_test, prepare_user_access_test, remap_frames_test, alias_pages_test, huge_page_test, snapshot_test, trampoline_layout_test, zero_page_test, user_stack_overlap_test, user_stack_size_test, verify_invariants_test, virtual_quota_test,
+    vm_error_test, zero_len_test,
+};
+#[cfg(all(feature = "selftest", feature = "user-tls"))]
+pub use memory_set::tls_test;
+#[cfg(all(feature = "selftest", feature = "access-trace"))]
+pub use memory_set::access_trace_test;
+#[cfg(feature = "selftest")]
+pub use paging::paging_scheme_test;
+#[cfg(feature = "selftest")]
+pub use page_table::{page_table_drop_test, page_table_nodes_test, translated_iovec_test, pte_flags_display_test, pte_rsw_test, vpn_indexes_test};
+
+use crate::config::{trampoline_layout_ok, TRAMPOLINE, TRAP_CONTEXT};
 
 /// initiate heap allocator, frame allocator and kernel space
-pub fn init() {
+/// dtb为启动时传入的设备树物理地址，用于探测物理内存大小
+pub fn init(dtb: usize) {
+    assert!(
+        trampoline_layout_ok(TRAMPOLINE, TRAP_CONTEXT),
+        "TRAMPOLINE {:#x} / TRAP_CONTEXT {:#x} layout is inconsistent",
+        TRAMPOLINE,
+        TRAP_CONTEXT
+    );
     heap_allocator::init_heap(); // 此处的heap是操作系统自己要用的（此处可以将操作系统作为整个电脑上的第一个应用程序，这个heap就是这个程序对应的heap）
-    frame_allocator::init_frame_allocator(); //将整个物理内存在ekernel之后的空间都转化为frame
+    extern "C" {
+        fn ekernel();
+    }
+    frame_allocator::init_frame_allocator(dtb::probe_memory_end(dtb, ekernel as usize)); //将整个物理内存在ekernel之后的空间都转化为frame
+    #[cfg(feature = "frame-walk")]
+    frame_allocator::frame_walk_test();
     KERNEL_SPACE.lock().activate();
+    // print_memory_layout核对空闲页帧数与内核页表占用的页帧数之和，必须在分配其他全局页帧之前调用
+    print_memory_layout();
+    // 时间页在每个用户地址空间中都会用到，提前分配以免影响各测试对空闲页帧的计数
+    lazy_static::initialize(&TIME_FRAME);
+    #[cfg(feature = "kernel-audit")]
+    if let Err(err) = KERNEL_SPACE.lock().audit_kernel() {
+        panic!("kernel page table audit failed: {}", err);
+    }
 }
\ No newline at end of file
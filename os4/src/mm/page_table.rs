@@ -1,11 +1,12 @@
 //! Implementation of [`PageTableEntry`] and [`PageTable`].
 
-use crate::config::PAGE_SIZE;
+use crate::config::USER_SPACE_END;
 
-use super::{frame_alloc, FrameTracker, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use super::{clear_fail_after, fail_after, frame_alloc, get_num_empty_frame, FrameTracker, Paging, PagingScheme, PhysPageNum, StepByOne, VirtAddr, VirtPageNum, VmError};
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::*;
+use core::fmt::{self, Display, Formatter};
 
 bitflags! {
     /// page table entry flags
@@ -27,6 +28,46 @@ bitflags! {
     }
 }
 
+/// 按VRWXUAD的顺序输出各位，未设置的位输出'-'；G位不输出
+impl Display for PTEFlags {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for &(bit, c) in [
+            (PTEFlags::V, 'V'),
+            (PTEFlags::R, 'R'),
+            (PTEFlags::W, 'W'),
+            (PTEFlags::X, 'X'),
+            (PTEFlags::U, 'U'),
+            (PTEFlags::A, 'A'),
+            (PTEFlags::D, 'D'),
+        ]
+        .iter()
+        {
+            write!(f, "{}", if self.contains(bit) { c } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+bitflags! {
+    /// 页表项中留给软件使用的两个RSW位（第8、9位），硬件会忽略它们
+    pub struct PTESoftFlags: u8 {
+        /// 写时复制：页面映射到共享的页帧，写入前要先复制出私有页帧
+        const COW = 1 << 0;
+        /// 尚未分配私有页帧（映射到零页），读到的都是0
+        const LAZY = 1 << 1;
+    }
+}
+
+const RSW_SHIFT: usize = 8;
+/// 页表项中物理页号的起始位
+const PPN_SHIFT: usize = 10;
+/// 叶子页表所在的级
+const LEAF_LEVEL: usize = Paging::LEVELS - 1;
+/// 大页所在的级：这一级的叶子页表项映射HUGE_PAGES个连续的页面（SV39中为2MiB）
+const HUGE_LEVEL: usize = LEAF_LEVEL - 1;
+/// 一个大页包含的普通页面数
+pub const HUGE_PAGES: usize = Paging::ENTRIES_PER_NODE;
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 /// page table entry structure
@@ -37,14 +78,14 @@ pub struct PageTableEntry {
 impl PageTableEntry {
     pub fn new(ppn: PhysPageNum, flags: PTEFlags) -> Self {
         PageTableEntry {
-            bits: ppn.0 << 10 | flags.bits as usize,
+            bits: ppn.0 << PPN_SHIFT | flags.bits as usize,
         }
     }
     pub fn empty() -> Self {
         PageTableEntry { bits: 0 }
     }
     pub fn ppn(&self) -> PhysPageNum {
-        (self.bits >> 10 & ((1usize << 44) - 1)).into()
+        (self.bits >> PPN_SHIFT & ((1usize << Paging::PPN_BITS) - 1)).into()
     }
     pub fn flags(&self) -> PTEFlags {
         PTEFlags::from_bits(self.bits as u8).unwrap()
@@ -61,11 +102,27 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    pub fn user_accessible(&self) -> bool {
+        (self.flags() & PTEFlags::U) != PTEFlags::empty()
+    }
+    /// R/W/X不全为0的页表项是叶子，否则指向下一级节点
+    fn is_leaf(&self) -> bool {
+        self.readable() || self.writable() || self.executable()
+    }
+    pub fn rsw(&self) -> PTESoftFlags {
+        PTESoftFlags::from_bits_truncate((self.bits >> RSW_SHIFT) as u8)
+    }
+    /// 只改变RSW位，PPN和其余标志位保持不变
+    pub fn set_rsw(&mut self, rsw: PTESoftFlags) {
+        self.bits = (self.bits & !(0b11 << RSW_SHIFT)) | (rsw.bits as usize) << RSW_SHIFT;
+    }
 }
 
 /// page table structure
 /** root_ppn: 页表一级节点所在的PhysPageNum,
-    frams: 整个页表所包含的节点（一级、二级、三级）所在的PhysPageNum
+    frams: 整个页表所包含的节点（一级、二级、三级）所在的PhysPageNum；
+    根节点和find_pte_create新建的中间节点都由这里的FrameTracker持有，页表drop时随之归还分配器，
+    from_token得到的临时页表不持有任何节点
 */
 pub struct PageTable {
     root_ppn: PhysPageNum,
@@ -75,64 +132,149 @@ pub struct PageTable {
 /// Assume that it won't oom when creating/mapping.
 impl PageTable {
     pub fn new() -> Self {
-        let frame = frame_alloc().unwrap();
-        PageTable {
-            root_ppn: frame.ppn,
+        Self::try_new().expect("no frame left for the root page table")
+    }
+    /// new的可失败版本，没有空闲页帧存放根节点时返回Err(VmError::OutOfFrames)
+    pub fn try_new() -> Result<Self, VmError> {
+        let frame = frame_alloc().ok_or(VmError::OutOfFrames)?;
+        Ok(PageTable {
+            root_ppn: frame.ppn(),
             frames: vec![frame],
-        }
+        })
     }
     /// Temporarily used to get arguments from user space.
     pub fn from_token(satp: usize) -> Self {
         Self {
-            root_ppn: PhysPageNum::from(satp & ((1usize << 44) - 1)),
+            root_ppn: PhysPageNum::from(satp & ((1usize << Paging::PPN_BITS) - 1)),
             frames: Vec::new(),
         }
     }
 
     /// 在多级页表找到一个虚拟页号对应的页表项的可变引用。如果在遍历的过程中发现有节点尚未创建则会申请一个新的物理页以新建一个节点。
+    /// 途经的大页先被拆成普通页面。申请不到物理页时返回None，已经建好的中间节点保留在frames中，随页表一起释放
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
         let mut idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
         let mut result: Option<&mut PageTableEntry> = None;
         for (i, idx) in idxs.iter_mut().enumerate() {
             let pte = &mut ppn.get_pte_array()[*idx];
-            if i == 2 {
+            if i == LEAF_LEVEL {
                 result = Some(pte);
                 break;
             }
             if !pte.is_valid() {
-                let frame = frame_alloc().unwrap();
-                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+                let frame = frame_alloc()?;
+                *pte = PageTableEntry::new(frame.ppn(), PTEFlags::V);
                 self.frames.push(frame);
+            } else if pte.is_leaf() {
+                self.split_huge(pte, i)?;
             }
             ppn = pte.ppn();
         }
         result
     }
-    /// 返回vpn在页表（self）中的页表项
-    pub fn find_pte(&self, vpn: VirtPageNum) -> Option<&PageTableEntry> {
+    /// 沿vpn的各级索引向下遍历，返回遇到的叶子页表项（普通页或大页）及其所在的级；
+    /// 最后一级的页表项即使不合法也会返回，中间节点不存在时返回None
+    fn walk(&self, vpn: VirtPageNum) -> Option<(&PageTableEntry, usize)> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
-        let mut result: Option<&PageTableEntry> = None;
         for (i, idx) in idxs.iter().enumerate() {
             let pte = &ppn.get_pte_array()[*idx];
-            if i == 2 {
+            if i == LEAF_LEVEL || pte.is_valid() && pte.is_leaf() {
+                return Some((pte, i));
+            }
+            if !pte.is_valid() {
+                return None;
+            }
+            ppn = pte.ppn();
+        }
+        None
+    }
+    /// 返回vpn在页表（self）中的页表项；vpn落在大页内时返回大页的页表项
+    pub fn find_pte(&self, vpn: VirtPageNum) -> Option<&PageTableEntry> {
+        self.walk(vpn).map(|(pte, _)| pte)
+    }
+    /// 与find_pte相同，但返回页表项的可变引用；除了拆分途经的大页之外不会新建节点，
+    /// 拆分时申请不到物理页则返回None
+    fn find_pte_mut(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array()[*idx];
+            if i == LEAF_LEVEL {
                 result = Some(pte);
                 break;
             }
             if !pte.is_valid() {
                 return None;
             }
+            if pte.is_leaf() {
+                self.split_huge(pte, i)?;
+            }
             ppn = pte.ppn();
         }
         result
     }
+    /// 把第level级的大页页表项pte拆成一个下一级节点，节点中的页表项依次映射到原来的页帧，
+    /// 权限和RSW位不变，因此地址翻译的结果不受影响；申请不到节点时返回None
+    fn split_huge(&mut self, pte: &mut PageTableEntry, level: usize) -> Option<()> {
+        let frame = frame_alloc()?;
+        let step = 1usize << (Paging::INDEX_BITS * (LEAF_LEVEL - level - 1));
+        for (i, child) in frame.ppn().get_pte_array().iter_mut().enumerate() {
+            child.bits = pte.bits + ((i * step) << PPN_SHIFT);
+        }
+        *pte = PageTableEntry::new(frame.ppn(), PTEFlags::V);
+        self.frames.push(frame);
+        Some(())
+    }
+    /// 把从vpn开始的HUGE_PAGES个页面映射为一个大页，vpn和ppn都必须按HUGE_PAGES对齐。
+    /// 这段范围内已有映射时返回Err(VmError::Overlap)；只剩空节点时回收该节点；
+    /// 申请不到中间节点时返回Err(VmError::OutOfFrames)
+    pub fn try_map_huge(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> Result<(), VmError> {
+        assert!(vpn.0 % HUGE_PAGES == 0 && ppn.0 % HUGE_PAGES == 0, "huge page {:?} -> {:?} is not aligned", vpn, ppn);
+        let idxs = vpn.indexes();
+        let mut node = self.root_ppn;
+        for &idx in idxs[..HUGE_LEVEL].iter() {
+            let pte = &mut node.get_pte_array()[idx];
+            if !pte.is_valid() {
+                let frame = frame_alloc().ok_or(VmError::OutOfFrames)?;
+                *pte = PageTableEntry::new(frame.ppn(), PTEFlags::V);
+                self.frames.push(frame);
+            } else if pte.is_leaf() {
+                return Err(VmError::Overlap);
+            }
+            node = pte.ppn();
+        }
+        let pte = &mut node.get_pte_array()[idxs[HUGE_LEVEL]];
+        if pte.is_valid() {
+            if pte.is_leaf() || pte.ppn().get_pte_array().iter().any(|child| child.is_valid()) {
+                return Err(VmError::Overlap);
+            }
+            // munmap之后留下的空节点
+            if let Some(pos) = self.frames.iter().position(|frame| frame.ppn() == pte.ppn()) {
+                self.frames.swap_remove(pos);
+            }
+        }
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        Ok(())
+    }
+    /// vpn是否落在一个大页内
+    pub fn is_huge(&self, vpn: VirtPageNum) -> bool {
+        self.walk(vpn).map_or(false, |(_, level)| level < LEAF_LEVEL)
+    }
     /// 在页表self中更新(vpn, ppn)对应的页表项，如果页表项不存在，就先新建后更新
     #[allow(unused)]
     pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.find_pte_create(vpn).unwrap();
-        assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn); //有可能121行找到的页表项是合法的，那么就报错防止覆盖之前的合法页表项
+        self.try_map(vpn, ppn, flags)
+            .expect("no frame left for page table nodes");
+    }
+    /// map的可失败版本，没有空闲页帧新建中间节点时返回Err(VmError::OutOfFrames)
+    pub fn try_map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> Result<(), VmError> {
+        let pte = self.find_pte_create(vpn).ok_or(VmError::OutOfFrames)?;
+        assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn); //有可能找到的页表项是合法的，那么就报错防止覆盖之前的合法页表项
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        Ok(())
     }
     /// 在页表self中将(vpn, ppn)对应的页表项置为空，如果页表项不存在，就先新建后更新
     #[allow(unused)]
@@ -141,26 +283,139 @@ impl PageTable {
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
-    /// 寻早self中对应于vpn的页表项，如果能够找到，就将页表项拷贝一份并返回
+    /// 修改vpn对应的合法页表项的权限位，ppn保持不变
+    pub fn set_flags(&mut self, vpn: VirtPageNum, flags: PTEFlags) -> Result<(), VmError> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => {
+                let rsw = pte.rsw();
+                *pte = PageTableEntry::new(pte.ppn(), flags | PTEFlags::V);
+                pte.set_rsw(rsw);
+                Ok(())
+            }
+            _ => Err(VmError::NotMapped),
+        }
+    }
+    /// 设置合法页表项的RSW位
+    pub fn set_rsw(&mut self, vpn: VirtPageNum, rsw: PTESoftFlags) -> Result<(), VmError> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => {
+                pte.set_rsw(rsw);
+                Ok(())
+            }
+            _ => Err(VmError::NotMapped),
+        }
+    }
+    /// 页表节点占用的物理页帧数
+    pub fn node_count(&self) -> usize {
+        self.frames.len()
+    }
+    /// 回收所有不再包含合法页表项的二级、三级节点（根节点始终保留），返回回收的节点数
+    pub fn reclaim_empty_nodes(&mut self) -> usize {
+        let root_ppn = self.root_ppn;
+        let mut reclaimed = 0;
+        self.reclaim_node(root_ppn, 0, &mut reclaimed);
+        reclaimed
+    }
+    /// 递归地回收以ppn为根、位于第level级的节点下的空节点，返回该节点回收后是否为空
+    fn reclaim_node(&mut self, ppn: PhysPageNum, level: usize, reclaimed: &mut usize) -> bool {
+        let mut empty = true;
+        for idx in 0..Paging::ENTRIES_PER_NODE {
+            let pte = ppn.get_pte_array()[idx];
+            if !pte.is_valid() {
+                continue;
+            }
+            // 第三级的页表项都是叶子；R/W/X均为0的页表项指向下一级节点
+            let is_leaf = level == LEAF_LEVEL || pte.readable() || pte.writable() || pte.executable();
+            if !is_leaf && self.reclaim_node(pte.ppn(), level + 1, reclaimed) {
+                ppn.get_pte_array()[idx] = PageTableEntry::empty();
+                if let Some(pos) = self.frames.iter().position(|frame| frame.ppn() == pte.ppn()) {
+                    let frame = self.frames.swap_remove(pos);
+                    // 删除页表项时写入的都是全零的页表项，节点为空时整页为零
+                    if pte.ppn().get_pte_array().iter().all(|child| child.bits == 0) {
+                        frame.release_clean();
+                    }
+                }
+                *reclaimed += 1;
+            } else {
+                empty = false;
+            }
+        }
+        empty
+    }
+    /// 按虚拟地址从低到高遍历页表，返回所有合法的叶子页表项及其vpn；
+    /// 高半部分（如跳板）的vpn按符号扩展还原，与VirtAddr::floor得到的vpn一致
+    pub fn leaves(&self) -> Vec<(VirtPageNum, PageTableEntry)> {
+        let mut leaves = Vec::new();
+        Self::collect_leaves(self.root_ppn, 0, 0, &mut leaves);
+        leaves
+    }
+    /// 把以ppn为根、位于第level级、vpn高位为prefix的节点下的叶子页表项追加到leaves
+    fn collect_leaves(
+        ppn: PhysPageNum,
+        level: usize,
+        prefix: usize,
+        leaves: &mut Vec<(VirtPageNum, PageTableEntry)>,
+    ) {
+        const VPN_BITS: usize = Paging::VA_BITS - Paging::PAGE_SIZE_BITS;
+        for idx in 0..Paging::ENTRIES_PER_NODE {
+            let pte = ppn.get_pte_array()[idx];
+            if !pte.is_valid() {
+                continue;
+            }
+            let vpn = (prefix << Paging::INDEX_BITS) | idx;
+            if level == LEAF_LEVEL || pte.readable() || pte.writable() || pte.executable() {
+                // 大页的叶子只记录其起始vpn
+                let vpn = vpn << (Paging::INDEX_BITS * (LEAF_LEVEL - level));
+                let vpn = if vpn >> (VPN_BITS - 1) & 1 == 1 { vpn | !((1 << VPN_BITS) - 1) } else { vpn };
+                leaves.push((VirtPageNum(vpn), pte));
+            } else {
+                Self::collect_leaves(pte.ppn(), level + 1, vpn, leaves);
+            }
+        }
+    }
+    /// 寻早self中对应于vpn的页表项，如果能够找到，就将页表项拷贝一份并返回；
+    /// vpn落在大页内时返回的页表项中的ppn是vpn实际对应的页帧
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        self.find_pte(vpn).copied()
+        self.walk(vpn).map(|(pte, level)| {
+            let offset = vpn.0 & ((1usize << (Paging::INDEX_BITS * (LEAF_LEVEL - level))) - 1);
+            PageTableEntry {
+                bits: pte.bits + (offset << PPN_SHIFT),
+            }
+        })
     }
     ///
     pub fn token(&self) -> usize {
-        8usize << 60 | self.root_ppn.0
+        Paging::SATP_MODE << 60 | self.root_ppn.0
+    }
+}
+
+/// 判断 [start, start + len) 是否完整地落在用户地址空间内，
+/// 所有接收用户指针的系统调用都应在地址转换之前调用它，防止借用户指针读写内核地址
+pub fn is_user_addr_range(start: usize, len: usize) -> bool {
+    match start.checked_add(len) {
+        Some(end) => end <= USER_SPACE_END,
+        None => false,
     }
 }
 
 /// translate a pointer to a mutable u8 Vec through page table
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+pub fn translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+) -> Result<Vec<&'static mut [u8]>, VmError> {
     let page_table = PageTable::from_token(token);
     let mut start = ptr as usize;
-    let end = start + len;
+    let end = start.checked_add(len).ok_or(VmError::Overflow)?;
     let mut v = Vec::new();
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.floor();
-        let ppn = page_table.translate(vpn).unwrap().ppn();
+        let ppn = match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.user_accessible() => pte.ppn(),
+            Some(pte) if pte.is_valid() => return Err(VmError::Fault),
+            _ => return Err(VmError::NotMapped),
+        };
         vpn.step();
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end));
@@ -171,31 +426,276 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
         }
         start = end_va.into();
     }
-    v
+    Ok(v)
 }
 
+/// 用户态的iovec：从base开始的len字节，布局与C的struct iovec相同
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct IoVec {
+    pub base: usize,
+    pub len: usize,
+}
 
-/// 如果给定的虚拟地址范围中的地址在给定的pagetable中都没被占用（被关联了物理页），返回true
-pub fn vpn_range_is_unused(pt: &mut PageTable, start: usize, len: usize) -> bool {
-    for id in (start..(start + len)).filter(|x| (x % PAGE_SIZE == 0))  {
-        let va = VirtPageNum::from(id);
-        let tmp = pt.find_pte_create(va).unwrap();
-        
-        if tmp.is_valid() {
-            return false
+/// 检查[start, start + len)的每一页都已映射且用户可读：未映射时返回Err(VmError::NotMapped)，
+/// 不可读或不带U时返回Err(VmError::Fault)
+fn check_user_readable(page_table: &PageTable, start: usize, len: usize) -> Result<(), VmError> {
+    let end = start.checked_add(len).ok_or(VmError::Overflow)?;
+    let mut vpn = VirtAddr::from(start).floor();
+    while VirtAddr::from(vpn).0 < end {
+        match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.user_accessible() && pte.readable() => {}
+            Some(pte) if pte.is_valid() => return Err(VmError::Fault),
+            _ => return Err(VmError::NotMapped),
         }
+        vpn.step();
     }
-    true
+    Ok(())
 }
 
-/// 如果给定的虚拟地址范围中的地址在给定的pagetable中都被占用（被关联了物理页），返回true
-pub fn vpn_range_is_used(pt: &PageTable, start: usize, len: usize) -> bool {
-    for id in (start..(start + len)).filter(|x| (x % PAGE_SIZE == 0)) {
-        let va = VirtPageNum::from(id);
-        let tmp = pt.find_pte(va);
-        if let None = tmp {
-            false;
-        };
+#[allow(unused)]
+/// 供writev一类的系统调用使用：读取用户地址空间中从iov_ptr开始的iov_count个IoVec（数组本身可以跨页），
+/// 按顺序返回各缓冲区的只读分片，跨页的缓冲区对应多个分片，长度为0的缓冲区没有分片。
+/// 数组或任一缓冲区越出用户地址空间时返回Err(VmError::Overflow)，含有未映射的页面时返回Err(VmError::NotMapped)，
+/// 含有不可读的页面时返回Err(VmError::Fault)。尚未驻留的页面算作未映射，调用者应先对每个缓冲区调用prepare_user_read
+pub fn translated_iovec(token: usize, iov_ptr: *const IoVec, iov_count: usize) -> Result<Vec<&'static [u8]>, VmError> {
+    const WORD: usize = core::mem::size_of::<usize>();
+    let page_table = PageTable::from_token(token);
+    let iov_len = iov_count
+        .checked_mul(core::mem::size_of::<IoVec>())
+        .ok_or(VmError::Overflow)?;
+    if !is_user_addr_range(iov_ptr as usize, iov_len) {
+        return Err(VmError::Overflow);
+    }
+    check_user_readable(&page_table, iov_ptr as usize, iov_len)?;
+    let bytes: Vec<u8> = translated_byte_buffer(token, iov_ptr as *const u8, iov_len)?
+        .iter()
+        .flat_map(|buffer| buffer.iter().copied())
+        .collect();
+    let mut slices = Vec::new();
+    for iov in bytes.chunks_exact(2 * WORD) {
+        let mut word = [0u8; WORD];
+        word.copy_from_slice(&iov[..WORD]);
+        let base = usize::from_ne_bytes(word);
+        word.copy_from_slice(&iov[WORD..]);
+        let len = usize::from_ne_bytes(word);
+        if !is_user_addr_range(base, len) {
+            return Err(VmError::Overflow);
+        }
+        check_user_readable(&page_table, base, len)?;
+        for buffer in translated_byte_buffer(token, base as *const u8, len)? {
+            slices.push(&*buffer);
+        }
+    }
+    Ok(slices)
+}
+
+/// 将data按页拷贝到用户地址空间中从ptr开始的缓冲区
+pub fn copy_to_user(token: usize, ptr: *mut u8, data: &[u8]) -> Result<(), VmError> {
+    let mut copied = 0;
+    for buffer in translated_byte_buffer(token, ptr, data.len())? {
+        buffer.copy_from_slice(&data[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+    Ok(())
+}
+
+/// 如果从start_vpn开始的page_count个虚拟页在给定的pagetable中都没被占用（被关联了物理页），返回true
+pub fn vpn_range_is_unused(pt: &PageTable, start_vpn: usize, page_count: usize) -> bool {
+    (start_vpn..start_vpn + page_count).all(|vpn| match pt.find_pte(VirtPageNum::from(vpn)) {
+        Some(pte) => !pte.is_valid(),
+        None => true,
+    })
+}
+
+/// 如果从start_vpn开始的page_count个虚拟页在给定的pagetable中都被占用（被关联了物理页），返回true
+pub fn vpn_range_is_used(pt: &PageTable, start_vpn: usize, page_count: usize) -> bool {
+    (start_vpn..start_vpn + page_count).all(|vpn| match pt.find_pte(VirtPageNum::from(vpn)) {
+        Some(pte) => pte.is_valid(),
+        None => false,
+    })
+}
+
+#[allow(unused)]
+/// 检查PTEFlags按VRWXUAD输出，未设置的位为'-'，G位被忽略
+pub fn pte_flags_display_test() {
+    use alloc::format;
+    assert_eq!(format!("{}", PTEFlags::empty()), "-------");
+    assert_eq!(format!("{}", PTEFlags::V | PTEFlags::R | PTEFlags::X), "VR-X---");
+    assert_eq!(format!("{}", PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::U | PTEFlags::A), "VRW-UA-");
+    assert_eq!(format!("{}", PTEFlags::all()), "VRWXUAD");
+    assert_eq!(format!("{}", PTEFlags::V | PTEFlags::G | PTEFlags::D), "V-----D");
+    info!("pte_flags_display_test passed!");
+}
+
+#[allow(unused)]
+/// 检查RSW位的读写不会影响PPN、合法位和权限位
+pub fn pte_rsw_test() {
+    let flags = PTEFlags::V | PTEFlags::R | PTEFlags::U | PTEFlags::A | PTEFlags::D;
+    let mut pte = PageTableEntry::new(PhysPageNum(0x8_1234), flags);
+    assert!(pte.rsw().is_empty());
+    pte.set_rsw(PTESoftFlags::COW | PTESoftFlags::LAZY);
+    assert_eq!(pte.rsw(), PTESoftFlags::COW | PTESoftFlags::LAZY);
+    assert_eq!(pte.flags(), flags);
+    assert_eq!(pte.ppn().0, 0x8_1234);
+    pte.set_rsw(PTESoftFlags::LAZY);
+    assert_eq!(pte.rsw(), PTESoftFlags::LAZY);
+    assert!(pte.is_valid() && !pte.writable());
+    pte.set_rsw(PTESoftFlags::empty());
+    assert_eq!(pte.bits, PageTableEntry::new(PhysPageNum(0x8_1234), flags).bits);
+    // 修改权限时保留RSW位
+    let mut page_table = PageTable::new();
+    let vpn = VirtPageNum(0x10000);
+    page_table.map(vpn, PhysPageNum(0x8_1234), PTEFlags::R | PTEFlags::U);
+    assert_eq!(page_table.set_rsw(vpn, PTESoftFlags::COW), Ok(()));
+    assert_eq!(page_table.set_flags(vpn, PTEFlags::R | PTEFlags::W | PTEFlags::U), Ok(()));
+    let pte = page_table.translate(vpn).unwrap();
+    assert!(pte.writable() && pte.rsw() == PTESoftFlags::COW);
+    assert_eq!(page_table.set_rsw(VirtPageNum(0x20000), PTESoftFlags::COW), Err(VmError::NotMapped));
+    info!("pte_rsw_test passed!");
+}
+
+#[allow(unused)]
+/// 检查VirtPageNum::indexes按根到叶子的顺序给出各级索引，三个索引能拼回原来的vpn，且与页表遍历使用的索引一致
+pub fn vpn_indexes_test() {
+    let vpn = VirtPageNum(0x68d6f5c);
+    assert_eq!(vpn.indexes(), [0x1a3, 0x0b7, 0x15c]);
+    for &vpn in [vpn, VirtPageNum(0), VirtPageNum((1 << 27) - 1), VirtPageNum(0x10000)].iter() {
+        let idx = vpn.indexes();
+        let joined = idx.iter().fold(0, |acc, &i| acc << Paging::INDEX_BITS | i);
+        assert_eq!(joined, vpn.0);
+    }
+    // 页表遍历：根页表中idx[0]处的页表项有效，其余根页表项都无效
+    let mut page_table = PageTable::new();
+    page_table.map(vpn, PhysPageNum(0x8_1234), PTEFlags::R | PTEFlags::U);
+    let root = page_table.root_ppn.get_pte_array();
+    for (i, pte) in root.iter().enumerate() {
+        assert_eq!(pte.is_valid(), i == vpn.indexes()[0]);
     }
-    true
-}
\ No newline at end of file
+    page_table.unmap(vpn);
+    info!("vpn_indexes_test passed!");
+}
+
+#[allow(unused)]
+/// 构造一个iovec：两个缓冲区各自跨过一个页边界，iovec数组本身也跨页，检查汇集得到的字节；
+/// 指向未映射、不可读或越出用户地址空间的缓冲区被拒绝
+pub fn translated_iovec_test() {
+    let mut page_table = PageTable::new();
+    let mut frames = Vec::new();
+    let rwu = PTEFlags::R | PTEFlags::W | PTEFlags::U;
+    for (vpn, flags) in [(0x10, rwu), (0x11, rwu), (0x12, rwu), (0x13, rwu), (0x14, PTEFlags::X | PTEFlags::U), (0x15, PTEFlags::R)] {
+        let frame = frame_alloc().unwrap();
+        page_table.map(VirtPageNum(vpn), frame.ppn(), flags);
+        frames.push(frame);
+    }
+    let token = page_table.token();
+    let write = |va: usize, data: &[u8]| copy_to_user(token, va as *mut u8, data).unwrap();
+    let pattern = |va: usize, len: usize| (va..va + len).map(|addr| addr as u8).collect::<Vec<u8>>();
+    let (a, a_len) = (0x10ff0, 0x20);
+    let (b, b_len) = (0x11ff8, 0x10);
+    write(a, &pattern(a, a_len));
+    write(b, &pattern(b, b_len));
+    let iov_ptr = 0x12ff8;
+    let put_iovecs = |iovecs: &[(usize, usize)]| {
+        let mut bytes = Vec::new();
+        for &(base, len) in iovecs {
+            bytes.extend_from_slice(&base.to_ne_bytes());
+            bytes.extend_from_slice(&len.to_ne_bytes());
+        }
+        write(iov_ptr, &bytes);
+    };
+    let gather = |count: usize| {
+        translated_iovec(token, iov_ptr as *const IoVec, count)
+            .map(|slices| slices.iter().flat_map(|slice| slice.iter().copied()).collect::<Vec<u8>>())
+    };
+    put_iovecs(&[(a, a_len), (0x10000, 0), (b, b_len)]);
+    let slices = translated_iovec(token, iov_ptr as *const IoVec, 3).unwrap();
+    assert_eq!(slices.len(), 4);
+    let mut expected = pattern(a, a_len);
+    expected.extend(pattern(b, b_len));
+    assert_eq!(gather(3), Ok(expected));
+    assert_eq!(gather(0), Ok(Vec::new()));
+    put_iovecs(&[(a, a_len), (0x16ff0, 0x20)]);
+    assert_eq!(gather(2), Err(VmError::NotMapped));
+    put_iovecs(&[(a, a_len), (0x13ff0, 0x20)]);
+    assert_eq!(gather(2), Err(VmError::Fault));
+    put_iovecs(&[(0x15000, 1)]);
+    assert_eq!(gather(1), Err(VmError::Fault));
+    put_iovecs(&[(a, usize::MAX)]);
+    assert_eq!(gather(1), Err(VmError::Overflow));
+    // iovec数组延伸到不可读的页面
+    assert_eq!(translated_iovec(token, 0x13ff8 as *const IoVec, 0x200), Err(VmError::Fault));
+    assert_eq!(translated_iovec(token, iov_ptr as *const IoVec, usize::MAX), Err(VmError::Overflow));
+    drop(page_table);
+    info!("translated_iovec_test passed!");
+}
+
+#[allow(unused)]
+/// 反复创建并丢弃带有多级中间节点的页表，页帧数回到初始值；from_token得到的页表不会释放节点
+pub fn page_table_drop_test() {
+    let before = get_num_empty_frame();
+    for round in 0..32 {
+        let mut page_table = PageTable::new();
+        // 分散在不同一级、二级索引下的页面，迫使页表新建多个中间节点
+        for i in 0..4 {
+            let vpn = VirtPageNum((i << 18) | (i << 9) | round);
+            page_table.map(vpn, PhysPageNum(0x8_0000 + i), PTEFlags::R | PTEFlags::U);
+        }
+        assert_eq!(page_table.node_count(), 1 + 4 * 2);
+        assert_eq!(get_num_empty_frame(), before - page_table.node_count());
+        let view = PageTable::from_token(page_table.token());
+        assert!(view.translate(VirtPageNum(round)).is_some());
+        drop(view);
+        assert_eq!(get_num_empty_frame(), before - page_table.node_count());
+        drop(page_table);
+        assert_eq!(get_num_empty_frame(), before);
+    }
+    info!("page_table_drop_test passed!");
+}
+
+#[allow(unused)]
+/// 检查frames恰好持有根节点和从根可达的全部中间节点；find_pte_create中途申请失败时，
+/// 已经建好的中间节点同样被记录，随页表drop归还
+pub fn page_table_nodes_test() {
+    let before = get_num_empty_frame();
+    let mut page_table = PageTable::new();
+    let flags = PTEFlags::R | PTEFlags::U;
+    // 三个不同的一级索引，其中第一个下面有两页共用同一个二级节点
+    let vpns = [0x10000, 0x10001, (1 << 18) | 5, (2 << 18) | (3 << 9)];
+    for (i, &vpn) in vpns.iter().enumerate() {
+        page_table.map(VirtPageNum(vpn), PhysPageNum(0x8_0000 + i), flags);
+    }
+    let reachable = |page_table: &PageTable| {
+        let mut nodes = vec![page_table.root_ppn.0];
+        let mut level = vec![page_table.root_ppn];
+        for _ in 0..LEAF_LEVEL {
+            let children: Vec<PhysPageNum> = level
+                .iter()
+                .flat_map(|node| node.get_pte_array().iter())
+                .filter(|pte| pte.is_valid() && !pte.is_leaf())
+                .map(|pte| pte.ppn())
+                .collect();
+            nodes.extend(children.iter().map(|ppn| ppn.0));
+            level = children;
+        }
+        nodes.sort_unstable();
+        nodes
+    };
+    let tracked = |page_table: &PageTable| {
+        let mut nodes: Vec<usize> = page_table.frames.iter().map(|frame| frame.ppn().0).collect();
+        nodes.sort_unstable();
+        nodes
+    };
+    assert_eq!(page_table.node_count(), 1 + 3 + 3);
+    assert_eq!(tracked(&page_table), reachable(&page_table));
+    // 新的一级索引需要两个中间节点，第二个申请失败
+    fail_after(1);
+    assert_eq!(page_table.try_map(VirtPageNum(3 << 18), PhysPageNum(0x8_0010), flags), Err(VmError::OutOfFrames));
+    clear_fail_after();
+    assert_eq!(page_table.node_count(), 1 + 4 + 3);
+    assert_eq!(tracked(&page_table), reachable(&page_table));
+    assert_eq!(get_num_empty_frame(), before - page_table.node_count());
+    drop(page_table);
+    assert_eq!(get_num_empty_frame(), before);
+    info!("page_table_nodes_test passed!");
+}
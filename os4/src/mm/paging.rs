@@ -0,0 +1,65 @@
+//! 分页方案的参数（级数、每级索引位数、页面大小等）。
+//! 页表和地址类型只通过[`Paging`]使用这些参数，移植到SV48时只需新增一个实现并修改[`Paging`]
+
+use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS, USER_SPACE_END};
+
+/// 一种多级页表分页方案
+pub trait PagingScheme {
+    /// 页表级数
+    const LEVELS: usize;
+    /// 每级页表索引的位数
+    const INDEX_BITS: usize;
+    /// 页内偏移的位数
+    const PAGE_SIZE_BITS: usize;
+    /// 物理页号的位数
+    const PPN_BITS: usize;
+    /// satp寄存器MODE字段的取值
+    const SATP_MODE: usize;
+    /// 每个页表节点中的页表项数
+    const ENTRIES_PER_NODE: usize = 1 << Self::INDEX_BITS;
+    /// 虚拟地址的有效位数
+    const VA_BITS: usize = Self::PAGE_SIZE_BITS + Self::LEVELS * Self::INDEX_BITS;
+
+    /// vpn在第level级（0为根）页表中的索引
+    fn index(vpn: usize, level: usize) -> usize {
+        let shift = Self::INDEX_BITS * (Self::LEVELS - 1 - level);
+        (vpn >> shift) & (Self::ENTRIES_PER_NODE - 1)
+    }
+}
+
+/// RISC-V SV39：三级页表，每级9位索引，4KiB页面，44位物理页号
+pub struct Sv39;
+
+impl PagingScheme for Sv39 {
+    const LEVELS: usize = 3;
+    const INDEX_BITS: usize = 9;
+    const PAGE_SIZE_BITS: usize = PAGE_SIZE_BITS;
+    const PPN_BITS: usize = 44;
+    const SATP_MODE: usize = 8;
+}
+
+/// 内核当前使用的分页方案
+pub type Paging = Sv39;
+
+const _: () = assert!(PAGE_SIZE == 1 << Paging::PAGE_SIZE_BITS);
+const _: () = assert!(USER_SPACE_END == 1 << (Paging::VA_BITS - 1));
+
+#[allow(unused)]
+/// 检查参数化的索引计算与SV39的硬编码计算一致，且各级索引可以拼回原来的vpn
+pub fn paging_scheme_test() {
+    use super::{VirtAddr, VirtPageNum};
+    assert_eq!(Paging::ENTRIES_PER_NODE, 512);
+    assert_eq!(Paging::VA_BITS, 39);
+    for va in [0usize, 0x1000, 0x1234_5678_9000, USER_SPACE_END - PAGE_SIZE, usize::MAX] {
+        let vpn = VirtAddr(va).floor();
+        let idx = vpn.indexes();
+        let sv39 = [(vpn.0 >> 18) & 511, (vpn.0 >> 9) & 511, vpn.0 & 511];
+        assert_eq!(idx, sv39);
+        let recomposed = idx
+            .iter()
+            .fold(0, |vpn, idx| (vpn << Paging::INDEX_BITS) | idx);
+        let mask = (1usize << (Paging::VA_BITS - Paging::PAGE_SIZE_BITS)) - 1;
+        assert_eq!(VirtPageNum(recomposed).0, vpn.0 & mask);
+    }
+    info!("paging_scheme_test passed!");
+}
@@ -1,12 +1,23 @@
-use crate::mm::translated_byte_buffer;
-use crate::task::current_user_token;
+use crate::mm::{is_user_addr_range, translated_byte_buffer};
+use crate::task::{current_user_token, prepare_user_read};
+
+use super::vm_error_code;
 
 const FD_STDOUT: usize = 1;
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     match fd {
         FD_STDOUT => {
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
+            if !is_user_addr_range(buf as usize, len) {
+                return -1;
+            }
+            if let Err(err) = prepare_user_read(buf as usize, len) {
+                return vm_error_code(err);
+            }
+            let buffers = match translated_byte_buffer(current_user_token(), buf, len) {
+                Ok(buffers) => buffers,
+                Err(err) => return vm_error_code(err),
+            };
             for buffer in buffers {
                 print!("{}", core::str::from_utf8(buffer).unwrap());
             }
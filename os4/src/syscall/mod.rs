@@ -1,13 +1,61 @@
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_EXIT_GROUP: usize = 94;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_FORK: usize = 220;
 const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_MLOCK: usize = 228;
+const SYSCALL_MUNLOCK: usize = 229;
+const SYSCALL_MADVISE: usize = 233;
 const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_TASK_INFO: usize = 410;
+const SYSCALL_MAPS: usize = 411;
+const SYSCALL_TLB_FLUSH: usize = 412;
+const SYSCALL_FRAME_COUNT: usize = 413;
+const SYSCALL_SET_MEM_QUOTA: usize = 414;
+const SYSCALL_YIELD_TO: usize = 415;
+const SYSCALL_BRK: usize = 416;
+const SYSCALL_SYSCALL_TIME: usize = 417;
+const SYSCALL_DMESG: usize = 418;
+const SYSCALL_GET_TLS_BASE: usize = 419;
+const SYSCALL_NPROC: usize = 420;
+const SYSCALL_SCHED_YIELD: usize = 421;
+const SYSCALL_TRAPSTAT: usize = 422;
+const SYSCALL_WAITPID_TIMEOUT: usize = 423;
+const SYSCALL_MINCORE: usize = 424;
+const SYSCALL_SET_NAME: usize = 425;
+const SYSCALL_GET_NAME: usize = 426;
+const SYSCALL_MAP_PHYS: usize = 427;
+const SYSCALL_PAUSE: usize = 428;
+const SYSCALL_WAKE: usize = 429;
+const SYSCALL_FUTEX_WAIT: usize = 430;
+const SYSCALL_FUTEX_WAKE: usize = 431;
+const SYSCALL_MEMBARRIER: usize = 432;
+const SYSCALL_MEMSTAT: usize = 433;
+const SYSCALL_PROCESS_VM_READ: usize = 434;
+const SYSCALL_DUMP_MAPS: usize = 435;
+const SYSCALL_GETRUSAGE: usize = 436;
+const SYSCALL_REMAP: usize = 437;
+const SYSCALL_SET_SYSCALL_FILTER: usize = 438;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 439;
+const SYSCALL_SET_ITIMER: usize = 440;
+const SYSCALL_SIGNAL: usize = 441;
+const SYSCALL_SIGRETURN: usize = 442;
+const SYSCALL_SCHED_STAT: usize = 443;
 
-use crate::task::plus_one_to_syscall_used;
+use crate::mm::VmError;
+use crate::task::{
+    current_syscall_allowed, exit_current_and_run_next, get_name_of_current_task, get_pid_of_current_task,
+    plus_one_to_syscall_used,
+};
+#[cfg(feature = "syscall-latency")]
+use crate::{task::add_syscall_ns, timer::get_time_ns};
 
 mod fs;
 mod process;
@@ -16,19 +64,94 @@ use fs::*;
 use process::*;
 
 /// handle syscall exception with `syscall_id` and other arguments
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+/// 被当前任务的系统调用过滤器禁止的调用不会执行，任务以-4退出
+pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
+    if !current_syscall_allowed(syscall_id) {
+        error!(
+            "[kernel] Syscall {} denied by the filter of application {} ({}), killed.",
+            syscall_id,
+            get_pid_of_current_task(),
+            get_name_of_current_task()
+        );
+        exit_current_and_run_next(-4);
+        unreachable!("a task killed by its syscall filter was scheduled again");
+    }
     // LAB1: You may need to update syscall info here.
     plus_one_to_syscall_used(syscall_id);
+    #[cfg(feature = "syscall-latency")]
+    let start_ns = get_time_ns();
 
-    match syscall_id {
+    let ret = match syscall_id {
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_EXIT_GROUP => sys_exit_group(args[0] as i32),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_YIELD_TO => sys_yield_to(args[0]),
+        SYSCALL_SCHED_YIELD => sys_sched_yield(),
+        SYSCALL_PAUSE => sys_pause(),
+        SYSCALL_WAKE => sys_wake(args[0]),
+        SYSCALL_FUTEX_WAIT => sys_futex_wait(args[0], args[1] as u32),
+        SYSCALL_FUTEX_WAKE => sys_futex_wake(args[0], args[1]),
+        SYSCALL_MEMBARRIER => sys_membarrier(),
+        SYSCALL_MEMSTAT => sys_memstat(args[0] as *mut MemStat),
+        SYSCALL_PROCESS_VM_READ => sys_process_vm_read(args[0], args[1], args[2] as *mut u8, args[3]),
+        SYSCALL_DUMP_MAPS => sys_dump_maps(args[0]),
+        SYSCALL_GETRUSAGE => sys_getrusage(args[0] as *mut Rusage),
         SYSCALL_GET_TIME => sys_get_time(args[0] as *mut TimeVal, args[1]),
+        SYSCALL_CLOCK_NANOSLEEP => sys_clock_nanosleep(args[0] as *const TimeSpec, args[1]),
+        SYSCALL_SET_ITIMER => sys_set_itimer(args[0]),
+        SYSCALL_SIGNAL => sys_signal(args[0]),
+        SYSCALL_SIGRETURN => sys_sigreturn(),
+        SYSCALL_SCHED_STAT => sys_sched_stat(args[0] as *mut SchedStat),
+        SYSCALL_FORK => sys_fork(),
+        SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_GETPPID => sys_getppid(),
+        SYSCALL_SBRK => sys_sbrk(args[0] as isize),
+        SYSCALL_BRK => sys_brk(args[0]),
         SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
         SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYSCALL_MADVISE => sys_madvise(args[0], args[1], args[2]),
+        SYSCALL_SET_SYSCALL_FILTER => sys_set_syscall_filter(args[0] as *const u8, args[1]),
+        SYSCALL_REMAP => sys_remap(args[0], args[1], args[2], args[3]),
+        SYSCALL_MLOCK => sys_mlock(args[0], args[1]),
+        SYSCALL_MUNLOCK => sys_munlock(args[0], args[1]),
         SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
         SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
+        SYSCALL_MAPS => sys_maps(args[0] as *mut u8, args[1]),
+        SYSCALL_TLB_FLUSH => sys_tlb_flush(),
+        SYSCALL_FRAME_COUNT => sys_frame_count(),
+        SYSCALL_SET_MEM_QUOTA => sys_set_mem_quota(args[0]),
+        SYSCALL_SYSCALL_TIME => sys_syscall_time(args[0]),
+        SYSCALL_DMESG => sys_dmesg(args[0] as *mut u8, args[1]),
+        SYSCALL_GET_TLS_BASE => sys_get_tls_base(),
+        SYSCALL_NPROC => sys_nproc(),
+        SYSCALL_WAITPID_TIMEOUT => sys_waitpid_timeout(args[0] as isize, args[1] as *mut i32, args[2]),
+        SYSCALL_SET_NAME => sys_set_name(args[0] as *const u8, args[1]),
+        SYSCALL_GET_NAME => sys_get_name(args[0] as *mut u8, args[1]),
+        SYSCALL_MINCORE => sys_mincore(args[0], args[1], args[2] as *mut u8),
+        SYSCALL_MAP_PHYS => sys_map_phys(args[0], args[1], args[2], args[3]),
+        SYSCALL_TRAPSTAT => sys_trapstat(args[0] as *mut usize, args[1]),
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
+    };
+    // sys_yield等会切换任务的系统调用记录的是包括其他任务运行在内的墙上时间
+    #[cfg(feature = "syscall-latency")]
+    add_syscall_ns(syscall_id, (get_time_ns() - start_ns) as u64);
+    ret
+}
+/// 将虚拟内存子系统的错误转换为稳定的系统调用返回值。
+/// ch4 的测试约定 mmap/munmap 的参数错误一律返回 -1，资源不足与非法地址则沿用 Linux 的 errno
+fn vm_error_code(err: VmError) -> isize {
+    match err {
+        VmError::Unaligned
+        | VmError::BadPermission
+        | VmError::Overlap
+        | VmError::NotMapped
+        | VmError::Overflow
+        | VmError::QuotaExceeded
+        | VmError::NotOwned
+        | VmError::BadElf => -1,
+        VmError::OutOfFrames | VmError::NoSpace => -12, // ENOMEM
+        VmError::Fault => -14,       // EFAULT
     }
-}
\ No newline at end of file
+}
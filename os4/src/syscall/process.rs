@@ -1,10 +1,33 @@
-use crate::config::{MAX_SYSCALL_NUM};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::config::{LOG_BUFFER_SIZE, MAP_PHYS_PID, MAX_SYSCALL_NUM, PAGE_SIZE, TASK_NAME_LEN};
+use crate::logging::read_recent_logs;
+use crate::mm::{copy_to_user, is_user_addr_range, translated_byte_buffer, MAP_STACK, VmError};
 use crate::task::{
-    exit_current_and_run_next, suspend_current_and_run_next, TaskStatus, 
+    exit_current_and_run_next, exit_group_and_run_next, suspend_current_and_run_next, sched_yield, sleep_current_until, wait_child_until, TaskStatus, 
+    pause_current_and_run_next, wake, futex_key, futex_wait, futex_wake,
     get_status_of_current_task, get_syscall_times_of_current_task, get_start_time_of_current_task,
-    get_phyaddress_from_current_task, mmap, munmap
+    get_sched_stats_of_current_task, current_fault_stats,
+    map_phys, mmap, mmap_anywhere, mmap_stack, munmap, mprotect, madvise_dontneed, madvise_willneed, mlock, munlock, alias_pages, current_maps,
+    current_user_token, current_page_counts, task_user_token, task_dump_maps, flush_current_tlb, membarrier_current, prepare_user_write, current_allocated_frames,
+    set_mem_quota, yield_to, fork_current, change_program_brk, current_program_brk,
+    get_pid_of_current_task, get_parent_pid_of_current_task, ready_count, reap_child, WaitStatus,
+    current_mapping_states, get_name_of_current_task, set_name_of_current_task, prepare_user_read,
+    set_current_syscall_filter, set_current_itimer, set_current_signal_handler, sigreturn_current
 };
 use crate::timer::get_time_us;
+use crate::trap::{trap_counts, NUM_TRAP_CAUSES};
+#[cfg(feature = "syscall-latency")]
+use crate::task::get_syscall_ns;
+#[cfg(feature = "user-tls")]
+use crate::config::TLS_BASE;
+
+use super::vm_error_code;
+
+/// sys_madvise 的 advice：立即调入区间内尚未驻留的页面，之后访问时不再缺页
+const MADV_WILLNEED: usize = 3;
+/// sys_madvise 的 advice：释放区间内已驻留的页面，之后访问时重新得到全零页面
+const MADV_DONTNEED: usize = 4;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -13,6 +36,44 @@ pub struct TimeVal {
     pub usec: usize,
 }
 
+/// sys_clock_nanosleep的时间，以启动以来的时间（与sys_get_time相同的时钟）或一段时长表示
+#[repr(C)]
+#[derive(Debug)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// sys_clock_nanosleep的flags：TimeSpec是绝对的截止时间而不是一段时长
+const TIMER_ABSTIME: usize = 1;
+/// sys_clock_nanosleep的flags：绝对截止时间已经过去时返回-1，而不是立即返回0
+const TIMER_STRICT: usize = 2;
+
+/// sys_memstat的结果，单位为页
+#[repr(C)]
+#[derive(Debug)]
+pub struct MemStat {
+    /// 已有物理页帧的页面数
+    pub resident: usize,
+    /// 已映射但尚未分配物理页帧（懒分配）的页面数
+    pub reserved: usize,
+}
+
+/// sys_getrusage的结果，时间均以微秒为单位
+#[repr(C)]
+pub struct Rusage {
+    /// 在用户态运行的时间
+    pub user_time: usize,
+    /// 在内核中处理trap的时间
+    pub kernel_time: usize,
+    /// 同时持有物理页帧数的最大值
+    pub max_resident_pages: usize,
+    /// 用户态触发的缺页异常次数
+    pub page_faults: usize,
+    /// 发起过的系统调用总数（含本次）
+    pub syscalls: usize,
+}
+
 #[derive(Clone, Copy)]
 pub struct TaskInfo {
     pub status: TaskStatus,
@@ -20,32 +81,328 @@ pub struct TaskInfo {
     pub time: usize,
 }
 
+/// sys_sched_stat的结果；不放在TaskInfo中，以免改变TaskInfo的布局
+#[repr(C)]
+pub struct SchedStat {
+    /// 被调度器选中的次数
+    pub run_count: usize,
+    /// 累计占用CPU的时间（毫秒）
+    pub cpu_time: usize,
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
-    info!("[kernel] Application exited with code {}", exit_code);
-    exit_current_and_run_next();
+    info!(
+        "[kernel] Application {} ({}) exited with code {}",
+        get_pid_of_current_task(),
+        get_name_of_current_task(),
+        exit_code
+    );
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
+/// 结束当前任务以及它fork出的所有后代任务，并回收它们的地址空间
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    info!(
+        "[kernel] Application {} ({}) exited as a group with code {}",
+        get_pid_of_current_task(),
+        get_name_of_current_task(),
+        exit_code
+    );
+    exit_group_and_run_next();
+    panic!("Unreachable in sys_exit_group!");
+}
+
 /// current task gives up resources for other tasks
 pub fn sys_yield() -> isize {
     suspend_current_and_run_next();
     0
 }
 
+/// 与sys_yield相同，但别的任务被调度运行过时返回1，只有调用者可运行（立即被重新选中）时返回0
+pub fn sys_sched_yield() -> isize {
+    sched_yield() as isize
+}
+
+/// 阻塞直到被另一个任务用sys_wake唤醒；在此之前已被唤醒过时立即返回。总是返回0
+pub fn sys_pause() -> isize {
+    pause_current_and_run_next();
+    0
+}
+
+/// 唤醒任务pid：阻塞的任务重新变为可运行，未阻塞的任务下一次pause不会阻塞；
+/// 任务不存在或已退出时返回-1
+pub fn sys_wake(pid: usize) -> isize {
+    if wake(pid) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// addr处的u32仍等于expected时阻塞，直到有任务对映射到同一物理地址的futex调用sys_futex_wake；
+/// 被唤醒后返回0，值不等于expected时立即返回-2，addr未对齐时返回-1，不可读时返回相应的错误码
+pub fn sys_futex_wait(addr: usize, expected: u32) -> isize {
+    if !is_user_addr_range(addr, core::mem::size_of::<u32>()) {
+        return -1;
+    }
+    match futex_key(addr) {
+        Ok(pa) if futex_wait(pa, expected) => 0,
+        Ok(_) => -2,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 唤醒在addr对应的futex上等待的至多n个任务，返回唤醒的任务数；addr非法时的返回值同sys_futex_wait
+pub fn sys_futex_wake(addr: usize, n: usize) -> isize {
+    if !is_user_addr_range(addr, core::mem::size_of::<u32>()) {
+        return -1;
+    }
+    match futex_key(addr) {
+        Ok(pa) => futex_wake(pa, n) as isize,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 等待当前任务的子任务pid（-1表示任意一个子任务）退出，最多等待timeout_ms毫秒；
+/// 等待期间阻塞，由子任务退出或时钟中断唤醒。返回被回收的子任务编号并把退出码写入exit_code_ptr（为空时不写），
+/// 超时返回-2，没有这样的子任务或exit_code_ptr不可写时返回-1
+pub fn sys_waitpid_timeout(pid: isize, exit_code_ptr: *mut i32, timeout_ms: usize) -> isize {
+    let code_len = core::mem::size_of::<i32>();
+    if !exit_code_ptr.is_null()
+        && (!is_user_addr_range(exit_code_ptr as usize, code_len)
+            || prepare_user_write(exit_code_ptr as usize, code_len).is_err())
+    {
+        return -1;
+    }
+    let deadline = get_time_us().saturating_add(timeout_ms.saturating_mul(1000));
+    loop {
+        match reap_child(pid) {
+            WaitStatus::NoChild => return -1,
+            WaitStatus::Exited(child, exit_code) => {
+                if !exit_code_ptr.is_null() {
+                    // 地址在回收之前已检查过，且让出CPU期间只有本任务能修改自己的地址空间
+                    let _ = copy_to_user(
+                        current_user_token(),
+                        exit_code_ptr as *mut u8,
+                        &exit_code.to_ne_bytes(),
+                    );
+                }
+                return child as isize;
+            }
+            WaitStatus::Running if get_time_us() >= deadline => return -2,
+            WaitStatus::Running => wait_child_until(deadline),
+        }
+    }
+}
+
+/// 睡眠直到time给出的时刻（flags带TIMER_ABSTIME）或经过time给出的时长，期间阻塞，成功时返回0。
+/// 周期性的任务用绝对截止时间睡眠，唤醒的延迟不会累积。nsec不小于10^9、时间溢出或flags有未知的位时返回-1；
+/// 绝对截止时间已经过去时立即返回0，flags同时带TIMER_STRICT时返回-1
+pub fn sys_clock_nanosleep(time: *const TimeSpec, flags: usize) -> isize {
+    if flags & !(TIMER_ABSTIME | TIMER_STRICT) != 0 {
+        return -1;
+    }
+    let len = core::mem::size_of::<TimeSpec>();
+    if !is_user_addr_range(time as usize, len) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_read(time as usize, len) {
+        return vm_error_code(err);
+    }
+    let bytes: Vec<u8> = match translated_byte_buffer(current_user_token(), time as *const u8, len) {
+        Ok(buffers) => buffers.iter().flat_map(|buffer| buffer.iter().copied()).collect(),
+        Err(err) => return vm_error_code(err),
+    };
+    let field = |i: usize| {
+        const WORD: usize = core::mem::size_of::<usize>();
+        let mut word = [0u8; WORD];
+        word.copy_from_slice(&bytes[i * WORD..(i + 1) * WORD]);
+        usize::from_ne_bytes(word)
+    };
+    let (sec, nsec) = (field(0), field(1));
+    if nsec >= 1_000_000_000 {
+        return -1;
+    }
+    let us = match sec.checked_mul(1_000_000).and_then(|us| us.checked_add(nsec / 1000)) {
+        Some(us) => us,
+        None => return -1,
+    };
+    let now = get_time_us();
+    let deadline = if flags & TIMER_ABSTIME != 0 {
+        if us <= now && flags & TIMER_STRICT != 0 {
+            return -1;
+        }
+        us
+    } else {
+        now.saturating_add(us)
+    };
+    while get_time_us() < deadline {
+        sleep_current_until(deadline);
+    }
+    0
+}
+
+/// 设置当前任务的间隔定时器：从现在起每interval_us微秒到期一次，到期时投递定时器信号；0表示关闭定时器
+pub fn sys_set_itimer(interval_us: usize) -> isize {
+    set_current_itimer(interval_us);
+    0
+}
+
+/// 注册定时器信号的处理函数handler_va，0表示注销。处理函数不会正常返回，而是调用sys_sigreturn
+/// 回到被打断的位置；handler_va不在用户地址空间中时返回-1
+pub fn sys_signal(handler_va: usize) -> isize {
+    if handler_va == 0 {
+        set_current_signal_handler(None);
+        return 0;
+    }
+    if !is_user_addr_range(handler_va, 4) {
+        return -1;
+    }
+    set_current_signal_handler(Some(handler_va));
+    0
+}
+
+/// 从处理函数返回被打断的位置，所有寄存器恢复为被打断时的值（返回值即被打断时的a0）；
+/// 不在处理函数中时返回-1
+pub fn sys_sigreturn() -> isize {
+    match sigreturn_current() {
+        Some(a0) => a0 as isize,
+        None => -1,
+    }
+}
+
+/// 将program break移动size字节，返回原来的program break，失败时返回-1
+pub fn sys_sbrk(size: isize) -> isize {
+    match change_program_brk(size) {
+        Some(old_brk) => old_brk as isize,
+        None => -1,
+    }
+}
+
+/// new_brk为0时返回当前的program break；否则将program break设为new_brk，
+/// 返回新的program break，失败时返回-1
+pub fn sys_brk(new_brk: usize) -> isize {
+    let old_brk = current_program_brk();
+    if new_brk == 0 {
+        return old_brk as isize;
+    }
+    match change_program_brk(new_brk.wrapping_sub(old_brk) as isize) {
+        Some(_) => new_brk as isize,
+        None => -1,
+    }
+}
+
+/// 创建当前任务的子任务：只读页面共享物理页帧，可写页面被复制。
+/// 父任务得到子任务的pid（即任务编号），子任务得到0；没有空闲的内核栈槽位时返回-1
+pub fn sys_fork() -> isize {
+    match fork_current() {
+        Some(child_id) => child_id as isize,
+        None => -1,
+    }
+}
+
+/// 返回当前任务的pid（即任务编号）
+pub fn sys_getpid() -> isize {
+    get_pid_of_current_task() as isize
+}
+
+/// 把当前任务的名字设为用户缓冲区[ptr, ptr + len)中的字节，超过TASK_NAME_LEN的部分被截断
+pub fn sys_set_name(ptr: *const u8, len: usize) -> isize {
+    let len = len.min(TASK_NAME_LEN);
+    if !is_user_addr_range(ptr as usize, len) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_read(ptr as usize, len) {
+        return vm_error_code(err);
+    }
+    let buffers = match translated_byte_buffer(current_user_token(), ptr, len) {
+        Ok(buffers) => buffers,
+        Err(err) => return vm_error_code(err),
+    };
+    let name: Vec<u8> = buffers.iter().flat_map(|buffer| buffer.iter().copied()).collect();
+    set_name_of_current_task(&name);
+    0
+}
+
+/// 为当前任务安装系统调用过滤器：ptr处len字节的位图中，第i个字节的第j位为1表示允许编号为8 * i + j的系统调用，
+/// 位图之外的编号一律禁止。过滤器只能安装一次且不能撤销，fork出的子任务继承它；
+/// 已经安装过或位图超过MAX_SYSCALL_NUM位时返回-1
+pub fn sys_set_syscall_filter(ptr: *const u8, len: usize) -> isize {
+    if !is_user_addr_range(ptr as usize, len) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_read(ptr as usize, len) {
+        return vm_error_code(err);
+    }
+    let buffers = match translated_byte_buffer(current_user_token(), ptr, len) {
+        Ok(buffers) => buffers,
+        Err(err) => return vm_error_code(err),
+    };
+    let bitmap: Vec<u8> = buffers.iter().flat_map(|buffer| buffer.iter().copied()).collect();
+    if set_current_syscall_filter(&bitmap) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 把当前任务的名字写入用户缓冲区（最多len字节，不含结尾的0），返回写入的字节数
+pub fn sys_get_name(buf: *mut u8, len: usize) -> isize {
+    let name = get_name_of_current_task();
+    let n = name.len().min(len);
+    if !is_user_addr_range(buf as usize, n) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(buf as usize, n) {
+        return vm_error_code(err);
+    }
+    match copy_to_user(current_user_token(), buf, &name.as_bytes()[..n]) {
+        Ok(()) => n as isize,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 返回父任务的pid；由加载器创建的任务或父任务已经退出时返回-1
+pub fn sys_getppid() -> isize {
+    match get_parent_pid_of_current_task() {
+        Some(parent) => parent as isize,
+        None => -1,
+    }
+}
+
+/// 返回可运行（Ready或Running）的任务数，已退出的任务不计在内
+pub fn sys_nproc() -> isize {
+    ready_count() as isize
+}
+
+/// 让出CPU并直接切换到任务pid（即应用编号），目标不是Ready状态时返回-1
+pub fn sys_yield_to(pid: usize) -> isize {
+    if yield_to(pid) {
+        0
+    } else {
+        -1
+    }
+}
+
 // YOUR JOB: 引入虚地址后重写 sys_get_time
 pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
+    if !is_user_addr_range(ts as usize, core::mem::size_of::<TimeVal>()) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(ts as usize, core::mem::size_of::<TimeVal>()) {
+        return vm_error_code(err);
+    }
     let us = get_time_us();
-    
-    let ts_tmp = get_phyaddress_from_current_task(ts as usize);
-    
-    let ts = ts_tmp as *mut TimeVal;
-    unsafe {
-        *ts = TimeVal {
-            sec: us / 1_000_000,
-            usec: us % 1_000_000,
-        };
+    // TimeVal可能跨页，两页对应的物理页帧不一定相邻，经由页表逐页拷贝
+    let data: Vec<u8> = [us / 1_000_000, us % 1_000_000]
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    match copy_to_user(current_user_token(), ts as *mut u8, &data) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
     }
-    0
 }
 
 // CLUE: 从 ch4 开始不再对调度算法进行测试~
@@ -54,7 +411,20 @@ pub fn sys_set_priority(_prio: isize) -> isize {
 }
 
 // YOUR JOB: 扩展内核以实现 sys_mmap 和 sys_munmap
+/// 成功时返回非负值：start非0时（固定地址）返回0，start为0时返回内核选择的起始地址；
+/// 失败时返回负的错误码。
+/// len为0的sys_mmap/sys_munmap是空操作：不检查其余参数，直接返回0。
+/// port带MAP_STACK时start必须为0、len必须非零
 pub fn sys_mmap(start: usize, len: usize, port: usize) -> isize {
+    if port & MAP_STACK != 0 {
+        if start != 0 {
+            return -1;
+        }
+        return match mmap_stack(len, port) {
+            Ok(start) => start as isize,
+            Err(err) => vm_error_code(err),
+        };
+    }
     // MapArea::new(
     //     TRAP_CONTEXT.into(),
     //     TRAMPOLINE.into(),
@@ -62,24 +432,361 @@ pub fn sys_mmap(start: usize, len: usize, port: usize) -> isize {
     //     MapPermission::R | MapPermission::W,
     // );
     // MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-    mmap(start, len, port)
+    if len == 0 {
+        return 0;
+    }
+    if start == 0 {
+        return match mmap_anywhere(len, port) {
+            Ok(start) => start as isize,
+            Err(err) => vm_error_code(err),
+        };
+    }
+    match mmap(start, len, port) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
 }
 
 pub fn sys_munmap(start: usize, len: usize) -> isize {
-    munmap(start, len)
+    match munmap(start, len) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+pub fn sys_madvise(start: usize, len: usize, advice: usize) -> isize {
+    match advice {
+        MADV_DONTNEED => match madvise_dontneed(start, len) {
+            Ok(()) => 0,
+            Err(err) => vm_error_code(err),
+        },
+        MADV_WILLNEED => match madvise_willneed(start, len) {
+            Ok(()) => 0,
+            // 页帧不够调入整个区间与超出配额一样，都是无法全部调入，返回-1
+            Err(VmError::OutOfFrames) => -1,
+            Err(err) => vm_error_code(err),
+        },
+        _ => -1,
+    }
+}
+
+/// 锁定[start, start + len)，使其中的页面立即驻留且不会被回收；start必须按页对齐，区间必须已被映射。
+/// 同一页面可以被多次锁定，需要同样次数的sys_munlock才能解除
+pub fn sys_mlock(start: usize, len: usize) -> isize {
+    match mlock(start, len) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 解除[start, start + len)的一层锁定
+pub fn sys_munlock(start: usize, len: usize) -> isize {
+    match munlock(start, len) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 在起始于base的mmap区域内，把第offset_pages页起的count页所用的物理页帧再映射到第target_offset_pages页起，
+/// 使同一组页帧出现在两个虚拟偏移处。两个区间都必须落在该区域内且互不重叠
+pub fn sys_remap(base: usize, offset_pages: usize, count: usize, target_offset_pages: usize) -> isize {
+    match alias_pages(base, offset_pages, count, target_offset_pages) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+pub fn sys_mprotect(start: usize, len: usize, port: usize) -> isize {
+    match mprotect(start, len, port) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
 }
 
 // YOUR JOB: 引入虚地址后重写 sys_task_info
 pub fn sys_task_info(ti: *mut TaskInfo) -> isize {
-    let ts_tmp = get_phyaddress_from_current_task(ti as usize);
-    
-    let ti = ts_tmp as *mut TaskInfo;
+    if !is_user_addr_range(ti as usize, core::mem::size_of::<TaskInfo>()) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(ti as usize, core::mem::size_of::<TaskInfo>()) {
+        return vm_error_code(err);
+    }
+    let info = TaskInfo {
+        status: get_status_of_current_task(),
+        syscall_times: get_syscall_times_of_current_task(),
+        time: (get_time_us() - get_start_time_of_current_task()) / 1_000,
+    };
+    // TaskInfo有2KiB左右，一般会跨页；先按用户库中相同的布局写入内核缓冲区，再经由页表逐页拷贝
+    let size = core::mem::size_of::<TaskInfo>();
+    let mut data = vec![0u8; size];
     unsafe {
-        *ti = TaskInfo{
-            status: get_status_of_current_task(),
-            syscall_times: get_syscall_times_of_current_task(),
-            time: (get_time_us() - get_start_time_of_current_task()) / 1_000,
+        (data.as_mut_ptr() as *mut TaskInfo).write_unaligned(info);
+    }
+    match copy_to_user(current_user_token(), ti as *mut u8, &data) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 把当前任务被调度的次数和累计占用CPU的时间写入stat
+pub fn sys_sched_stat(stat: *mut SchedStat) -> isize {
+    let size = core::mem::size_of::<SchedStat>();
+    if !is_user_addr_range(stat as usize, size) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(stat as usize, size) {
+        return vm_error_code(err);
+    }
+    let now = get_time_us();
+    let sched_stats = get_sched_stats_of_current_task();
+    let data: Vec<u8> = [sched_stats.run_count, sched_stats.cpu_time_us(now) / 1_000]
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    match copy_to_user(current_user_token(), stat as *mut u8, &data) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+/// 将当前任务的逻辑段列表（/proc/self/maps格式）写入用户缓冲区，返回写入的字节数。
+/// 缓冲区不足时只写入能完整放下的行
+pub fn sys_maps(buf: *mut u8, len: usize) -> isize {
+    if !is_user_addr_range(buf as usize, len) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(buf as usize, len) {
+        return vm_error_code(err);
+    }
+    let text = current_maps();
+    let mut end = text.len().min(len);
+    if end < text.len() {
+        end = text[..end].rfind('\n').map_or(0, |pos| pos + 1);
+    }
+    match copy_to_user(current_user_token(), buf, &text.as_bytes()[..end]) {
+        Ok(()) => end as isize,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 把[start, start + len)中每一页的映射状态（0未映射、1已保留但未驻留、2已驻留）
+/// 依次写入vec的一个字节；start必须按页对齐且区间位于用户地址空间内
+pub fn sys_mincore(start: usize, len: usize, vec: *mut u8) -> isize {
+    if start % PAGE_SIZE != 0 || !is_user_addr_range(start, len) {
+        return -1;
+    }
+    let pages = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+    if !is_user_addr_range(vec as usize, pages) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(vec as usize, pages) {
+        return vm_error_code(err);
+    }
+    let states: Vec<u8> = current_mapping_states(start / PAGE_SIZE, pages)
+        .into_iter()
+        .map(|state| state as u8)
+        .collect();
+    match copy_to_user(current_user_token(), vec, &states) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 把[va, va + len)直接映射到从pa开始的物理页帧（如MMIO），port的低三位依次对应R/W/X；
+/// 只有编号为MAP_PHYS_PID的任务可以调用，物理区间与可分配的页帧重叠时失败
+pub fn sys_map_phys(va: usize, pa: usize, len: usize, port: usize) -> isize {
+    if get_pid_of_current_task() != MAP_PHYS_PID {
+        return -1;
+    }
+    match map_phys(va, pa, len, port) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 清空调用者地址空间的TLB缓存，用于观察TLB缺失的代价
+pub fn sys_tlb_flush() -> isize {
+    flush_current_tlb();
+    0
+}
+
+/// 把任务pid的逻辑段及其页表叶子逐行输出到内核日志（控制台与sys_dmesg的缓冲区）；
+/// 只有监督任务和pid的父任务可以调用，否则或任务不存在、已退出时返回-1
+pub fn sys_dump_maps(pid: usize) -> isize {
+    match task_dump_maps(pid) {
+        Some(text) => {
+            info!("[kernel] maps of task {}:", pid);
+            for line in text.lines() {
+                info!("{}", line);
+            }
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 把任务pid的地址空间中从remote_addr开始的len字节读到调用者的local_buf，返回读到的字节数；
+/// 与sys_dump_maps一样只有监督任务和pid的父任务可以调用，否则或任务不存在、已退出、
+/// 远端区间中有未映射的页面时返回-1，两边都可以跨页
+pub fn sys_process_vm_read(pid: usize, remote_addr: usize, local_buf: *mut u8, len: usize) -> isize {
+    if !is_user_addr_range(remote_addr, len) || !is_user_addr_range(local_buf as usize, len) {
+        return -1;
+    }
+    let remote_token = match task_user_token(pid) {
+        Some(token) => token,
+        None => return -1,
+    };
+    if let Err(err) = prepare_user_write(local_buf as usize, len) {
+        return vm_error_code(err);
+    }
+    let remote = match translated_byte_buffer(remote_token, remote_addr as *const u8, len) {
+        Ok(buffers) => buffers,
+        Err(err) => return vm_error_code(err),
+    };
+    let local_token = current_user_token();
+    let mut copied = 0;
+    for chunk in remote {
+        let dst = (local_buf as usize + copied) as *mut u8;
+        if let Err(err) = copy_to_user(local_token, dst, chunk) {
+            return vm_error_code(err);
         }
+        copied += chunk.len();
     }
+    copied as isize
+}
+
+/// 对调用者的地址空间执行sfence.vma和fence.i，自修改代码在写入指令之后、跳转执行之前调用。总是返回0
+pub fn sys_membarrier() -> isize {
+    membarrier_current();
     0
-}
\ No newline at end of file
+}
+
+/// 返回调用者当前持有的物理页帧数（ELF段、用户栈、Trap上下文与已写入的mmap页面），
+/// 用于分析各任务之间的内存占用是否公平
+pub fn sys_frame_count() -> isize {
+    current_allocated_frames() as isize
+}
+
+/// 把调用者地址空间中已驻留和仅保留的页面数写入stat，区分真正占用的内存和只是申请了的内存
+pub fn sys_memstat(stat: *mut MemStat) -> isize {
+    let size = core::mem::size_of::<MemStat>();
+    if !is_user_addr_range(stat as usize, size) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(stat as usize, size) {
+        return vm_error_code(err);
+    }
+    let (resident, reserved) = current_page_counts();
+    let data: Vec<u8> = [resident, reserved]
+        .iter()
+        .flat_map(|count| count.to_ne_bytes())
+        .collect();
+    match copy_to_user(current_user_token(), stat as *mut u8, &data) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 把调用者的资源使用情况（CPU时间、页帧高水位、缺页与系统调用次数）写入out
+pub fn sys_getrusage(out: *mut Rusage) -> isize {
+    let size = core::mem::size_of::<Rusage>();
+    if !is_user_addr_range(out as usize, size) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(out as usize, size) {
+        return vm_error_code(err);
+    }
+    let now = get_time_us();
+    let sched_stats = get_sched_stats_of_current_task();
+    let (max_resident_pages, page_faults) = current_fault_stats();
+    let syscalls: usize = get_syscall_times_of_current_task()
+        .iter()
+        .map(|&times| times as usize)
+        .sum();
+    let usage = Rusage {
+        user_time: sched_stats.user_time_us(now),
+        kernel_time: sched_stats.kernel_time_us(now),
+        max_resident_pages,
+        page_faults,
+        syscalls,
+    };
+    let data: Vec<u8> = [
+        usage.user_time,
+        usage.kernel_time,
+        usage.max_resident_pages,
+        usage.page_faults,
+        usage.syscalls,
+    ]
+    .iter()
+    .flat_map(|value| value.to_ne_bytes())
+    .collect();
+    match copy_to_user(current_user_token(), out as *mut u8, &data) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 将调用者的物理页帧配额设为pages页，pages为0表示不限制。
+/// 超出配额的mmap返回-1，写缺页超出配额时任务会像访问非法地址一样被杀死
+pub fn sys_set_mem_quota(pages: usize) -> isize {
+    let pages = if pages == 0 { usize::MAX } else { pages };
+    match set_mem_quota(pages) {
+        Ok(()) => 0,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 返回当前任务在系统调用syscall_id上累计花费的时间（纳秒）；
+/// syscall_id超出范围或内核没有启用syscall-latency特性时返回-1
+#[allow(clippy::needless_return)]
+pub fn sys_syscall_time(syscall_id: usize) -> isize {
+    if syscall_id >= MAX_SYSCALL_NUM {
+        return -1;
+    }
+    #[cfg(feature = "syscall-latency")]
+    return get_syscall_ns(syscall_id) as isize;
+    #[cfg(not(feature = "syscall-latency"))]
+    return -1;
+}
+
+/// 将最近的内核日志（最多len字节）拷贝到用户缓冲区，返回拷贝的字节数
+pub fn sys_dmesg(buf: *mut u8, len: usize) -> isize {
+    if !is_user_addr_range(buf as usize, len) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(buf as usize, len) {
+        return vm_error_code(err);
+    }
+    let mut logs = vec![0u8; len.min(LOG_BUFFER_SIZE)];
+    let n = read_recent_logs(&mut logs);
+    match copy_to_user(current_user_token(), buf, &logs[..n]) {
+        Ok(()) => n as isize,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 把各trap原因的计数（下标见trap::stats）写入buf中最多len个usize，返回写入的个数
+pub fn sys_trapstat(buf: *mut usize, len: usize) -> isize {
+    let n = len.min(NUM_TRAP_CAUSES);
+    let bytes = n * core::mem::size_of::<usize>();
+    if !is_user_addr_range(buf as usize, bytes) {
+        return -1;
+    }
+    if let Err(err) = prepare_user_write(buf as usize, bytes) {
+        return vm_error_code(err);
+    }
+    let counts = trap_counts();
+    let data: Vec<u8> = counts[..n].iter().flat_map(|count| count.to_ne_bytes()).collect();
+    match copy_to_user(current_user_token(), buf as *mut u8, &data) {
+        Ok(()) => n as isize,
+        Err(err) => vm_error_code(err),
+    }
+}
+
+/// 返回当前任务TLS页面的起始地址；内核没有启用user-tls特性时返回-1
+#[allow(clippy::needless_return)]
+pub fn sys_get_tls_base() -> isize {
+    #[cfg(feature = "user-tls")]
+    return TLS_BASE as isize;
+    #[cfg(not(feature = "user-tls"))]
+    return -1;
+}
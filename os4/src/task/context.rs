@@ -19,10 +19,27 @@ impl TaskContext {
             s: [0; 12],
         }
     }
+    /// 保存的内核栈指针
+    pub fn sp(&self) -> usize {
+        self.sp
+    }
+    /// 内核栈的内容从[bottom, top)整体搬到了偏移offset（按wrapping加法）的位置之后，
+    /// 修正保存的sp以及指向旧栈的被调用者保存寄存器（如帧指针s0）
+    pub fn relocate_stack(&mut self, bottom: usize, top: usize, offset: usize) {
+        for reg in core::iter::once(&mut self.sp).chain(self.s.iter_mut()) {
+            if bottom <= *reg && *reg <= top {
+                *reg = reg.wrapping_add(offset);
+            }
+        }
+    }
     /// 唯一经常使用的初始化方法
     pub fn goto_trap_return(kstack_ptr: usize) -> Self {
+        Self::goto_entry(trap_return as usize, kstack_ptr)
+    }
+    /// 切换到这个上下文时在栈kstack_ptr上从entry开始执行
+    pub fn goto_entry(entry: usize, kstack_ptr: usize) -> Self {
         Self {
-            ra: trap_return as usize,
+            ra: entry,
             sp: kstack_ptr,
             s: [0; 12],
         }
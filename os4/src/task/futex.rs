@@ -0,0 +1,82 @@
+//! 按物理地址组织的futex等待队列
+
+use crate::mm::PhysAddr;
+use crate::sync::UPSafeCell;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// futex等待队列：以futex字的物理地址为键，因此不同任务通过不同虚拟地址访问同一个物理页帧时，
+/// 等待的是同一个futex
+pub struct FutexTable {
+    queues: BTreeMap<PhysAddr, VecDeque<usize>>,
+}
+
+impl FutexTable {
+    pub fn new() -> Self {
+        Self {
+            queues: BTreeMap::new(),
+        }
+    }
+    /// 任务id开始在pa上等待，排在队尾
+    pub fn enqueue(&mut self, pa: PhysAddr, id: usize) {
+        self.queues.entry(pa).or_insert_with(VecDeque::new).push_back(id);
+    }
+    /// 按等待的先后从pa的队列中取出至多n个任务
+    pub fn dequeue(&mut self, pa: PhysAddr, n: usize) -> Vec<usize> {
+        let queue = match self.queues.get_mut(&pa) {
+            Some(queue) => queue,
+            None => return Vec::new(),
+        };
+        let count = n.min(queue.len());
+        let woken: Vec<usize> = queue.drain(..count).collect();
+        if queue.is_empty() {
+            self.queues.remove(&pa);
+        }
+        woken
+    }
+    /// 把任务id从所有等待队列中删除，任务退出或不再等待时调用
+    pub fn forget(&mut self, id: usize) {
+        for queue in self.queues.values_mut() {
+            queue.retain(|&waiter| waiter != id);
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+    }
+    /// 在pa上等待的任务数
+    pub fn waiters(&self, pa: PhysAddr) -> usize {
+        self.queues.get(&pa).map_or(0, |queue| queue.len())
+    }
+}
+
+lazy_static! {
+    /// 全局的futex等待队列
+    pub static ref FUTEX_TABLE: UPSafeCell<FutexTable> =
+        unsafe { UPSafeCell::new(FutexTable::new()) };
+}
+
+#[allow(unused)]
+/// 生产者每放入一个产品就唤醒一个消费者：同一物理地址上的等待者按先后被唤醒，
+/// 不同物理地址互不影响，退出的等待者不会再被唤醒
+pub fn futex_test() {
+    let word = PhysAddr(0x8040_0000);
+    let other = PhysAddr(0x8040_0004);
+    let mut table = FutexTable::new();
+    // 三个消费者等待产品，另一个任务等待无关的futex
+    for consumer in 1..=3 {
+        table.enqueue(word, consumer);
+    }
+    table.enqueue(other, 9);
+    assert_eq!(table.waiters(word), 3);
+    // 消费者2在等待时退出
+    table.forget(2);
+    assert_eq!(table.waiters(word), 2);
+    // 生产者放入一个产品，唤醒一个消费者
+    assert_eq!(table.dequeue(word, 1), [1]);
+    // 再放入两个产品，只剩一个消费者可以唤醒
+    assert_eq!(table.dequeue(word, 2), [3]);
+    assert_eq!(table.dequeue(word, 1), []);
+    assert_eq!(table.waiters(word), 0);
+    assert_eq!(table.dequeue(other, usize::MAX), [9]);
+    assert!(table.queues.is_empty());
+    info!("futex_test passed!");
+}
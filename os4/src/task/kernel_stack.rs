@@ -0,0 +1,133 @@
+//! 内核栈槽位的分配与回收：槽位与任务编号无关，任务销毁后其内核栈的虚拟地址区间可以被复用
+
+use crate::config::{kernel_stack_position, MAX_KERNEL_STACKS};
+use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// 与StackFrameAllocator类似：优先复用回收的槽位，否则取下一个从未使用过的槽位
+pub struct KernelStackAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl KernelStackAllocator {
+    pub fn new() -> Self {
+        Self {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+    /// 槽位用完时返回None
+    pub fn alloc(&mut self) -> Option<usize> {
+        if let Some(slot) = self.recycled.pop() {
+            Some(slot)
+        } else if self.current < MAX_KERNEL_STACKS {
+            self.current += 1;
+            Some(self.current - 1)
+        } else {
+            None
+        }
+    }
+    /// 分配指定的槽位；槽位已被占用或超出MAX_KERNEL_STACKS时返回false。
+    /// 为此越过的从未使用过的槽位放入recycled，仍可被alloc分配
+    pub fn alloc_at(&mut self, slot: usize) -> bool {
+        if let Some(idx) = self.recycled.iter().position(|&recycled| recycled == slot) {
+            self.recycled.swap_remove(idx);
+            return true;
+        }
+        if slot < self.current || slot >= MAX_KERNEL_STACKS {
+            return false;
+        }
+        self.recycled.extend(self.current..slot);
+        self.current = slot + 1;
+        true
+    }
+    pub fn dealloc(&mut self, slot: usize) {
+        assert!(
+            slot < self.current && !self.recycled.contains(&slot),
+            "kernel stack slot {} has not been allocated!",
+            slot
+        );
+        self.recycled.push(slot);
+    }
+}
+
+lazy_static! {
+    static ref KSTACK_ALLOCATOR: UPSafeCell<KernelStackAllocator> =
+        unsafe { UPSafeCell::new(KernelStackAllocator::new()) };
+}
+
+/// 一个已映射在KERNEL_SPACE中的内核栈，drop时解除映射并归还槽位
+pub struct KernelStack {
+    slot: usize,
+}
+
+impl KernelStack {
+    /// 分配一个槽位并映射对应的内核栈，槽位用完时返回None
+    pub fn new() -> Option<Self> {
+        let slot = KSTACK_ALLOCATOR.exclusive_access().alloc()?;
+        Some(Self::map(slot))
+    }
+    /// 在指定的槽位上分配并映射内核栈，槽位已被占用时返回None
+    pub fn new_at(slot: usize) -> Option<Self> {
+        if !KSTACK_ALLOCATOR.exclusive_access().alloc_at(slot) {
+            return None;
+        }
+        Some(Self::map(slot))
+    }
+    fn map(slot: usize) -> Self {
+        let (bottom, top) = kernel_stack_position(slot).unwrap();
+        KERNEL_SPACE
+            .lock()
+            .insert_framed_area(bottom.into(), top.into(), MapPermission::R | MapPermission::W)
+            .expect("failed to map a kernel stack");
+        Self { slot }
+    }
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+    pub fn bottom(&self) -> usize {
+        kernel_stack_position(self.slot).unwrap().0
+    }
+    pub fn top(&self) -> usize {
+        kernel_stack_position(self.slot).unwrap().1
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (bottom, _) = kernel_stack_position(self.slot).unwrap();
+        KERNEL_SPACE
+            .lock()
+            .remove_area_with_start_vpn(VirtAddr::from(bottom).floor());
+        KSTACK_ALLOCATOR.exclusive_access().dealloc(self.slot);
+    }
+}
+
+#[allow(unused)]
+/// 反复创建、销毁内核栈，检查用到的内核栈区间数量不超过同时存在的内核栈数量
+pub fn kernel_stack_recycle_test() {
+    use alloc::collections::BTreeSet;
+    let mut tops = BTreeSet::new();
+    for _ in 0..100 {
+        let stacks: Vec<KernelStack> = (0..4).map(|_| KernelStack::new().unwrap()).collect();
+        for stack in stacks.iter() {
+            tops.insert(stack.top());
+        }
+    }
+    assert_eq!(tops.len(), 4);
+    let mut allocator = KernelStackAllocator::new();
+    let slots: Vec<usize> = (0..MAX_KERNEL_STACKS).map(|_| allocator.alloc().unwrap()).collect();
+    assert!(allocator.alloc().is_none());
+    allocator.dealloc(slots[7]);
+    assert_eq!(allocator.alloc(), Some(slots[7]));
+    let mut allocator = KernelStackAllocator::new();
+    assert!(allocator.alloc_at(3) && !allocator.alloc_at(3));
+    assert!(!allocator.alloc_at(MAX_KERNEL_STACKS));
+    let mut slots: Vec<usize> = (0..4).map(|_| allocator.alloc().unwrap()).collect();
+    slots.sort_unstable();
+    assert_eq!(slots, [0, 1, 2, 4]);
+    info!("kernel_stack_recycle_test passed!");
+}
@@ -1,22 +1,33 @@
 mod context;
+mod futex;
+mod kernel_stack;
 mod switch;
 #[allow(clippy::module_inception)]
 mod task;
 
-use crate::loader::{get_app_data, get_num_app};
+use crate::loader::{get_app_data, get_app_name, get_num_app};
 use crate::sync::UPSafeCell;
-use crate::mm::{VirtAddr, PhysAddr};
-use crate::config::{PAGE_SIZE_BITS, MAX_SYSCALL_NUM};
+use crate::mm::{FaultKind, MappingState, PTEFlags, VirtAddr, VirtPageNum, PhysAddr, VmError, KERNEL_SPACE};
+use crate::sbi::shutdown;
+use crate::config::{PAGE_SIZE_BITS, MAX_SYSCALL_NUM, SUPERVISOR_PID};
 use crate::trap::TrapContext;
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
-use crate::timer::get_time_us;
+use crate::timer::{get_time_us, set_next_trigger};
 
 use lazy_static::*;
 pub use switch::__switch;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use task::{SchedStats, TaskControlBlock, TaskStatus};
+use task::{syscall_filter_from_bytes, WaitEvents};
 
 pub use context::TaskContext;
+#[cfg(feature = "selftest")]
+pub use futex::futex_test;
+use futex::FUTEX_TABLE;
+#[cfg(feature = "selftest")]
+pub use kernel_stack::kernel_stack_recycle_test;
 
 /// The task manager, where all the tasks are managed.
 ///
@@ -28,8 +39,6 @@ pub use context::TaskContext;
 /// borrowing checks to runtime. You can see examples on how to use `inner` in
 /// existing functions on `TaskManager`.
 pub struct TaskManager {
-    /// total number of tasks
-    num_app: usize,
     /// use inner value to get mutable access
     inner: UPSafeCell<TaskManagerInner>,
 }
@@ -40,6 +49,39 @@ struct TaskManagerInner {
     tasks: Vec<TaskControlBlock>,
     /// id of current `Running` task
     current_task: usize,
+    /// sys_yield_to指定的下一个任务，下一次调度时优先运行
+    yield_target: Option<usize>,
+    /// run_first_task中的idle控制流：没有可运行的任务时切换回这里
+    idle_task_cx: TaskContext,
+}
+
+impl TaskManagerInner {
+    /// 正在运行的任务；启动阶段以及所有任务都结束之后（运行idle控制流时）为None
+    fn running_task(&self) -> Option<usize> {
+        self.tasks
+            .get(self.current_task)
+            .filter(|task| task.task_status == TaskStatus::Running)
+            .map(|_| self.current_task)
+    }
+    /// 唤醒截止时间不晚于now的阻塞任务
+    fn wake_expired(&mut self, now: usize) {
+        for task in self.tasks.iter_mut() {
+            if task.task_status == TaskStatus::Blocked
+                && task.wait.deadline.map_or(false, |deadline| deadline <= now)
+            {
+                task.task_status = TaskStatus::Ready;
+                task.wait = WaitEvents::default();
+            }
+        }
+    }
+    /// 阻塞任务中最早的截止时间；没有任务在定时等待时返回None
+    fn next_deadline(&self) -> Option<usize> {
+        self.tasks
+            .iter()
+            .filter(|task| task.task_status == TaskStatus::Blocked)
+            .filter_map(|task| task.wait.deadline)
+            .min()
+    }
 }
 
 lazy_static! {
@@ -50,14 +92,17 @@ lazy_static! {
         info!("num_app = {}", num_app);
         let mut tasks: Vec<TaskControlBlock> = Vec::new();
         for i in 0..num_app {
-            tasks.push(TaskControlBlock::new(get_app_data(i), i));
+            let mut task = TaskControlBlock::new(get_app_data(i), i);
+            task.set_name(get_app_name(i).as_bytes());
+            tasks.push(task);
         }
         TaskManager {
-            num_app,
             inner: unsafe {
                 UPSafeCell::new(TaskManagerInner {
                     tasks,
                     current_task: 0,
+                    yield_target: None,
+                    idle_task_cx: TaskContext::zero_init(),
                 })
             },
         }
@@ -69,22 +114,53 @@ impl TaskManager {
     ///
     /// Generally, the first task in task list is an idle task (we call it zero process later).
     /// But in ch4, we load apps statically, so the first task is a real app.
+    /// 启动时的控制流保存在idle_task_cx中，没有可运行的任务时调度器切换回这里：
+    /// 有任务在定时等待时睡眠到最早的截止时间再继续调度，否则关机退出
     fn run_first_task(&self) -> ! {
-        let mut inner = self.inner.exclusive_access();
-        let next_task = &mut inner.tasks[0];
-        next_task.task_status = TaskStatus::Running;
-        let next_task_cx_ptr = &next_task.task_cx as *const TaskContext;
-
-        // recod the start time point of the first task
-        inner.tasks[0].start_time = get_time_us();
-
-        drop(inner);
-        let mut _unused = TaskContext::zero_init();
-        // before this, we should drop local variables that must be dropped manually
-        unsafe {
-            __switch(&mut _unused as *mut _, next_task_cx_ptr);
+        let mut last = self.inner.exclusive_access().tasks.len().saturating_sub(1);
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            inner.wake_expired(get_time_us());
+            let statuses: Vec<TaskStatus> = inner.tasks.iter().map(|task| task.task_status).collect();
+            if let Some(next) = pick_next_task(&statuses, last, None) {
+                let next_task = &mut inner.tasks[next];
+                next_task.task_status = TaskStatus::Running;
+                let now = get_time_us();
+                // recod the start time point of the task
+                if next_task.start_time == 0 {
+                    next_task.start_time = now;
+                }
+                next_task.sched_stats.scheduled(now);
+                let next_task_cx_ptr = &next_task.task_cx as *const TaskContext;
+                inner.current_task = next;
+                let idle_task_cx_ptr = &mut inner.idle_task_cx as *mut TaskContext;
+                drop(inner);
+                // before this, we should drop local variables that must be dropped manually
+                unsafe {
+                    __switch(idle_task_cx_ptr, next_task_cx_ptr);
+                }
+                last = self.inner.exclusive_access().current_task;
+                continue;
+            }
+            let deadline = inner.next_deadline();
+            drop(inner);
+            match deadline {
+                Some(deadline) => idle_until(deadline),
+                None => break,
+            }
+        }
+        let blocked = self
+            .inner
+            .exclusive_access()
+            .tasks
+            .iter()
+            .filter(|task| task.task_status == TaskStatus::Blocked)
+            .count();
+        if blocked > 0 {
+            println!("[kernel] {} blocked task(s) can never be woken up", blocked);
         }
-        panic!("unreachable in run_first_task!");
+        println!("[kernel] All applications completed!");
+        shutdown()
     }
 
     /// Change the status of current `Running` task into `Ready`.
@@ -95,27 +171,104 @@ impl TaskManager {
     }
 
     /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
+    fn mark_current_exited(&self, exit_code: i32) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        exit_task(&mut inner.tasks, current, exit_code);
+    }
+
+    /// 把当前任务标记为Blocked；已有待处理的唤醒时消耗它并返回false，任务保持运行
+    fn mark_current_blocked(&self) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        let task = &mut inner.tasks[current];
+        if task.wakeup_pending {
+            task.wakeup_pending = false;
+            return false;
+        }
+        task.task_status = TaskStatus::Blocked;
+        true
+    }
+
+    /// 唤醒任务id，任务不存在或已退出时返回false
+    fn wake(&self, id: usize) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        let task = match inner.tasks.get_mut(id) {
+            Some(task) => task,
+            None => return false,
+        };
+        match wake_transition(task.task_status) {
+            Some((status, pending)) => {
+                task.task_status = status;
+                task.wakeup_pending |= pending;
+                task.wait = WaitEvents::default();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 把当前任务标记为Blocked，除了wake之外还会在wait中的事件发生时被唤醒
+    fn block_current(&self, wait: WaitEvents) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        let task = &mut inner.tasks[current];
+        task.task_status = TaskStatus::Blocked;
+        task.wait = wait;
+    }
+
+    /// 唤醒截止时间已到的阻塞任务
+    fn wake_expired(&self) {
+        self.inner.exclusive_access().wake_expired(get_time_us());
+    }
+
+    /// 结束当前任务及其所有后代
+    /// 返回被结束的任务编号
+    fn mark_current_group_exited(&self) -> Vec<usize> {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        inner.tasks[current].task_status = TaskStatus::Exited;
+        exit_task_tree(&mut inner.tasks, current)
     }
 
     /// Find next task to run and return task id.
     ///
     /// In this case, we only return the first `Ready` task in task list.
     fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
+        let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+        let target = inner.yield_target.take();
+        let statuses: Vec<TaskStatus> = inner.tasks.iter().map(|task| task.task_status).collect();
+        pick_next_task(&statuses, current, target)
+    }
+
+    /// 指定下一次调度时优先运行的任务，目标不存在、不是Ready或者就是当前任务时返回false
+    fn set_yield_target(&self, id: usize) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if id >= inner.tasks.len()
+            || id == inner.current_task
+            || inner.tasks[id].task_status != TaskStatus::Ready
+        {
+            return false;
+        }
+        inner.yield_target = Some(id);
+        true
+    }
+
+    /// 处于Ready或Running状态的任务数
+    fn ready_count(&self) -> usize {
+        let inner = self.inner.exclusive_access();
+        let statuses: Vec<TaskStatus> = inner.tasks.iter().map(|task| task.task_status).collect();
+        count_runnable(&statuses)
     }
 
     /// Get the current 'Running' task's token.
+    /// 没有正在运行的任务时返回内核地址空间的token
     fn get_current_token(&self) -> usize {
         let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_user_token()
+        match inner.running_task() {
+            Some(current) => inner.tasks[current].get_user_token(),
+            None => KERNEL_SPACE.lock().token(),
+        }
     }
 
     #[allow(clippy::mut_from_ref)]
@@ -127,10 +280,12 @@ impl TaskManager {
 
     /// Switch current `Running` task to the task we have found,
     /// or there is no `Ready` task and we can exit with all applications completed
-    fn run_next_task(&self) {
+    /// 切换到下一个任务；当前任务被重新调度回来后返回，返回值表示期间是否运行了别的任务
+    fn run_next_task(&self) -> bool {
         if let Some(next) = self.find_next_task() {
             let mut inner = self.inner.exclusive_access();
             let current = inner.current_task;
+            let switched = next != current;
             inner.tasks[next].task_status = TaskStatus::Running;
             inner.current_task = next;
             let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
@@ -140,6 +295,9 @@ impl TaskManager {
             if  inner.tasks[next].start_time == 0{
                 inner.tasks[next].start_time = get_time_us();
             } else {}
+            let now = get_time_us();
+            inner.tasks[current].sched_stats.descheduled(now);
+            inner.tasks[next].sched_stats.scheduled(now);
             
             drop(inner);
             // before this, we should drop local variables that must be dropped manually
@@ -147,20 +305,72 @@ impl TaskManager {
                 __switch(current_task_cx_ptr, next_task_cx_ptr);
             }
             // go back to user mode
+            switched
         } else {
-            panic!("All applications completed!");
+            // 没有可运行的任务了，回到run_first_task中的idle控制流
+            let mut inner = self.inner.exclusive_access();
+            let current = inner.current_task;
+            inner.tasks[current].sched_stats.descheduled(get_time_us());
+            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
+            let idle_task_cx_ptr = &inner.idle_task_cx as *const TaskContext;
+            drop(inner);
+            unsafe {
+                __switch(current_task_cx_ptr, idle_task_cx_ptr);
+            }
+            // 阻塞的任务在idle控制流中被唤醒后重新调度到这里
+            true
         }
     }
 
+    /// fork当前任务，子任务的编号为任务列表的长度，返回子任务的编号
+    fn fork_current(&self) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        // 已退出的任务不会再运行，先回收它们的内核栈以便子任务复用
+        for task in inner.tasks.iter_mut() {
+            if task.task_status == TaskStatus::Exited {
+                task.kernel_stack = None;
+            }
+        }
+        let current = inner.current_task;
+        let child_id = inner.tasks.len();
+        let mut child = inner.tasks[current].fork()?;
+        child.parent = Some(current);
+        inner.tasks.push(child);
+        Some(child_id)
+    }
+
     /// translate the virture address to physical address
-    fn get_get_phyaddress_from_current_task(&self, v: usize) -> usize{
+    fn get_get_phyaddress_from_current_task(&self, v: usize) -> Result<usize, VmError> {
         let inner = self.inner.exclusive_access();
+        let current = inner.running_task().ok_or(VmError::NotMapped)?;
+        let ppn = match inner.tasks[current].memory_set.translate(VirtAddr(v).floor()) {
+            Some(pte) if pte.is_valid() && pte.user_accessible() => pte.ppn(),
+            Some(pte) if pte.is_valid() => return Err(VmError::Fault),
+            _ => return Err(VmError::NotMapped),
+        };
+        Ok(PhysAddr::from(ppn).0 | (v & ( (1 << PAGE_SIZE_BITS) - 1 )))
+    }
+
+    /// 查看当前任务的子任务pid（pid为-1时表示任意一个子任务）是否已退出；
+    /// 已退出的子任务被回收（不再是当前任务的子任务），返回它的编号和退出码
+    fn reap_child(&self, pid: isize) -> WaitStatus {
+        let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        let ppn = inner.tasks[current].memory_set
-            .translate(VirtAddr(v).floor())
-            .unwrap()
-            .ppn();
-        PhysAddr::from(ppn).0 | (v & ( (1 << PAGE_SIZE_BITS) - 1 ))
+        let parents: Vec<Option<usize>> = inner.tasks.iter().map(|task| task.parent).collect();
+        let statuses: Vec<TaskStatus> = inner.tasks.iter().map(|task| task.task_status).collect();
+        let status = wait_status(&parents, &statuses, current, pid);
+        if let WaitStatus::Exited(child, _) = status {
+            inner.tasks[child].parent = None;
+            return WaitStatus::Exited(child, inner.tasks[child].exit_code);
+        }
+        status
+    }
+
+    /// 当前任务的父任务编号
+    fn get_parent_pid_of_current_task(&self) -> Option<usize> {
+        let inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].parent
     }
 
     /// Get the status of current task
@@ -184,6 +394,13 @@ impl TaskManager {
         inner.tasks[current].start_time
     }
 
+    /// 当前任务的调度统计
+    fn get_sched_stats_of_current_task(&self) -> SchedStats {
+        let inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].sched_stats
+    }
+
     /// 当一个系统调用被调用时，给它的调用次数加一
     fn plus_one_to_syscall_used(&self, syscall_id: usize) {
         let mut inner = self.inner.exclusive_access();
@@ -191,6 +408,197 @@ impl TaskManager {
         inner.tasks[current].syscall_times[syscall_id] += 1;
     }
 
+    /// 将ns纳秒累加到当前任务的系统调用syscall_id上
+    #[cfg(feature = "syscall-latency")]
+    fn add_syscall_ns(&self, syscall_id: usize, ns: u64) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].syscall_ns[syscall_id] += ns;
+    }
+
+    /// 当前任务在系统调用syscall_id上累计花费的时间（纳秒）
+    #[cfg(feature = "syscall-latency")]
+    fn get_syscall_ns(&self, syscall_id: usize) -> u64 {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.current_task].syscall_ns[syscall_id]
+    }
+
+}
+
+/// parents[i]为任务i的父任务，返回以root为根的任务子树（含root），按广度优先顺序
+fn task_subtree(parents: &[Option<usize>], root: usize) -> Vec<usize> {
+    let mut subtree = vec![root];
+    let mut i = 0;
+    while i < subtree.len() {
+        let parent = subtree[i];
+        subtree.extend((0..parents.len()).filter(|&id| parents[id] == Some(parent)));
+        i += 1;
+    }
+    subtree
+}
+
+/// 任务id退出时，唤醒阻塞等待子任务退出的父任务
+fn notify_parent(tasks: &mut [TaskControlBlock], id: usize) {
+    if let Some(parent) = tasks[id].parent {
+        let parent = &mut tasks[parent];
+        if parent.task_status == TaskStatus::Blocked && parent.wait.child_exit {
+            parent.task_status = TaskStatus::Ready;
+            parent.wait = WaitEvents::default();
+        }
+    }
+}
+
+/// 把tasks中的任务id标记为Exited并回收它的用户地址空间，它的子任务成为孤儿。
+/// TASK_MANAGER不会销毁任务，因此页表、Trap上下文等剩余的页帧一直保留，用户页面必须在退出时回收
+fn exit_task(tasks: &mut [TaskControlBlock], id: usize, exit_code: i32) {
+    tasks[id].task_status = TaskStatus::Exited;
+    tasks[id].exit_code = exit_code;
+    tasks[id].memory_set.clear_user_areas();
+    tasks[id].sync_allocated_frames();
+    notify_parent(tasks, id);
+    for task in tasks.iter_mut() {
+        if task.parent == Some(id) {
+            task.parent = None;
+        }
+    }
+}
+
+/// 仅供测试：在tasks上按pick_next_task的轮转顺序调度，直到所有任务都退出并被回收，然后销毁所有任务。
+/// 被调度的任务先像waitpid(-1)一样回收已退出的子任务，再交给run执行一步：run可以fork出子任务
+/// （加到tasks末尾），返回Some(退出码)时任务退出。父任务已退出的任务视为由0号任务回收。
+/// 返回按回收顺序排列的(任务编号, 退出码)
+fn drain_all_tasks(
+    mut tasks: Vec<TaskControlBlock>,
+    mut run: impl FnMut(&mut Vec<TaskControlBlock>, usize) -> Option<i32>,
+) -> Vec<(usize, i32)> {
+    let mut reaped = Vec::new();
+    let mut current = tasks.len().saturating_sub(1);
+    loop {
+        let statuses: Vec<TaskStatus> = tasks.iter().map(|task| task.task_status).collect();
+        let next = match pick_next_task(&statuses, current, None) {
+            Some(next) => next,
+            None => break,
+        };
+        current = next;
+        loop {
+            let parents: Vec<Option<usize>> = tasks.iter().map(|task| task.parent).collect();
+            let statuses: Vec<TaskStatus> = tasks.iter().map(|task| task.task_status).collect();
+            match wait_status(&parents, &statuses, current, -1) {
+                WaitStatus::Exited(child, _) => {
+                    tasks[child].parent = None;
+                    reaped.push((child, tasks[child].exit_code));
+                }
+                _ => break,
+            }
+        }
+        tasks[current].task_status = TaskStatus::Running;
+        match run(&mut tasks, current) {
+            Some(exit_code) => exit_task(&mut tasks, current, exit_code),
+            None => tasks[current].task_status = TaskStatus::Ready,
+        }
+        // 没有父任务的已退出任务（包括父任务先退出的）由0号任务回收
+        for id in 0..tasks.len() {
+            let orphan = tasks[id].task_status == TaskStatus::Exited
+                && tasks[id].parent.is_none()
+                && !reaped.iter().any(|&(pid, _)| pid == id);
+            if orphan {
+                reaped.push((id, tasks[id].exit_code));
+            }
+        }
+    }
+    assert!(tasks.iter().all(|task| task.task_status == TaskStatus::Exited));
+    // 用户页面在退出时就已回收，不依赖之后销毁任务：只剩下Trap上下文
+    assert!(tasks.iter().all(|task| task.memory_set.framed_pages() == 1));
+    assert_eq!(reaped.len(), tasks.len());
+    reaped
+}
+
+/// 结束tasks中以root为根的整棵任务树：全部标记为Exited并回收用户地址空间；
+/// root可能正运行在自己的内核栈上，它的内核栈留给fork_current回收，其余任务的立即回收。
+/// 返回被结束的任务
+fn exit_task_tree(tasks: &mut [TaskControlBlock], root: usize) -> Vec<usize> {
+    let parents: Vec<Option<usize>> = tasks.iter().map(|task| task.parent).collect();
+    let subtree = task_subtree(&parents, root);
+    // root不再是父任务的子任务，等待它的父任务醒来后会发现没有这个子任务
+    notify_parent(tasks, root);
+    for &id in subtree.iter() {
+        let task = &mut tasks[id];
+        task.task_status = TaskStatus::Exited;
+        task.parent = None;
+        task.memory_set.clear_user_areas();
+        task.sync_allocated_frames();
+        if id != root {
+            task.kernel_stack = None;
+        }
+    }
+    subtree
+}
+
+/// waitpid查看子任务的结果
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WaitStatus {
+    /// 没有符合条件的子任务
+    NoChild,
+    /// 符合条件的子任务都还没有退出
+    Running,
+    /// 子任务已退出：(编号, 退出码)
+    Exited(usize, i32),
+}
+
+/// parents[i]和statuses[i]为任务i的父任务和状态，查看parent的子任务pid（-1表示任意一个）是否已退出；
+/// 返回的Exited中退出码总是0，由调用者从任务控制块中读取
+fn wait_status(
+    parents: &[Option<usize>],
+    statuses: &[TaskStatus],
+    parent: usize,
+    pid: isize,
+) -> WaitStatus {
+    let mut children = (0..parents.len())
+        .filter(|&id| parents[id] == Some(parent) && (pid == -1 || pid as usize == id))
+        .peekable();
+    if children.peek().is_none() {
+        return WaitStatus::NoChild;
+    }
+    match children.find(|&id| statuses[id] == TaskStatus::Exited) {
+        Some(id) => WaitStatus::Exited(id, 0),
+        None => WaitStatus::Running,
+    }
+}
+
+/// 处于Ready或Running状态的任务数，UnInit和Exited的任务不计在内
+fn count_runnable(statuses: &[TaskStatus]) -> usize {
+    statuses
+        .iter()
+        .filter(|status| matches!(status, TaskStatus::Ready | TaskStatus::Running))
+        .count()
+}
+
+/// 唤醒状态为status的任务后的新状态，以及是否要记下一次待处理的唤醒：
+/// Blocked的任务变为Ready；Ready或Running的任务状态不变，但下一次pause不会阻塞；
+/// 尚未初始化或已退出的任务无法唤醒，返回None
+fn wake_transition(status: TaskStatus) -> Option<(TaskStatus, bool)> {
+    match status {
+        TaskStatus::Blocked => Some((TaskStatus::Ready, false)),
+        TaskStatus::Ready | TaskStatus::Running => Some((status, true)),
+        TaskStatus::UnInit | TaskStatus::Exited => None,
+    }
+}
+
+/// 在current之后按轮转顺序找到下一个Ready的任务；若target仍是Ready，则优先选择它
+fn pick_next_task(
+    statuses: &[TaskStatus],
+    current: usize,
+    target: Option<usize>,
+) -> Option<usize> {
+    if let Some(id) = target {
+        if statuses.get(id) == Some(&TaskStatus::Ready) {
+            return Some(id);
+        }
+    }
+    let num_app = statuses.len();
+    (current + 1..current + num_app + 1)
+        .map(|id| id % num_app)
+        .find(|id| statuses[*id] == TaskStatus::Ready)
 }
 
 /// Run the first task in task list.
@@ -210,8 +618,8 @@ fn mark_current_suspended() {
 }
 
 /// Change the status of current `Running` task into `Exited`.
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+fn mark_current_exited(exit_code: i32) {
+    TASK_MANAGER.mark_current_exited(exit_code);
 }
 
 /// Suspend the current 'Running' task and run the next task in task list.
@@ -220,27 +628,206 @@ pub fn suspend_current_and_run_next() {
     run_next_task();
 }
 
+/// 让出CPU，返回是否有别的任务被调度运行；只有当前任务可运行时立即返回false
+pub fn sched_yield() -> bool {
+    mark_current_suspended();
+    TASK_MANAGER.run_next_task()
+}
+
+/// 让出CPU并直接切换到任务id；任务id不可运行时不切换并返回false
+pub fn yield_to(id: usize) -> bool {
+    if !TASK_MANAGER.set_yield_target(id) {
+        return false;
+    }
+    suspend_current_and_run_next();
+    true
+}
+
+/// 阻塞当前任务直到被wake唤醒；之前收到过唤醒时立即返回。
+/// 阻塞的任务不会被调度，所有任务都阻塞时调度器回到idle控制流
+pub fn pause_current_and_run_next() {
+    if TASK_MANAGER.mark_current_blocked() {
+        run_next_task();
+    }
+}
+
+/// 唤醒任务id，任务不存在或已退出时返回false
+pub fn wake(id: usize) -> bool {
+    TASK_MANAGER.wake(id)
+}
+
+/// 阻塞当前任务，直到到达deadline（微秒）或被wake唤醒；
+/// 返回后调用者需要重新检查是否已到达deadline
+pub fn sleep_current_until(deadline: usize) {
+    TASK_MANAGER.block_current(WaitEvents {
+        deadline: Some(deadline),
+        child_exit: false,
+    });
+    run_next_task();
+}
+
+/// 阻塞当前任务，直到有子任务退出、到达deadline（微秒）或被wake唤醒；
+/// 返回后调用者需要重新检查自己等待的条件
+pub fn wait_child_until(deadline: usize) {
+    TASK_MANAGER.block_current(WaitEvents {
+        deadline: Some(deadline),
+        child_exit: true,
+    });
+    run_next_task();
+}
+
+/// 唤醒截止时间已到的阻塞任务，由时钟中断调用
+pub fn wake_expired_tasks() {
+    TASK_MANAGER.wake_expired();
+}
+
+/// idle控制流中没有可运行的任务：等待时钟中断，直到到达deadline（微秒）。
+/// 内核态不处理中断，wfi只是让出处理器，每次醒来后重新设置下一次时钟中断以清除挂起位
+fn idle_until(deadline: usize) {
+    while get_time_us() < deadline {
+        unsafe { riscv::asm::wfi() };
+        set_next_trigger();
+    }
+}
+
 /// Exit the current 'Running' task and run the next task in task list.
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
+pub fn exit_current_and_run_next(exit_code: i32) {
+    FUTEX_TABLE.exclusive_access().forget(get_pid_of_current_task());
+    mark_current_exited(exit_code);
+    run_next_task();
+}
+
+/// 结束当前任务及其所有后代，回收它们的地址空间，然后运行下一个任务
+pub fn exit_group_and_run_next() {
+    for id in TASK_MANAGER.mark_current_group_exited() {
+        FUTEX_TABLE.exclusive_access().forget(id);
+    }
     run_next_task();
 }
 
+/// futex的键：当前任务中va处的u32所在的物理地址。va必须按4字节对齐且可读；
+/// 映射到共享零页的页面先复制出私有页帧，以免不相关的futex共用同一个键
+pub fn futex_key(va: usize) -> Result<PhysAddr, VmError> {
+    if va % core::mem::size_of::<u32>() != 0 {
+        return Err(VmError::Unaligned);
+    }
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let vpn = VirtAddr::from(va).floor();
+    task.memory_set.prepare_user_read(va, core::mem::size_of::<u32>())?;
+    if task.memory_set.mapping_state(vpn) == MappingState::Reserved {
+        task.memory_set.prepare_user_write(va, core::mem::size_of::<u32>())?;
+    }
+    task.sync_allocated_frames();
+    let ppn = task.memory_set.translate(vpn).ok_or(VmError::NotMapped)?.ppn();
+    Ok(PhysAddr(PhysAddr::from(ppn).0 | (va & ((1 << PAGE_SIZE_BITS) - 1))))
+}
+
+/// 当前任务在futex pa上等待：*pa仍等于expected时阻塞，直到被futex_wake（或wake）唤醒；
+/// 返回是否进入了等待
+pub fn futex_wait(pa: PhysAddr, expected: u32) -> bool {
+    // 单核且内核不可抢占，读取与入队之间不会有别的任务修改futex字
+    let value = unsafe { (pa.0 as *const u32).read_volatile() };
+    if value != expected {
+        return false;
+    }
+    let current = get_pid_of_current_task();
+    FUTEX_TABLE.exclusive_access().enqueue(pa, current);
+    pause_current_and_run_next();
+    // 之前留下的唤醒使pause立即返回，或被wake而不是futex_wake唤醒时，仍在队列中
+    FUTEX_TABLE.exclusive_access().forget(current);
+    true
+}
+
+/// 唤醒在futex pa上等待的至多n个任务，返回唤醒的任务数
+pub fn futex_wake(pa: PhysAddr, n: usize) -> usize {
+    let waiters = FUTEX_TABLE.exclusive_access().dequeue(pa, n);
+    waiters.into_iter().filter(|&id| wake(id)).count()
+}
+
+/// fork当前任务，返回子任务的编号；内核栈槽位用完时返回None
+pub fn fork_current() -> Option<usize> {
+    TASK_MANAGER.fork_current()
+}
+
 /// Get the current 'Running' task's token.
 pub fn current_user_token() -> usize {
     TASK_MANAGER.get_current_token()
 }
 
+/// 任务current能否查看task的地址空间：task必须已初始化且尚未退出，
+/// 且current是监督任务或task的父任务
+fn may_inspect(current: usize, task: &TaskControlBlock) -> bool {
+    !matches!(task.task_status, TaskStatus::UnInit | TaskStatus::Exited)
+        && (current == SUPERVISOR_PID || task.parent == Some(current))
+}
+
+/// 当前任务有权查看时，返回任务pid的地址空间的token（权限同task_dump_maps）；
+/// 无权查看、任务不存在、尚未初始化或已退出时返回None
+pub fn task_user_token(pid: usize) -> Option<usize> {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    match inner.tasks.get(pid) {
+        Some(task) if may_inspect(current, task) => Some(task.get_user_token()),
+        _ => None,
+    }
+}
+
+/// 当前任务有权查看时，返回任务pid的逻辑段与页表叶子（见MemorySet::dump_maps）；
+/// 只有监督任务和pid的父任务有权查看，任务不存在或已退出时返回None
+pub fn task_dump_maps(pid: usize) -> Option<String> {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    match inner.tasks.get(pid) {
+        Some(task) if may_inspect(current, task) => Some(task.memory_set.dump_maps()),
+        _ => None,
+    }
+}
+
 /// Get the current 'Running' task's trap contexts.
 pub fn current_trap_cx() -> &'static mut TrapContext {
     TASK_MANAGER.get_current_trap_cx()
 }
 
 /// translate the virture address to physical address
-pub fn get_phyaddress_from_current_task (va: usize) -> usize {
+pub fn get_phyaddress_from_current_task (va: usize) -> Result<usize, VmError> {
     TASK_MANAGER.get_get_phyaddress_from_current_task(va)
 }
 
+/// 可运行（Ready或Running）的任务数
+pub fn ready_count() -> usize {
+    TASK_MANAGER.ready_count()
+}
+
+/// 回收当前任务已退出的子任务pid（-1表示任意一个子任务），见[`WaitStatus`]
+pub fn reap_child(pid: isize) -> WaitStatus {
+    TASK_MANAGER.reap_child(pid)
+}
+
+/// 设置当前任务的名字，超长部分被截断
+pub fn set_name_of_current_task(name: &[u8]) {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].set_name(name);
+}
+
+/// 当前任务的名字
+pub fn get_name_of_current_task() -> String {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    String::from(inner.tasks[inner.current_task].name())
+}
+
+/// 当前任务的编号，即pid
+pub fn get_pid_of_current_task() -> usize {
+    TASK_MANAGER.inner.exclusive_access().current_task
+}
+
+/// 当前任务的父任务编号，没有父任务时返回None
+pub fn get_parent_pid_of_current_task() -> Option<usize> {
+    TASK_MANAGER.get_parent_pid_of_current_task()
+}
+
 /// Get the status of current task
 pub fn get_status_of_current_task() -> TaskStatus{
     TASK_MANAGER.get_status_of_current_task()
@@ -256,22 +843,840 @@ pub fn get_start_time_of_current_task() -> usize {
     TASK_MANAGER.get_start_time_of_current_task()
 }
 
+/// 当前任务的调度统计
+pub fn get_sched_stats_of_current_task() -> SchedStats {
+    TASK_MANAGER.get_sched_stats_of_current_task()
+}
+
+/// 当前任务是否可以发起编号为syscall_id的系统调用：没有安装过滤器时总是允许，
+/// 否则只允许过滤器位图中对应位为1的系统调用；由syscall在分发之前检查
+pub fn current_syscall_allowed(syscall_id: usize) -> bool {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].syscall_allowed(syscall_id)
+}
+
+/// 为当前任务安装由字节位图bytes描述的系统调用过滤器；已经安装过或位图过长时返回false
+pub fn set_current_syscall_filter(bytes: &[u8]) -> bool {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    if task.syscall_filter.is_some() {
+        return false;
+    }
+    task.syscall_filter = syscall_filter_from_bytes(bytes);
+    task.syscall_filter.is_some()
+}
+
+/// 为当前任务注册定时器信号的处理函数，None表示注销
+pub fn set_current_signal_handler(handler: Option<usize>) {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].signal.set_handler(handler);
+}
+
+/// 设置当前任务的间隔定时器，每interval_us微秒触发一次，0表示关闭
+pub fn set_current_itimer(interval_us: usize) {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].signal.set_itimer(interval_us, get_time_us());
+}
+
+/// 当前任务的定时器到期时，让它返回用户态时先进入处理函数；由trap_return调用
+pub fn deliver_signal_current() {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    task.signal.deliver(task.get_trap_cx(), get_time_us());
+}
+
+/// 当前任务从处理函数返回，恢复被打断时的上下文，返回恢复后的a0；不在处理函数中时返回None
+pub fn sigreturn_current() -> Option<usize> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    task.signal.sigreturn(task.get_trap_cx())
+}
+
 /// 当一个系统调用被调用时，给它的调用次数加一
 pub fn plus_one_to_syscall_used(syscall_id: usize) {
     TASK_MANAGER.plus_one_to_syscall_used(syscall_id);
 }
 
-pub fn mmap(start: usize, len: usize, port: usize) -> isize {
+/// 将ns纳秒累加到当前任务的系统调用syscall_id上
+#[cfg(feature = "syscall-latency")]
+pub fn add_syscall_ns(syscall_id: usize, ns: u64) {
+    TASK_MANAGER.add_syscall_ns(syscall_id, ns);
+}
+
+/// 当前任务在系统调用syscall_id上累计花费的时间（纳秒）
+#[cfg(feature = "syscall-latency")]
+pub fn get_syscall_ns(syscall_id: usize) -> u64 {
+    TASK_MANAGER.get_syscall_ns(syscall_id)
+}
+
+pub fn mmap(start: usize, len: usize, port: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.mmap(start, len, port);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 在当前任务的地址空间中由内核选择位置映射len字节，返回其起始地址
+pub fn mmap_anywhere(len: usize, port: usize) -> Result<usize, VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.mmap_anywhere(len, port);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 把当前任务的[va, va + len)直接映射到从pa开始的物理页帧
+pub fn map_phys(va: usize, pa: usize, len: usize, port: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].memory_set.map_phys(va, pa, len, port)
+}
+
+/// 在当前任务的地址空间高端自动放置一段向下增长的栈映射，返回其起始地址
+pub fn mmap_stack(len: usize, port: usize) -> Result<usize, VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.mmap_stack(len, port);
+    task.sync_allocated_frames();
+    result
+}
+
+pub fn munmap(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.munmap(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+pub fn mprotect(start: usize, len: usize, port: usize) -> Result<(), VmError> {
     let mut inner = TASK_MANAGER.inner.exclusive_access();
     let current = inner.current_task;
     let ms = &mut inner.tasks[current].memory_set;
-    ms.mmap(start, len, port)
+    ms.mprotect(start, len, port)
+}
+pub fn madvise_dontneed(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.madvise_dontneed(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 对当前任务的[start, start + len)执行MADV_WILLNEED，立即调入其中尚未驻留的页面
+pub fn madvise_willneed(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.madvise_willneed(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 锁定当前任务的[start, start + len)，使其保持驻留
+pub fn mlock(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.mlock(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 在当前任务起始于base的mmap逻辑段内，让target_offset页起的count页与offset页起的count页共享物理页帧
+pub fn alias_pages(base: usize, offset: usize, count: usize, target_offset: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.alias_pages(base, offset, count, target_offset);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 解除当前任务[start, start + len)的一层锁定
+pub fn munlock(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].memory_set.munlock(start, len)
+}
+
+/// 当前任务中va所在页面的页表项标志位，没有页表项时为空
+pub fn current_pte_flags(va: usize) -> PTEFlags {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task]
+        .memory_set
+        .translate(VirtAddr(va).floor())
+        .map_or(PTEFlags::empty(), |pte| pte.flags())
+}
+
+/// 处理当前任务在地址va上的缺页异常，write表示是否由写操作触发
+pub fn handle_page_fault(va: usize, kind: FaultKind) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    task.page_faults += 1;
+    let result = task.memory_set.handle_page_fault(VirtAddr(va), kind);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 当前任务因trap进入内核，开始计入内核态时间
+pub fn enter_kernel_current() {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].sched_stats.enter_kernel(get_time_us());
 }
 
+/// 当前任务即将返回用户态，结束本段内核态时间
+pub fn leave_kernel_current() {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].sched_stats.leave_kernel(get_time_us());
+}
 
-pub fn munmap(start: usize, len: usize) -> isize {
+/// 当前任务持有物理页帧数的最大值与用户态缺页异常的次数
+pub fn current_fault_stats() -> (usize, usize) {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    let task = &inner.tasks[inner.current_task];
+    (task.max_allocated_frames, task.page_faults)
+}
+
+/// 在内核写入当前任务的用户区间[start, start + len)之前，确保这些页面是私有且可写的
+pub fn prepare_user_write(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.prepare_user_write(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 在内核读取当前任务的用户区间[start, start + len)之前，确保这些页面已经映射
+pub fn prepare_user_read(start: usize, len: usize) -> Result<(), VmError> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    let task = &mut inner.tasks[current];
+    let result = task.memory_set.prepare_user_read(start, len);
+    task.sync_allocated_frames();
+    result
+}
+
+/// 将当前任务的program break移动size字节，成功时返回原来的program break
+pub fn change_program_brk(size: isize) -> Option<usize> {
+    let mut inner = TASK_MANAGER.inner.exclusive_access();
+    let current = inner.current_task;
+    inner.tasks[current].change_program_brk(size)
+}
+
+/// 当前任务的program break
+pub fn current_program_brk() -> usize {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].program_brk
+}
+
+/// 当前任务持有的物理页帧数
+pub fn current_allocated_frames() -> usize {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].allocated_frames
+}
+
+/// 设置当前任务的物理页帧配额（单位为页），不能低于当前已持有的页帧数
+pub fn set_mem_quota(pages: usize) -> Result<(), VmError> {
     let mut inner = TASK_MANAGER.inner.exclusive_access();
     let current = inner.current_task;
     let ms = &mut inner.tasks[current].memory_set;
-    ms.munmap(start, len)
-}
\ No newline at end of file
+    ms.set_frame_quota(pages)
+}
+
+/// 当前任务从start_vpn开始的pages个页面的映射状态
+pub fn current_mapping_states(start_vpn: usize, pages: usize) -> Vec<MappingState> {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    let memory_set = &inner.tasks[inner.current_task].memory_set;
+    (start_vpn..start_vpn + pages)
+        .map(|vpn| memory_set.mapping_state(VirtPageNum(vpn)))
+        .collect()
+}
+
+/// 当前任务地址空间中已驻留和仅保留的页面数，见MemorySet::page_counts
+pub fn current_page_counts() -> (usize, usize) {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].memory_set.page_counts()
+}
+
+/// 以/proc/self/maps的格式列出当前任务的所有逻辑段
+pub fn current_maps() -> String {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].memory_set.maps()
+}
+
+/// 清空当前任务地址空间的TLB缓存，只作用于调用者自己的地址空间
+pub fn flush_current_tlb() {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].memory_set.flush_tlb();
+}
+
+/// 对当前任务的地址空间执行sfence.vma与fence.i，让其写入的指令对之后的取指可见
+pub fn membarrier_current() {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    inner.tasks[inner.current_task].memory_set.membarrier();
+}
+
+#[allow(unused)]
+/// 检查sys_yield_to指定的任务会被优先调度，而目标不可运行时退回轮转顺序
+pub fn yield_to_test() {
+    use TaskStatus::*;
+    let statuses = [Running, Ready, Ready, Exited];
+    assert_eq!(pick_next_task(&statuses, 0, None), Some(1));
+    assert_eq!(pick_next_task(&statuses, 0, Some(2)), Some(2));
+    assert_eq!(pick_next_task(&statuses, 0, Some(3)), Some(1));
+    assert_eq!(pick_next_task(&statuses, 0, Some(7)), Some(1));
+    assert_eq!(pick_next_task(&[Running, Exited], 0, None), None);
+    info!("yield_to_test passed!");
+}
+
+#[allow(unused)]
+/// 检查让出CPU时只有调用者可运行则重新选中它自己，否则选中另一个任务
+pub fn sched_yield_test() {
+    use TaskStatus::*;
+    // 让出CPU的任务已被标记为Ready
+    assert_eq!(pick_next_task(&[Ready], 0, None), Some(0));
+    assert_eq!(pick_next_task(&[Exited, Ready, Exited], 1, None), Some(1));
+    assert_eq!(pick_next_task(&[Ready, Ready], 0, None), Some(1));
+    assert_eq!(pick_next_task(&[Ready, Exited, Ready], 2, None), Some(0));
+    info!("sched_yield_test passed!");
+}
+
+#[allow(unused)]
+/// 两个任务轮流让出CPU时，轮转调度让它们被选中的次数和占用的CPU时间基本相同
+pub fn sched_stats_test() {
+    use TaskStatus::*;
+    let mut statuses = [Running, Ready, Exited];
+    let mut stats = [SchedStats::default(); 3];
+    let mut current = 0;
+    let mut now = 0;
+    stats[current].scheduled(now);
+    for _ in 0..101 {
+        now += 10;
+        statuses[current] = Ready;
+        let next = pick_next_task(&statuses, current, None).unwrap();
+        stats[current].descheduled(now);
+        stats[next].scheduled(now);
+        statuses[next] = Running;
+        current = next;
+    }
+    assert_eq!(stats[0].run_count, 51);
+    assert_eq!(stats[1].run_count, 51);
+    assert_eq!(stats[2].run_count, 0);
+    assert_eq!(stats[0].cpu_time_us(now), 510);
+    // 正在运行的任务包含本次运行至今的时间
+    assert_eq!(stats[1].cpu_time_us(now + 5), 505);
+    info!("sched_stats_test passed!");
+}
+
+#[allow(unused)]
+/// 检查内核态时间只计入任务在CPU上处理trap的部分，被切换出去的时间不算，用户态时间为其余部分
+pub fn kernel_time_test() {
+    let mut stats = SchedStats::default();
+    stats.scheduled(0);
+    // 用户态运行100，处理一次trap用了20
+    stats.enter_kernel(100);
+    stats.leave_kernel(120);
+    // 在系统调用中让出CPU：切换出去之前在内核中用了10，回来之后又用了5
+    stats.enter_kernel(200);
+    stats.descheduled(210);
+    assert_eq!(stats.kernel_time_us(500), 30);
+    stats.scheduled(1000);
+    assert_eq!(stats.kernel_time_us(1003), 33);
+    stats.leave_kernel(1005);
+    assert_eq!(stats.kernel_time_us(1100), 35);
+    assert_eq!(stats.cpu_time_us(1100), 210 + 100);
+    assert_eq!(stats.user_time_us(1100), 310 - 35);
+    // 刚创建的任务直接从trap_return进入用户态
+    let mut fresh = SchedStats::default();
+    fresh.scheduled(0);
+    fresh.leave_kernel(5);
+    assert_eq!(fresh.kernel_time_us(50), 0);
+    assert_eq!(fresh.user_time_us(50), 50);
+    info!("kernel_time_test passed!");
+}
+
+#[allow(unused)]
+/// 阻塞的任务不会被调度也不算可运行；唤醒使它重新可运行，唤醒未阻塞的任务会留下一次待处理的唤醒
+pub fn pause_test() {
+    use TaskStatus::*;
+    assert_eq!(pick_next_task(&[Blocked, Blocked], 0, None), None);
+    assert_eq!(pick_next_task(&[Blocked, Ready, Blocked], 1, Some(2)), Some(1));
+    assert_eq!(count_runnable(&[Running, Blocked, Ready]), 2);
+    assert_eq!(wake_transition(Blocked), Some((Ready, false)));
+    assert_eq!(wake_transition(Ready), Some((Ready, true)));
+    assert_eq!(wake_transition(Running), Some((Running, true)));
+    assert_eq!(wake_transition(Exited), None);
+    assert_eq!(wake_transition(UnInit), None);
+    assert!(!wake(usize::MAX));
+    info!("pause_test passed!");
+}
+
+#[allow(unused)]
+/// 检查没有任务或所有任务都已结束时调度器选不出任务（交给idle控制流），且不存在正在运行的任务
+pub fn idle_test() {
+    use TaskStatus::*;
+    let inner = TaskManagerInner {
+        tasks: Vec::new(),
+        current_task: 0,
+        yield_target: None,
+        idle_task_cx: TaskContext::zero_init(),
+    };
+    assert_eq!(inner.running_task(), None);
+    assert_eq!(pick_next_task(&[], 0, None), None);
+    assert_eq!(pick_next_task(&[Exited, Exited, Exited], 1, Some(2)), None);
+    // 启动阶段还没有任务在运行
+    assert_eq!(TASK_MANAGER.inner.exclusive_access().running_task(), None);
+    assert_eq!(current_user_token(), KERNEL_SPACE.lock().token());
+    assert_eq!(get_phyaddress_from_current_task(0x10000), Err(VmError::NotMapped));
+    info!("idle_test passed!");
+}
+
+#[allow(unused)]
+/// 检查waitpid只能等待自己的子任务，且只有已退出的子任务才会被回收
+pub fn wait_status_test() {
+    use TaskStatus::*;
+    let parents = [None, Some(0), Some(0), Some(1), None];
+    let statuses = [Running, Ready, Exited, Exited, Exited];
+    assert_eq!(wait_status(&parents, &statuses, 0, 1), WaitStatus::Running);
+    assert_eq!(wait_status(&parents, &statuses, 0, 2), WaitStatus::Exited(2, 0));
+    assert_eq!(wait_status(&parents, &statuses, 0, -1), WaitStatus::Exited(2, 0));
+    // 孙任务和没有父任务的任务不能被等待
+    assert_eq!(wait_status(&parents, &statuses, 0, 3), WaitStatus::NoChild);
+    assert_eq!(wait_status(&parents, &statuses, 0, 4), WaitStatus::NoChild);
+    assert_eq!(wait_status(&parents, &statuses, 0, 9), WaitStatus::NoChild);
+    assert_eq!(wait_status(&parents, &statuses, 1, -1), WaitStatus::Exited(3, 0));
+    assert_eq!(wait_status(&parents, &statuses, 4, -1), WaitStatus::NoChild);
+    info!("wait_status_test passed!");
+}
+
+#[allow(unused)]
+/// 只统计Ready和Running的任务；启动时所有应用都是Ready，还没有任务在运行
+pub fn ready_count_test() {
+    use TaskStatus::*;
+    assert_eq!(count_runnable(&[Running, Ready, Exited, UnInit, Ready]), 3);
+    assert_eq!(count_runnable(&[Exited, Exited]), 0);
+    assert_eq!(ready_count(), get_num_app());
+    info!("ready_count_test passed!");
+}
+
+#[allow(unused)]
+/// 检查任务名默认为应用名，超长的名字被截断，fork出的子任务继承名字
+pub fn task_name_test() {
+    use crate::config::TASK_NAME_LEN;
+    for (id, task) in TASK_MANAGER.inner.exclusive_access().tasks.iter().enumerate() {
+        assert_eq!(task.name(), get_app_name(id));
+    }
+    let mut task = TaskControlBlock::new(get_app_data(0), 0);
+    task.set_name(b"worker");
+    assert_eq!(task.name(), "worker");
+    task.set_name(b"a-very-long-task-name");
+    assert_eq!(task.name(), &"a-very-long-task-name"[..TASK_NAME_LEN]);
+    let child = task.fork().unwrap();
+    assert_eq!(child.name(), task.name());
+    task.set_name(&[0xff, 0xfe]);
+    assert_eq!(task.name(), "?");
+    info!("task_name_test passed!");
+}
+
+#[allow(unused)]
+/// 结束一棵任务树：树中的任务都被标记为Exited，用户页帧和除根以外的内核栈都被回收，
+/// 树外的任务不受影响
+pub fn exit_group_test() {
+    use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE};
+    use crate::mm::get_num_empty_frame;
+    assert_eq!(
+        task_subtree(&[None, None, Some(1), Some(2), Some(1), Some(3)], 1),
+        vec![1, 2, 4, 3, 5]
+    );
+    assert_eq!(task_subtree(&[None, Some(0)], 1), vec![1]);
+    let mut tasks = vec![
+        TaskControlBlock::new(get_app_data(0), 0),
+        TaskControlBlock::new(get_app_data(0), 1),
+    ];
+    // 1 -> 2 -> 3，1 -> 4
+    for parent in [1, 2, 1] {
+        let mut child = tasks[parent].fork().unwrap();
+        child.parent = Some(parent);
+        tasks.push(child);
+    }
+    let empty_frames = get_num_empty_frame();
+    let mut exited = exit_task_tree(&mut tasks, 1);
+    exited.sort_unstable();
+    assert_eq!(exited, vec![1, 2, 3, 4]);
+    assert!(tasks[0].task_status == TaskStatus::Ready && tasks[0].kernel_stack.is_some());
+    assert!(tasks[1].kernel_stack.is_some());
+    for task in tasks[1..].iter() {
+        assert_eq!(task.task_status, TaskStatus::Exited);
+        // 只剩下Trap上下文
+        assert_eq!(task.memory_set.framed_pages(), 1);
+    }
+    assert!(tasks[2..].iter().all(|task| task.kernel_stack.is_none()));
+    // 至少回收了三个后代的内核栈，另外还有用户页帧和页表节点
+    assert!(get_num_empty_frame() > empty_frames + 3 * KERNEL_STACK_SIZE / PAGE_SIZE);
+    info!("exit_group_test passed!");
+}
+
+#[allow(unused)]
+/// 用drain_all_tasks运行一组会fork、退出的任务，检查回收顺序确定，任务退出时已交还用户页面，
+/// 且任务全部销毁后空闲页帧数回到运行任务之前的基线
+pub fn drain_tasks_test() {
+    use crate::mm::get_num_empty_frame;
+    fn fork(tasks: &mut Vec<TaskControlBlock>, parent: usize) {
+        let mut child = tasks[parent].fork().unwrap();
+        child.parent = Some(parent);
+        tasks.push(child);
+    }
+    let scenario = || {
+        let tasks = (0..3).map(|i| TaskControlBlock::new(get_app_data(0), i)).collect();
+        let mut runs: Vec<usize> = Vec::new();
+        drain_all_tasks(tasks, move |tasks, id| {
+            runs.resize(tasks.len(), 0);
+            runs[id] += 1;
+            match (id, runs[id]) {
+                (0, 1) => {
+                    fork(tasks, 0);
+                    fork(tasks, 0);
+                    None
+                }
+                (0, _) => Some(10),
+                // 1号任务的子任务成为孤儿
+                (1, _) => {
+                    fork(tasks, 1);
+                    Some(11)
+                }
+                (2, 1) => None,
+                (2, _) => Some(12),
+                _ => Some(20 + id as i32),
+            }
+        })
+    };
+    // 以运行任何任务之前为基线；新的内核栈槽位可能让内核页表新建节点，这些节点不会被回收，单独计入
+    let empty_frames = get_num_empty_frame();
+    let kernel_nodes = KERNEL_SPACE.lock().page_table_frames();
+    let first = scenario();
+    for _ in 0..3 {
+        assert_eq!(scenario(), first);
+        let new_kernel_nodes = KERNEL_SPACE.lock().page_table_frames() - kernel_nodes;
+        assert_eq!(get_num_empty_frame() + new_kernel_nodes, empty_frames);
+    }
+    assert_eq!(first, vec![(1, 11), (5, 25), (3, 23), (4, 24), (0, 10), (2, 12)]);
+    info!("drain_tasks_test passed!");
+}
+
+#[allow(unused)]
+/// 检查字节位图到过滤器的转换、位图之外的编号被禁止、过长的位图被拒绝，以及fork时继承过滤器
+pub fn syscall_filter_test() {
+    use crate::config::MAX_SYSCALL_NUM;
+    let mut bitmap = [0xffu8; 16];
+    // 禁止124（sys_yield）
+    bitmap[124 / 8] &= !(1 << (124 % 8));
+    let filter = syscall_filter_from_bytes(&bitmap).unwrap();
+    let mut task = TaskControlBlock::new(get_app_data(0), 0);
+    assert!(task.syscall_allowed(124) && task.syscall_allowed(MAX_SYSCALL_NUM + 1));
+    task.syscall_filter = Some(filter);
+    assert!(task.syscall_allowed(0) && task.syscall_allowed(93) && task.syscall_allowed(127));
+    assert!(!task.syscall_allowed(124));
+    assert!(!task.syscall_allowed(128) && !task.syscall_allowed(MAX_SYSCALL_NUM + 1));
+    let child = task.fork().unwrap();
+    assert!(child.syscall_filter == task.syscall_filter);
+    assert!(syscall_filter_from_bytes(&[0xff; (MAX_SYSCALL_NUM + 7) / 8]).is_some());
+    assert!(syscall_filter_from_bytes(&[0xff; (MAX_SYSCALL_NUM + 7) / 8 + 1]).is_none());
+    info!("syscall_filter_test passed!");
+}
+
+#[allow(unused)]
+/// 检查定时器信号：到期时保存上下文并跳转到处理函数，处理函数中不重复投递，
+/// sigreturn恢复被打断的寄存器和pc；fork出的子任务继承处理函数但不继承定时器
+pub fn signal_test() {
+    const HANDLER: usize = 0x1000;
+    let mut task = TaskControlBlock::new(get_app_data(0), 0);
+    let cx = task.get_trap_cx();
+    let entry = cx.sepc;
+    cx.x[10] = 42;
+    task.signal.set_itimer(100, 1000);
+    assert!(!task.signal.deliver(cx, 2000));
+    task.signal.set_handler(Some(HANDLER));
+    assert!(!task.signal.deliver(cx, 1099));
+    // 错过了1100、1200、1300三个周期，只投递一次
+    assert!(task.signal.deliver(cx, 1350));
+    assert_eq!(cx.sepc, HANDLER);
+    cx.x[10] = 7;
+    cx.sepc += 4;
+    assert!(!task.signal.deliver(cx, 1500));
+    let child = task.fork().unwrap();
+    assert_eq!(task.signal.sigreturn(cx), Some(42));
+    assert_eq!((cx.sepc, cx.x[10]), (entry, 42));
+    assert_eq!(task.signal.sigreturn(cx), None);
+    // 下一次到期的时刻为1400，在处理函数中错过的那次在sigreturn之后投递
+    assert!(task.signal.deliver(cx, 1500));
+    assert_eq!(task.signal.sigreturn(cx), Some(42));
+    assert!(!task.signal.deliver(cx, 1599));
+    task.signal.set_itimer(0, 1600);
+    assert!(!task.signal.deliver(cx, 10000));
+    let mut child = child;
+    let child_cx = child.get_trap_cx();
+    assert_eq!(child.signal.sigreturn(child_cx), Some(42));
+    assert!(!child.signal.deliver(child_cx, 10000));
+    child.signal.set_itimer(100, 10000);
+    assert!(child.signal.deliver(child_cx, 10100));
+    assert_eq!(child_cx.sepc, HANDLER);
+    info!("signal_test passed!");
+}
+
+#[allow(unused)]
+/// 让一个任务在自己的内核栈上运行到一半后挂起（栈上有数组和指向它的指针），把它的内核栈搬到另一个槽位，
+/// 再恢复它：指针被修正到新栈上，读回的数组内容不变；正在运行的任务不能搬迁
+pub fn relocate_kernel_stack_test() {
+    use kernel_stack::KernelStack;
+
+    struct Probe {
+        main_cx: TaskContext,
+        task_cx: *mut TaskContext,
+        seen: Option<(usize, usize)>,
+    }
+    lazy_static! {
+        static ref PROBE: UPSafeCell<Probe> = unsafe {
+            UPSafeCell::new(Probe {
+                main_cx: TaskContext::zero_init(),
+                task_cx: core::ptr::null_mut(),
+                seen: None,
+            })
+        };
+    }
+    fn switch_to_main() {
+        let (task_cx, main_cx) = {
+            let probe = PROBE.exclusive_access();
+            (probe.task_cx, &probe.main_cx as *const TaskContext)
+        };
+        unsafe { __switch(task_cx, main_cx) };
+    }
+    fn switch_to_task(task_cx: &TaskContext) {
+        let main_cx = &mut PROBE.exclusive_access().main_cx as *mut TaskContext;
+        unsafe { __switch(main_cx, task_cx) };
+    }
+    extern "C" fn probe() -> ! {
+        let values: [usize; 8] = core::array::from_fn(|i| i * i);
+        let ptr = &values as *const [usize; 8];
+        switch_to_main();
+        let sum = unsafe { ptr.read_volatile() }.iter().sum();
+        PROBE.exclusive_access().seen = Some((ptr as usize, sum));
+        switch_to_main();
+        unreachable!("relocated probe resumed after the test finished");
+    }
+
+    let mut task = TaskControlBlock::new(get_app_data(0), 0);
+    let old_top = task.kernel_stack.as_ref().unwrap().top();
+    task.task_cx = TaskContext::goto_entry(probe as usize, old_top);
+    PROBE.exclusive_access().task_cx = &mut task.task_cx;
+    switch_to_task(&task.task_cx);
+    // 任务挂起在旧栈上；借用一个刚回收的槽位作为新位置
+    let slot = KernelStack::new().unwrap().slot();
+    task.task_status = TaskStatus::Running;
+    assert!(!task.relocate_kernel_stack(slot));
+    task.task_status = TaskStatus::Ready;
+    assert!(task.relocate_kernel_stack(slot));
+    assert!(!task.relocate_kernel_stack(slot));
+    let new_stack = task.kernel_stack.as_ref().unwrap();
+    let (bottom, top) = (new_stack.bottom(), new_stack.top());
+    assert!(top != old_top && bottom <= task.task_cx.sp() && task.task_cx.sp() < top);
+    assert_eq!(task.get_trap_cx().kernel_sp, top);
+    switch_to_task(&task.task_cx);
+    let (ptr, sum) = PROBE.exclusive_access().seen.take().unwrap();
+    assert!(bottom <= ptr && ptr < top);
+    assert_eq!(sum, (0..8).map(|i| i * i).sum::<usize>());
+    info!("relocate_kernel_stack_test passed!");
+}
+
+#[allow(unused)]
+/// 相邻内核栈之间留有不映射的保护页；在一个填满标记值的新内核栈上走一遍已知较深的内核路径
+/// （从ELF创建任务、fork、检查并打印地址空间），栈的最深位置仍在配置的KERNEL_STACK_SIZE之内
+pub fn kernel_stack_depth_test() {
+    use crate::config::{kernel_stack_position, KERNEL_STACK_SIZE, MAX_KERNEL_STACKS, PAGE_SIZE};
+    use kernel_stack::KernelStack;
+    const PATTERN: usize = 0x5a5a_5a5a_5a5a_5a5a;
+
+    for slot in [0, 1, MAX_KERNEL_STACKS - 2] {
+        let (bottom, top) = kernel_stack_position(slot).unwrap();
+        let (_, next_top) = kernel_stack_position(slot + 1).unwrap();
+        assert_eq!(top - bottom, KERNEL_STACK_SIZE);
+        assert!(bottom % PAGE_SIZE == 0 && next_top + PAGE_SIZE == bottom);
+    }
+    assert!(kernel_stack_position(MAX_KERNEL_STACKS).is_none());
+
+    struct Probe {
+        main_cx: TaskContext,
+        task_cx: TaskContext,
+        done: bool,
+    }
+    lazy_static! {
+        static ref PROBE: UPSafeCell<Probe> = unsafe {
+            UPSafeCell::new(Probe {
+                main_cx: TaskContext::zero_init(),
+                task_cx: TaskContext::zero_init(),
+                done: false,
+            })
+        };
+    }
+    extern "C" fn deep_path() -> ! {
+        let task = TaskControlBlock::new(get_app_data(0), 0);
+        let child = task.fork().unwrap();
+        child.memory_set.verify_invariants().unwrap();
+        assert!(!child.memory_set.dump_maps().is_empty());
+        drop(child);
+        drop(task);
+        let (task_cx, main_cx) = {
+            let mut probe = PROBE.exclusive_access();
+            probe.done = true;
+            (&mut probe.task_cx as *mut TaskContext, &probe.main_cx as *const TaskContext)
+        };
+        unsafe { __switch(task_cx, main_cx) };
+        unreachable!("deep path resumed after the test finished");
+    }
+
+    let stack = KernelStack::new().unwrap();
+    let (bottom, top) = (stack.bottom(), stack.top());
+    {
+        let kernel_space = KERNEL_SPACE.lock();
+        let mapped = |va: usize| {
+            kernel_space
+                .translate(VirtAddr::from(va).floor())
+                .map_or(false, |pte| pte.is_valid())
+        };
+        assert!(mapped(bottom) && mapped(top - 1));
+        assert!(!mapped(bottom - 1));
+    }
+    let words = unsafe {
+        core::slice::from_raw_parts_mut(bottom as *mut usize, KERNEL_STACK_SIZE / 8)
+    };
+    words.fill(PATTERN);
+    let (main_cx, task_cx) = {
+        let mut probe = PROBE.exclusive_access();
+        probe.task_cx = TaskContext::goto_entry(deep_path as usize, top);
+        (&mut probe.main_cx as *mut TaskContext, &probe.task_cx as *const TaskContext)
+    };
+    unsafe { __switch(main_cx, task_cx) };
+    assert!(PROBE.exclusive_access().done);
+    let untouched = words.iter().take_while(|&&word| word == PATTERN).count();
+    // 最底部的一页始终没有被用到，说明离保护页还有余量
+    assert!(untouched * 8 >= PAGE_SIZE);
+    info!(
+        "kernel_stack_depth_test passed! deepest use {} of {} bytes",
+        KERNEL_STACK_SIZE - untouched * 8,
+        KERNEL_STACK_SIZE
+    );
+}
+
+#[allow(unused)]
+/// 加载应用后entry_point与ELF头中的入口地址一致，initial_sp与Trap上下文中的初始sp一致；fork后二者不变
+pub fn entry_point_test() {
+    let elf_data = get_app_data(0);
+    let entry = xmas_elf::ElfFile::new(elf_data).unwrap().header.pt2.entry_point() as usize;
+    let task = TaskControlBlock::new(elf_data, 0);
+    assert_eq!(task.entry_point(), entry);
+    assert_eq!(task.get_trap_cx().sepc, entry);
+    assert_eq!(task.get_trap_cx().x[2], task.initial_sp());
+    assert!(task.memory_set.translate(VirtAddr::from(entry).floor()).is_some());
+    let child = task.fork().unwrap();
+    assert_eq!(child.entry_point(), entry);
+    assert_eq!(child.initial_sp(), task.initial_sp());
+    info!("entry_point_test passed!");
+}
+
+#[allow(unused)]
+/// new_with_stack_size映射出指定页数的用户栈，栈底下方的保护页不映射；
+/// 栈大小非法或空闲页帧不足时返回Err，并且不占用任何页帧
+pub fn task_stack_size_test() {
+    use crate::config::{PAGE_SIZE, USER_STACK_SIZE};
+    use crate::mm::get_num_empty_frame;
+    let elf_data = get_app_data(0);
+    let stack_pages = USER_STACK_SIZE / PAGE_SIZE * 4;
+    let task = TaskControlBlock::new_with_stack_size(elf_data, stack_pages).unwrap();
+    let top = VirtAddr::from(task.initial_sp()).floor();
+    assert_eq!(task.get_trap_cx().x[2], task.initial_sp());
+    for i in 1..=stack_pages {
+        let pte = task.memory_set.translate(VirtPageNum(top.0 - i));
+        assert!(pte.map_or(false, |pte| pte.is_valid()));
+    }
+    let guard = VirtPageNum(top.0 - stack_pages - 1);
+    assert!(!task.memory_set.translate(guard).map_or(false, |pte| pte.is_valid()));
+    drop(task);
+    let empty_frames = get_num_empty_frame();
+    assert_eq!(TaskControlBlock::new_with_stack_size(elf_data, 0).err(), Some(VmError::Unaligned));
+    assert_eq!(
+        TaskControlBlock::new_with_stack_size(elf_data, empty_frames + 1).err(),
+        Some(VmError::OutOfFrames)
+    );
+    assert_eq!(get_num_empty_frame(), empty_frames);
+    info!("task_stack_size_test passed!");
+}
+
+#[allow(unused)]
+/// 只有监督任务和父任务可以查看一个任务的地址空间（sys_process_vm_read、sys_dump_maps），
+/// 任务自己、兄弟任务以及任何任务对已退出的任务都不行
+pub fn inspect_permission_test() {
+    let other = SUPERVISOR_PID + 1;
+    let mut task = TaskControlBlock::new(get_app_data(0), 0);
+    task.parent = Some(other);
+    assert!(may_inspect(SUPERVISOR_PID, &task));
+    assert!(may_inspect(other, &task));
+    assert!(!may_inspect(other + 1, &task));
+    task.parent = None;
+    assert!(!may_inspect(other, &task));
+    task.task_status = TaskStatus::Exited;
+    assert!(!may_inspect(SUPERVISOR_PID, &task));
+    info!("inspect_permission_test passed!");
+}
+
+#[allow(unused)]
+/// 定时阻塞的任务在截止时间到达时变为Ready，等待子任务的父任务在子任务退出时被唤醒；
+/// 只因pause阻塞的任务两者都不响应
+pub fn timed_wait_test() {
+    use TaskStatus::*;
+    let mut inner = TaskManagerInner {
+        tasks: (0..3).map(|_| TaskControlBlock::new(get_app_data(0), 0)).collect(),
+        current_task: 1,
+        yield_target: None,
+        idle_task_cx: TaskContext::zero_init(),
+    };
+    inner.tasks[0].task_status = Blocked;
+    inner.tasks[0].wait = WaitEvents { deadline: Some(100), child_exit: true };
+    inner.tasks[1].task_status = Running;
+    inner.tasks[1].parent = Some(0);
+    inner.tasks[2].task_status = Blocked;
+    inner.tasks[2].wait = WaitEvents { deadline: Some(50), child_exit: false };
+    assert_eq!(inner.next_deadline(), Some(50));
+    inner.wake_expired(49);
+    assert_eq!(inner.tasks[2].task_status, Blocked);
+    inner.wake_expired(60);
+    assert_eq!(inner.tasks[2].task_status, Ready);
+    assert_eq!(inner.tasks[2].wait, WaitEvents::default());
+    assert_eq!((inner.tasks[0].task_status, inner.next_deadline()), (Blocked, Some(100)));
+    // 子任务退出唤醒等待中的父任务
+    exit_task(&mut inner.tasks, 1, 3);
+    assert_eq!(inner.tasks[0].task_status, Ready);
+    assert_eq!(inner.next_deadline(), None);
+    // 因pause阻塞的父任务不会被子任务退出唤醒
+    inner.tasks[2].task_status = Blocked;
+    inner.tasks[0].parent = Some(2);
+    exit_task(&mut inner.tasks, 0, 0);
+    assert_eq!(inner.tasks[2].task_status, Blocked);
+    inner.wake_expired(usize::MAX);
+    assert_eq!(inner.tasks[2].task_status, Blocked);
+    info!("timed_wait_test passed!");
+}
@@ -1,8 +1,25 @@
+use super::kernel_stack::KernelStack;
 use super::TaskContext;
-use crate::config::{kernel_stack_position, TRAP_CONTEXT, MAX_SYSCALL_NUM};
-use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::config::{MAX_SYSCALL_NUM, PAGE_SIZE, TASK_NAME_LEN, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::mm::{MemorySet, PhysPageNum, VirtAddr, VmError, KERNEL_SPACE};
 use crate::trap::{trap_handler, TrapContext};
 
+/// 系统调用过滤器：编号为i的系统调用对应第i / 64个字的第i % 64位，为1表示允许
+pub type SyscallFilter = [u64; (MAX_SYSCALL_NUM + 63) / 64];
+
+/// 按字节给出的位图构造过滤器：第i个字节的第j位对应编号为8 * i + j的系统调用，
+/// 位图之外的编号一律禁止；位图超过MAX_SYSCALL_NUM位时返回None
+pub fn syscall_filter_from_bytes(bytes: &[u8]) -> Option<SyscallFilter> {
+    if bytes.len() * 8 > MAX_SYSCALL_NUM + 7 {
+        return None;
+    }
+    let mut filter = [0; (MAX_SYSCALL_NUM + 63) / 64];
+    for (i, &byte) in bytes.iter().enumerate() {
+        filter[i / 8] |= (byte as u64) << (i % 8 * 8);
+    }
+    Some(filter)
+}
+
 /// task control block structure
 pub struct TaskControlBlock {
     pub task_status: TaskStatus,
@@ -10,9 +27,42 @@ pub struct TaskControlBlock {
     pub memory_set: MemorySet,
     pub trap_cx_ppn: PhysPageNum, // trapcontext对应的物理页的页号（应用空间）
     pub base_size: usize, // user stack的栈顶
+    /// 加载时ELF头中的入口地址，fork时继承
+    entry_point: usize,
+    /// 任务的内核栈；任务退出后由fork_current回收为None
+    pub kernel_stack: Option<KernelStack>,
 
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
+    /// 每个系统调用累计花费的时间（纳秒）
+    #[cfg(feature = "syscall-latency")]
+    pub syscall_ns: [u64; MAX_SYSCALL_NUM],
     pub start_time: usize,
+    /// 地址空间中当前持有的物理页帧数，每次分配或回收页帧后由sync_allocated_frames更新
+    pub allocated_frames: usize,
+    /// allocated_frames曾经达到的最大值
+    pub max_allocated_frames: usize,
+    /// 用户态触发、交给handle_page_fault处理的缺页异常次数（含处理失败的那一次）
+    pub page_faults: usize,
+    /// 堆的起始地址（用户栈栈顶）
+    pub heap_bottom: usize,
+    /// 当前的program break，即堆的结尾
+    pub program_brk: usize,
+    /// 父任务的编号；由加载器创建的任务以及父任务已退出的任务为None
+    pub parent: Option<usize>,
+    /// 退出码，任务退出时设置，由父任务的waitpid读取
+    pub exit_code: i32,
+    /// 任务的名字，不足TASK_NAME_LEN的部分以0填充；默认为应用名，fork时继承
+    pub name: [u8; TASK_NAME_LEN],
+    /// 被调度的次数与累计占用CPU的时间
+    pub sched_stats: SchedStats,
+    /// 任务未阻塞时收到的唤醒，下一次pause直接消耗它而不阻塞
+    pub wakeup_pending: bool,
+    /// 阻塞期间除了wake之外还在等待的事件，任务重新变为Ready时清空
+    pub wait: WaitEvents,
+    /// 系统调用过滤器，None表示不过滤；安装后不能更改或撤销，fork时继承
+    pub syscall_filter: Option<SyscallFilter>,
+    /// 间隔定时器与定时器信号的处理函数
+    pub signal: SignalState,
 }
 
 impl TaskControlBlock {
@@ -20,35 +70,90 @@ impl TaskControlBlock {
     pub fn get_trap_cx(&self) -> &'static mut TrapContext {
         self.trap_cx_ppn.get_mut()
     }
+    /// 加载的应用的入口地址
+    pub fn entry_point(&self) -> usize {
+        self.entry_point
+    }
+    /// 加载时用户栈的初始栈顶，即base_size
+    pub fn initial_sp(&self) -> usize {
+        self.base_size
+    }
     pub fn get_user_token(&self) -> usize {
         self.memory_set.token()
     }
+    /// 设置任务的名字，超过TASK_NAME_LEN字节的部分被截断
+    pub fn set_name(&mut self, name: &[u8]) {
+        let len = name.len().min(TASK_NAME_LEN);
+        self.name = [0; TASK_NAME_LEN];
+        self.name[..len].copy_from_slice(&name[..len]);
+    }
+    /// 任务的名字（去掉末尾的0），不是合法的UTF-8时返回"?"
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(TASK_NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+    /// 编号为syscall_id的系统调用是否被过滤器允许；没有安装过滤器时全部允许
+    pub fn syscall_allowed(&self, syscall_id: usize) -> bool {
+        match &self.syscall_filter {
+            Some(filter) => filter
+                .get(syscall_id / 64)
+                .map_or(false, |word| word & (1 << (syscall_id % 64)) != 0),
+            None => true,
+        }
+    }
+    /// 按memory_set的实际情况更新allocated_frames，地址空间发生变化后调用
+    pub fn sync_allocated_frames(&mut self) {
+        self.allocated_frames = self.memory_set.framed_pages();
+        self.max_allocated_frames = self.max_allocated_frames.max(self.allocated_frames);
+    }
 
     pub fn new(elf_data: &[u8], app_id: usize) -> Self {
+        Self::new_with_stack_size(elf_data, USER_STACK_SIZE / PAGE_SIZE)
+            .unwrap_or_else(|err| panic!("failed to create task {}: {:?}", app_id, err))
+    }
+
+    /// 与new相同，但用户栈大小为stack_pages个页面；
+    /// 建立地址空间失败时返回try_from_elf的错误，内核栈槽位用完时返回Err(VmError::NoSpace)
+    pub fn new_with_stack_size(elf_data: &[u8], stack_pages: usize) -> Result<Self, VmError> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, entry_point) =
+            MemorySet::try_from_elf(elf_data, stack_pages * PAGE_SIZE)?;
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
             .ppn(); // 获得trapcontext对应的物理页的页号
         let task_status = TaskStatus::Ready;
         // map a kernel-stack in kernel space （虚拟地址空间）
-        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(app_id);
-        KERNEL_SPACE.lock().insert_framed_area(
-            kernel_stack_bottom.into(),
-            kernel_stack_top.into(),
-            MapPermission::R | MapPermission::W,
-        );
-        let task_control_block = Self {
+        let kernel_stack = KernelStack::new().ok_or(VmError::NoSpace)?;
+        let kernel_stack_top = kernel_stack.top();
+        let mut task_control_block = Self {
             task_status,
             task_cx: TaskContext::goto_trap_return(kernel_stack_top),
             memory_set,
             trap_cx_ppn,
             base_size: user_sp,
+            entry_point,
+            kernel_stack: Some(kernel_stack),
 
             syscall_times: [0 as u32; MAX_SYSCALL_NUM],
+            #[cfg(feature = "syscall-latency")]
+            syscall_ns: [0; MAX_SYSCALL_NUM],
             start_time: 0 as usize,
+            allocated_frames: 0,
+            max_allocated_frames: 0,
+            page_faults: 0,
+            heap_bottom: user_sp,
+            program_brk: user_sp,
+            parent: None,
+            exit_code: 0,
+            name: [0; TASK_NAME_LEN],
+            sched_stats: SchedStats::default(),
+            wakeup_pending: false,
+            wait: WaitEvents::default(),
+            syscall_filter: None,
+            signal: SignalState::default(),
         };
+        task_control_block.sync_allocated_frames();
         // prepare TrapContext in user space
         // 注意：本函数第一行代码中创建memory_set的过程中并没有初始化TrapContext对应的物理页，这里就是初始化一下
         let trap_cx = task_control_block.get_trap_cx();
@@ -59,15 +164,256 @@ impl TaskControlBlock {
             kernel_stack_top,
             trap_handler as usize,
         );
-        task_control_block
+        Ok(task_control_block)
+    }
+
+    /// 将program break移动size字节（可以为负），成功时返回原来的program break；
+    /// 堆不能低于heap_bottom，也不能扩展进已有的映射
+    pub fn change_program_brk(&mut self, size: isize) -> Option<usize> {
+        let old_break = self.program_brk;
+        let new_brk = if size < 0 {
+            old_break.checked_sub(size.unsigned_abs())?
+        } else {
+            old_break.checked_add(size as usize)?
+        };
+        if new_brk < self.heap_bottom {
+            return None;
+        }
+        let result = if size < 0 {
+            self.memory_set
+                .shrink_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk))
+        } else {
+            self.memory_set
+                .append_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk))
+        };
+        self.sync_allocated_frames();
+        result.ok()?;
+        self.program_brk = new_brk;
+        Some(old_break)
+    }
+
+    /// 把处于Ready或Blocked状态（不在CPU上运行）的任务的内核栈搬到槽位new_slot：
+    /// 复制已用的部分，修正TaskContext中保存的sp和寄存器，以及栈上指向旧栈的值（帧指针、对栈上变量的引用等），
+    /// 再让下一次trap使用新的内核栈，最后释放旧的内核栈。
+    /// 栈上恰好落在旧栈地址范围内的其他数据也会被当作指针修正；内核栈位于地址空间的最高处，普通数据几乎不会落在这个范围内。
+    /// 任务正在运行、已经退出或者槽位已被占用时返回false，什么都不改变
+    pub fn relocate_kernel_stack(&mut self, new_slot: usize) -> bool {
+        if !matches!(self.task_status, TaskStatus::Ready | TaskStatus::Blocked) {
+            return false;
+        }
+        let (old_bottom, old_top) = match &self.kernel_stack {
+            Some(stack) => (stack.bottom(), stack.top()),
+            None => return false,
+        };
+        let new_stack = match KernelStack::new_at(new_slot) {
+            Some(stack) => stack,
+            None => return false,
+        };
+        let offset = new_stack.top().wrapping_sub(old_top);
+        let sp = self.task_cx.sp();
+        assert!(old_bottom <= sp && sp <= old_top, "saved sp {:#x} is outside the kernel stack", sp);
+        let words = (old_top - sp) / core::mem::size_of::<usize>();
+        let new_sp = sp.wrapping_add(offset);
+        unsafe {
+            core::ptr::copy_nonoverlapping(sp as *const usize, new_sp as *mut usize, words);
+            for word in core::slice::from_raw_parts_mut(new_sp as *mut usize, words) {
+                if old_bottom <= *word && *word <= old_top {
+                    *word = word.wrapping_add(offset);
+                }
+            }
+        }
+        self.task_cx.relocate_stack(old_bottom, old_top, offset);
+        self.get_trap_cx().kernel_sp = new_stack.top();
+        self.kernel_stack = Some(new_stack);
+        true
+    }
+
+    /// 以当前任务为父任务创建子任务：只读页面与父任务共享物理页帧，
+    /// 其余页面被复制；子任务从同一位置返回用户态，但系统调用返回值为0。
+    /// 没有空闲的内核栈槽位、空闲页帧不够复制地址空间或超出配额时返回None
+    pub fn fork(&self) -> Option<Self> {
+        let kernel_stack = KernelStack::new()?;
+        let kernel_stack_top = kernel_stack.top();
+        let memory_set = MemorySet::from_existed_user(&self.memory_set).ok()?;
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT).into())
+            .unwrap()
+            .ppn();
+        let mut task_control_block = Self {
+            task_status: TaskStatus::Ready,
+            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+            memory_set,
+            trap_cx_ppn,
+            base_size: self.base_size,
+            entry_point: self.entry_point,
+            kernel_stack: Some(kernel_stack),
+
+            syscall_times: [0 as u32; MAX_SYSCALL_NUM],
+            #[cfg(feature = "syscall-latency")]
+            syscall_ns: [0; MAX_SYSCALL_NUM],
+            start_time: 0 as usize,
+            allocated_frames: 0,
+            max_allocated_frames: 0,
+            page_faults: 0,
+            heap_bottom: self.heap_bottom,
+            program_brk: self.program_brk,
+            parent: None,
+            exit_code: 0,
+            name: self.name,
+            sched_stats: SchedStats::default(),
+            wakeup_pending: false,
+            wait: WaitEvents::default(),
+            syscall_filter: self.syscall_filter,
+            signal: self.signal.fork(),
+        };
+        task_control_block.sync_allocated_frames();
+        // Trap上下文已从父任务复制，只需换成子任务自己的内核栈
+        let trap_cx = task_control_block.get_trap_cx();
+        trap_cx.kernel_sp = kernel_stack_top;
+        trap_cx.x[10] = 0;
+        Some(task_control_block)
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+/// 任务的调度统计，时间均以微秒为单位
+pub struct SchedStats {
+    /// 被调度器选中的次数
+    pub run_count: usize,
+    /// 已结束的各次运行累计的时间
+    cpu_time_us: usize,
+    /// 本次开始运行的时刻；不在CPU上时为None
+    running_since: Option<usize>,
+    /// 已结束的各段内核态时间之和，是cpu_time_us的一部分
+    kernel_time_us: usize,
+    /// 是否处于从trap进入内核到返回用户态之间；任务总是在内核态被切换出去
+    in_kernel: bool,
+    /// 本段内核态时间的起点，只在in_kernel且正在运行时有意义
+    kernel_since: usize,
+}
+
+impl SchedStats {
+    /// 在now时刻被调度器选中
+    pub fn scheduled(&mut self, now: usize) {
+        self.run_count += 1;
+        self.running_since = Some(now);
+        if self.in_kernel {
+            self.kernel_since = now;
+        }
+    }
+    /// 在now时刻离开CPU
+    pub fn descheduled(&mut self, now: usize) {
+        if let Some(since) = self.running_since.take() {
+            self.cpu_time_us += now.saturating_sub(since);
+            if self.in_kernel {
+                self.kernel_time_us += now.saturating_sub(self.kernel_since);
+            }
+        }
+    }
+    /// 在now时刻因trap从用户态进入内核
+    pub fn enter_kernel(&mut self, now: usize) {
+        self.in_kernel = true;
+        self.kernel_since = now;
+    }
+    /// 在now时刻返回用户态
+    pub fn leave_kernel(&mut self, now: usize) {
+        if self.in_kernel && self.running_since.is_some() {
+            self.kernel_time_us += now.saturating_sub(self.kernel_since);
+        }
+        self.in_kernel = false;
+    }
+    /// 截至now累计占用CPU的时间，正在运行时包含本次运行至今的部分
+    pub fn cpu_time_us(&self, now: usize) -> usize {
+        self.cpu_time_us + self.running_since.map_or(0, |since| now.saturating_sub(since))
+    }
+    /// 截至now在内核态（处理trap）累计花费的时间
+    pub fn kernel_time_us(&self, now: usize) -> usize {
+        let current = if self.in_kernel && self.running_since.is_some() {
+            now.saturating_sub(self.kernel_since)
+        } else {
+            0
+        };
+        self.kernel_time_us + current
+    }
+    /// 截至now在用户态累计花费的时间
+    pub fn user_time_us(&self, now: usize) -> usize {
+        self.cpu_time_us(now).saturating_sub(self.kernel_time_us(now))
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
-/// task status: UnInit, Ready, Running, Exited
+#[derive(Copy, Clone, Default, Debug)]
+/// 简化的SIGALRM：间隔定时器到期后，任务下一次返回用户态时先跳转到注册的处理函数，
+/// 处理函数调用sigreturn回到被打断的位置。时间均以微秒为单位
+pub struct SignalState {
+    /// 用户态处理函数的地址；None表示没有注册，定时器到期也不做任何事
+    handler: Option<usize>,
+    /// 间隔定时器的周期和下一次到期的时刻
+    itimer: Option<(usize, usize)>,
+    /// 正在执行处理函数时保存的被打断的通用寄存器和sepc
+    saved: Option<([usize; 32], usize)>,
+}
+
+impl SignalState {
+    /// 注册处理函数，None表示注销
+    pub fn set_handler(&mut self, handler: Option<usize>) {
+        self.handler = handler;
+    }
+    /// 从now开始每interval_us触发一次定时器，interval_us为0时关闭定时器
+    pub fn set_itimer(&mut self, interval_us: usize, now: usize) {
+        self.itimer = match interval_us {
+            0 => None,
+            interval => Some((interval, now.saturating_add(interval))),
+        };
+    }
+    /// 定时器在now时已经到期、注册了处理函数且不在处理函数中时，保存cx中的寄存器和sepc，
+    /// 让任务返回用户态时跳转到处理函数，返回是否投递了信号。
+    /// 错过的多个周期只投递一次，下一次到期的时刻仍对齐到周期上；在处理函数中到期的信号等到sigreturn之后投递
+    pub fn deliver(&mut self, cx: &mut TrapContext, now: usize) -> bool {
+        let (handler, (interval, next)) = match (self.handler, self.itimer) {
+            (Some(handler), Some(itimer)) if self.saved.is_none() && now >= itimer.1 => (handler, itimer),
+            _ => return false,
+        };
+        let next = next + ((now - next) / interval + 1) * interval;
+        self.itimer = Some((interval, next));
+        self.saved = Some((cx.x, cx.sepc));
+        cx.sepc = handler;
+        true
+    }
+    /// 从处理函数返回：恢复cx中被打断时的寄存器和sepc，并返回恢复后的a0，
+    /// 以便作为sigreturn的返回值写回a0；不在处理函数中时返回None
+    pub fn sigreturn(&mut self, cx: &mut TrapContext) -> Option<usize> {
+        let (x, sepc) = self.saved.take()?;
+        cx.x = x;
+        cx.sepc = sepc;
+        Some(x[10])
+    }
+    /// fork出的子任务继承处理函数，子任务的定时器处于关闭状态；
+    /// 父任务正在处理函数中时子任务同样可以sigreturn
+    pub fn fork(&self) -> Self {
+        Self {
+            handler: self.handler,
+            itimer: None,
+            saved: self.saved,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+/// task status: UnInit, Ready, Running, Exited, Blocked
 pub enum TaskStatus {
     UnInit,
     Ready,
     Running,
     Exited,
+    /// 因pause、睡眠或等待子任务而阻塞，被唤醒前不会被调度
+    Blocked,
+}
+
+/// 阻塞的任务除了被wake唤醒之外还在等待的事件，任一事件发生时任务变为Ready
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct WaitEvents {
+    /// 到达这个时刻（微秒）时唤醒，由时钟中断和idle控制流检查
+    pub deadline: Option<usize>,
+    /// 有子任务退出时唤醒
+    pub child_exit: bool,
 }
\ No newline at end of file
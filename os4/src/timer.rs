@@ -1,9 +1,12 @@
 use crate::config::CLOCK_FREQ;
+use crate::mm::TIME_FRAME;
 use crate::sbi::set_timer;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 const MICRO_PER_SEC: usize = 1_000_000;
+#[cfg(feature = "syscall-latency")]
+const NANO_PER_SEC: usize = 1_000_000_000;
 
 pub fn get_time() -> usize {
     time::read()
@@ -13,6 +16,16 @@ pub fn get_time_us() -> usize {
     time::read() / (CLOCK_FREQ / MICRO_PER_SEC)
 }
 
+#[cfg(feature = "syscall-latency")]
+pub fn get_time_ns() -> usize {
+    time::read() * (NANO_PER_SEC / CLOCK_FREQ)
+}
+
+/// 把当前的mtime和CLOCK_FREQ写入共享的时间页，用户程序从TIME_PAGE处读取
+pub fn update_time_page() {
+    *TIME_FRAME.ppn().get_mut::<[usize; 2]>() = [get_time(), CLOCK_FREQ];
+}
+
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
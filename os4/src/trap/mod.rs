@@ -1,15 +1,25 @@
 mod context;
+mod stats;
 
 use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::mm::FaultKind;
 use crate::syscall::syscall;
 use crate::task::{
-    current_trap_cx, current_user_token, exit_current_and_run_next, suspend_current_and_run_next,
+    current_pte_flags, current_trap_cx, current_user_token, deliver_signal_current, exit_current_and_run_next, get_name_of_current_task,
+    enter_kernel_current, get_pid_of_current_task, handle_page_fault, leave_kernel_current,
+    suspend_current_and_run_next, wake_expired_tasks,
 };
-use crate::timer::set_next_trigger;
+use crate::sync::UPSafeCell;
+use crate::timer::{set_next_trigger, update_time_page};
+use alloc::format;
+use alloc::string::String;
+use lazy_static::*;
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sepc, sie,
+    sstatus::{self, SPP},
+    stval, stvec,
 };
 
 core::arch::global_asm!(include_str!("trap.S"));
@@ -38,29 +48,103 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+lazy_static! {
+    /// 是否正在处理来自用户态的trap：trap_handler入口置为true，trap_return返回用户态前清除
+    static ref IN_TRAP: UPSafeCell<bool> = unsafe { UPSafeCell::new(false) };
+}
+
+/// trap的来源；trap_handler只能处理来自用户态、且不是在处理另一个trap的过程中发生的trap
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum TrapOrigin {
+    /// 来自用户态
+    User,
+    /// 来自内核态（sstatus.SPP为S），例如系统调用中内核访问了非法地址
+    Kernel,
+    /// SPP为U，但内核还没有返回用户态就再次进入了trap_handler
+    Nested,
+}
+
+/// 由sstatus.SPP和进入时是否已经在处理trap判断trap的来源
+fn trap_origin(spp: SPP, in_trap: bool) -> TrapOrigin {
+    match (spp, in_trap) {
+        (SPP::Supervisor, _) => TrapOrigin::Kernel,
+        (SPP::User, true) => TrapOrigin::Nested,
+        (SPP::User, false) => TrapOrigin::User,
+    }
+}
+
+/// 缺页异常的访问类型：取指缺页按执行处理，只执行的页面也能按需映射
+fn fault_kind(cause: Trap) -> FaultKind {
+    match cause {
+        Trap::Exception(Exception::StorePageFault) => FaultKind::Write,
+        Trap::Exception(Exception::InstructionPageFault) => FaultKind::Execute,
+        _ => FaultKind::Read,
+    }
+}
+
+/// 内核态的trap的详细信息
+fn kernel_fault_message(origin: TrapOrigin, cause: Trap, sepc: usize, stval: usize) -> String {
+    format!(
+        "[kernel] unrecoverable {:?} trap: cause = {:?}, sepc = {:#x}, stval = {:#x}",
+        origin, cause, sepc, stval
+    )
+}
+
+/// 内核态的trap无法恢复，现场可能已被破坏：不再调度任何用户任务，打印原因、sepc和stval后panic
+fn kernel_fault(origin: TrapOrigin, cause: Trap, sepc: usize, stval: usize) -> ! {
+    panic!("{}", kernel_fault_message(origin, cause, sepc, stval));
+}
+
 #[no_mangle]
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
-    let cx = current_trap_cx();
     let scause = scause::read();
     let stval = stval::read();
+    let origin = trap_origin(sstatus::read().spp(), *IN_TRAP.exclusive_access());
+    if origin != TrapOrigin::User {
+        kernel_fault(origin, scause.cause(), sepc::read(), stval);
+    }
+    *IN_TRAP.exclusive_access() = true;
+    enter_kernel_current();
+    let cx = current_trap_cx();
+    TRAP_STATS.exclusive_access().record(scause.cause());
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13]]) as usize;
+        }
+        Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault)
+            if handle_page_fault(stval, fault_kind(scause.cause())).is_ok() =>
+        {
+            // 懒分配/写时复制的页面已映射，返回用户态重新执行触发异常的指令
         }
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
-        | Trap::Exception(Exception::LoadPageFault) => {
-            error!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.", stval, cx.sepc);
-            exit_current_and_run_next();
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault) => {
+            error!(
+                "[kernel] PageFault in application {} ({}), bad addr = {:#x} ({}), bad instruction = {:#x}, core dumped.",
+                get_pid_of_current_task(),
+                get_name_of_current_task(),
+                stval,
+                current_pte_flags(stval),
+                cx.sepc
+            );
+            exit_current_and_run_next(-2);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            error!("[kernel] IllegalInstruction in application, core dumped.");
-            exit_current_and_run_next();
+            error!(
+                "[kernel] IllegalInstruction in application {} ({}), core dumped.",
+                get_pid_of_current_task(),
+                get_name_of_current_task()
+            );
+            exit_current_and_run_next(-3);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
+            wake_expired_tasks();
             suspend_current_and_run_next();
         }
         _ => {
@@ -76,7 +160,13 @@ pub fn trap_handler() -> ! {
 
 #[no_mangle]
 pub fn trap_return() -> ! {
+    // 定时器信号在这里投递：Trap上下文已经是要恢复的最终状态（系统调用的返回值已写入a0），
+    // 整体保存下来由sigreturn原样恢复
+    deliver_signal_current();
+    *IN_TRAP.exclusive_access() = false;
     set_user_trap_entry();
+    update_time_page();
+    leave_kernel_current();
     let trap_cx_ptr = TRAP_CONTEXT;
     let user_satp = current_user_token();
     extern "C" {
@@ -98,7 +188,31 @@ pub fn trap_return() -> ! {
 
 #[no_mangle]
 pub fn trap_from_kernel() -> ! {
-    panic!("a trap from kernel!");
+    kernel_fault(TrapOrigin::Kernel, scause::read().cause(), sepc::read(), stval::read());
+}
+
+#[allow(unused)]
+/// 检查trap来源的判断：来自S态的trap（如系统调用中内核访问了非法地址）和处理trap时再次进入trap_handler
+/// 都走内核错误的路径，报告中带有原因、sepc和stval
+pub fn nested_trap_test() {
+    assert_eq!(trap_origin(SPP::User, false), TrapOrigin::User);
+    assert_eq!(trap_origin(SPP::Supervisor, false), TrapOrigin::Kernel);
+    assert_eq!(trap_origin(SPP::Supervisor, true), TrapOrigin::Kernel);
+    assert_eq!(trap_origin(SPP::User, true), TrapOrigin::Nested);
+    // 系统调用中解引用空指针
+    let message = kernel_fault_message(
+        trap_origin(SPP::Supervisor, true),
+        Trap::Exception(Exception::LoadPageFault),
+        0x80201234,
+        0,
+    );
+    assert_eq!(
+        message,
+        "[kernel] unrecoverable Kernel trap: cause = Exception(LoadPageFault), sepc = 0x80201234, stval = 0x0"
+    );
+    info!("nested_trap_test passed!");
 }
 
-pub use context::TrapContext;
\ No newline at end of file
+pub use context::TrapContext;
+pub use stats::{trap_counts, trap_stats_test, NUM_TRAP_CAUSES};
+use stats::TRAP_STATS;
\ No newline at end of file
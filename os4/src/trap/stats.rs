@@ -0,0 +1,89 @@
+//! 按trap原因统计的计数器，供sys_trapstat读取
+
+use crate::sync::UPSafeCell;
+use lazy_static::*;
+use riscv::register::scause::{Exception, Interrupt, Trap};
+
+/// 系统调用（来自U态的ecall）
+pub const TRAP_SYSCALL: usize = 0;
+/// 写缺页
+pub const TRAP_STORE_PAGE_FAULT: usize = 1;
+/// 读缺页
+pub const TRAP_LOAD_PAGE_FAULT: usize = 2;
+/// 取指缺页
+pub const TRAP_INSTRUCTION_PAGE_FAULT: usize = 3;
+/// 写访问异常（StoreFault）
+pub const TRAP_STORE_FAULT: usize = 4;
+/// 非法指令
+pub const TRAP_ILLEGAL_INSTRUCTION: usize = 5;
+/// S态时钟中断
+pub const TRAP_TIMER: usize = 6;
+/// 其他原因
+pub const TRAP_OTHER: usize = 7;
+/// 计数器的个数
+pub const NUM_TRAP_CAUSES: usize = 8;
+
+/// trap原因对应的计数器下标
+pub fn trap_cause_index(cause: Trap) -> usize {
+    match cause {
+        Trap::Exception(Exception::UserEnvCall) => TRAP_SYSCALL,
+        Trap::Exception(Exception::StorePageFault) => TRAP_STORE_PAGE_FAULT,
+        Trap::Exception(Exception::LoadPageFault) => TRAP_LOAD_PAGE_FAULT,
+        Trap::Exception(Exception::InstructionPageFault) => TRAP_INSTRUCTION_PAGE_FAULT,
+        Trap::Exception(Exception::StoreFault) => TRAP_STORE_FAULT,
+        Trap::Exception(Exception::IllegalInstruction) => TRAP_ILLEGAL_INSTRUCTION,
+        Trap::Interrupt(Interrupt::SupervisorTimer) => TRAP_TIMER,
+        _ => TRAP_OTHER,
+    }
+}
+
+/// 各trap原因发生的次数
+pub struct TrapStats {
+    counts: [usize; NUM_TRAP_CAUSES],
+}
+
+impl TrapStats {
+    pub const fn new() -> Self {
+        Self {
+            counts: [0; NUM_TRAP_CAUSES],
+        }
+    }
+    pub fn record(&mut self, cause: Trap) {
+        self.counts[trap_cause_index(cause)] += 1;
+    }
+    pub fn counts(&self) -> [usize; NUM_TRAP_CAUSES] {
+        self.counts
+    }
+}
+
+lazy_static! {
+    /// 全局的trap计数器，由trap_handler在分发之前更新
+    pub static ref TRAP_STATS: UPSafeCell<TrapStats> = unsafe { UPSafeCell::new(TrapStats::new()) };
+}
+
+/// 当前各trap原因的计数
+pub fn trap_counts() -> [usize; NUM_TRAP_CAUSES] {
+    TRAP_STATS.exclusive_access().counts()
+}
+
+#[allow(unused)]
+/// 检查各trap原因被计入各自的计数器，未知的原因计入TRAP_OTHER
+pub fn trap_stats_test() {
+    let mut stats = TrapStats::new();
+    for _ in 0..5 {
+        stats.record(Trap::Exception(Exception::UserEnvCall));
+    }
+    for _ in 0..3 {
+        stats.record(Trap::Interrupt(Interrupt::SupervisorTimer));
+    }
+    stats.record(Trap::Exception(Exception::StorePageFault));
+    stats.record(Trap::Exception(Exception::Breakpoint));
+    let counts = stats.counts();
+    assert_eq!(counts[TRAP_SYSCALL], 5);
+    assert_eq!(counts[TRAP_TIMER], 3);
+    assert_eq!(counts[TRAP_STORE_PAGE_FAULT], 1);
+    assert_eq!(counts[TRAP_LOAD_PAGE_FAULT], 0);
+    assert_eq!(counts[TRAP_OTHER], 1);
+    assert_eq!(counts.iter().sum::<usize>(), 10);
+    info!("trap_stats_test passed!");
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{brk, sbrk};
+
+/*
+理想结果：brk(0) 返回当前的 program break，brk/sbrk 可以扩展和收缩堆，输出 Test 04_13 brk OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    // 查询形式
+    let base = brk(0);
+    assert!(base > 0);
+    assert_eq!(brk(0), base);
+    // sbrk 返回原来的 program break
+    assert_eq!(sbrk(4096), base);
+    assert_eq!(brk(0), base + 4096);
+    // 设置形式
+    let top = base as usize + 3 * 4096;
+    assert_eq!(brk(top), top as isize);
+    for addr in (base as usize..top).step_by(512) {
+        unsafe {
+            *(addr as *mut u8) = addr as u8;
+        }
+    }
+    for addr in (base as usize..top).step_by(512) {
+        unsafe {
+            assert_eq!(*(addr as *const u8), addr as u8);
+        }
+    }
+    assert_eq!(brk(base as usize), base);
+    assert_eq!(brk(0), base);
+    // 不能收缩到堆底之下
+    assert_eq!(brk(base as usize - 4096), -1);
+    assert_eq!(sbrk(-1), -1);
+    println!("Test 04_13 brk OK!");
+    0
+}
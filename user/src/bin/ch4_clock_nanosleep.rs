@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{clock_nanosleep, sys_get_time, TimeSpec, TimeVal, TIMER_ABSTIME, TIMER_STRICT};
+
+/*
+理想结果：按绝对截止时间周期性睡眠，每次都不早于截止时间醒来，且延迟不随次数累积；
+已经过去的截止时间立即返回0，带TIMER_STRICT时返回-1，输出 Test 04_47 clock nanosleep OK!
+*/
+
+const PERIOD_US: usize = 50_000;
+const ROUNDS: usize = 6;
+
+fn now_us() -> usize {
+    let time = TimeVal::new();
+    assert_eq!(sys_get_time(&time, 0), 0);
+    time.sec * 1_000_000 + time.usec
+}
+
+fn timespec(us: usize) -> TimeSpec {
+    TimeSpec {
+        sec: us / 1_000_000,
+        nsec: us % 1_000_000 * 1000,
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = now_us();
+    let mut deadline = start;
+    for _ in 0..ROUNDS {
+        deadline += PERIOD_US;
+        assert_eq!(clock_nanosleep(&timespec(deadline), TIMER_ABSTIME), 0);
+        assert!(now_us() >= deadline);
+    }
+    // 最后一次醒来相对于start的偏差只是这一次的延迟，不是ROUNDS次延迟之和
+    let end = now_us();
+    assert_eq!(deadline, start + ROUNDS * PERIOD_US);
+    assert!(end - deadline < PERIOD_US);
+
+    let past = timespec(start);
+    assert_eq!(clock_nanosleep(&past, TIMER_ABSTIME), 0);
+    assert_eq!(clock_nanosleep(&past, TIMER_ABSTIME | TIMER_STRICT), -1);
+
+    let before = now_us();
+    assert_eq!(clock_nanosleep(&timespec(10_000), 0), 0);
+    assert!(now_us() - before >= 10_000);
+
+    let bad = TimeSpec { sec: 0, nsec: 1_000_000_000 };
+    assert_eq!(clock_nanosleep(&bad, 0), -1);
+    assert_eq!(clock_nanosleep(&timespec(0), 4), -1);
+    println!("Test 04_47 clock nanosleep OK!");
+    0
+}
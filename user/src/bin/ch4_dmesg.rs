@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::dmesg;
+
+/*
+理想结果：读到的内核日志以完整的一行结尾，且不超过缓冲区长度，输出 Test 04_15 dmesg OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buf = [0u8; 256];
+    let n = dmesg(&mut buf);
+    assert!(0 < n && n <= 256);
+    assert_eq!(buf[n as usize - 1], b'\n');
+    // 缓冲区跨页时同样可以写入
+    let mut big = [0u8; 4200];
+    let m = dmesg(&mut big);
+    assert!(n <= m && m <= 4096);
+    assert_eq!(&big[(m - n) as usize..m as usize], &buf[..n as usize]);
+    assert!(big[..m as usize].windows(2).any(|w| w == b"] "));
+    println!("Test 04_15 dmesg OK!");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{dmesg, dump_maps, exit, fork, getppid, maps, pause, wake, waitpid_timeout};
+
+/*
+理想结果：父任务可以把子任务的映射输出到内核日志，日志中包含子任务的用户栈区间；
+子任务不能查看父任务，任务不存在时返回 -1，输出 Test 04_42 dump maps OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        // 父任务是0号任务时它是监督任务，子任务仍然不能查看它
+        assert_eq!(dump_maps(getppid() as usize), -1);
+        pause();
+        exit(0);
+    }
+    assert_eq!(dump_maps(1000), -1);
+    assert_eq!(dump_maps(pid as usize), 0);
+    // fork出的子任务与父任务的布局相同，从自己的maps中找出用户栈那一行
+    let mut buf = [0u8; 1024];
+    let n = maps(&mut buf);
+    let text = core::str::from_utf8(&buf[..n as usize]).unwrap();
+    let stack = text.lines().find(|line| line.ends_with(" rw-u stack")).unwrap();
+    let mut logs = [0u8; 4096];
+    let n = dmesg(&mut logs);
+    assert!(n > 0);
+    let logs = core::str::from_utf8(&logs[..n as usize]).unwrap();
+    let dump = &logs[logs.rfind("maps of task ").unwrap()..];
+    assert!(dump.contains(stack));
+    wake(pid as usize);
+    let mut exit_code = -1;
+    assert_eq!(waitpid_timeout(pid, &mut exit_code, 2000), pid);
+    assert_eq!(exit_code, 0);
+    println!("Test 04_42 dump maps OK!");
+    0
+}
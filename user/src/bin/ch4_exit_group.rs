@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit_group, fork, yield_, yield_to};
+
+/*
+理想结果：子树的根调用 exit_group 后，它的子孙任务（都在无限循环中让出CPU）也一起结束，
+父任务输出 Test 04_21 exit group OK!；若子孙任务没有被结束，测试将无法终止
+*/
+
+/// fork一个只会不断让出CPU的子任务，返回它的pid
+fn spawn_spinner() -> usize {
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    pid as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let root = fork();
+    assert!(root >= 0);
+    if root == 0 {
+        // root -> child -> grandchild，root -> child2
+        let child = fork();
+        assert!(child >= 0);
+        if child == 0 {
+            spawn_spinner();
+            loop {
+                yield_();
+            }
+        }
+        // 让child先fork出孙任务
+        yield_to(child as usize);
+        spawn_spinner();
+        exit_group(0);
+    }
+    // 等待root结束
+    while yield_to(root as usize) == 0 {}
+    println!("Test 04_21 exit group OK!");
+    0
+}
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{frame_count, mmap, munmap};
+
+/*
+理想结果：mmap 后只有写入过的页面才占用物理页帧，munmap 后页帧数恢复，输出 Test 04_11 frame count OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let prot: usize = 3;
+    let base = frame_count();
+    assert!(base > 0);
+    assert_eq!(0, mmap(start, pages * 4096, prot));
+    assert_eq!(frame_count(), base);
+    for i in 0..pages {
+        let addr: *mut u8 = (start + i * 4096) as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(frame_count(), base + pages as isize);
+    assert_eq!(0, munmap(start, pages * 4096));
+    assert_eq!(frame_count(), base);
+    println!("Test 04_11 frame count OK!");
+    0
+}
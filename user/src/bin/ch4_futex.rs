@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, futex_wait, futex_wake, sched_yield, waitpid_timeout};
+
+/*
+理想结果：fork 后父子任务共享只读页面，对其中的同一个 futex 字，
+消费者（子任务）等待、生产者（父任务）唤醒后消费者继续运行；
+私有的可写页面在 fork 后各自独立，互不唤醒，输出 Test 04_34 futex OK!
+*/
+
+/// 位于只读数据段，fork后父子任务映射到同一个物理页帧
+static READY: u32 = 7;
+/// 位于可写数据段，fork后父子任务各有一份
+static mut PRIVATE: u32 = 0;
+
+#[no_mangle]
+fn main() -> i32 {
+    // 值不相等时不会阻塞
+    assert_eq!(futex_wait(&READY, 0), -2);
+    assert_eq!(futex_wake(&READY, 1), 0);
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        // 消费者：等待生产者的通知
+        assert_eq!(futex_wait(&READY, 7), 0);
+        exit(0);
+    }
+    // 生产者：等消费者进入等待后唤醒它
+    let mut woken = 0;
+    for _ in 0..100 {
+        // 父任务自己的PRIVATE与子任务的不是同一个futex
+        assert_eq!(unsafe { futex_wake(&*core::ptr::addr_of!(PRIVATE), 1) }, 0);
+        woken = futex_wake(&READY, 1);
+        if woken > 0 {
+            break;
+        }
+        sched_yield();
+    }
+    assert_eq!(woken, 1);
+    let mut code: i32 = -1;
+    assert_eq!(waitpid_timeout(pid, &mut code, 1000), pid);
+    assert_eq!(code, 0);
+    println!("Test 04_34 futex OK!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, getppid, yield_, yield_to};
+
+/*
+理想结果：子任务的 getppid 等于父任务的 getpid；父任务退出后孙任务的 getppid 变为 -1，
+由孙任务输出 Test 04_17 getppid OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = getpid();
+    let child = fork();
+    assert!(child >= 0);
+    if child > 0 {
+        // 让子任务在本任务退出之前检查getppid
+        yield_to(child as usize);
+        return 0;
+    }
+    let ppid = getppid();
+    println!("child {}: ppid = {}, parent pid = {}", getpid(), ppid, pid);
+    assert_eq!(ppid, pid);
+    let me = getpid();
+    let grandchild = fork();
+    assert!(grandchild >= 0);
+    if grandchild > 0 {
+        yield_to(grandchild as usize);
+        exit(0);
+    }
+    assert_eq!(getppid(), me);
+    while getppid() != -1 {
+        yield_();
+    }
+    println!("Test 04_17 getppid OK!");
+    0
+}
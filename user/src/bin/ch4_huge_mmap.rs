@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    dmesg, dump_maps, exit, fork, getppid, mmap, munmap, pause, wake, waitpid_timeout, HUGE_PAGE_SIZE, MAP_HUGE,
+};
+
+/*
+理想结果：带MAP_HUGE的mmap返回按2MiB对齐的地址，子任务在固定地址映射的大页出现在父任务导出的页表中，
+输出 Test 04_45 huge mmap OK!
+*/
+
+const FIXED: usize = 0x10000000;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = mmap(0, HUGE_PAGE_SIZE, 3 | MAP_HUGE);
+    assert!(start > 0 && start as usize % HUGE_PAGE_SIZE == 0);
+    let start = start as usize;
+    unsafe {
+        ((start + HUGE_PAGE_SIZE - 1) as *mut u8).write_volatile(0x5a);
+        assert_eq!(((start + HUGE_PAGE_SIZE - 1) as *const u8).read_volatile(), 0x5a);
+        assert_eq!(((start + 4096) as *const u8).read_volatile(), 0);
+    }
+    assert_eq!(0, munmap(start, HUGE_PAGE_SIZE));
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        assert_eq!(0, mmap(FIXED, 2 * HUGE_PAGE_SIZE, 3 | MAP_HUGE));
+        unsafe {
+            (FIXED as *mut u8).write_volatile(1);
+        }
+        // 通知父任务映射已建立，等它导出页表之后再退出
+        wake(getppid() as usize);
+        pause();
+        exit(0);
+    }
+    pause();
+    assert_eq!(dump_maps(pid as usize), 0);
+    let mut logs = [0u8; 4096];
+    let n = dmesg(&mut logs);
+    assert!(n > 0);
+    let logs = core::str::from_utf8(&logs[..n as usize]).unwrap();
+    let dump = &logs[logs.rfind("maps of task ").unwrap()..];
+    let huge: usize = dump.lines().filter(|line| line.ends_with(" huge")).count();
+    assert_eq!(huge, 2);
+    assert!(dump.lines().any(|line| line.starts_with("  0x10000 -> ") && line.ends_with(" huge")));
+    wake(pid as usize);
+    let mut exit_code = -1;
+    assert_eq!(waitpid_timeout(pid, &mut exit_code, 2000), pid);
+    assert_eq!(exit_code, 0);
+    println!("Test 04_45 huge mmap OK!");
+    0
+}
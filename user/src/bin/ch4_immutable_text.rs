@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mprotect, MAP_JIT};
+
+/*
+理想结果：程序自己的代码段不能被mprotect改为可写（去掉X或带MAP_JIT也不行），
+改为它原有的R|X仍然可以，输出 Test 04_50 immutable text OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let text = main as usize & !4095;
+    assert_eq!(mprotect(text, 4096, 3), -1);
+    assert_eq!(mprotect(text, 4096, 7 | MAP_JIT), -1);
+    assert_eq!(mprotect(text, 4096, 5), 0);
+    assert_eq!(mprotect(text, 4096, 3), -1);
+    println!("Test 04_50 immutable text OK!");
+    0
+}
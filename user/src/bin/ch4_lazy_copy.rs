@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{madvise, mmap, munmap, sys_get_time, TimeVal, MADV_DONTNEED};
+
+/*
+理想结果：sys_get_time 的 ts 指向尚未驻留（映射到零页或被 MADV_DONTNEED 释放）的页面时
+内核先处理缺页再写入，系统调用成功，输出 Test 04_18 lazy copy OK!
+*/
+
+/// 先把两个字段都填成哨兵值，调用sys_get_time后检查两个字段都被正确写入：
+/// 跨页时usec落在第二页，写到别处的话usec仍是哨兵值。
+/// 内核在系统调用中修改了*ts，用volatile读写避免编译器沿用之前的值
+fn get_time_checked(addr: usize) {
+    let ts = addr as *mut TimeVal;
+    unsafe {
+        core::ptr::write_volatile(&mut (*ts).sec, usize::MAX);
+        core::ptr::write_volatile(&mut (*ts).usec, usize::MAX);
+        assert_eq!(sys_get_time(&*ts, 0), 0);
+        let sec = core::ptr::read_volatile(&(*ts).sec);
+        let usec = core::ptr::read_volatile(&(*ts).usec);
+        assert!(sec != usize::MAX && usec < 1_000_000);
+        assert!(sec != 0 || usec != 0);
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 2;
+    assert_eq!(0, mmap(start, len, 3));
+    // 跨越两个页面的TimeVal
+    let addrs = [start, start + 4096 - 8];
+    for addr in addrs {
+        get_time_checked(addr);
+        // 页面被释放后，由内核在系统调用中处理缺页再写入
+        assert_eq!(madvise(start, len, MADV_DONTNEED), 0);
+        let ts = unsafe { &*(addr as *const TimeVal) };
+        assert_eq!(sys_get_time(ts, 0), 0);
+        let (sec, usec) = unsafe {
+            (core::ptr::read_volatile(&ts.sec), core::ptr::read_volatile(&ts.usec))
+        };
+        assert!(usec < 1_000_000 && (sec != 0 || usec != 0));
+        get_time_checked(addr);
+    }
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_18 lazy copy OK!");
+    0
+}
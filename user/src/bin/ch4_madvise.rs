@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{madvise, mmap, munmap, MADV_DONTNEED};
+
+/*
+理想结果：MADV_DONTNEED 之后再次读取得到全零的页面，最终输出 Test 04_8 madvise OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    for i in start..(start + len) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = 0xff;
+        }
+    }
+    assert_eq!(madvise(start + 1, len, MADV_DONTNEED), -1);
+    assert_eq!(madvise(start, len * 2, MADV_DONTNEED), -1);
+    assert_eq!(madvise(start, len, MADV_DONTNEED), 0);
+    for i in start..(start + len) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, 0);
+        }
+    }
+    assert_eq!(munmap(start, len), 0);
+    println!("Test 04_8 madvise OK!");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getrusage, madvise, mmap, munmap, Rusage, MADV_WILLNEED};
+
+/*
+理想结果：MADV_WILLNEED 一次调入整个惰性映射的区间，之后写每一页都不再缺页，
+输出 Test 04_49 madvise willneed OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 16;
+    assert_eq!(0, mmap(start, pages * 4096, 3));
+    assert_eq!(madvise(start, (pages + 1) * 4096, MADV_WILLNEED), -1);
+    assert_eq!(madvise(start + 1, pages * 4096, MADV_WILLNEED), -1);
+    assert_eq!(madvise(start, pages * 4096, MADV_WILLNEED), 0);
+    let mut before = Rusage::default();
+    assert_eq!(0, getrusage(&mut before));
+    for i in 0..pages {
+        unsafe {
+            ((start + i * 4096) as *mut u8).write_volatile(i as u8);
+        }
+    }
+    let mut after = Rusage::default();
+    assert_eq!(0, getrusage(&mut after));
+    assert_eq!(after.page_faults, before.page_faults);
+    assert!(after.max_resident_pages >= pages);
+    for i in 0..pages {
+        assert_eq!(unsafe { ((start + i * 4096) as *const u8).read_volatile() }, i as u8);
+    }
+    assert_eq!(0, munmap(start, pages * 4096));
+    println!("Test 04_49 madvise willneed OK!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getpid, map_phys, munmap};
+
+/*
+理想结果：只有0号任务可以把物理页帧直接映射进自己的地址空间，
+且不能映射可分配的物理内存，映射之后也不能munmap，输出 Test 04_31 map phys OK!
+*/
+
+/// virt机器上goldfish RTC的MMIO页
+const RTC: usize = 0x0010_1000;
+
+#[no_mangle]
+fn main() -> i32 {
+    let va: usize = 0x10000000;
+    let len: usize = 4096;
+    if getpid() != 0 {
+        assert_eq!(map_phys(va, RTC, len, 1), -1);
+    } else {
+        // 可分配的物理内存不能被映射
+        assert!(map_phys(va, 0x8700_0000, len, 3) < 0);
+        assert_eq!(map_phys(va, RTC, len, 1), 0);
+        let time_low = unsafe { (va as *const u32).read_volatile() };
+        println!("rtc time_low = {}", time_low);
+        // 借用的物理页帧不归任务所有，不能munmap
+        assert_eq!(munmap(va, len), -1);
+    }
+    println!("Test 04_31 map phys OK!");
+    0
+}
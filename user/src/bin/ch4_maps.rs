@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{maps, mmap};
+
+/*
+理想结果：maps 的输出中包含 mmap 得到的区间及其权限，最终输出 Test 04_9 maps OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    let mut buf = [0u8; 1024];
+    let n = maps(&mut buf);
+    assert!(n > 0);
+    let text = core::str::from_utf8(&buf[..n as usize]).unwrap();
+    assert!(text.contains("10000000-10001000 rw-u mmap\n"));
+    assert!(text.contains(" r-xu elf\n"));
+    assert!(text.contains(" rw-u stack\n"));
+    // 缓冲区不足时只返回完整的行
+    let mut small = [0u8; 40];
+    let m = maps(&mut small);
+    assert!(0 < m && m < n && m <= 40);
+    assert_eq!(small[m as usize - 1], b'\n');
+    println!("Test 04_9 maps OK!");
+    0
+}
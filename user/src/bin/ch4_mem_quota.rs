@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{frame_count, mmap, munmap, set_mem_quota};
+
+/*
+理想结果：超出配额的 mmap 返回 -1，配额内的 mmap 正常使用，输出 Test 04_12 mem quota OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    let base = frame_count() as usize;
+    // 配额不能低于已经持有的页帧数
+    assert_eq!(-1, set_mem_quota(base - 1));
+    assert_eq!(0, set_mem_quota(base + 2));
+    assert_eq!(-1, mmap(start, 3 * 4096, prot));
+    assert_eq!(0, mmap(start, 2 * 4096, prot));
+    for i in 0..2 {
+        let addr: *mut u8 = (start + i * 4096) as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(frame_count() as usize, base + 2);
+    assert_eq!(0, munmap(start, 2 * 4096));
+    assert_eq!(0, set_mem_quota(0));
+    assert_eq!(0, mmap(start, 3 * 4096, prot));
+    println!("Test 04_12 mem quota OK!");
+    0
+}
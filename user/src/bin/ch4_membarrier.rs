@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, membarrier, munmap, MAP_JIT};
+
+/*
+理想结果：向可写可执行的页面写入指令，调用 membarrier 后跳转执行得到正确结果；
+改写其中的指令并再次调用 membarrier 后执行的是新指令，输出 Test 04_36 membarrier OK!
+*/
+
+/// addi a0, zero, imm
+fn li_a0(imm: u32) -> u32 {
+    (imm << 20) | (10 << 7) | 0x13
+}
+
+/// jalr zero, 0(ra)
+const RET: u32 = 0x0000_8067;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    assert_eq!(0, mmap(start, len, 7 | MAP_JIT));
+    let code = start as *mut u32;
+    let func: extern "C" fn() -> usize = unsafe { core::mem::transmute(start) };
+    unsafe {
+        code.write_volatile(li_a0(42));
+        code.add(1).write_volatile(RET);
+    }
+    assert_eq!(0, membarrier());
+    assert_eq!(func(), 42);
+    // 改写已经执行过的指令
+    unsafe {
+        code.write_volatile(li_a0(43));
+    }
+    assert_eq!(0, membarrier());
+    assert_eq!(func(), 43);
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_36 membarrier OK!");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{memstat, mmap, munmap, MemStat};
+
+/*
+理想结果：懒分配的映射只增加 reserved 页数，写过的一半页面变为 resident，
+输出 Test 04_38 memstat OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 8;
+    let mut before = MemStat::default();
+    assert_eq!(0, memstat(&mut before));
+    assert_eq!(0, mmap(start, pages * 4096, 3));
+    let mut after = MemStat::default();
+    assert_eq!(0, memstat(&mut after));
+    assert_eq!(after.resident, before.resident);
+    assert_eq!(after.reserved, before.reserved + pages);
+    for i in 0..pages / 2 {
+        unsafe {
+            ((start + i * 4096) as *mut u8).write_volatile(1);
+        }
+    }
+    assert_eq!(0, memstat(&mut after));
+    assert_eq!(after.resident, before.resident + pages / 2);
+    assert_eq!(after.reserved, before.reserved + pages / 2);
+    assert_eq!(0, munmap(start, pages * 4096));
+    assert_eq!(0, memstat(&mut after));
+    assert_eq!(after.resident, before.resident);
+    assert_eq!(after.reserved, before.reserved);
+    println!("Test 04_38 memstat OK!");
+    0
+}
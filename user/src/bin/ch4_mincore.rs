@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mincore, mmap, munmap, MINCORE_RESERVED, MINCORE_RESIDENT, MINCORE_UNMAPPED};
+
+/*
+理想结果：惰性映射的页面在写入前是 reserved，写入后是 resident，映射之外是 unmapped，
+输出 Test 04_28 mincore OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 3;
+    assert_eq!(0, mmap(start, len, 3));
+    let mut vec = [0xffu8; 4];
+    assert_eq!(0, mincore(start, len + 4096, &mut vec));
+    assert_eq!(vec, [MINCORE_RESERVED, MINCORE_RESERVED, MINCORE_RESERVED, MINCORE_UNMAPPED]);
+    unsafe {
+        *((start + 4096) as *mut u8) = 1;
+    }
+    assert_eq!(0, mincore(start, len, &mut vec));
+    assert_eq!(vec[..3], [MINCORE_RESERVED, MINCORE_RESIDENT, MINCORE_RESERVED]);
+    assert_eq!(-1, mincore(start + 1, len, &mut vec));
+    assert_eq!(0, munmap(start, len));
+    assert_eq!(0, mincore(start, len, &mut vec));
+    assert_eq!(vec[..3], [MINCORE_UNMAPPED; 3]);
+    println!("Test 04_28 mincore OK!");
+    0
+}
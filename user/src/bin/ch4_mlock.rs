@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    madvise, mincore, mlock, mmap, munlock, munmap, MADV_DONTNEED, MINCORE_RESERVED, MINCORE_RESIDENT,
+};
+
+/*
+理想结果：mlock的页面立即驻留，MADV_DONTNEED只释放没有锁定的页面；
+嵌套锁定要munlock同样次数之后才能释放，输出 Test 04_41 mlock OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 4;
+    let len = pages * 4096;
+    assert_eq!(0, mmap(start, len, 3));
+    assert_eq!(mlock(start + 1, 4096), -1);
+    assert_eq!(mlock(start, len + 4096), -1);
+    // 第一页锁定两次，第二页锁定一次
+    assert_eq!(0, mlock(start, 4096));
+    assert_eq!(0, mlock(start, 2 * 4096));
+    let mut vec = [0u8; 4];
+    assert_eq!(0, mincore(start, len, &mut vec));
+    assert_eq!(vec, [MINCORE_RESIDENT, MINCORE_RESIDENT, MINCORE_RESERVED, MINCORE_RESERVED]);
+    for i in 0..pages {
+        unsafe {
+            ((start + i * 4096) as *mut u8).write_volatile(i as u8 + 1);
+        }
+    }
+    assert_eq!(0, madvise(start, len, MADV_DONTNEED));
+    assert_eq!(0, mincore(start, len, &mut vec));
+    assert_eq!(vec, [MINCORE_RESIDENT, MINCORE_RESIDENT, MINCORE_RESERVED, MINCORE_RESERVED]);
+    unsafe {
+        assert_eq!((start as *const u8).read_volatile(), 1);
+        assert_eq!(((start + 4096) as *const u8).read_volatile(), 2);
+        assert_eq!(((start + 2 * 4096) as *const u8).read_volatile(), 0);
+    }
+    // 第一次munlock之后只有第一页仍被锁定
+    assert_eq!(0, munlock(start, 2 * 4096));
+    assert_eq!(0, madvise(start, len, MADV_DONTNEED));
+    assert_eq!(0, mincore(start, 2 * 4096, &mut vec));
+    assert_eq!(vec[..2], [MINCORE_RESIDENT, MINCORE_RESERVED]);
+    assert_eq!(0, munlock(start, 4096));
+    assert_eq!(0, madvise(start, len, MADV_DONTNEED));
+    assert_eq!(0, mincore(start, 4096, &mut vec));
+    assert_eq!(vec[0], MINCORE_RESERVED);
+    unsafe {
+        assert_eq!((start as *const u8).read_volatile(), 0);
+    }
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_41 mlock OK!");
+    0
+}
@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：固定地址的 mmap 返回 0，start 为 0 时返回内核选定的页对齐地址，
+两段映射都可读写且互不重叠，输出 Test 04_35 mmap addr OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 2;
+    assert_eq!(0, mmap(start, len, 3));
+    let auto = mmap(0, len, 3);
+    assert!(auto > 0);
+    let auto = auto as usize;
+    assert_eq!(auto % 4096, 0);
+    assert!(auto + len <= start || auto >= start + len);
+    unsafe {
+        *(start as *mut usize) = 0x1234;
+        *((auto + len - 8) as *mut usize) = 0x5678;
+        assert_eq!(*(start as *const usize), 0x1234);
+        assert_eq!(*((auto + len - 8) as *const usize), 0x5678);
+    }
+    // 出错时仍返回负值
+    assert!(mmap(auto, len, 3) < 0);
+    assert_eq!(0, munmap(auto, len));
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_35 mmap addr OK!");
+    0
+}
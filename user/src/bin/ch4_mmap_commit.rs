@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, MAP_COMMIT};
+
+/*
+理想结果：MAP_COMMIT 映射的页面可以正常读写；超出物理内存的承诺在 mmap 时就返回 -12（ENOMEM），
+最终输出 Test 04_19 mmap commit OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 16;
+    assert_eq!(0, mmap(start, len, 3 | MAP_COMMIT));
+    for i in (start..(start + len)).step_by(4096) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = 0x5a;
+            assert_eq!(*addr, 0x5a);
+        }
+    }
+    assert_eq!(0, munmap(start, len));
+    // 远大于物理内存（128MiB）的承诺
+    assert_eq!(-12, mmap(start, 1 << 30, 3 | MAP_COMMIT));
+    // 未知的标志位仍然被拒绝
+    assert_eq!(-1, mmap(start, len, 3 | (1 << 9)));
+    println!("Test 04_19 mmap commit OK!");
+    0
+}
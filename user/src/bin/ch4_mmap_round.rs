@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：映射 1 字节得到一整页，页内任意位置都可读写，
+用同样的长度可以完整地解除映射，输出 Test 04_32 mmap round up OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 1, 3));
+    let last = (start + 4095) as *mut u8;
+    unsafe {
+        *last = 42;
+        assert_eq!(*last, 42);
+    }
+    assert_eq!(0, munmap(start, 1));
+    // 整页都已解除映射，可以重新映射
+    assert_eq!(0, mmap(start, 4096, 3));
+    assert_eq!(0, munmap(start, 4096));
+    println!("Test 04_32 mmap round up OK!");
+    0
+}
@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, MAP_STACK};
+
+/*
+理想结果：MAP_STACK 映射被放在用户地址空间的高端，访问其下方的页面时自动向下扩展，
+最终输出 Test 04_22 mmap stack OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let len: usize = 4096 * 2;
+    // MAP_STACK 要求 start 为 0
+    assert_eq!(-1, mmap(0x10000000, len, 3 | MAP_STACK));
+    let start = mmap(0, len, 3 | MAP_STACK);
+    assert!(start > 0);
+    let start = start as usize;
+    assert_eq!(start % 4096, 0);
+    // 位于用户地址空间（1 << 38）的上半部分
+    assert!(start >= 1 << 37);
+    unsafe {
+        *((start + len - 8) as *mut usize) = 0x1234;
+        // 栈底之下的一页：触发缺页后向下扩展
+        let below = (start - 4096) as *mut usize;
+        *below = 0x5678;
+        assert_eq!(*below, 0x5678);
+        assert_eq!(*((start + len - 8) as *const usize), 0x1234);
+    }
+    assert_eq!(0, munmap(start - 4096, len + 4096));
+    println!("Test 04_22 mmap stack OK!");
+    0
+}
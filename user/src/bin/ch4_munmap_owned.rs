@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：munmap 只能解除自己用 mmap 申请的映射，代码段和用户栈都被拒绝，
+跨越 mmap 区域和代码段的区间整体被拒绝，输出 Test 04_40 munmap owned OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let page = |addr: usize| addr & !4095;
+    let code = page(main as usize);
+    let local = 0usize;
+    let stack = page(&local as *const usize as usize);
+    assert_eq!(munmap(code, 4096), -1);
+    assert_eq!(munmap(stack, 4096), -1);
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 4096, 3));
+    assert_eq!(munmap(start, 4096), 0);
+    // 仍能正常执行代码、使用栈
+    assert_eq!(unsafe { (&local as *const usize).read_volatile() }, 0);
+    println!("Test 04_40 munmap owned OK!");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, nproc, yield_to};
+
+/*
+理想结果：fork 出的子任务计入 nproc，子任务退出后不再是可运行的任务，输出 Test 04_20 nproc OK!
+*/
+
+const N: usize = 3;
+
+#[no_mangle]
+fn main() -> i32 {
+    let before = nproc();
+    assert!(before >= 1);
+    let mut children = [0usize; N];
+    for child in children.iter_mut() {
+        let pid = fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            exit(0);
+        }
+        *child = pid as usize;
+    }
+    // 子任务在父任务让出CPU之前不会运行
+    assert_eq!(nproc(), before + N as isize);
+    for &child in children.iter() {
+        yield_to(child);
+    }
+    // 其他任务此时可能也在fork或退出，nproc的具体值无法预料；
+    // 已退出的子任务不再是Ready，无法再切换过去
+    for &child in children.iter() {
+        assert_eq!(yield_to(child), -1);
+    }
+    assert!(nproc() >= 1);
+    println!("Test 04_20 nproc OK!");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, pause, sched_stat, wake, waitpid_timeout, yield_to, SchedStat};
+
+/*
+理想结果：子任务 pause 后不再被调度，直到父任务 wake 它；
+提前收到的唤醒不会丢失，输出 Test 04_33 pause wake OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    // 唤醒一个正在运行的任务会留下一次唤醒，下一次pause立即返回
+    assert_eq!(wake(user_lib::getpid() as usize), 0);
+    assert_eq!(pause(), 0);
+    assert_eq!(wake(usize::MAX), -1);
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        pause();
+        let mut stat = SchedStat::default();
+        sched_stat(&mut stat);
+        // 至少被调度了两次：第一次运行到pause，第二次在被唤醒之后
+        exit(stat.run_count as i32);
+    }
+    // 让子任务运行到pause
+    assert_eq!(yield_to(pid as usize), 0);
+    // 阻塞的任务不能被直接切换过去
+    assert_eq!(yield_to(pid as usize), -1);
+    // 阻塞期间子任务不会被调度，也就不会退出
+    let mut code: i32 = 0;
+    assert_eq!(waitpid_timeout(pid, &mut code, 10), -2);
+    assert_eq!(wake(pid as usize), 0);
+    assert_eq!(waitpid_timeout(pid, &mut code, 1000), pid);
+    assert!(code >= 2);
+    println!("Test 04_33 pause wake OK!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, getppid, mmap, pause, process_vm_read, wake, waitpid_timeout, yield_to};
+
+/*
+理想结果：子任务改写自己那份跨页的缓冲区之后，父任务通过 process_vm_read 读到的是子任务写入的内容，
+自己的缓冲区不变；子任务读取父任务或自己、远端地址未映射或任务不存在时返回 -1，
+输出 Test 04_39 process vm read OK!
+*/
+
+const LEN: usize = 200;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 4096 * 2, 3));
+    // 跨越两页的缓冲区
+    let addr = start + 4096 - LEN / 2;
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, LEN) };
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        buf.fill(0xee);
+        // 子任务无权查看父任务，也不能通过这个调用查看自己
+        let mut local = [0u8; LEN];
+        assert_eq!(process_vm_read(getppid() as usize, addr, &mut local), -1);
+        assert_eq!(process_vm_read(getpid() as usize, addr, &mut local), -1);
+        pause();
+        exit(0);
+    }
+    // 让子任务运行到pause
+    assert_eq!(yield_to(pid as usize), 0);
+    let mut local = [0u8; LEN];
+    assert_eq!(process_vm_read(pid as usize, addr, &mut local), LEN as isize);
+    assert!(local.iter().all(|&byte| byte == 0xee));
+    assert!(buf.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+    assert_eq!(process_vm_read(pid as usize, start + 4096 * 2, &mut local), -1);
+    assert_eq!(process_vm_read(1000, addr, &mut local), -1);
+    assert_eq!(wake(pid as usize), 0);
+    let mut exit_code = -1;
+    assert_eq!(waitpid_timeout(pid, &mut exit_code, 2000), pid);
+    assert_eq!(exit_code, 0);
+    println!("Test 04_39 process vm read OK!");
+    0
+}
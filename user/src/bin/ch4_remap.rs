@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, remap};
+
+/*
+理想结果：把8页mmap区域的后4页重映射到前4页的物理页帧上，构成两倍别名的环形缓冲区；
+跨过缓冲区末尾的写入可以从低处读到，输出 Test 04_44 remap OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let ring: usize = 4 * 4096;
+    assert_eq!(0, mmap(start, 2 * ring, 3));
+    // 越界、重叠和不是区域起始地址的请求都被拒绝
+    assert_eq!(remap(start, 0, 4, 5), -1);
+    assert_eq!(remap(start, 0, 4, 2), -1);
+    assert_eq!(remap(start + 4096, 0, 1, 2), -1);
+    assert_eq!(0, remap(start, 0, 4, 4));
+    unsafe {
+        ((start + 4096 + 8) as *mut u8).write_volatile(0x5a);
+        assert_eq!(((start + ring + 4096 + 8) as *const u8).read_volatile(), 0x5a);
+        // 从环形缓冲区末尾连续写，越过末尾的部分落在开头
+        let tail = (start + ring - 2) as *mut u8;
+        for i in 0..4 {
+            tail.add(i).write_volatile(i as u8 + 1);
+        }
+        assert_eq!((start as *const u8).read_volatile(), 3);
+        assert_eq!(((start + 1) as *const u8).read_volatile(), 4);
+    }
+    assert_eq!(0, munmap(start, 2 * ring));
+    println!("Test 04_44 remap OK!");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid_timeout};
+
+/*
+理想结果：只读映射可以读到全零，写入只读映射的子任务被内核杀死（退出码 -2），
+父任务看到的页面仍然是全零，输出 Test 04_37 ro fault OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    assert_eq!(0, mmap(start, len, 1));
+    let addr = start as *mut usize;
+    unsafe {
+        assert_eq!(addr.read_volatile(), 0);
+    }
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            addr.write_volatile(0x1234);
+        }
+        // 不应执行到这里
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid_timeout(pid, &mut exit_code, 2000), pid);
+    assert_eq!(exit_code, -2);
+    unsafe {
+        assert_eq!(addr.read_volatile(), 0);
+    }
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_37 ro fault OK!");
+    0
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sched_stat, sched_yield, task_info, waitpid_timeout, SchedStat, TaskInfo};
+
+/*
+理想结果：父子任务各让出CPU相同的次数后，被调度的次数基本相同，
+且累计占用CPU的时间不超过运行时间，输出 Test 04_30 run count OK!
+*/
+
+const YIELDS: usize = 50;
+
+fn run_count() -> usize {
+    let info = TaskInfo::new();
+    let mut stat = SchedStat::default();
+    assert_eq!(sched_stat(&mut stat), 0);
+    assert_eq!(task_info(&info), 0);
+    assert!(stat.cpu_time <= info.time + 1);
+    stat.run_count
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let before = run_count();
+    assert!(before >= 1);
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        for _ in 0..YIELDS {
+            sched_yield();
+        }
+        exit(run_count() as i32);
+    }
+    for _ in 0..YIELDS {
+        sched_yield();
+    }
+    let parent = run_count() - before;
+    let mut child: i32 = 0;
+    assert_eq!(waitpid_timeout(pid, &mut child, 1000), pid);
+    let child = child as usize;
+    assert!(parent >= YIELDS && child >= YIELDS);
+    assert!(parent.max(child) - parent.min(child) <= YIELDS / 5);
+    println!("Test 04_30 run count OK!");
+    0
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time_us, getpid, getrusage, memstat, mmap, munmap, MemStat, Rusage};
+
+/*
+理想结果：写过的每一页都计入缺页次数和页帧高水位，系统调用次数随调用增长，
+用户态与内核态时间之和不超过经过的时间，munmap之后高水位不回落，输出 Test 04_43 rusage OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 16;
+    let begin = get_time_us() as usize;
+    let mut before = Rusage::default();
+    assert_eq!(0, getrusage(&mut before));
+    assert!(before.syscalls > 0);
+    assert_eq!(0, mmap(start, pages * 4096, 3));
+    for i in 0..pages {
+        unsafe {
+            ((start + i * 4096) as *mut u8).write_volatile(1);
+        }
+    }
+    for _ in 0..100 {
+        getpid();
+    }
+    // 纯用户态的计算
+    let mut x: usize = 1;
+    for i in 0..2_000_000usize {
+        x = x.wrapping_mul(31).wrapping_add(i);
+    }
+    assert!(x != 0);
+    let mut stat = MemStat::default();
+    assert_eq!(0, memstat(&mut stat));
+    let mut after = Rusage::default();
+    assert_eq!(0, getrusage(&mut after));
+    let elapsed = get_time_us() as usize - begin;
+    assert!(after.page_faults >= before.page_faults + pages);
+    assert!(after.max_resident_pages >= before.max_resident_pages + pages);
+    assert!(after.max_resident_pages >= stat.resident);
+    // 两次getrusage之间至少有mmap、100次getpid、memstat和这一次getrusage
+    assert!(after.syscalls >= before.syscalls + 103);
+    assert!(after.user_time > before.user_time);
+    assert!(after.kernel_time > before.kernel_time);
+    assert!(after.user_time + after.kernel_time <= elapsed + before.user_time + before.kernel_time);
+    assert_eq!(0, munmap(start, pages * 4096));
+    let mut last = Rusage::default();
+    assert_eq!(0, getrusage(&mut last));
+    assert_eq!(last.max_resident_pages, after.max_resident_pages);
+    println!("Test 04_43 rusage OK!");
+    0
+}
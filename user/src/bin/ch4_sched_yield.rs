@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sched_yield, yield_to};
+
+/*
+理想结果：有另一个可运行的任务时 sched_yield 返回 1，输出 Test 04_23 sched yield OK!
+（只剩调用者一个任务时返回 0 的情况由内核的 sched_yield_test 检查）
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        exit(0);
+    }
+    // 子任务在父任务让出CPU之前不会运行，此时至少它是Ready的
+    assert_eq!(sched_yield(), 1);
+    // 子任务未必是刚才被调度的那个，确保它在父任务退出前运行完
+    yield_to(pid as usize);
+    println!("Test 04_23 sched yield OK!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{get_time, set_itimer, sigreturn, signal};
+
+/*
+理想结果：注册处理函数并设置20ms的间隔定时器后，处理函数每次到期都被调用一次，
+被打断的计算不受影响；关闭定时器后不再调用，输出 Test 04_48 signal OK!
+*/
+
+const INTERVAL_MS: usize = 20;
+const TICKS: usize = 5;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn on_alarm() -> ! {
+    COUNT.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+    unreachable!("sigreturn returned inside the handler");
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(sigreturn(), -1);
+    assert_eq!(signal(on_alarm), 0);
+    let start = get_time();
+    assert_eq!(set_itimer(INTERVAL_MS * 1000), 0);
+    // 处理函数随时可能打断这个循环，sigreturn之后寄存器必须原样恢复
+    let mut sum: u64 = 0;
+    let mut i: u64 = 0;
+    while COUNT.load(Ordering::SeqCst) < TICKS {
+        sum += i;
+        i += 1;
+        assert!(get_time() - start < 3000, "timer signal not delivered");
+    }
+    assert_eq!(sum, i * (i - 1) / 2);
+    // 第TICKS次到期不早于TICKS个周期之后
+    assert!(get_time() - start >= (TICKS * INTERVAL_MS) as isize - 1);
+
+    assert_eq!(set_itimer(0), 0);
+    let count = COUNT.load(Ordering::SeqCst);
+    let stop = get_time();
+    while get_time() - stop < (4 * INTERVAL_MS) as isize {}
+    assert_eq!(COUNT.load(Ordering::SeqCst), count);
+    println!("Test 04_48 signal OK!");
+    0
+}
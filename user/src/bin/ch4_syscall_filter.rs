@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, set_syscall_filter, waitpid_timeout, yield_, SYSCALL_YIELD};
+
+/*
+理想结果：安装了禁止sys_yield的过滤器之后，调用sys_yield的任务被内核以-4结束；
+过滤器只能安装一次，fork出的子任务同样受限，输出 Test 04_46 syscall filter OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        let mut bitmap = [0xffu8; 56];
+        bitmap[SYSCALL_YIELD / 8] &= !(1 << (SYSCALL_YIELD % 8));
+        assert_eq!(set_syscall_filter(&[0xff; 64]), -1);
+        assert_eq!(set_syscall_filter(&bitmap), 0);
+        assert_eq!(set_syscall_filter(&bitmap), -1);
+        assert!(getpid() > 0);
+        let grandchild = fork();
+        if grandchild == 0 {
+            yield_();
+            exit(0);
+        }
+        let mut exit_code = 0;
+        assert_eq!(waitpid_timeout(grandchild, &mut exit_code, 2000), grandchild);
+        assert_eq!(exit_code, -4);
+        yield_();
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid_timeout(pid, &mut exit_code, 2000), pid);
+    assert_eq!(exit_code, -4);
+    println!("Test 04_46 syscall filter OK!");
+    0
+}
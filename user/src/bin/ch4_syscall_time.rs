@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, mmap, munmap, syscall_time, SYSCALL_GETTIMEOFDAY, SYSCALL_MMAP};
+
+/*
+理想结果：映射大量页面的 mmap 累计耗时大于 get_time，输出 Test 04_14 syscall time OK!
+内核未启用 syscall-latency 特性时 syscall_time 返回 -1，测试直接通过
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    if syscall_time(SYSCALL_GETTIMEOFDAY) == -1 {
+        println!("syscall latency accounting is disabled");
+        println!("Test 04_14 syscall time OK!");
+        return 0;
+    }
+    get_time();
+    let start: usize = 0x10000000;
+    let len: usize = 256 * 4096;
+    assert_eq!(0, mmap(start, len, 3));
+    assert_eq!(0, munmap(start, len));
+    let fast = syscall_time(SYSCALL_GETTIMEOFDAY);
+    let slow = syscall_time(SYSCALL_MMAP);
+    println!("get_time: {} ns, mmap: {} ns", fast, slow);
+    assert!(slow > 0 && slow > fast);
+    println!("Test 04_14 syscall time OK!");
+    0
+}
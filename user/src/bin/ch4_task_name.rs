@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{dmesg, exit, fork, get_name, set_name, yield_to};
+
+/*
+理想结果：任务默认以应用名命名，设置的名字可以读回、被 fork 出的子任务继承，
+并出现在内核的退出日志中，输出 Test 04_29 task name OK!
+*/
+
+fn name(buf: &mut [u8; 32]) -> &[u8] {
+    let n = get_name(buf);
+    assert!(n >= 0);
+    &buf[..n as usize]
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buf = [0u8; 32];
+    assert_eq!(name(&mut buf), b"ch4_task_name");
+    assert_eq!(set_name("worker"), 0);
+    assert_eq!(name(&mut buf), b"worker");
+    assert_eq!(set_name("a-very-long-task-name"), 0);
+    assert_eq!(name(&mut buf), b"a-very-long-task");
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        assert_eq!(name(&mut buf), b"a-very-long-task");
+        set_name("named-child");
+        exit(3);
+    }
+    yield_to(pid as usize);
+    let mut logs = [0u8; 4096];
+    let n = dmesg(&mut logs) as usize;
+    let line = b"(named-child) exited with code 3";
+    assert!(logs[..n].windows(line.len()).any(|w| w == line));
+    println!("Test 04_29 task name OK!");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sleep, time_page};
+
+/*
+理想结果：无需系统调用即可从时间页读到时间，睡眠之后读到的时间更晚，
+输出 Test 04_26 time page OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let (first, freq) = time_page();
+    assert!(freq > 0);
+    assert!(first > 0);
+    sleep(10);
+    let (second, _) = time_page();
+    assert!(second > first);
+    // 至少过去了 10ms
+    assert!(second - first >= freq / 100);
+    println!("Test 04_26 time page OK!");
+    0
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time_us, mmap, tlb_flush};
+
+/*
+理想结果：清空 TLB 前后的访存结果一致，最终输出 Test 04_10 tlb flush OK!
+*/
+
+fn touch_pages(start: usize, pages: usize) -> isize {
+    let begin = get_time_us();
+    for i in 0..pages {
+        let addr: *mut u8 = (start + i * 4096) as *mut u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    get_time_us() - begin
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let pages: usize = 16;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, pages * 4096, prot));
+    for i in 0..pages {
+        let addr: *mut u8 = (start + i * 4096) as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    let warm = touch_pages(start, pages);
+    assert_eq!(0, tlb_flush());
+    let cold = touch_pages(start, pages);
+    println!("touch {} pages: {} us with warm TLB, {} us after flush", pages, warm, cold);
+    println!("Test 04_10 tlb flush OK!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_tls_base, mmap};
+
+/*
+理想结果：TLS页面可读写且初始为全零，mmap 无法覆盖它，输出 Test 04_16 tls OK!
+内核未启用 user-tls 特性时 get_tls_base 返回 -1，测试直接通过
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let base = get_tls_base();
+    if base == -1 {
+        println!("task-local storage is disabled");
+        println!("Test 04_16 tls OK!");
+        return 0;
+    }
+    let base = base as usize;
+    assert_eq!(base % 4096, 0);
+    let tls = unsafe { core::slice::from_raw_parts_mut(base as *mut u8, 4096) };
+    assert!(tls.iter().all(|byte| *byte == 0));
+    for (i, byte) in tls.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    assert!(tls.iter().enumerate().all(|(i, byte)| *byte == i as u8));
+    assert_eq!(mmap(base, 4096, 3), -1);
+    assert_eq!(mmap(base - 4096, 8192, 3), -1);
+    println!("Test 04_16 tls OK!");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, getpid, trapstat, NUM_TRAP_CAUSES, TRAP_SYSCALL, TRAP_TIMER};
+
+/*
+理想结果：每次系统调用都计入 TRAP_SYSCALL，忙等期间时钟中断计入 TRAP_TIMER，
+输出 Test 04_24 trapstat OK!
+*/
+
+const N: usize = 10;
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut before = [0usize; NUM_TRAP_CAUSES];
+    assert_eq!(trapstat(&mut before), NUM_TRAP_CAUSES as isize);
+    for _ in 0..N {
+        getpid();
+    }
+    let mut after = [0usize; NUM_TRAP_CAUSES];
+    assert_eq!(trapstat(&mut after), NUM_TRAP_CAUSES as isize);
+    // 计数是全局的，其他任务可能在期间被调度；N 次 getpid 加上第一次 trapstat 至少会被计入
+    assert!(after[TRAP_SYSCALL] >= before[TRAP_SYSCALL] + N + 1);
+    // 忙等直到发生一次时钟中断（最多 1 秒）
+    let start = get_time();
+    let mut counts = after;
+    while counts[TRAP_TIMER] == after[TRAP_TIMER] {
+        assert!(get_time() < start + 1000);
+        trapstat(&mut counts);
+    }
+    // 缓冲区更短时只写入前面的计数器
+    let mut short = [0usize; 1];
+    assert_eq!(trapstat(&mut short), 1);
+    println!("Test 04_24 trapstat OK!");
+    0
+}
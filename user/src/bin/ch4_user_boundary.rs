@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{syscall, SYSCALL_GETTIMEOFDAY, SYSCALL_TASK_INFO, SYSCALL_WRITE};
+
+/*
+理想结果：传入内核地址空间（跳板所在页）的指针时系统调用返回 -1，最终输出 Test 04_7 user boundary OK!
+*/
+
+const TRAMPOLINE: usize = usize::MAX - 4096 + 1;
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(syscall(SYSCALL_GETTIMEOFDAY, [TRAMPOLINE, 0, 0]), -1);
+    assert_eq!(syscall(SYSCALL_GETTIMEOFDAY, [TRAMPOLINE - 4096, 0, 0]), -1);
+    assert_eq!(syscall(SYSCALL_TASK_INFO, [TRAMPOLINE, 0, 0]), -1);
+    assert_eq!(syscall(SYSCALL_WRITE, [1, TRAMPOLINE, 16]), -1);
+    // 跨越用户地址空间上界的区间同样应被拒绝
+    assert_eq!(syscall(SYSCALL_WRITE, [1, (1 << 38) - 8, 16]), -1);
+    println!("Test 04_7 user boundary OK!");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sleep, waitpid_timeout};
+
+/*
+理想结果：子任务在超时之前退出时 waitpid_timeout 返回它的 pid 和退出码，
+子任务运行得更久时返回 -2，回收之后再等待返回 -1，输出 Test 04_25 waitpid timeout OK!
+*/
+
+/// fork 一个睡眠 sleep_ms 毫秒后以 exit_code 退出的子任务
+fn spawn(sleep_ms: usize, exit_code: i32) -> isize {
+    let pid = fork();
+    assert!(pid >= 0);
+    if pid == 0 {
+        sleep(sleep_ms);
+        exit(exit_code);
+    }
+    pid
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut exit_code = 0;
+    // 子任务先于超时退出
+    let fast = spawn(10, 7);
+    assert_eq!(waitpid_timeout(fast, &mut exit_code, 2000), fast);
+    assert_eq!(exit_code, 7);
+    assert_eq!(waitpid_timeout(fast, &mut exit_code, 10), -1);
+    // 子任务比超时运行得更久
+    let slow = spawn(300, 9);
+    assert_eq!(waitpid_timeout(slow, &mut exit_code, 10), -2);
+    assert_eq!(waitpid_timeout(-1, &mut exit_code, 5000), slow);
+    assert_eq!(exit_code, 9);
+    assert_eq!(waitpid_timeout(-1, &mut exit_code, 10), -1);
+    println!("Test 04_25 waitpid timeout OK!");
+    0
+}
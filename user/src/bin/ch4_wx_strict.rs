@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, MAP_JIT};
+
+/*
+理想结果：同时可写且可执行的映射默认被拒绝，带 MAP_JIT 时允许，
+输出 Test 04_27 wx strict OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    assert_eq!(-1, mmap(start, len, 7));
+    assert_eq!(0, mmap(start, len, 7 | MAP_JIT));
+    unsafe {
+        *(start as *mut u8) = 0x13;
+        assert_eq!(*(start as *const u8), 0x13);
+    }
+    assert_eq!(0, munmap(start, len));
+    println!("Test 04_27 wx strict OK!");
+    0
+}
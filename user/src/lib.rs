@@ -98,12 +98,25 @@ impl TimeVal {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// clock_nanosleep的flags：TimeSpec是启动以来的绝对时间
+pub const TIMER_ABSTIME: usize = 1;
+/// clock_nanosleep的flags：绝对时间已经过去时返回-1
+pub const TIMER_STRICT: usize = 2;
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum TaskStatus {
     UnInit,
     Ready,
     Running,
     Exited,
+    Blocked,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -131,6 +144,42 @@ impl TaskInfo {
     }
 }
 
+/// memstat的结果，单位为页
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct MemStat {
+    /// 已有物理页帧的页面数
+    pub resident: usize,
+    /// 已映射但尚未分配物理页帧（懒分配）的页面数
+    pub reserved: usize,
+}
+
+/// sched_stat的结果
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct SchedStat {
+    /// 被调度器选中的次数
+    pub run_count: usize,
+    /// 累计占用CPU的时间（毫秒）
+    pub cpu_time: usize,
+}
+
+/// getrusage的结果，时间均以微秒为单位
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Rusage {
+    /// 在用户态运行的时间
+    pub user_time: usize,
+    /// 在内核中处理trap的时间
+    pub kernel_time: usize,
+    /// 同时持有物理页帧数的最大值
+    pub max_resident_pages: usize,
+    /// 缺页异常次数
+    pub page_faults: usize,
+    /// 发起过的系统调用总数
+    pub syscalls: usize,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct Stat {
@@ -220,6 +269,11 @@ pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
 
+pub fn exit_group(exit_code: i32) -> ! {
+    console::flush();
+    sys_exit_group(exit_code);
+}
+
 pub fn yield_() -> isize {
     sys_yield()
 }
@@ -232,10 +286,23 @@ pub fn get_time() -> isize {
     }
 }
 
+/// 以微秒为单位的当前时间，用于测量较短的时间间隔
+pub fn get_time_us() -> isize {
+    let time = TimeVal::new();
+    match sys_get_time(&time, 0) {
+        0 => ((time.sec & 0xffff) * 1_000_000 + time.usec) as isize,
+        _ => -1,
+    }
+}
+
 pub fn getpid() -> isize {
     sys_getpid()
 }
 
+pub fn getppid() -> isize {
+    sys_getppid()
+}
+
 pub fn fork() -> isize {
     sys_fork()
 }
@@ -284,6 +351,19 @@ pub fn sleep(period_ms: usize) {
         sys_yield();
     }
 }
+/// 与prot按位或：映射时就为整段承诺物理页帧
+pub const MAP_COMMIT: usize = 1 << 8;
+/// 与prot按位或：start须为0，由内核在地址空间高端放置一段向下增长的栈，mmap返回其起始地址
+pub const MAP_STACK: usize = 1 << 10;
+/// 与prot按位或：允许同时可写且可执行的映射（内核默认拒绝W^X）
+pub const MAP_JIT: usize = 1 << 11;
+/// 与prot按位或：len是HUGE_PAGE_SIZE的整数倍时尽量用大页映射，start为0时返回的地址按HUGE_PAGE_SIZE对齐；
+/// 没有足够的连续物理内存时退回到普通页面
+pub const MAP_HUGE: usize = 1 << 12;
+/// 大页的大小
+pub const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// 成功时返回非负值：start非0时返回0，start为0时返回内核选择的起始地址；失败时返回负值
 pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
     sys_mmap(start, len, prot)
 }
@@ -292,6 +372,31 @@ pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
 
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(start, len, prot)
+}
+
+pub const MADV_WILLNEED: usize = 3;
+pub const MADV_DONTNEED: usize = 4;
+
+pub fn madvise(start: usize, len: usize, advice: usize) -> isize {
+    sys_madvise(start, len, advice)
+}
+
+/// 锁定[start, start + len)，使其中的页面保持驻留；可以嵌套，需要同样次数的munlock才能解除
+pub fn mlock(start: usize, len: usize) -> isize {
+    sys_mlock(start, len)
+}
+
+pub fn munlock(start: usize, len: usize) -> isize {
+    sys_munlock(start, len)
+}
+
+/// 在起始于base的mmap区域内，让第target_offset_pages页起的count页与第offset_pages页起的count页共享物理页帧
+pub fn remap(base: usize, offset_pages: usize, count: usize, target_offset_pages: usize) -> isize {
+    sys_remap(base, offset_pages, count, target_offset_pages)
+}
+
 pub fn spawn(path: &str) -> isize {
     sys_spawn(path)
 }
@@ -307,6 +412,187 @@ pub fn task_info(info: &TaskInfo) -> isize {
     sys_task_info(info)
 }
 
+pub fn maps(buf: &mut [u8]) -> isize {
+    sys_maps(buf)
+}
+
+pub fn tlb_flush() -> isize {
+    sys_tlb_flush()
+}
+
+/// 写入可执行页面的指令在调用之后对取指可见，自修改代码跳转执行前必须调用
+pub fn membarrier() -> isize {
+    sys_membarrier()
+}
+
+pub fn frame_count() -> isize {
+    sys_frame_count()
+}
+
+pub fn set_mem_quota(pages: usize) -> isize {
+    sys_set_mem_quota(pages)
+}
+
+pub fn yield_to(pid: usize) -> isize {
+    sys_yield_to(pid)
+}
+
+pub fn sbrk(size: isize) -> isize {
+    sys_sbrk(size)
+}
+
+pub fn brk(new_brk: usize) -> isize {
+    sys_brk(new_brk)
+}
+
+pub fn syscall_time(syscall_id: usize) -> isize {
+    sys_syscall_time(syscall_id)
+}
+
+pub fn dmesg(buf: &mut [u8]) -> isize {
+    sys_dmesg(buf)
+}
+
+pub fn get_tls_base() -> isize {
+    sys_get_tls_base()
+}
+
+pub fn nproc() -> isize {
+    sys_nproc()
+}
+
+/// 让出CPU，别的任务运行过时返回1，没有别的任务可运行时返回0
+pub fn sched_yield() -> isize {
+    sys_sched_yield()
+}
+
+/// 阻塞直到被别的任务wake；之前已被唤醒过时立即返回
+pub fn pause() -> isize {
+    sys_pause()
+}
+
+/// 唤醒任务pid，任务不存在或已退出时返回-1
+pub fn wake(pid: usize) -> isize {
+    sys_wake(pid)
+}
+
+/// *addr仍等于expected时阻塞，直到被futex_wake唤醒（返回0）；值不等时立即返回-2
+pub fn futex_wait(addr: &u32, expected: u32) -> isize {
+    sys_futex_wait(addr, expected)
+}
+
+/// 唤醒在addr上等待的至多n个任务，返回唤醒的任务数；
+/// futex以物理地址区分，不同任务中映射到同一物理页帧的地址是同一个futex
+pub fn futex_wake(addr: &u32, n: usize) -> isize {
+    sys_futex_wake(addr, n)
+}
+
+/// trapstat返回的计数器下标：系统调用
+pub const TRAP_SYSCALL: usize = 0;
+/// trapstat返回的计数器下标：S态时钟中断
+pub const TRAP_TIMER: usize = 6;
+/// 内核统计的trap原因个数
+pub const NUM_TRAP_CAUSES: usize = 8;
+
+/// 读取当前地址空间中已驻留和仅保留的页面数
+pub fn memstat(stat: &mut MemStat) -> isize {
+    sys_memstat(stat)
+}
+
+/// 读取当前任务的资源使用情况
+pub fn getrusage(usage: &mut Rusage) -> isize {
+    sys_getrusage(usage)
+}
+
+/// 读取当前任务被调度的次数和累计占用CPU的时间
+pub fn sched_stat(stat: &mut SchedStat) -> isize {
+    sys_sched_stat(stat)
+}
+
+/// 从任务pid的地址空间中remote_addr处读取buf.len()字节，返回读到的字节数；
+/// 只有0号任务和pid的父任务可以调用，否则或任务不存在、远端地址未映射时返回-1
+pub fn process_vm_read(pid: usize, remote_addr: usize, buf: &mut [u8]) -> isize {
+    sys_process_vm_read(pid, remote_addr, buf)
+}
+
+/// 把任务pid的映射和页表叶子输出到内核日志；只有0号任务和pid的父任务可以调用，否则返回-1
+pub fn dump_maps(pid: usize) -> isize {
+    sys_dump_maps(pid)
+}
+
+/// 读取内核按trap原因统计的计数，返回写入buf的个数
+pub fn trapstat(buf: &mut [usize]) -> isize {
+    sys_trapstat(buf)
+}
+
+/// 等待子任务pid（-1表示任意一个）退出，最多等待timeout_ms毫秒；
+/// 返回子任务的pid，超时返回-2，没有这样的子任务返回-1
+pub fn waitpid_timeout(pid: isize, exit_code: &mut i32, timeout_ms: usize) -> isize {
+    sys_waitpid_timeout(pid, exit_code as *mut _, timeout_ms)
+}
+
+/// 内核映射的只读时间页：依次存放最近一次返回用户态时的mtime和时钟频率
+pub const TIME_PAGE: usize = usize::MAX - 3 * 4096 + 1;
+
+/// 不经过系统调用读取时间页，返回(mtime, 时钟频率)；mtime在每次从内核返回时更新
+pub fn time_page() -> (usize, usize) {
+    let page = TIME_PAGE as *const usize;
+    unsafe { (page.read_volatile(), page.add(1).read_volatile()) }
+}
+
+/// mincore中页面的状态：不属于任何映射
+pub const MINCORE_UNMAPPED: u8 = 0;
+/// mincore中页面的状态：已映射但尚未驻留
+pub const MINCORE_RESERVED: u8 = 1;
+/// mincore中页面的状态：已驻留
+pub const MINCORE_RESIDENT: u8 = 2;
+
+/// 把[start, start + len)中每一页的状态写入vec，vec至少要有len按页向上取整个字节
+pub fn mincore(start: usize, len: usize, vec: &mut [u8]) -> isize {
+    assert!(vec.len() >= (len + 4095) / 4096);
+    sys_mincore(start, len, vec)
+}
+
+/// 把[va, va + len)直接映射到从pa开始的物理页帧（如MMIO），只有0号任务可以调用
+pub fn map_phys(va: usize, pa: usize, len: usize, prot: usize) -> isize {
+    sys_map_phys(va, pa, len, prot)
+}
+
+/// 设置当前任务的名字（最多16字节，超出部分被截断），内核日志中会显示它
+/// 安装系统调用过滤器：bitmap中第i个字节的第j位为1表示允许编号为8 * i + j的系统调用，其余一律禁止，
+/// 调用被禁止的系统调用时任务以-4退出。只能安装一次，fork出的子任务继承过滤器；失败时返回-1
+pub fn set_syscall_filter(bitmap: &[u8]) -> isize {
+    sys_set_syscall_filter(bitmap)
+}
+
+pub fn clock_nanosleep(time: &TimeSpec, flags: usize) -> isize {
+    sys_clock_nanosleep(time, flags)
+}
+
+/// 每interval_us微秒触发一次定时器信号，0表示关闭
+pub fn set_itimer(interval_us: usize) -> isize {
+    sys_set_itimer(interval_us)
+}
+
+/// 注册定时器信号的处理函数；处理函数必须以sigreturn结束，不能直接返回
+pub fn signal(handler: fn() -> !) -> isize {
+    sys_signal(handler as usize)
+}
+
+/// 在处理函数中调用时回到被打断的位置，不会返回；不在处理函数中时返回-1
+pub fn sigreturn() -> isize {
+    sys_sigreturn()
+}
+
+pub fn set_name(name: &str) -> isize {
+    sys_set_name(name.as_bytes())
+}
+
+/// 把当前任务的名字写入buf，返回写入的字节数
+pub fn get_name(buf: &mut [u8]) -> isize {
+    sys_get_name(buf)
+}
+
 pub fn thread_create(entry: usize, arg: usize) -> isize {
     sys_thread_create(entry, arg)
 }
@@ -1,6 +1,6 @@
 use crate::TaskInfo;
 
-use super::{Stat, TimeVal};
+use super::{MemStat, Rusage, SchedStat, Stat, TimeSpec, TimeVal};
 
 pub const SYSCALL_OPENAT: usize = 56;
 pub const SYSCALL_CLOSE: usize = 57;
@@ -10,23 +10,63 @@ pub const SYSCALL_UNLINKAT: usize = 35;
 pub const SYSCALL_LINKAT: usize = 37;
 pub const SYSCALL_FSTAT: usize = 80;
 pub const SYSCALL_EXIT: usize = 93;
+pub const SYSCALL_EXIT_GROUP: usize = 94;
 pub const SYSCALL_SLEEP: usize = 101;
 pub const SYSCALL_YIELD: usize = 124;
 pub const SYSCALL_GETTIMEOFDAY: usize = 169;
 pub const SYSCALL_GETPID: usize = 172;
+pub const SYSCALL_GETPPID: usize = 173;
 pub const SYSCALL_GETTID: usize = 178;
 pub const SYSCALL_FORK: usize = 220;
+pub const SYSCALL_SBRK: usize = 214;
 pub const SYSCALL_EXEC: usize = 221;
 pub const SYSCALL_WAITPID: usize = 260;
 pub const SYSCALL_SET_PRIORITY: usize = 140;
 pub const SYSCALL_MUNMAP: usize = 215;
 pub const SYSCALL_MMAP: usize = 222;
+pub const SYSCALL_MPROTECT: usize = 226;
+pub const SYSCALL_MADVISE: usize = 233;
+pub const SYSCALL_MLOCK: usize = 228;
+pub const SYSCALL_MUNLOCK: usize = 229;
 pub const SYSCALL_SPAWN: usize = 400;
 pub const SYSCALL_MAIL_READ: usize = 401;
 pub const SYSCALL_MAIL_WRITE: usize = 402;
 pub const SYSCALL_DUP: usize = 24;
 pub const SYSCALL_PIPE: usize = 59;
 pub const SYSCALL_TASK_INFO: usize = 410;
+pub const SYSCALL_MAPS: usize = 411;
+pub const SYSCALL_TLB_FLUSH: usize = 412;
+pub const SYSCALL_FRAME_COUNT: usize = 413;
+pub const SYSCALL_SET_MEM_QUOTA: usize = 414;
+pub const SYSCALL_YIELD_TO: usize = 415;
+pub const SYSCALL_BRK: usize = 416;
+pub const SYSCALL_SYSCALL_TIME: usize = 417;
+pub const SYSCALL_DMESG: usize = 418;
+pub const SYSCALL_GET_TLS_BASE: usize = 419;
+pub const SYSCALL_NPROC: usize = 420;
+pub const SYSCALL_SCHED_YIELD: usize = 421;
+pub const SYSCALL_TRAPSTAT: usize = 422;
+pub const SYSCALL_WAITPID_TIMEOUT: usize = 423;
+pub const SYSCALL_MINCORE: usize = 424;
+pub const SYSCALL_SET_NAME: usize = 425;
+pub const SYSCALL_GET_NAME: usize = 426;
+pub const SYSCALL_MAP_PHYS: usize = 427;
+pub const SYSCALL_PAUSE: usize = 428;
+pub const SYSCALL_WAKE: usize = 429;
+pub const SYSCALL_FUTEX_WAIT: usize = 430;
+pub const SYSCALL_FUTEX_WAKE: usize = 431;
+pub const SYSCALL_MEMBARRIER: usize = 432;
+pub const SYSCALL_MEMSTAT: usize = 433;
+pub const SYSCALL_PROCESS_VM_READ: usize = 434;
+pub const SYSCALL_DUMP_MAPS: usize = 435;
+pub const SYSCALL_GETRUSAGE: usize = 436;
+pub const SYSCALL_REMAP: usize = 437;
+pub const SYSCALL_SET_SYSCALL_FILTER: usize = 438;
+pub const SYSCALL_CLOCK_NANOSLEEP: usize = 439;
+pub const SYSCALL_SET_ITIMER: usize = 440;
+pub const SYSCALL_SIGNAL: usize = 441;
+pub const SYSCALL_SIGRETURN: usize = 442;
+pub const SYSCALL_SCHED_STAT: usize = 443;
 pub const SYSCALL_THREAD_CREATE: usize = 460;
 pub const SYSCALL_WAITTID: usize = 462;
 pub const SYSCALL_MUTEX_CREATE: usize = 463;
@@ -146,6 +186,11 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT_GROUP, [exit_code as usize, 0, 0]);
+    panic!("sys_exit_group never returns!");
+}
+
 pub fn sys_sleep(sleep_ms: usize) -> isize {
     syscall(SYSCALL_SLEEP, [sleep_ms, 0, 0])
 }
@@ -162,6 +207,10 @@ pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0])
 }
 
+pub fn sys_getppid() -> isize {
+    syscall(SYSCALL_GETPPID, [0, 0, 0])
+}
+
 pub fn sys_fork() -> isize {
     syscall(SYSCALL_FORK, [0, 0, 0])
 }
@@ -189,6 +238,26 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     syscall(SYSCALL_MUNMAP, [start, len, 0])
 }
 
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [start, len, prot])
+}
+
+pub fn sys_madvise(start: usize, len: usize, advice: usize) -> isize {
+    syscall(SYSCALL_MADVISE, [start, len, advice])
+}
+
+pub fn sys_mlock(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MLOCK, [start, len, 0])
+}
+
+pub fn sys_munlock(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNLOCK, [start, len, 0])
+}
+
+pub fn sys_remap(base: usize, offset_pages: usize, count: usize, target_offset_pages: usize) -> isize {
+    syscall6(SYSCALL_REMAP, [base, offset_pages, count, target_offset_pages, 0, 0])
+}
+
 pub fn sys_spawn(path: &str) -> isize {
     syscall(SYSCALL_SPAWN, [path.as_ptr() as usize, 0, 0])
 }
@@ -205,6 +274,141 @@ pub fn sys_task_info(info: &TaskInfo) -> isize {
     syscall(SYSCALL_TASK_INFO, [info as *const _ as usize, 0, 0])
 }
 
+pub fn sys_maps(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_MAPS, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+pub fn sys_tlb_flush() -> isize {
+    syscall(SYSCALL_TLB_FLUSH, [0, 0, 0])
+}
+
+pub fn sys_frame_count() -> isize {
+    syscall(SYSCALL_FRAME_COUNT, [0, 0, 0])
+}
+
+pub fn sys_set_mem_quota(pages: usize) -> isize {
+    syscall(SYSCALL_SET_MEM_QUOTA, [pages, 0, 0])
+}
+
+pub fn sys_yield_to(pid: usize) -> isize {
+    syscall(SYSCALL_YIELD_TO, [pid, 0, 0])
+}
+
+pub fn sys_sbrk(size: isize) -> isize {
+    syscall(SYSCALL_SBRK, [size as usize, 0, 0])
+}
+
+pub fn sys_brk(new_brk: usize) -> isize {
+    syscall(SYSCALL_BRK, [new_brk, 0, 0])
+}
+
+pub fn sys_syscall_time(syscall_id: usize) -> isize {
+    syscall(SYSCALL_SYSCALL_TIME, [syscall_id, 0, 0])
+}
+
+pub fn sys_dmesg(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_DMESG, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+pub fn sys_get_tls_base() -> isize {
+    syscall(SYSCALL_GET_TLS_BASE, [0, 0, 0])
+}
+
+pub fn sys_nproc() -> isize {
+    syscall(SYSCALL_NPROC, [0, 0, 0])
+}
+
+pub fn sys_sched_yield() -> isize {
+    syscall(SYSCALL_SCHED_YIELD, [0, 0, 0])
+}
+
+pub fn sys_pause() -> isize {
+    syscall(SYSCALL_PAUSE, [0, 0, 0])
+}
+
+pub fn sys_wake(pid: usize) -> isize {
+    syscall(SYSCALL_WAKE, [pid, 0, 0])
+}
+
+pub fn sys_futex_wait(addr: *const u32, expected: u32) -> isize {
+    syscall(SYSCALL_FUTEX_WAIT, [addr as usize, expected as usize, 0])
+}
+
+pub fn sys_futex_wake(addr: *const u32, n: usize) -> isize {
+    syscall(SYSCALL_FUTEX_WAKE, [addr as usize, n, 0])
+}
+
+pub fn sys_membarrier() -> isize {
+    syscall(SYSCALL_MEMBARRIER, [0, 0, 0])
+}
+
+pub fn sys_memstat(stat: &mut MemStat) -> isize {
+    syscall(SYSCALL_MEMSTAT, [stat as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_getrusage(usage: &mut Rusage) -> isize {
+    syscall(SYSCALL_GETRUSAGE, [usage as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_process_vm_read(pid: usize, remote_addr: usize, buf: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_PROCESS_VM_READ,
+        [pid, remote_addr, buf.as_mut_ptr() as usize, buf.len(), 0, 0],
+    )
+}
+
+pub fn sys_dump_maps(pid: usize) -> isize {
+    syscall(SYSCALL_DUMP_MAPS, [pid, 0, 0])
+}
+
+pub fn sys_trapstat(buf: &mut [usize]) -> isize {
+    syscall(SYSCALL_TRAPSTAT, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+pub fn sys_waitpid_timeout(pid: isize, exit_code: *mut i32, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_WAITPID_TIMEOUT, [pid as usize, exit_code as usize, timeout_ms])
+}
+
+pub fn sys_mincore(start: usize, len: usize, vec: &mut [u8]) -> isize {
+    syscall(SYSCALL_MINCORE, [start, len, vec.as_mut_ptr() as usize])
+}
+
+pub fn sys_map_phys(va: usize, pa: usize, len: usize, prot: usize) -> isize {
+    syscall6(SYSCALL_MAP_PHYS, [va, pa, len, prot, 0, 0])
+}
+
+pub fn sys_set_syscall_filter(bitmap: &[u8]) -> isize {
+    syscall(SYSCALL_SET_SYSCALL_FILTER, [bitmap.as_ptr() as usize, bitmap.len(), 0])
+}
+
+pub fn sys_clock_nanosleep(time: &TimeSpec, flags: usize) -> isize {
+    syscall(SYSCALL_CLOCK_NANOSLEEP, [time as *const _ as usize, flags, 0])
+}
+
+pub fn sys_set_itimer(interval_us: usize) -> isize {
+    syscall(SYSCALL_SET_ITIMER, [interval_us, 0, 0])
+}
+
+pub fn sys_signal(handler: usize) -> isize {
+    syscall(SYSCALL_SIGNAL, [handler, 0, 0])
+}
+
+pub fn sys_sigreturn() -> isize {
+    syscall(SYSCALL_SIGRETURN, [0, 0, 0])
+}
+
+pub fn sys_sched_stat(stat: &mut SchedStat) -> isize {
+    syscall(SYSCALL_SCHED_STAT, [stat as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_set_name(name: &[u8]) -> isize {
+    syscall(SYSCALL_SET_NAME, [name.as_ptr() as usize, name.len(), 0])
+}
+
+pub fn sys_get_name(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GET_NAME, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
     syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
 }